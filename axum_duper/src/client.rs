@@ -0,0 +1,359 @@
+use std::fmt::Display;
+
+use duper::ParseLimits;
+use http::{HeaderValue, header::CONTENT_TYPE};
+use reqwest::{RequestBuilder, Response};
+use serde_core::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    DUPER_ALT_CONTENT_TYPE, DUPER_CONTENT_TYPE, Duper, DuperDataError, DuperRejection,
+    DuperSyntaxError, JSON_CONTENT_TYPE, media_type,
+};
+
+/// Error returned by [`DuperRequestBuilderExt`] and [`DuperResponseExt`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DuperClientError {
+    /// The request failed, or the response body couldn't be read.
+    Reqwest(reqwest::Error),
+    /// The request body couldn't be serialized, or the response body was
+    /// rejected for the same reasons as the [`Duper`] extractor.
+    Duper(DuperRejection),
+}
+
+impl Display for DuperClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DuperClientError::Reqwest(error) => error.fmt(f),
+            DuperClientError::Duper(rejection) => rejection.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DuperClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DuperClientError::Reqwest(error) => Some(error),
+            DuperClientError::Duper(rejection) => Some(rejection),
+        }
+    }
+}
+
+impl From<reqwest::Error> for DuperClientError {
+    fn from(error: reqwest::Error) -> Self {
+        DuperClientError::Reqwest(error)
+    }
+}
+
+impl From<DuperRejection> for DuperClientError {
+    fn from(rejection: DuperRejection) -> Self {
+        DuperClientError::Duper(rejection)
+    }
+}
+
+/// Extension trait to send Duper request bodies with [`reqwest`].
+///
+/// # Example
+///
+/// ```no_run
+/// use axum_duper::{DuperRequestBuilderExt, DuperResponseExt};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize)]
+/// struct CreateUser {
+///     email: String,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     id: u64,
+/// }
+///
+/// # async fn create_user() -> Result<(), axum_duper::DuperClientError> {
+/// let user: User = reqwest::Client::new()
+///     .post("https://example.com/users")
+///     .duper(&CreateUser {
+///         email: "alice@example.com".into(),
+///     })?
+///     .send()
+///     .await?
+///     .duper()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait DuperRequestBuilderExt: Sized {
+    /// Serialize `body` as the Duper body of the request, and set the
+    /// `Content-Type: application/duper` header.
+    ///
+    /// Fails with [`DuperRejection::DuperDataError`] if the value can't be
+    /// serialized.
+    fn duper<T>(self, body: &T) -> Result<Self, DuperClientError>
+    where
+        T: Serialize;
+}
+
+impl DuperRequestBuilderExt for RequestBuilder {
+    fn duper<T>(self, body: &T) -> Result<Self, DuperClientError>
+    where
+        T: Serialize,
+    {
+        let body = duper::serde::ser::to_string(body).map_err(|err| {
+            DuperRejection::DuperDataError(DuperDataError {
+                path: None,
+                reason: err.to_string(),
+            })
+        })?;
+        Ok(self
+            .header(CONTENT_TYPE, HeaderValue::from_static(DUPER_CONTENT_TYPE))
+            .body(body))
+    }
+}
+
+/// Extension trait to parse Duper response bodies with [`reqwest`].
+///
+/// See [`DuperRequestBuilderExt`] for an example.
+pub trait DuperResponseExt {
+    /// Deserialize the Duper body of the response into `T`, checking it
+    /// against the default [`ParseLimits`].
+    ///
+    /// Like with the [`Duper`] extractor, the response must have a Duper or
+    /// JSON `Content-Type`. The status code isn't checked; use
+    /// [`Response::error_for_status`] for that.
+    fn duper<T>(self) -> impl Future<Output = Result<T, DuperClientError>> + Send
+    where
+        T: DeserializeOwned;
+
+    /// Deserialize the Duper body of the response into `T`, checking it
+    /// against the given [`ParseLimits`].
+    fn duper_with_limits<T>(
+        self,
+        limits: ParseLimits,
+    ) -> impl Future<Output = Result<T, DuperClientError>> + Send
+    where
+        T: DeserializeOwned;
+}
+
+impl DuperResponseExt for Response {
+    fn duper<T>(self) -> impl Future<Output = Result<T, DuperClientError>> + Send
+    where
+        T: DeserializeOwned,
+    {
+        self.duper_with_limits(ParseLimits::default())
+    }
+
+    async fn duper_with_limits<T>(mut self, limits: ParseLimits) -> Result<T, DuperClientError>
+    where
+        T: DeserializeOwned,
+    {
+        match media_type(self.headers()) {
+            Some(content_type)
+                if content_type == DUPER_CONTENT_TYPE
+                    || content_type == DUPER_ALT_CONTENT_TYPE
+                    || content_type == JSON_CONTENT_TYPE => {}
+            _ => return Err(DuperRejection::MissingDuperContentType.into()),
+        }
+        // Read the body in chunks, so that oversized responses are rejected
+        // without buffering them whole
+        let too_large = || {
+            DuperRejection::DuperLimitExceeded(DuperSyntaxError::document_too_large(
+                limits.max_document_size,
+            ))
+        };
+        if self
+            .content_length()
+            .is_some_and(|length| length > limits.max_document_size as u64)
+        {
+            return Err(too_large().into());
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = self.chunk().await? {
+            if bytes.len() + chunk.len() > limits.max_document_size {
+                return Err(too_large().into());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        let Duper(value) = Duper::from_bytes_with_limits(&bytes, &limits)?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod client_tests {
+    use std::{
+        convert::Infallible,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    use bytes::Bytes;
+    use futures::executor::block_on;
+    use http_body::Frame;
+    use http_body_util::StreamBody;
+    use serde::{Deserialize, Serialize, Serializer};
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct User {
+        id: u64,
+    }
+
+    fn response(content_type: Option<&str>, body: &'static str) -> Response {
+        let mut response = http::Response::builder();
+        if let Some(content_type) = content_type {
+            response = response.header(CONTENT_TYPE, content_type);
+        }
+        Response::from(response.body(body).unwrap())
+    }
+
+    fn rejection(result: Result<User, DuperClientError>) -> DuperRejection {
+        match result {
+            Err(DuperClientError::Duper(rejection)) => rejection,
+            result => panic!("expected a rejection, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn response_content_types() {
+        for content_type in [
+            "application/duper",
+            "application/x-duper; charset=utf-8",
+            "application/json",
+        ] {
+            let user = block_on(response(Some(content_type), r#"{"id": 1}"#).duper::<User>());
+            assert_eq!(user.unwrap(), User { id: 1 });
+        }
+        assert_eq!(
+            rejection(block_on(response(None, "{id: 1}").duper())),
+            DuperRejection::MissingDuperContentType
+        );
+        assert_eq!(
+            rejection(block_on(response(Some("text/plain"), "{id: 1}").duper())),
+            DuperRejection::MissingDuperContentType
+        );
+    }
+
+    #[test]
+    fn response_rejections() {
+        assert!(matches!(
+            rejection(block_on(
+                response(Some(DUPER_CONTENT_TYPE), "{id: 1").duper()
+            )),
+            DuperRejection::DuperSyntaxError(_)
+        ));
+        let DuperRejection::DuperDataError(error) = rejection(block_on(
+            response(Some(DUPER_CONTENT_TYPE), r#"{id: "1"}"#).duper(),
+        )) else {
+            panic!("expected a data error");
+        };
+        assert_eq!(error.path.as_deref(), Some("id"));
+        let limits = ParseLimits {
+            max_document_size: 4,
+            ..Default::default()
+        };
+        assert!(matches!(
+            rejection(block_on(
+                response(Some(DUPER_CONTENT_TYPE), "{id: 1}").duper_with_limits(limits)
+            )),
+            DuperRejection::DuperLimitExceeded(_)
+        ));
+    }
+
+    #[test]
+    fn response_size_limit() {
+        // An endless body without a length, which must stop being read once
+        // it exceeds the limit.
+        let polls = Arc::new(AtomicUsize::new(0));
+        let chunks = futures::stream::repeat_with({
+            let polls = polls.clone();
+            move || {
+                polls.fetch_add(1, Ordering::Relaxed);
+                Ok::<_, Infallible>(Frame::data(Bytes::from_static(b"[1, 2, 3, 4, 5, 6, 7],")))
+            }
+        });
+        let endless = Response::from(
+            http::Response::builder()
+                .header(CONTENT_TYPE, DUPER_CONTENT_TYPE)
+                .body(reqwest::Body::wrap(StreamBody::new(chunks)))
+                .unwrap(),
+        );
+        let limits = ParseLimits {
+            max_document_size: 1000,
+            ..Default::default()
+        };
+        let DuperRejection::DuperLimitExceeded(error) =
+            rejection(block_on(endless.duper_with_limits(limits)))
+        else {
+            panic!("expected a limit error");
+        };
+        assert_eq!(
+            error.errors[0].reason,
+            "document exceeds limit of 1000 bytes"
+        );
+        assert!(polls.load(Ordering::Relaxed) <= 1000 / 22 + 2);
+
+        // A declared length over the limit is rejected without reading.
+        let limits = ParseLimits {
+            max_document_size: 4,
+            ..Default::default()
+        };
+        let response = response(Some(DUPER_CONTENT_TYPE), "{id: 1}");
+        assert_eq!(response.content_length(), Some(7));
+        assert!(matches!(
+            rejection(block_on(response.duper_with_limits(limits))),
+            DuperRejection::DuperLimitExceeded(_)
+        ));
+    }
+
+    #[test]
+    fn request_body() {
+        let request = reqwest::Client::new()
+            .post("http://localhost/users")
+            .duper(&User { id: 1 })
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()[CONTENT_TYPE], DUPER_CONTENT_TYPE);
+        let body = request.body().and_then(reqwest::Body::as_bytes).unwrap();
+        assert_eq!(
+            duper::serde::de::from_string::<User>(str::from_utf8(body).unwrap()).unwrap(),
+            User { id: 1 }
+        );
+    }
+
+    struct Unserializable;
+
+    impl Serialize for Unserializable {
+        fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("unserializable"))
+        }
+    }
+
+    #[test]
+    fn request_body_error() {
+        let Err(error) = reqwest::Client::new()
+            .post("http://localhost/users")
+            .duper(&Unserializable)
+        else {
+            panic!("expected an error");
+        };
+        let DuperClientError::Duper(DuperRejection::DuperDataError(data_error)) = &error else {
+            panic!("expected a data error, got {error:?}");
+        };
+        assert_eq!(data_error.path, None);
+        assert!(data_error.reason.contains("unserializable"));
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn reqwest_errors() {
+        let error =
+            DuperClientError::from(reqwest::Client::new().get("not a url").build().unwrap_err());
+        assert!(matches!(error, DuperClientError::Reqwest(_)));
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(!error.to_string().is_empty());
+    }
+}
@@ -0,0 +1,918 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, hash_map::Entry},
+};
+
+use axum_core::extract::{FromRequest, FromRequestParts, Request};
+use duper::{DuperParser, DuperValue, ParseLimits, serde::de::Deserializer};
+use http::request::Parts;
+use serde_core::{
+    de::{
+        self, DeserializeOwned, IntoDeserializer, Visitor,
+        value::{MapDeserializer, SeqDeserializer},
+    },
+    forward_to_deserialize_any,
+};
+
+use crate::{
+    DUPER_ALT_CONTENT_TYPE, DUPER_CONTENT_TYPE, DuperDataError, DuperRejection, DuperSyntaxError,
+    JSON_CONTENT_TYPE, body_bytes, content_type, deserialize_with, parse_limits, parse_value,
+};
+
+/// MIME type for URL-encoded forms, handled by [`DuperForm`].
+pub static FORM_URLENCODED_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+/// MIME type for multipart forms, handled by [`DuperForm`].
+pub static MULTIPART_FORM_DATA_CONTENT_TYPE: &str = "multipart/form-data";
+
+/// A field of a query string or form, deserialized lazily so that its type
+/// decides how textual values are interpreted.
+enum Field {
+    /// A textual value, along with its parsed Duper literal if it's valid
+    /// Duper. String types get the text as is, and other types the literal.
+    Text {
+        text: String,
+        literal: Option<DuperValue<'static>>,
+    },
+    /// A value which is always Duper, such as a file upload.
+    Value(DuperValue<'static>),
+    /// The values of a repeated field.
+    Repeated(Vec<Field>),
+}
+
+impl Field {
+    /// Create a textual field, parsing it as a Duper literal if possible.
+    /// Fields exceeding the limits are rejected either way.
+    fn text(text: String, limits: &ParseLimits) -> Result<Self, DuperRejection> {
+        limits.check_document_size(&text).map_err(|errors| {
+            DuperRejection::DuperLimitExceeded(DuperSyntaxError::new(&text, &errors))
+        })?;
+        let literal = match DuperParser::parse_duper_value_with_limits(&text, limits) {
+            Ok(value) => Some(value.static_clone()),
+            Err(errors) if errors.iter().any(ParseLimits::is_limit_error) => {
+                return Err(DuperRejection::DuperLimitExceeded(DuperSyntaxError::new(
+                    &text, &errors,
+                )));
+            }
+            Err(_) => None,
+        };
+        Ok(Field::Text { text, literal })
+    }
+
+    /// Convert the field into a Duper value, preferring the parsed literal.
+    fn into_value(self) -> DuperValue<'static> {
+        match self {
+            Field::Text {
+                literal: Some(literal),
+                ..
+            } => literal,
+            Field::Text { text, .. } => DuperValue::String {
+                identifier: None,
+                inner: Cow::Owned(text),
+            },
+            Field::Value(value) => value,
+            Field::Repeated(fields) => DuperValue::Array {
+                identifier: None,
+                inner: fields.into_iter().map(Field::into_value).collect(),
+            },
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, de::value::Error> for Field {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Field {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Field::Repeated(fields) => visitor.visit_seq(SeqDeserializer::new(fields.into_iter())),
+            field => Deserializer::from_value(field.into_value()).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            // Temporal values are deserialized from strings too.
+            Field::Text {
+                literal: literal @ Some(DuperValue::Temporal(_)),
+                text,
+            } => Field::Text { text, literal }.deserialize_any(visitor),
+            Field::Text { text, .. } => visitor.visit_string(text),
+            field => field.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Field::Value(DuperValue::Null { .. }) => visitor.visit_none(),
+            field => visitor.visit_some(field),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Field::Repeated(fields) => visitor.visit_seq(SeqDeserializer::new(fields.into_iter())),
+            Field::Text {
+                literal: Some(DuperValue::Array { .. } | DuperValue::Tuple { .. }),
+                ..
+            }
+            | Field::Value(_) => {
+                Deserializer::from_value(self.into_value()).deserialize_seq(visitor)
+            }
+            // A single value of a field which could have been repeated.
+            Field::Text { .. } => visitor.visit_seq(SeqDeserializer::new(std::iter::once(self))),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Field::Repeated(fields) => visitor.visit_seq(SeqDeserializer::new(fields.into_iter())),
+            field => Deserializer::from_value(field.into_value()).deserialize_tuple(len, visitor),
+        }
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            // Unit variants are named by the text, unless it's a Duper object.
+            Field::Text {
+                literal: Some(DuperValue::Object { .. }),
+                ..
+            }
+            | Field::Value(_)
+            | Field::Repeated(_) => Deserializer::from_value(self.into_value())
+                .deserialize_enum(name, variants, visitor),
+            Field::Text { text, .. } => visitor.visit_enum(text.into_deserializer()),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Deserializer::from_value(self.into_value()).deserialize_unit(visitor)
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64
+        bytes byte_buf map struct ignored_any
+    }
+}
+
+/// Group fields by name, in the order they first appear. Repeated fields are
+/// collected into a sequence.
+fn group_fields(fields: Vec<(String, Field)>) -> Vec<(String, Field)> {
+    let mut grouped: Vec<(String, Vec<Field>)> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    for (name, field) in fields {
+        match indices.entry(name) {
+            Entry::Occupied(entry) => grouped[*entry.get()].1.push(field),
+            Entry::Vacant(entry) => {
+                grouped.push((entry.key().clone(), vec![field]));
+                entry.insert(grouped.len() - 1);
+            }
+        }
+    }
+    grouped
+        .into_iter()
+        .map(|(name, mut values)| {
+            let field = if values.len() == 1 {
+                values.pop().expect("length checked")
+            } else {
+                Field::Repeated(values)
+            };
+            (name, field)
+        })
+        .collect()
+}
+
+/// Deserialize grouped fields as an object.
+fn deserialize_fields<T>(fields: Vec<(String, Field)>) -> Result<T, DuperRejection>
+where
+    T: DeserializeOwned,
+{
+    deserialize_with(MapDeserializer::new(group_fields(fields).into_iter()))
+}
+
+fn urlencoded_fields(
+    input: &[u8],
+    limits: &ParseLimits,
+) -> Result<Vec<(String, Field)>, DuperRejection> {
+    form_urlencoded::parse(input)
+        .map(|(name, value)| Ok((name.into_owned(), Field::text(value.into_owned(), limits)?)))
+        .collect()
+}
+
+/// Extractor for query strings whose values are Duper literals.
+///
+/// Each query parameter becomes a field of an object, which is deserialized
+/// into `T`. Values are kept as is for string fields (eg. `?name=123`), and
+/// parsed as Duper for other types (eg.
+/// `?since=Instant('2025-01-01T00:00:00Z')&ids=[1,2,3]`). Repeated parameters
+/// are collected into a sequence.
+///
+/// # Example
+///
+/// ```
+/// use axum::{Router, routing::get};
+/// use axum_duper::DuperQuery;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Search {
+///     name: String,
+///     ids: Vec<u64>,
+///     limit: Option<usize>,
+/// }
+///
+/// async fn search(DuperQuery(search): DuperQuery<Search>) {
+///     // search is a `Search`
+/// }
+///
+/// let app = Router::new().route("/search", get(search));
+/// # let _: Router = app;
+/// ```
+pub struct DuperQuery<T>(pub T);
+
+impl<T> DuperQuery<T>
+where
+    T: DeserializeOwned,
+{
+    /// Construct a `DuperQuery<T>` from a query string, without the leading
+    /// `?`. Each value is checked against the default [`ParseLimits`].
+    pub fn from_query(query: &str) -> Result<Self, DuperRejection> {
+        Self::from_query_with_limits(query, &ParseLimits::default())
+    }
+
+    /// Construct a `DuperQuery<T>` from a query string, without the leading
+    /// `?`, rejecting values which exceed the given [`ParseLimits`].
+    pub fn from_query_with_limits(
+        query: &str,
+        limits: &ParseLimits,
+    ) -> Result<Self, DuperRejection> {
+        deserialize_fields(urlencoded_fields(query.as_bytes(), limits)?).map(Self)
+    }
+}
+
+impl<T, S> FromRequestParts<S> for DuperQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = DuperRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Self::from_query_with_limits(
+            parts.uri.query().unwrap_or_default(),
+            &parse_limits(&parts.extensions),
+        )
+    }
+}
+
+/// Extractor for URL-encoded or multipart forms whose fields are Duper.
+///
+/// Like with [`DuperQuery`], each field becomes an entry of an object, which
+/// is deserialized into `T`. For multipart forms:
+///
+/// - Parts with a Duper or JSON `Content-Type` are parsed as Duper, and
+///   rejected if invalid.
+/// - File uploads and other non-text parts become bytes.
+/// - Text parts are kept as is for string fields, and parsed as Duper for
+///   other types.
+///
+/// Each field is checked against the [`ParseLimits`] of the request.
+///
+/// The whole body is buffered before parsing.
+///
+/// # Example
+///
+/// ```
+/// use axum::{Router, routing::post};
+/// use axum_duper::DuperForm;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Upload {
+///     title: String,
+///     tags: Vec<String>,
+///     file: bytes::Bytes,
+/// }
+///
+/// async fn upload(DuperForm(upload): DuperForm<Upload>) {
+///     // upload is an `Upload`
+/// }
+///
+/// let app = Router::new().route("/upload", post(upload));
+/// # let _: Router = app;
+/// ```
+pub struct DuperForm<T>(pub T);
+
+impl<T, S> FromRequest<S> for DuperForm<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = DuperRejection;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        let boundary = match content_type(&req) {
+            Some(content_type) if content_type == FORM_URLENCODED_CONTENT_TYPE => None,
+            Some(content_type) if content_type == MULTIPART_FORM_DATA_CONTENT_TYPE => Some(
+                multipart_boundary(&req).ok_or_else(|| invalid_multipart("missing boundary"))?,
+            ),
+            _ => return Err(DuperRejection::MissingFormContentType),
+        };
+        let limits = parse_limits(req.extensions());
+        let bytes = body_bytes(req, &limits).await?;
+        let fields = match boundary {
+            Some(boundary) => multipart_fields(&bytes, &boundary, &limits)?,
+            None => urlencoded_fields(&bytes, &limits)?,
+        };
+        deserialize_fields(fields).map(Self)
+    }
+}
+
+fn invalid_multipart(reason: &str) -> DuperRejection {
+    DuperRejection::DuperDataError(DuperDataError {
+        path: None,
+        reason: format!("invalid multipart body: {reason}"),
+    })
+}
+
+/// Split the `name=value` parameters of a header, unquoting quoted values.
+fn header_parameters(header: &str) -> Vec<(&str, String)> {
+    let mut parameters = Vec::new();
+    let Some((_, mut rest)) = header.split_once(';') else {
+        return parameters;
+    };
+    while let Some((name, value)) = rest.split_once('=') {
+        // Skip over parameters without a value.
+        let name = name.rsplit(';').next().unwrap_or(name).trim();
+        let value = value.trim_start();
+        let value = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let mut unquoted = String::new();
+                let mut end = quoted.len();
+                let mut chars = quoted.char_indices();
+                while let Some((index, char)) = chars.next() {
+                    match char {
+                        '\\' => unquoted.extend(chars.next().map(|(_, char)| char)),
+                        '"' => {
+                            end = index + 1;
+                            break;
+                        }
+                        char => unquoted.push(char),
+                    }
+                }
+                rest = quoted[end..].split_once(';').map_or("", |(_, rest)| rest);
+                unquoted
+            }
+            None => {
+                let (value, next) = value.split_once(';').unwrap_or((value, ""));
+                rest = next;
+                value.trim().to_string()
+            }
+        };
+        parameters.push((name, value));
+    }
+    parameters
+}
+
+fn multipart_boundary(req: &Request) -> Option<String> {
+    let content_type = req
+        .headers()
+        .get(http::header::CONTENT_TYPE)?
+        .to_str()
+        .ok()?;
+    header_parameters(content_type)
+        .into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("boundary"))
+        .map(|(_, boundary)| boundary)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn multipart_fields(
+    body: &[u8],
+    boundary: &str,
+    limits: &ParseLimits,
+) -> Result<Vec<(String, Field)>, DuperRejection> {
+    let delimiter = format!("\r\n--{boundary}").into_bytes();
+    // Prefix the body with a line break, so that the first delimiter can be
+    // found like the others.
+    let body = [b"\r\n".as_slice(), body].concat();
+    let mut rest = &body
+        [find(&body, &delimiter).ok_or_else(|| invalid_multipart("no parts"))? + delimiter.len()..];
+    let mut fields = Vec::new();
+    loop {
+        if rest.starts_with(b"--") {
+            break;
+        }
+        // Skip the transport padding after the delimiter.
+        let padding = rest
+            .iter()
+            .take_while(|&&byte| byte == b' ' || byte == b'\t')
+            .count();
+        rest = rest[padding..]
+            .strip_prefix(b"\r\n")
+            .ok_or_else(|| invalid_multipart("malformed delimiter"))?;
+        let end = find(rest, &delimiter).ok_or_else(|| invalid_multipart("unterminated part"))?;
+        let (part, next) = rest.split_at(end);
+        rest = &next[delimiter.len()..];
+
+        let headers_end =
+            find(part, b"\r\n\r\n").ok_or_else(|| invalid_multipart("malformed part headers"))?;
+        let headers = str::from_utf8(&part[..headers_end])
+            .map_err(|_| invalid_multipart("malformed part headers"))?;
+        let content = &part[headers_end + 4..];
+
+        let mut name = None;
+        let mut filename = None;
+        let mut part_type = None;
+        for header in headers.split("\r\n") {
+            let Some((header_name, header_value)) = header.split_once(':') else {
+                continue;
+            };
+            if header_name
+                .trim()
+                .eq_ignore_ascii_case("content-disposition")
+            {
+                for (parameter, value) in header_parameters(header_value) {
+                    if parameter.eq_ignore_ascii_case("name") {
+                        name = Some(value);
+                    } else if parameter.eq_ignore_ascii_case("filename") {
+                        filename = Some(value);
+                    }
+                }
+            } else if header_name.trim().eq_ignore_ascii_case("content-type") {
+                let value = header_value.trim();
+                part_type = Some(
+                    value
+                        .split_once(';')
+                        .map_or(value, |(value, _)| value.trim())
+                        .to_ascii_lowercase(),
+                );
+            }
+        }
+        let name = name.ok_or_else(|| invalid_multipart("part without a name"))?;
+
+        let value = match part_type.as_deref() {
+            Some(part_type)
+                if part_type == DUPER_CONTENT_TYPE
+                    || part_type == DUPER_ALT_CONTENT_TYPE
+                    || part_type == JSON_CONTENT_TYPE =>
+            {
+                let text = str::from_utf8(content).map_err(|err| {
+                    DuperRejection::DuperDataError(DuperDataError {
+                        path: Some(name.clone()),
+                        reason: err.to_string(),
+                    })
+                })?;
+                Field::Value(parse_value(text, limits)?.static_clone())
+            }
+            None | Some("text/plain") if filename.is_none() => match str::from_utf8(content) {
+                Ok(text) => Field::text(text.to_string(), limits)?,
+                Err(_) => Field::Value(DuperValue::Bytes {
+                    identifier: None,
+                    inner: Cow::Owned(content.to_vec()),
+                }),
+            },
+            _ => Field::Value(DuperValue::Bytes {
+                identifier: None,
+                inner: Cow::Owned(content.to_vec()),
+            }),
+        };
+        fields.push((name, value));
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod form_tests {
+    use axum_core::body::Body;
+    use futures::executor::block_on;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Search {
+        name: String,
+        #[serde(default)]
+        ids: Vec<u64>,
+        limit: Option<usize>,
+        nickname: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Kind {
+        Active,
+        Archived,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Filter {
+        kind: Kind,
+        tags: Vec<String>,
+        archived: bool,
+    }
+
+    fn search(query: &str) -> Result<Search, DuperRejection> {
+        DuperQuery::from_query(query).map(|DuperQuery(search)| search)
+    }
+
+    #[test]
+    fn query_strings_stay_strings() {
+        assert_eq!(
+            search("name=123&ids=[1,2,3]&limit=5&nickname=true").unwrap(),
+            Search {
+                name: "123".into(),
+                ids: vec![1, 2, 3],
+                limit: Some(5),
+                nickname: Some("true".into()),
+            }
+        );
+        assert_eq!(
+            search("name=%22quoted%22&ids=4").unwrap(),
+            Search {
+                name: "\"quoted\"".into(),
+                ids: vec![4],
+                limit: None,
+                nickname: None,
+            }
+        );
+        assert_eq!(search("name=[1]").unwrap().name, "[1]");
+    }
+
+    #[test]
+    fn query_sequences_and_enums() {
+        let DuperQuery(filter) =
+            DuperQuery::<Filter>::from_query("kind=Archived&tags=a&tags=1&archived=false").unwrap();
+        assert_eq!(
+            filter,
+            Filter {
+                kind: Kind::Archived,
+                tags: vec!["a".into(), "1".into()],
+                archived: false,
+            }
+        );
+        let DuperQuery(filter) =
+            DuperQuery::<Filter>::from_query("kind=Active&tags=single&archived=true").unwrap();
+        assert_eq!(filter.tags, vec!["single".to_string()]);
+        let DuperQuery(filter) =
+            DuperQuery::<Filter>::from_query(r#"kind=Active&tags=["a","b"]&archived=true"#)
+                .unwrap();
+        assert_eq!(filter.tags, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn grouped_fields() {
+        let field = |name: String| {
+            let field = Field::Text {
+                text: name.clone(),
+                literal: None,
+            };
+            (name, field)
+        };
+        let fields = ["b", "a", "b", "c", "b", "a"]
+            .map(String::from)
+            .into_iter()
+            .map(field)
+            .collect();
+        let grouped = group_fields(fields)
+            .into_iter()
+            .map(|(name, field)| match field {
+                Field::Repeated(values) => (name, values.len()),
+                _ => (name, 1),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            grouped,
+            [("b".into(), 3), ("a".into(), 2), ("c".to_string(), 1)]
+        );
+
+        // Many distinct names are grouped in linear time.
+        let fields = (0..100_000).map(|i| field(i.to_string())).collect();
+        assert_eq!(group_fields(fields).len(), 100_000);
+    }
+
+    #[test]
+    fn query_errors() {
+        let Err(DuperRejection::DuperDataError(error)) = search(r#"name=a&ids=[1,"x"]"#) else {
+            panic!("expected a data error");
+        };
+        assert_eq!(error.path.as_deref(), Some("ids[1]"));
+        assert!(matches!(
+            search("name=a&limit=many"),
+            Err(DuperRejection::DuperDataError(_))
+        ));
+        let limits = ParseLimits {
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            DuperQuery::<Search>::from_query_with_limits("name=[[1]]", &limits),
+            Err(DuperRejection::DuperLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn parameters() {
+        assert_eq!(
+            header_parameters(r#"form-data; name="a;b"; filename="say \"hi\".txt";flag; x = y "#),
+            vec![
+                ("name", "a;b".to_string()),
+                ("filename", "say \"hi\".txt".to_string()),
+                ("x", "y".to_string()),
+            ]
+        );
+        assert!(header_parameters("form-data").is_empty());
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Upload {
+        title: String,
+        count: u64,
+        meta: Option<Vec<u64>>,
+        file: Option<bytes::Bytes>,
+    }
+
+    fn upload(
+        content_type: &str,
+        body: &'static str,
+        limits: ParseLimits,
+    ) -> Result<Upload, DuperRejection> {
+        let mut req = Request::builder()
+            .header(http::header::CONTENT_TYPE, content_type)
+            .body(Body::from(body.replace('\n', "\r\n")))
+            .unwrap();
+        req.extensions_mut().insert(limits);
+        block_on(DuperForm::from_request(req, &())).map(|DuperForm(upload)| upload)
+    }
+
+    #[test]
+    fn urlencoded_form() {
+        assert_eq!(
+            upload(
+                FORM_URLENCODED_CONTENT_TYPE,
+                "title=42&count=3&meta=[1,2]",
+                ParseLimits::default()
+            )
+            .unwrap(),
+            Upload {
+                title: "42".into(),
+                count: 3,
+                meta: Some(vec![1, 2]),
+                file: None,
+            }
+        );
+        assert!(matches!(
+            upload("text/plain", "title=a&count=1", ParseLimits::default()),
+            Err(DuperRejection::MissingFormContentType)
+        ));
+    }
+
+    #[test]
+    fn multipart_form() {
+        let body = "preamble, ignored
+--b-1
+Content-Disposition: form-data; name=\"title\"
+
+123 --b-1
+--b-1\t 
+Content-Disposition: form-data; name=\"count\"
+Content-Type: text/plain; charset=utf-8
+
+7
+--b-1
+content-disposition: form-data; name=\"meta\"
+content-type: application/duper
+
+[1, /* comment */ 2]
+--b-1
+Content-Disposition: form-data; name=\"file\"; filename=\"a;b.duper\"
+Content-Type: application/octet-stream
+
+[1, 2]
+--b-1--
+epilogue, ignored";
+        assert_eq!(
+            upload(
+                "multipart/form-data; boundary=\"b-1\"",
+                body,
+                ParseLimits::default()
+            )
+            .unwrap(),
+            Upload {
+                title: "123 --b-1".into(),
+                count: 7,
+                meta: Some(vec![1, 2]),
+                file: Some(bytes::Bytes::from_static(b"[1, 2]")),
+            }
+        );
+    }
+
+    #[test]
+    fn multipart_duper_parts() {
+        let body = "--b
+Content-Disposition: form-data; name=\"title\"
+
+a
+--b
+Content-Disposition: form-data; name=\"count\"
+Content-Type: application/duper
+
+\"1\"
+--b--
+";
+        let Err(DuperRejection::DuperDataError(error)) = upload(
+            "multipart/form-data; boundary=b",
+            body,
+            ParseLimits::default(),
+        ) else {
+            panic!("expected a data error");
+        };
+        assert_eq!(error.path.as_deref(), Some("count"));
+
+        let body = "--b
+Content-Disposition: form-data; name=\"meta\"
+Content-Type: application/duper
+
+[1 2]
+--b--
+";
+        assert!(matches!(
+            upload(
+                "multipart/form-data; boundary=b",
+                body,
+                ParseLimits::default()
+            ),
+            Err(DuperRejection::DuperSyntaxError(_))
+        ));
+    }
+
+    #[test]
+    fn multipart_limits() {
+        let body = "--b
+Content-Disposition: form-data; name=\"meta\"
+Content-Type: application/duper
+
+[[1]]
+--b--
+";
+        let limits = ParseLimits {
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            upload("multipart/form-data; boundary=b", body, limits),
+            Err(DuperRejection::DuperLimitExceeded(_))
+        ));
+
+        let body = "--b
+Content-Disposition: form-data; name=\"title\"
+
+0123456789
+--b--
+";
+        let limits = ParseLimits {
+            max_document_size: 8,
+            ..Default::default()
+        };
+        // The body itself is larger than the limit too.
+        assert!(matches!(
+            upload("multipart/form-data; boundary=b", body, limits),
+            Err(DuperRejection::DuperLimitExceeded(_))
+        ));
+        assert!(matches!(
+            multipart_fields(body.replace('\n', "\r\n").as_bytes(), "b", &limits),
+            Err(DuperRejection::DuperLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_multipart_bodies() {
+        let reason = |body: &str, boundary: &str| match multipart_fields(
+            body.replace('\n', "\r\n").as_bytes(),
+            boundary,
+            &ParseLimits::default(),
+        ) {
+            Err(DuperRejection::DuperDataError(error)) => error.reason,
+            _ => panic!("expected a data error"),
+        };
+        assert_eq!(
+            reason("no delimiter", "b"),
+            "invalid multipart body: no parts"
+        );
+        assert_eq!(
+            reason(
+                "--b\nContent-Disposition: form-data; name=\"a\"\n\n1\n",
+                "b"
+            ),
+            "invalid multipart body: unterminated part"
+        );
+        assert_eq!(
+            reason("--b\nContent-Disposition: form-data\n\n1\n--b--", "b"),
+            "invalid multipart body: part without a name"
+        );
+        assert_eq!(
+            reason("--b\nContent-Disposition: form-data; name=a\n1\n--b--", "b"),
+            "invalid multipart body: malformed part headers"
+        );
+        assert_eq!(
+            reason("--bx\n\n1\n--b--", "b"),
+            "invalid multipart body: malformed delimiter"
+        );
+        assert!(matches!(
+            upload("multipart/form-data", "", ParseLimits::default()),
+            Err(DuperRejection::DuperDataError(_))
+        ));
+    }
+}
@@ -5,17 +5,70 @@
 //!
 //! This crate provides the [`Duper`] struct, which can be used to extract typed
 //! information from request's body, or to serialize a structured response.
+//!
+//! For APIs that must also serve JSON clients, [`Negotiated`] responses pick
+//! between Duper and JSON based on the `Accept` header (see [`Negotiate`]).
+//! Large collections can be streamed as newline-delimited Duper records with
+//! [`DuperStream`]. Query strings and forms can hold Duper values too, with
+//! [`DuperQuery`] and [`DuperForm`].
+//!
+//! With the `reqwest` feature, the same content types and rejections are used
+//! to call Duper APIs with [`reqwest`](https://docs.rs/reqwest), through the
+//! `DuperRequestBuilderExt` and `DuperResponseExt` traits.
+//!
+//! All extractors reject input exceeding the [`ParseLimits`] found in the
+//! request extensions, or the default limits otherwise. Use
+//! [`Extension`](https://docs.rs/axum/latest/axum/struct.Extension.html) as a
+//! layer to configure them:
+//!
+//! ```
+//! use axum::{Extension, Router, routing::post};
+//! use axum_duper::{Duper, ParseLimits};
+//!
+//! async fn create(Duper(value): Duper<Vec<u64>>) {}
+//!
+//! let app = Router::new()
+//!     .route("/create", post(create))
+//!     .layer(Extension(ParseLimits {
+//!         max_depth: 16,
+//!         ..Default::default()
+//!     }));
+//! # let _: Router = app;
+//! ```
+
+#[cfg(feature = "reqwest")]
+mod client;
+mod form;
+mod negotiate;
+mod rejection;
+mod stream;
 
-use std::ops::Deref;
+use std::{marker::PhantomData, ops::Deref};
 
 use axum_core::{
     extract::{FromRequest, OptionalFromRequest, Request},
     response::{IntoResponse, Response},
 };
-use duper::serde::error::DuperSerdeErrorKind;
-use http::{HeaderValue, StatusCode, header::CONTENT_TYPE};
+use bytes::Bytes;
+use duper::{DuperParser, DuperValue, serde::de::Deserializer};
+use http::{Extensions, HeaderMap, HeaderValue, header::CONTENT_TYPE};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
 use serde_core::{Serialize, de::DeserializeOwned};
 
+pub use duper::ParseLimits;
+
+#[cfg(feature = "reqwest")]
+pub use crate::client::{DuperClientError, DuperRequestBuilderExt, DuperResponseExt};
+pub use crate::{
+    form::{DuperForm, DuperQuery, FORM_URLENCODED_CONTENT_TYPE, MULTIPART_FORM_DATA_CONTENT_TYPE},
+    negotiate::{Negotiate, Negotiated, ResponseFormat},
+    rejection::{
+        DuperDataError, DuperRejection, DuperSyntaxError, PROBLEM_JSON_CONTENT_TYPE, ProblemJson,
+        SyntaxErrorDetail, SyntaxErrorLocation,
+    },
+    stream::{DUPER_LINES_CONTENT_TYPE, DuperRecords, DuperStream},
+};
+
 /// Default MIME type for Duper files.
 pub static DUPER_CONTENT_TYPE: &str = "application/duper";
 /// Alternative MIME type for Duper files, handled by the Duper extractor.
@@ -24,36 +77,6 @@ pub static DUPER_ALT_CONTENT_TYPE: &str = "application/x-duper";
 /// that Duper is a superset of JSON).
 pub static JSON_CONTENT_TYPE: &str = "application/json";
 
-/// Rejection used for [`Duper`].
-///
-/// Contains one variant for each way the [`Duper`] extractor can fail.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[non_exhaustive]
-pub enum DuperRejection {
-    DuperDataError,
-    DuperSyntaxError,
-    MissingDuperContentType,
-    InternalDuperError,
-}
-
-impl IntoResponse for DuperRejection {
-    fn into_response(self) -> Response {
-        match self {
-            DuperRejection::DuperDataError | DuperRejection::DuperSyntaxError => {
-                (StatusCode::BAD_REQUEST, "Failed to parse duper").into_response()
-            }
-            DuperRejection::MissingDuperContentType => (
-                StatusCode::BAD_REQUEST,
-                format!("Content-Type header must be {DUPER_CONTENT_TYPE}"),
-            )
-                .into_response(),
-            DuperRejection::InternalDuperError => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error").into_response()
-            }
-        }
-    }
-}
-
 /// Duper extractor / response.
 ///
 /// When used as an extractor, it can deserialize request bodies into some type
@@ -63,6 +86,7 @@ impl IntoResponse for DuperRejection {
 /// - The request doesn’t have a `Content-Type: application/duper` or
 ///   `Content-Type: application/x-duper` header.
 /// - The body doesn’t contain a syntactically valid Duper value.
+/// - The body exceeds the configured [`ParseLimits`].
 /// - The body contains a syntactically valid Duper value, but it couldn’t be
 ///   deserialized into the target type.
 /// - Buffering the request body fails.
@@ -136,26 +160,155 @@ where
     /// use the `FromRequest` impl, but special cases may require first
     /// extracting a `Request` into `Bytes`, then optionally constructing a
     /// `Duper<T>`.
+    ///
+    /// The input is checked against the default [`ParseLimits`].
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, DuperRejection> {
-        let string = str::from_utf8(bytes).map_err(|_| DuperRejection::DuperDataError)?;
-        Self::from_string(string)
+        Self::from_bytes_with_limits(bytes, &ParseLimits::default())
+    }
+
+    /// Construct a `Duper<T>` from a byte slice, rejecting input which
+    /// exceeds the given [`ParseLimits`].
+    pub fn from_bytes_with_limits(
+        bytes: &[u8],
+        limits: &ParseLimits,
+    ) -> Result<Self, DuperRejection> {
+        let string = str::from_utf8(bytes).map_err(|err| {
+            DuperRejection::DuperDataError(DuperDataError {
+                path: None,
+                reason: err.to_string(),
+            })
+        })?;
+        Self::from_string_with_limits(string, limits)
     }
 
     /// Construct a `Duper<T>` from a str slice. Most users should prefer to
     /// use the `FromRequest` impl, but special cases may require first
     /// extracting a `Request` into `String`, then optionally constructing a
     /// `Duper<T>`.
+    ///
+    /// The input is checked against the default [`ParseLimits`].
+    ///
+    /// ```
+    /// use axum_duper::{Duper, DuperDataError, DuperRejection};
+    ///
+    /// let Err(DuperRejection::DuperSyntaxError(error)) =
+    ///     Duper::<Vec<u64>>::from_string("[1, 2,\n  3 4]")
+    /// else {
+    ///     panic!("expected a syntax error");
+    /// };
+    /// let location = error.errors[0].location.as_ref().unwrap();
+    /// assert_eq!((location.line, location.column), (2, 5));
+    ///
+    /// let Err(DuperRejection::DuperDataError(error)) =
+    ///     Duper::<Vec<u64>>::from_string(r#"[1, 2, "3"]"#)
+    /// else {
+    ///     panic!("expected a data error");
+    /// };
+    /// assert_eq!(error.path.as_deref(), Some("[2]"));
+    /// ```
     pub fn from_string(string: &str) -> Result<Self, DuperRejection> {
-        match duper::serde::de::from_string(string) {
-            Ok(value) => Ok(Self(value)),
-            Err(err) => match err.inner.kind {
-                DuperSerdeErrorKind::ParseError(_) => Err(DuperRejection::DuperSyntaxError),
-                DuperSerdeErrorKind::SerializationError
-                | DuperSerdeErrorKind::DeserializationError(_)
-                | DuperSerdeErrorKind::InvalidValue
-                | DuperSerdeErrorKind::Custom => Err(DuperRejection::InternalDuperError),
-            },
+        Self::from_string_with_limits(string, &ParseLimits::default())
+    }
+
+    /// Construct a `Duper<T>` from a str slice, rejecting input which exceeds
+    /// the given [`ParseLimits`].
+    ///
+    /// ```
+    /// use axum_duper::{Duper, DuperRejection, ParseLimits};
+    ///
+    /// let limits = ParseLimits {
+    ///     max_depth: 1,
+    ///     ..Default::default()
+    /// };
+    /// assert!(matches!(
+    ///     Duper::<Vec<Vec<u64>>>::from_string_with_limits("[[1]]", &limits),
+    ///     Err(DuperRejection::DuperLimitExceeded(_)),
+    /// ));
+    /// ```
+    pub fn from_string_with_limits(
+        string: &str,
+        limits: &ParseLimits,
+    ) -> Result<Self, DuperRejection> {
+        deserialize_value(parse_value(string, limits)?).map(Self)
+    }
+}
+
+/// Parse a value, rejecting it if it exceeds the limits.
+pub(crate) fn parse_value<'a>(
+    string: &'a str,
+    limits: &ParseLimits,
+) -> Result<DuperValue<'a>, DuperRejection> {
+    DuperParser::parse_duper_value_with_limits(string, limits).map_err(|errors| {
+        let error = DuperSyntaxError::new(string, &errors);
+        if errors.iter().any(ParseLimits::is_limit_error) {
+            DuperRejection::DuperLimitExceeded(error)
+        } else {
+            DuperRejection::DuperSyntaxError(error)
         }
+    })
+}
+
+/// Get the [`ParseLimits`] configured for a request.
+pub(crate) fn parse_limits(extensions: &Extensions) -> ParseLimits {
+    extensions.get().copied().unwrap_or_default()
+}
+
+/// Deserialize a parsed value, keeping track of the path of any errors.
+pub(crate) fn deserialize_value<T>(value: DuperValue<'_>) -> Result<T, DuperRejection>
+where
+    T: DeserializeOwned,
+{
+    deserialize_with(&mut Deserializer::from_value(value))
+}
+
+/// Deserialize from any deserializer, keeping track of the path of any
+/// errors.
+pub(crate) fn deserialize_with<'de, D, T>(deserializer: D) -> Result<T, DuperRejection>
+where
+    D: serde_core::Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let path = err.path().to_string();
+        DuperRejection::DuperDataError(DuperDataError {
+            path: (path != ".").then_some(path),
+            reason: err.into_inner().to_string(),
+        })
+    })
+}
+
+/// Get the media type of a request, without parameters.
+pub(crate) fn content_type(req: &Request) -> Option<&str> {
+    media_type(req.headers())
+}
+
+/// Get the media type of a request or response, without parameters.
+pub(crate) fn media_type(headers: &HeaderMap) -> Option<&str> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    Some(match content_type.split_once(';') {
+        Some((content_type, _)) => content_type.trim(),
+        None => content_type,
+    })
+}
+
+/// Read the body of a request, rejecting it as soon as it exceeds the
+/// [`ParseLimits::max_document_size`].
+pub(crate) async fn body_bytes(
+    req: Request,
+    limits: &ParseLimits,
+) -> Result<Bytes, DuperRejection> {
+    match Limited::new(req.into_body(), limits.max_document_size)
+        .collect()
+        .await
+    {
+        Ok(collected) => Ok(collected.to_bytes()),
+        Err(err) if err.is::<LengthLimitError>() => Err(DuperRejection::DuperLimitExceeded(
+            DuperSyntaxError::document_too_large(limits.max_document_size),
+        )),
+        Err(err) => Err(DuperRejection::DuperDataError(DuperDataError {
+            path: None,
+            reason: format!("Failed to buffer the request body: {err}"),
+        })),
     }
 }
 
@@ -166,26 +319,15 @@ where
 {
     type Rejection = DuperRejection;
 
-    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
-        if let Some(content_type) = req
-            .headers()
-            .get(CONTENT_TYPE)
-            .and_then(|content_type| content_type.to_str().ok())
-        {
-            let content_type = if let Some((content_type, _)) = content_type.split_once(';') {
-                content_type
-            } else {
-                content_type
-            };
-            if content_type == DUPER_CONTENT_TYPE
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(content_type) = content_type(&req)
+            && (content_type == DUPER_CONTENT_TYPE
                 || content_type == DUPER_ALT_CONTENT_TYPE
-                || content_type == JSON_CONTENT_TYPE
-            {
-                let string = String::from_request(req, state)
-                    .await
-                    .map_err(|_| DuperRejection::DuperDataError)?;
-                return Self::from_string(&string);
-            }
+                || content_type == JSON_CONTENT_TYPE)
+        {
+            let limits = parse_limits(req.extensions());
+            let bytes = body_bytes(req, &limits).await?;
+            return Self::from_bytes_with_limits(&bytes, &limits);
         }
         Err(DuperRejection::MissingDuperContentType)
     }
@@ -198,17 +340,16 @@ where
 {
     type Rejection = DuperRejection;
 
-    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+    async fn from_request(req: Request, _state: &S) -> Result<Option<Self>, Self::Rejection> {
         let Some(content_type) = req.headers().get(CONTENT_TYPE) else {
             return Ok(None);
         };
         if let Ok(content_type) = content_type.to_str()
             && (content_type == DUPER_CONTENT_TYPE || content_type == DUPER_ALT_CONTENT_TYPE)
         {
-            let string = String::from_request(req, state)
-                .await
-                .map_err(|_| DuperRejection::DuperDataError)?;
-            Self::from_string(&string).map(Some)
+            let limits = parse_limits(req.extensions());
+            let bytes = body_bytes(req, &limits).await?;
+            Self::from_bytes_with_limits(&bytes, &limits).map(Some)
         } else {
             Err(DuperRejection::MissingDuperContentType)
         }
@@ -245,3 +386,144 @@ impl<T> From<T> for Duper<T> {
         Self(value)
     }
 }
+
+/// Duper extractor with a custom rejection.
+///
+/// This behaves like the [`Duper`] extractor, but converts its
+/// [`DuperRejection`] into `R`, which allows customizing the error response.
+/// For example, use [`ProblemJson`] for `application/problem+json` bodies:
+///
+/// ```rust, no_run
+/// use axum::{Router, routing::post};
+/// use axum_duper::{DuperWithRejection, ProblemJson};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct CreateUser {
+///     email: String,
+/// }
+///
+/// async fn create_user(
+///     DuperWithRejection(payload, _): DuperWithRejection<CreateUser, ProblemJson>,
+/// ) {
+///     // payload is a `CreateUser`
+/// }
+///
+/// let app = Router::new().route("/user", post(create_user));
+/// # let _: Router = app;
+/// ```
+pub struct DuperWithRejection<T, R>(pub T, pub PhantomData<fn() -> R>);
+
+impl<T, R, S> FromRequest<S> for DuperWithRejection<T, R>
+where
+    T: DeserializeOwned,
+    R: From<DuperRejection> + IntoResponse,
+    S: Send + Sync,
+{
+    type Rejection = R;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        <Duper<T> as FromRequest<S>>::from_request(req, state)
+            .await
+            .map(|Duper(value)| DuperWithRejection(value, PhantomData))
+            .map_err(R::from)
+    }
+}
+
+impl<T, R> Deref for DuperWithRejection<T, R> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod body_limits_tests {
+    use axum_core::body::Body;
+    use futures::executor::block_on;
+
+    use super::*;
+
+    fn request(body: Body, limits: ParseLimits) -> Request {
+        let mut req = Request::builder()
+            .header(CONTENT_TYPE, DUPER_CONTENT_TYPE)
+            .body(body)
+            .unwrap();
+        req.extensions_mut().insert(limits);
+        req
+    }
+
+    fn extract(body: Body, limits: ParseLimits) -> Result<Vec<Vec<u64>>, DuperRejection> {
+        block_on(<Duper<_> as FromRequest<()>>::from_request(
+            request(body, limits),
+            &(),
+        ))
+        .map(|Duper(value)| value)
+    }
+
+    #[test]
+    fn body_within_limits() {
+        let limits = ParseLimits {
+            max_document_size: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            extract(Body::from("[[1], [2]]"), limits).unwrap(),
+            vec![vec![1], vec![2]]
+        );
+        assert!(matches!(
+            extract(Body::from("[[1] [2]]"), limits),
+            Err(DuperRejection::DuperSyntaxError(_))
+        ));
+    }
+
+    #[test]
+    fn oversized_body() {
+        let limits = ParseLimits {
+            max_document_size: 10,
+            ..Default::default()
+        };
+        let Err(DuperRejection::DuperLimitExceeded(error)) =
+            extract(Body::from("[[1], [2], [3]]"), limits)
+        else {
+            panic!("expected a limit error");
+        };
+        assert_eq!(error.errors[0].reason, "document exceeds limit of 10 bytes");
+        // The body isn't read in full, so the error has no location.
+        assert_eq!(error.errors[0].location, None);
+        let rejection = DuperRejection::DuperLimitExceeded(error);
+        assert_eq!(
+            rejection.to_string(),
+            "Duper input exceeds the parse limits; document exceeds limit of 10 bytes"
+        );
+        assert_eq!(
+            rejection.into_response().status(),
+            http::StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[test]
+    fn endless_body() {
+        // The body is never read in full, so this would hang otherwise.
+        let body = Body::from_stream(futures::stream::repeat_with(|| {
+            Ok::<_, std::io::Error>(Bytes::from_static(b"[1], "))
+        }));
+        assert!(matches!(
+            extract(body, ParseLimits::default()),
+            Err(DuperRejection::DuperLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn nested_body() {
+        let limits = ParseLimits {
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            extract(Body::from("[[1]]"), limits),
+            Err(DuperRejection::DuperLimitExceeded(_))
+        ));
+    }
+}
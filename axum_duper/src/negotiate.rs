@@ -0,0 +1,388 @@
+use std::convert::Infallible;
+
+use axum_core::{
+    extract::FromRequestParts,
+    response::{IntoResponse, Response},
+};
+use duper::ToJson;
+use http::{
+    HeaderMap, HeaderValue,
+    header::{ACCEPT, CONTENT_TYPE, VARY},
+    request::Parts,
+};
+use serde_core::Serialize;
+
+use crate::{DUPER_ALT_CONTENT_TYPE, DUPER_CONTENT_TYPE, DuperRejection, JSON_CONTENT_TYPE};
+
+/// The format of a [`Negotiated`] response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseFormat {
+    /// Duper, with `Content-Type: application/duper`.
+    #[default]
+    Duper,
+    /// Minified Duper, with `Content-Type: application/duper`. Requested with
+    /// the `minify=true` media type parameter, eg.
+    /// `Accept: application/duper; minify=true`.
+    DuperMinified,
+    /// JSON, with `Content-Type: application/json`. Values which are not
+    /// representable in JSON are converted with [`ToJson`].
+    Json,
+}
+
+impl ResponseFormat {
+    /// Choose the response format preferred by the given `Accept` headers.
+    ///
+    /// The media type with the highest quality value wins. On ties, the most
+    /// specific match wins, then Duper is preferred over JSON. If no header is
+    /// present, or none of the formats is acceptable, Duper is chosen.
+    pub fn from_accept(headers: &HeaderMap) -> Self {
+        let ranges: Vec<MediaRange<'_>> = headers
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(MediaRange::parse)
+            .collect();
+        if ranges.is_empty() {
+            return ResponseFormat::Duper;
+        }
+
+        let duper = [DUPER_CONTENT_TYPE, DUPER_ALT_CONTENT_TYPE]
+            .into_iter()
+            .filter_map(|media_type| best_match(&ranges, media_type))
+            .max_by(|a, b| a.specificity.cmp(&b.specificity).then(a.q.total_cmp(&b.q)));
+        let json = best_match(&ranges, JSON_CONTENT_TYPE);
+
+        match (duper, json) {
+            (Some(duper), Some(json))
+                if json.q > duper.q
+                    || (json.q == duper.q && json.specificity > duper.specificity) =>
+            {
+                ResponseFormat::Json
+            }
+            (Some(duper), _) if duper.q > 0.0 => {
+                if duper.minify {
+                    ResponseFormat::DuperMinified
+                } else {
+                    ResponseFormat::Duper
+                }
+            }
+            (_, Some(json)) if json.q > 0.0 => ResponseFormat::Json,
+            _ => ResponseFormat::Duper,
+        }
+    }
+}
+
+/// A single entry of an `Accept` header.
+struct MediaRange<'a> {
+    r#type: &'a str,
+    subtype: &'a str,
+    q: f32,
+    minify: bool,
+}
+
+impl<'a> MediaRange<'a> {
+    fn parse(input: &'a str) -> Option<Self> {
+        let mut parts = input.split(';').map(str::trim);
+        let (r#type, subtype) = parts.next()?.split_once('/')?;
+        let mut range = MediaRange {
+            r#type: r#type.trim(),
+            subtype: subtype.trim(),
+            q: 1.0,
+            minify: false,
+        };
+        for parameter in parts {
+            let Some((key, value)) = parameter.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"');
+            match key.trim() {
+                "q" => range.q = value.parse().ok().filter(|q: &f32| q.is_finite())?,
+                "minify" => range.minify = value.eq_ignore_ascii_case("true"),
+                _ => (),
+            }
+        }
+        Some(range)
+    }
+}
+
+/// How a media type was matched by a [`MediaRange`].
+struct Match {
+    specificity: u8,
+    q: f32,
+    minify: bool,
+}
+
+/// Find the most specific range matching `media_type`, as per RFC 9110.
+fn best_match(ranges: &[MediaRange<'_>], media_type: &str) -> Option<Match> {
+    let (r#type, subtype) = media_type.split_once('/')?;
+    ranges
+        .iter()
+        .filter_map(|range| {
+            let specificity = if range.r#type.eq_ignore_ascii_case(r#type)
+                && range.subtype.eq_ignore_ascii_case(subtype)
+            {
+                2
+            } else if range.r#type.eq_ignore_ascii_case(r#type) && range.subtype == "*" {
+                1
+            } else if range.r#type == "*" && range.subtype == "*" {
+                0
+            } else {
+                return None;
+            };
+            Some(Match {
+                specificity,
+                q: range.q,
+                minify: range.minify,
+            })
+        })
+        .max_by_key(|m| m.specificity)
+}
+
+/// Extractor for the [`ResponseFormat`] preferred by the client, based on
+/// its `Accept` header.
+///
+/// This never rejects the request. Use [`Negotiate::respond`] to create a
+/// [`Negotiated`] response in the chosen format.
+///
+/// # Example
+///
+/// ```
+/// use axum::{Router, routing::get};
+/// use axum_duper::{Negotiate, Negotiated};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Status {
+///     healthy: bool,
+/// }
+///
+/// async fn status(negotiate: Negotiate) -> Negotiated<Status> {
+///     negotiate.respond(Status { healthy: true })
+/// }
+///
+/// let app = Router::new().route("/status", get(status));
+/// # let _: Router = app;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Negotiate(pub ResponseFormat);
+
+impl Negotiate {
+    /// Create a response in the negotiated format.
+    pub fn respond<T>(self, value: T) -> Negotiated<T> {
+        Negotiated {
+            format: self.0,
+            value,
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Negotiate
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Negotiate(ResponseFormat::from_accept(&parts.headers)))
+    }
+}
+
+/// A response serialized as Duper, minified Duper, or JSON, depending on the
+/// [`ResponseFormat`] negotiated with the client.
+///
+/// The response always includes a `Vary: Accept` header. If serialization
+/// fails, a 500 response is issued instead, like with [`Duper`](crate::Duper).
+#[derive(Debug, Clone)]
+pub struct Negotiated<T> {
+    /// The format of the response.
+    pub format: ResponseFormat,
+    /// The value to serialize.
+    pub value: T,
+}
+
+impl<T> Negotiated<T> {
+    /// Create a response in the given format.
+    pub fn new(format: ResponseFormat, value: T) -> Self {
+        Negotiated { format, value }
+    }
+}
+
+impl<T> IntoResponse for Negotiated<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let body = match self.format {
+            ResponseFormat::Duper => duper::serde::ser::to_string(&self.value)
+                .map(|string| (DUPER_CONTENT_TYPE, string))
+                .map_err(|_| DuperRejection::InternalDuperError),
+            ResponseFormat::DuperMinified => duper::serde::ser::to_string_minified(&self.value)
+                .map(|string| (DUPER_CONTENT_TYPE, string))
+                .map_err(|_| DuperRejection::InternalDuperError),
+            ResponseFormat::Json => duper::serde::ser::to_duper(&self.value)
+                .map_err(|_| DuperRejection::InternalDuperError)
+                .and_then(|value| {
+                    serde_json::to_string(&value.accept(&mut ToJson))
+                        .map_err(|_| DuperRejection::InternalDuperError)
+                })
+                .map(|string| (JSON_CONTENT_TYPE, string)),
+        };
+        let mut response = body
+            .map(|(content_type, string)| {
+                (
+                    [(CONTENT_TYPE, HeaderValue::from_static(content_type))],
+                    string,
+                )
+            })
+            .into_response();
+        response
+            .headers_mut()
+            .append(VARY, HeaderValue::from_static("accept"));
+        response
+    }
+}
+
+#[cfg(test)]
+mod negotiate_tests {
+    use super::*;
+
+    fn format(accept: &[&str]) -> ResponseFormat {
+        let mut headers = HeaderMap::new();
+        for value in accept {
+            headers.append(ACCEPT, HeaderValue::from_str(value).unwrap());
+        }
+        ResponseFormat::from_accept(&headers)
+    }
+
+    #[test]
+    fn media_range_parse() {
+        let range = MediaRange::parse(" application/duper ; q = 0.5; minify=\"TRUE\"").unwrap();
+        assert_eq!((range.r#type, range.subtype), ("application", "duper"));
+        assert_eq!(range.q, 0.5);
+        assert!(range.minify);
+
+        let range = MediaRange::parse("*/*;charset=utf-8;flag").unwrap();
+        assert_eq!((range.r#type, range.subtype), ("*", "*"));
+        assert_eq!(range.q, 1.0);
+        assert!(!range.minify);
+
+        assert!(MediaRange::parse("application").is_none());
+        assert!(MediaRange::parse("application/json;q=high").is_none());
+        assert!(MediaRange::parse("application/json;q=NaN").is_none());
+    }
+
+    #[test]
+    fn best_match_specificity() {
+        let ranges: Vec<_> = ["*/*;q=0.1", "application/json;q=0.2", "application/*;q=0.3"]
+            .into_iter()
+            .filter_map(MediaRange::parse)
+            .collect();
+        let json = best_match(&ranges, JSON_CONTENT_TYPE).unwrap();
+        assert_eq!((json.specificity, json.q), (2, 0.2));
+        let duper = best_match(&ranges, DUPER_CONTENT_TYPE).unwrap();
+        assert_eq!((duper.specificity, duper.q), (1, 0.3));
+        let html = best_match(&ranges, "text/html").unwrap();
+        assert_eq!((html.specificity, html.q), (0, 0.1));
+
+        let ranges: Vec<_> = ["Application/JSON"]
+            .into_iter()
+            .filter_map(MediaRange::parse)
+            .collect();
+        assert!(best_match(&ranges, JSON_CONTENT_TYPE).is_some());
+        assert!(best_match(&ranges, DUPER_CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn defaults_to_duper() {
+        assert_eq!(format(&[]), ResponseFormat::Duper);
+        assert_eq!(format(&["*/*"]), ResponseFormat::Duper);
+        assert_eq!(format(&["text/html"]), ResponseFormat::Duper);
+        assert_eq!(format(&["garbage"]), ResponseFormat::Duper);
+        assert_eq!(
+            format(&["application/duper;q=0, application/json;q=0"]),
+            ResponseFormat::Duper
+        );
+    }
+
+    #[test]
+    fn quality_values() {
+        assert_eq!(format(&["application/json"]), ResponseFormat::Json);
+        assert_eq!(
+            format(&["application/json, application/duper;q=0.9"]),
+            ResponseFormat::Json
+        );
+        assert_eq!(
+            format(&["application/json;q=0.5, application/duper"]),
+            ResponseFormat::Duper
+        );
+        assert_eq!(
+            format(&["application/json;q=0.5", "application/x-duper;q=0.8"]),
+            ResponseFormat::Duper
+        );
+        assert_eq!(format(&["application/json, */*;q=0"]), ResponseFormat::Json);
+        // An invalid quality value discards its range.
+        assert_eq!(
+            format(&["application/json;q=high, application/duper;q=0.1"]),
+            ResponseFormat::Duper
+        );
+    }
+
+    #[test]
+    fn specificity() {
+        // The most specific range decides the quality, even if it's lower.
+        assert_eq!(
+            format(&["application/duper;q=0.1, */*"]),
+            ResponseFormat::Json
+        );
+        assert_eq!(
+            format(&["application/json;q=0.1, application/*"]),
+            ResponseFormat::Duper
+        );
+        // On equal quality, the most specific match wins.
+        assert_eq!(
+            format(&["application/*, application/json"]),
+            ResponseFormat::Json
+        );
+        assert_eq!(
+            format(&["application/*, application/duper"]),
+            ResponseFormat::Duper
+        );
+        // On equal quality and specificity, Duper wins.
+        assert_eq!(format(&["application/*"]), ResponseFormat::Duper);
+        assert_eq!(
+            format(&["application/json, application/duper"]),
+            ResponseFormat::Duper
+        );
+    }
+
+    #[test]
+    fn minify() {
+        assert_eq!(
+            format(&["application/duper; minify=true"]),
+            ResponseFormat::DuperMinified
+        );
+        assert_eq!(
+            format(&["application/duper; minify=false"]),
+            ResponseFormat::Duper
+        );
+        // The parameter only applies to the range it's in.
+        assert_eq!(
+            format(&["application/duper, */*; minify=true"]),
+            ResponseFormat::Duper
+        );
+    }
+
+    #[test]
+    fn negotiated_response() {
+        let response = Negotiated::new(ResponseFormat::Json, vec![1, 2]).into_response();
+        assert_eq!(response.headers()[CONTENT_TYPE], JSON_CONTENT_TYPE);
+        assert_eq!(response.headers()[VARY], "accept");
+
+        let response = Negotiate(ResponseFormat::DuperMinified)
+            .respond(vec![1, 2])
+            .into_response();
+        assert_eq!(response.headers()[CONTENT_TYPE], DUPER_CONTENT_TYPE);
+        assert_eq!(response.headers()[VARY], "accept");
+    }
+}
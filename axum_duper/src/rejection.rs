@@ -0,0 +1,370 @@
+use std::{fmt::Display, ops::Range};
+
+use axum_core::response::{IntoResponse, Response};
+use duper::{DuperKey, DuperObject, DuperValue, Serializer, ToJson};
+use http::{HeaderValue, StatusCode, header::CONTENT_TYPE};
+
+use crate::{DUPER_CONTENT_TYPE, FORM_URLENCODED_CONTENT_TYPE, MULTIPART_FORM_DATA_CONTENT_TYPE};
+
+/// MIME type for RFC 9457 problem details, used by [`ProblemJson`].
+pub static PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Rejection used for [`Duper`](crate::Duper).
+///
+/// Contains one variant for each way the [`Duper`](crate::Duper) extractor
+/// can fail.
+///
+/// The response body is a Duper value describing the error, eg.:
+///
+/// ```duper
+/// DuperRejection({
+///   kind: "DuperSyntaxError",
+///   message: "Failed to parse duper",
+///   errors: [
+///     {
+///       line: 1,
+///       column: 10,
+///       offset: 9,
+///       reason: "found end of input expected '}'",
+///       expected: ["'}'"],
+///       found: null,
+///     },
+///   ],
+/// })
+/// ```
+///
+/// Use [`ProblemJson`] to render it as `application/problem+json` instead,
+/// or [`DuperWithRejection`](crate::DuperWithRejection) with a custom
+/// rejection to fully customize the response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DuperRejection {
+    DuperDataError(DuperDataError),
+    DuperSyntaxError(DuperSyntaxError),
+    /// The input exceeds the configured [`ParseLimits`](crate::ParseLimits).
+    DuperLimitExceeded(DuperSyntaxError),
+    MissingDuperContentType,
+    MissingFormContentType,
+    InternalDuperError,
+}
+
+/// The request body is valid Duper, but doesn't match the target type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuperDataError {
+    /// The path to the value that failed to deserialize (eg. `users[0].email`),
+    /// if known.
+    pub path: Option<String>,
+    /// The reason for the failure, including the expected type.
+    pub reason: String,
+}
+
+/// The request body isn't valid Duper, or exceeds the parse limits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuperSyntaxError {
+    /// Each of the errors reported by the parser.
+    pub errors: Vec<SyntaxErrorDetail>,
+}
+
+/// A single syntax error within the request body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxErrorDetail {
+    /// The location of the error, or None if the body was rejected before
+    /// being read in full.
+    pub location: Option<SyntaxErrorLocation>,
+    /// A description of the error.
+    pub reason: String,
+    /// The tokens that were expected instead.
+    pub expected: Vec<String>,
+    /// The character that was found, or None at the end of input.
+    pub found: Option<String>,
+}
+
+/// The location of a syntax error within the request body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxErrorLocation {
+    /// The byte range of the error in the body.
+    pub span: Range<usize>,
+    /// The line of the start of the error, starting at 1.
+    pub line: usize,
+    /// The column (in characters) of the start of the error, starting at 1.
+    pub column: usize,
+}
+
+impl DuperSyntaxError {
+    pub(crate) fn new(input: &str, errors: &[chumsky::error::Rich<'_, char>]) -> Self {
+        DuperSyntaxError {
+            errors: errors
+                .iter()
+                .map(|error| {
+                    let span = error.span().into_range();
+                    let (line, column) = line_column(input, span.start);
+                    SyntaxErrorDetail {
+                        location: Some(SyntaxErrorLocation { span, line, column }),
+                        reason: error.to_string(),
+                        expected: error.expected().map(ToString::to_string).collect(),
+                        found: error.found().map(ToString::to_string),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// The error for a body exceeding the maximum document size, which was
+    /// rejected before being read in full, and so has no location.
+    pub(crate) fn document_too_large(max_document_size: usize) -> Self {
+        DuperSyntaxError {
+            errors: vec![SyntaxErrorDetail {
+                location: None,
+                reason: format!("document exceeds limit of {max_document_size} bytes"),
+                expected: vec![],
+                found: None,
+            }],
+        }
+    }
+}
+
+/// Compute the 1-based line and column of a byte offset.
+fn line_column(input: &str, offset: usize) -> (usize, usize) {
+    let before = input.get(..offset).unwrap_or(input);
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+fn string(inner: impl Display) -> DuperValue<'static> {
+    DuperValue::String {
+        identifier: None,
+        inner: inner.to_string().into(),
+    }
+}
+
+fn integer(inner: usize) -> DuperValue<'static> {
+    DuperValue::Integer {
+        identifier: None,
+        inner: inner.try_into().unwrap_or(i64::MAX),
+    }
+}
+
+fn optional(value: Option<DuperValue<'static>>) -> DuperValue<'static> {
+    value.unwrap_or(DuperValue::Null { identifier: None })
+}
+
+fn object(
+    identifier: Option<&'static str>,
+    entries: Vec<(&'static str, DuperValue<'static>)>,
+) -> DuperValue<'static> {
+    DuperValue::Object {
+        identifier: identifier.map(|identifier| identifier.try_into().expect("valid identifier")),
+        inner: DuperObject::try_from(
+            entries
+                .into_iter()
+                .map(|(key, value)| (DuperKey::from(key), value))
+                .collect::<Vec<_>>(),
+        )
+        .expect("no duplicate keys"),
+    }
+}
+
+impl DuperRejection {
+    /// The status code of the response.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            DuperRejection::DuperDataError(_)
+            | DuperRejection::DuperSyntaxError(_)
+            | DuperRejection::MissingDuperContentType
+            | DuperRejection::MissingFormContentType => StatusCode::BAD_REQUEST,
+            DuperRejection::DuperLimitExceeded(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            DuperRejection::InternalDuperError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The name of the variant, eg. `DuperSyntaxError`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DuperRejection::DuperDataError(_) => "DuperDataError",
+            DuperRejection::DuperSyntaxError(_) => "DuperSyntaxError",
+            DuperRejection::DuperLimitExceeded(_) => "DuperLimitExceeded",
+            DuperRejection::MissingDuperContentType => "MissingDuperContentType",
+            DuperRejection::MissingFormContentType => "MissingFormContentType",
+            DuperRejection::InternalDuperError => "InternalDuperError",
+        }
+    }
+
+    /// A human-readable summary of the error.
+    pub fn message(&self) -> String {
+        match self {
+            DuperRejection::DuperDataError(_) => "Failed to deserialize duper".into(),
+            DuperRejection::DuperSyntaxError(_) => "Failed to parse duper".into(),
+            DuperRejection::DuperLimitExceeded(_) => "Duper input exceeds the parse limits".into(),
+            DuperRejection::MissingDuperContentType => {
+                format!("Content-Type header must be {DUPER_CONTENT_TYPE}")
+            }
+            DuperRejection::MissingFormContentType => format!(
+                "Content-Type header must be {FORM_URLENCODED_CONTENT_TYPE} or {MULTIPART_FORM_DATA_CONTENT_TYPE}"
+            ),
+            DuperRejection::InternalDuperError => "Internal server error".into(),
+        }
+    }
+
+    /// The fields describing this error, besides the kind and message.
+    fn details(&self) -> Vec<(&'static str, DuperValue<'static>)> {
+        match self {
+            DuperRejection::DuperDataError(error) => vec![
+                ("path", optional(error.path.as_ref().map(string))),
+                ("reason", string(&error.reason)),
+            ],
+            DuperRejection::DuperSyntaxError(error) | DuperRejection::DuperLimitExceeded(error) => {
+                vec![(
+                    "errors",
+                    DuperValue::Array {
+                        identifier: None,
+                        inner: error
+                            .errors
+                            .iter()
+                            .map(|detail| {
+                                object(
+                                    None,
+                                    vec![
+                                        (
+                                            "line",
+                                            optional(
+                                                detail.location.as_ref().map(|l| integer(l.line)),
+                                            ),
+                                        ),
+                                        (
+                                            "column",
+                                            optional(
+                                                detail.location.as_ref().map(|l| integer(l.column)),
+                                            ),
+                                        ),
+                                        (
+                                            "offset",
+                                            optional(
+                                                detail
+                                                    .location
+                                                    .as_ref()
+                                                    .map(|l| integer(l.span.start)),
+                                            ),
+                                        ),
+                                        ("reason", string(&detail.reason)),
+                                        (
+                                            "expected",
+                                            DuperValue::Array {
+                                                identifier: None,
+                                                inner: detail.expected.iter().map(string).collect(),
+                                            },
+                                        ),
+                                        ("found", optional(detail.found.as_ref().map(string))),
+                                    ],
+                                )
+                            })
+                            .collect(),
+                    },
+                )]
+            }
+            DuperRejection::MissingDuperContentType
+            | DuperRejection::MissingFormContentType
+            | DuperRejection::InternalDuperError => vec![],
+        }
+    }
+
+    /// Describe this error as a Duper value, as used in the response body.
+    pub fn to_duper(&self) -> DuperValue<'static> {
+        let mut entries = vec![
+            ("kind", string(self.kind())),
+            ("message", string(self.message())),
+        ];
+        entries.extend(self.details());
+        object(Some("DuperRejection"), entries)
+    }
+
+    /// Describe this error as RFC 9457 problem details, with the fields of
+    /// [`to_duper`](Self::to_duper) as extension members.
+    pub fn to_problem_json(&self) -> serde_json::Value {
+        let status = self.status();
+        let mut entries = vec![
+            ("type", string("about:blank")),
+            (
+                "title",
+                string(status.canonical_reason().unwrap_or("Unknown error")),
+            ),
+            ("status", integer(status.as_u16().into())),
+            ("detail", string(self.message())),
+            ("kind", string(self.kind())),
+        ];
+        entries.extend(self.details());
+        object(None, entries).accept(&mut ToJson)
+    }
+}
+
+impl Display for DuperRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message())?;
+        match self {
+            DuperRejection::DuperDataError(DuperDataError {
+                path: Some(path),
+                reason,
+            }) => write!(f, " at {path}: {reason}"),
+            DuperRejection::DuperDataError(DuperDataError { path: None, reason }) => {
+                write!(f, ": {reason}")
+            }
+            DuperRejection::DuperSyntaxError(DuperSyntaxError { errors })
+            | DuperRejection::DuperLimitExceeded(DuperSyntaxError { errors }) => {
+                for error in errors {
+                    match &error.location {
+                        Some(location) => write!(
+                            f,
+                            "; {}:{}: {}",
+                            location.line, location.column, error.reason
+                        )?,
+                        None => write!(f, "; {}", error.reason)?,
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for DuperRejection {}
+
+impl IntoResponse for DuperRejection {
+    fn into_response(self) -> Response {
+        (
+            self.status(),
+            [(CONTENT_TYPE, HeaderValue::from_static(DUPER_CONTENT_TYPE))],
+            Serializer::new(false, false).serialize(&self.to_duper()),
+        )
+            .into_response()
+    }
+}
+
+/// A [`DuperRejection`] rendered as `application/problem+json`.
+///
+/// It can be used as the rejection of
+/// [`DuperWithRejection`](crate::DuperWithRejection).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProblemJson(pub DuperRejection);
+
+impl From<DuperRejection> for ProblemJson {
+    fn from(value: DuperRejection) -> Self {
+        ProblemJson(value)
+    }
+}
+
+impl IntoResponse for ProblemJson {
+    fn into_response(self) -> Response {
+        (
+            self.0.status(),
+            [(
+                CONTENT_TYPE,
+                HeaderValue::from_static(PROBLEM_JSON_CONTENT_TYPE),
+            )],
+            self.0.to_problem_json().to_string(),
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,350 @@
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum_core::{
+    body::{Body, BodyDataStream},
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Response},
+};
+use duper::ParseLimits;
+use futures::{Stream, StreamExt, ready};
+use http::{HeaderValue, header::CONTENT_TYPE};
+use serde_core::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    DUPER_ALT_CONTENT_TYPE, DUPER_CONTENT_TYPE, Duper, DuperDataError, DuperRejection,
+    DuperSyntaxError, content_type, parse_limits,
+};
+
+/// MIME type for newline-delimited Duper records, as used by [`DuperStream`].
+pub static DUPER_LINES_CONTENT_TYPE: &str = "application/x-duper-lines";
+
+/// Newline-delimited Duper stream extractor / response.
+///
+/// When used as a response, it serializes each item of the inner [`Stream`]
+/// as a single line of Duper as soon as it is produced, with the
+/// `Content-Type: application/x-duper-lines` header. This output can be
+/// consumed directly by line-based tools such as `duperq`. If an item fails to
+/// serialize, the response body is aborted.
+///
+/// When used as an extractor with [`DuperRecords`], it parses each non-empty
+/// line of the request body as a separate record, without buffering the
+/// whole body. The request must have a `Content-Type` of
+/// `application/x-duper-lines`, or any of the [`Duper`] content types. Each
+/// line is checked against the [`ParseLimits`] of the request.
+///
+/// # Example
+///
+/// ```
+/// use axum::{Router, routing::post};
+/// use axum_duper::{DuperRecords, DuperStream};
+/// use futures::{Stream, StreamExt};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Event {
+///     id: u64,
+/// }
+///
+/// async fn echo_valid(
+///     DuperStream(events): DuperStream<DuperRecords<Event>>,
+/// ) -> DuperStream<impl Stream<Item = Event>> {
+///     DuperStream(events.filter_map(async |event| event.ok()))
+/// }
+///
+/// let app = Router::new().route("/events", post(echo_valid));
+/// # let _: Router = app;
+/// ```
+pub struct DuperStream<S>(pub S);
+
+impl<S> IntoResponse for DuperStream<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    fn into_response(self) -> Response {
+        let body = Body::from_stream(self.0.map(|item| {
+            duper::serde::ser::to_string(&item).map(|mut line| {
+                line.push('\n');
+                line
+            })
+        }));
+        (
+            [(
+                CONTENT_TYPE,
+                HeaderValue::from_static(DUPER_LINES_CONTENT_TYPE),
+            )],
+            body,
+        )
+            .into_response()
+    }
+}
+
+impl<T, S> FromRequest<S> for DuperStream<DuperRecords<T>>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = DuperRejection;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        match content_type(&req) {
+            Some(content_type)
+                if content_type == DUPER_LINES_CONTENT_TYPE
+                    || content_type == DUPER_CONTENT_TYPE
+                    || content_type == DUPER_ALT_CONTENT_TYPE =>
+            {
+                Ok(DuperStream(DuperRecords {
+                    limits: parse_limits(req.extensions()),
+                    body: req.into_body().into_data_stream(),
+                    buffer: Vec::new(),
+                    done: false,
+                    skipping: false,
+                    _marker: PhantomData,
+                }))
+            }
+            _ => Err(DuperRejection::MissingDuperContentType),
+        }
+    }
+}
+
+/// A stream of records parsed from a newline-delimited Duper request body.
+///
+/// Created by the [`DuperStream`] extractor. Each record that fails to parse
+/// yields a [`DuperRejection`], and the stream continues with the next line.
+/// A failure to read the body ends the stream after yielding its error.
+///
+/// A line longer than the maximum document size yields a
+/// [`DuperRejection::DuperLimitExceeded`] as soon as the limit is reached,
+/// and the rest of the line is discarded without buffering it.
+pub struct DuperRecords<T> {
+    limits: ParseLimits,
+    body: BodyDataStream,
+    buffer: Vec<u8>,
+    done: bool,
+    /// Whether the rest of the current line is being discarded.
+    skipping: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> DuperRecords<T>
+where
+    T: DeserializeOwned,
+{
+    /// Parse the next complete line in the buffer, if any.
+    fn next_record(&mut self) -> Option<Result<T, DuperRejection>> {
+        loop {
+            let line: Vec<u8> = match self.buffer.iter().position(|&byte| byte == b'\n') {
+                Some(index) => {
+                    let mut line: Vec<u8> = self.buffer.drain(..=index).collect();
+                    line.pop();
+                    line
+                }
+                None if self.done && !self.buffer.is_empty() => std::mem::take(&mut self.buffer),
+                None => return None,
+            };
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            return Some(
+                Duper::from_bytes_with_limits(&line, &self.limits).map(|Duper(value)| value),
+            );
+        }
+    }
+
+    /// Append a chunk of the body to the buffer, discarding the rest of a line
+    /// that exceeded the limits.
+    fn push_chunk(&mut self, chunk: &[u8]) -> Option<DuperRejection> {
+        let chunk = if self.skipping {
+            match chunk.iter().position(|&byte| byte == b'\n') {
+                Some(index) => {
+                    self.skipping = false;
+                    &chunk[index + 1..]
+                }
+                None => return None,
+            }
+        } else {
+            chunk
+        };
+        self.buffer.extend_from_slice(chunk);
+        let line_length = self
+            .buffer
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .unwrap_or(self.buffer.len());
+        if line_length <= self.limits.max_document_size {
+            return None;
+        }
+        let line = String::from_utf8_lossy(&self.buffer[..line_length]).into_owned();
+        let errors = self.limits.check_document_size(&line).err()?;
+        if line_length == self.buffer.len() {
+            self.skipping = true;
+            self.buffer.clear();
+        } else {
+            self.buffer.drain(..=line_length);
+        }
+        Some(DuperRejection::DuperLimitExceeded(DuperSyntaxError::new(
+            &line, &errors,
+        )))
+    }
+}
+
+impl<T> Stream for DuperRecords<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<T, DuperRejection>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(record) = this.next_record() {
+                return Poll::Ready(Some(record));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            match ready!(this.body.poll_next_unpin(cx)) {
+                Some(Ok(chunk)) => {
+                    if let Some(rejection) = this.push_chunk(&chunk) {
+                        return Poll::Ready(Some(Err(rejection)));
+                    }
+                }
+                Some(Err(err)) => {
+                    this.done = true;
+                    this.buffer.clear();
+                    return Poll::Ready(Some(Err(DuperRejection::DuperDataError(
+                        DuperDataError {
+                            path: None,
+                            reason: err.to_string(),
+                        },
+                    ))));
+                }
+                None => this.done = true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use bytes::Bytes;
+    use futures::executor::block_on;
+
+    use super::*;
+
+    /// Extract the records of a body sent in the given chunks.
+    fn collect_records(
+        chunks: &[&'static str],
+        limits: ParseLimits,
+    ) -> Vec<Result<Vec<u64>, DuperRejection>> {
+        let chunks: Vec<_> = chunks
+            .iter()
+            .map(|chunk| Ok::<_, std::io::Error>(Bytes::from_static(chunk.as_bytes())))
+            .collect();
+        let mut req = Request::builder()
+            .header(CONTENT_TYPE, DUPER_LINES_CONTENT_TYPE)
+            .body(Body::from_stream(futures::stream::iter(chunks)))
+            .unwrap();
+        req.extensions_mut().insert(limits);
+        block_on(async {
+            let DuperStream(records) =
+                DuperStream::<DuperRecords<Vec<u64>>>::from_request(req, &())
+                    .await
+                    .unwrap();
+            records.collect().await
+        })
+    }
+
+    #[test]
+    fn records_across_chunks() {
+        let records = collect_records(
+            &["[1, ", "2]\n\n  \n[", "3]\r\n[4]"],
+            ParseLimits::default(),
+        );
+        assert_eq!(
+            records.into_iter().collect::<Result<Vec<_>, _>>().unwrap(),
+            vec![vec![1, 2], vec![3], vec![4]]
+        );
+    }
+
+    #[test]
+    fn invalid_record_continues() {
+        let records = collect_records(&["[1]\n[1 2]\n[\"3\"]\n[4]\n"], ParseLimits::default());
+        assert_eq!(records.len(), 4);
+        assert!(matches!(
+            records[1],
+            Err(DuperRejection::DuperSyntaxError(_))
+        ));
+        assert!(matches!(records[2], Err(DuperRejection::DuperDataError(_))));
+        assert_eq!(records[3].as_ref().unwrap(), &vec![4]);
+    }
+
+    #[test]
+    fn limits_per_line() {
+        let limits = ParseLimits {
+            max_elements: 2,
+            ..Default::default()
+        };
+        let records = collect_records(&["[1, 2]\n[1, 2, 3]\n[3]\n"], limits);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].as_ref().unwrap(), &vec![1, 2]);
+        assert!(matches!(
+            records[1],
+            Err(DuperRejection::DuperLimitExceeded(_))
+        ));
+        assert_eq!(records[2].as_ref().unwrap(), &vec![3]);
+    }
+
+    #[test]
+    fn long_line_is_discarded() {
+        let limits = ParseLimits {
+            max_document_size: 8,
+            ..Default::default()
+        };
+        // The limit is reached before the end of the line, whose rest is
+        // skipped over the following chunks.
+        let records = collect_records(&["[1]\n[1, 2, ", "3, 4, 5", ", 6]\n[", "7]\n"], limits);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].as_ref().unwrap(), &vec![1]);
+        let Err(DuperRejection::DuperLimitExceeded(error)) = &records[1] else {
+            panic!("expected a limit error");
+        };
+        assert_eq!(error.errors[0].reason, "document exceeds limit of 8 bytes");
+        assert_eq!(records[2].as_ref().unwrap(), &vec![7]);
+
+        // The whole line is in a single chunk.
+        let records = collect_records(&["[1, 2, 3, 4]\n[5]\n"], limits);
+        assert_eq!(records.len(), 2);
+        assert!(matches!(
+            records[0],
+            Err(DuperRejection::DuperLimitExceeded(_))
+        ));
+        assert_eq!(records[1].as_ref().unwrap(), &vec![5]);
+
+        // A line of exactly the maximum size is accepted.
+        let records = collect_records(
+            &["[1, 2, 3]\n"],
+            ParseLimits {
+                max_document_size: 9,
+                ..Default::default()
+            },
+        );
+        assert_eq!(records[0].as_ref().unwrap(), &vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn missing_content_type() {
+        let req = Request::builder().body(Body::from("[1]")).unwrap();
+        assert!(matches!(
+            block_on(DuperStream::<DuperRecords<Vec<u64>>>::from_request(
+                req,
+                &()
+            )),
+            Err(DuperRejection::MissingDuperContentType)
+        ));
+    }
+}
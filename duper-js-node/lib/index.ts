@@ -813,17 +813,46 @@ export function stringify(value: any, options?: StringifyOptions): string {
   );
 }
 
+/**
+ * Limits for parsing untrusted Duper input. Unspecified limits take their
+ * default values.
+ *
+ * @property {number} [maxDepth] - Maximum nesting of objects, arrays, and
+ * tuples. Defaults to 128.
+ * @property {number} [maxStringLength] - Maximum length of a string or bytes
+ * literal, in bytes as written in the input. Defaults to 1 MiB.
+ * @property {number} [maxElements] - Maximum number of elements in a single
+ * object, array, or tuple. Defaults to 65,536.
+ * @property {number} [maxDocumentSize] - Maximum size of the input, in bytes.
+ * Defaults to 2 MiB.
+ */
+export type ParseLimits = {
+  maxDepth?: number;
+  maxStringLength?: number;
+  maxElements?: number;
+  maxDocumentSize?: number;
+};
+
 /**
  * Parses the provided Duper string into a Duper value, or a JSON-safe alternative if specified.
  *
  * @param value The Duper string to parse.
  * @param jsonSafe Whether to emit a JSON-safe alternative instead of a `DuperValue`.
+ * @param limits Limits to enforce on the input, which is rejected if it exceeds them.
  * @returns The parsed value.
  */
-export function parse(value: string, jsonSafe?: false): DuperValue;
-export function parse(value: string, jsonSafe: true): any;
-export function parse(value: string, jsonSafe?: boolean): DuperValue | any {
-  const parsed = duperNapi.parse(value, true);
+export function parse(
+  value: string,
+  jsonSafe?: false,
+  limits?: ParseLimits,
+): DuperValue;
+export function parse(value: string, jsonSafe: true, limits?: ParseLimits): any;
+export function parse(
+  value: string,
+  jsonSafe?: boolean,
+  limits?: ParseLimits,
+): DuperValue | any {
+  const parsed = duperNapi.parse(value, true, limits);
   const transformed = fromNapi(parsed as NapiValue);
   if (jsonSafe) {
     return transformed.toJSON();
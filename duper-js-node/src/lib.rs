@@ -31,11 +31,38 @@ pub enum DuperError {
     InvalidTemporal(#[from] DuperTemporalTryFromError<'static>),
 }
 
+#[napi(object)]
+pub struct ParseLimits {
+    pub max_depth: Option<u32>,
+    pub max_string_length: Option<u32>,
+    pub max_elements: Option<u32>,
+    pub max_document_size: Option<u32>,
+}
+
+impl From<ParseLimits> for duper::ParseLimits {
+    fn from(limits: ParseLimits) -> Self {
+        let limit = |limit: Option<u32>| limit.map(|limit| limit as usize);
+        duper::ParseLimits::with_defaults(
+            limit(limits.max_depth),
+            limit(limits.max_string_length),
+            limit(limits.max_elements),
+            limit(limits.max_document_size),
+        )
+    }
+}
+
 #[napi]
-pub fn parse(env: &Env, input: String, parse_any: bool) -> anyhow::Result<Object<'_>> {
-    let value = match parse_any {
-        true => DuperParser::parse_duper_value(&input),
-        false => DuperParser::parse_duper_trunk(&input),
+pub fn parse(
+    env: &Env,
+    input: String,
+    parse_any: bool,
+    limits: Option<ParseLimits>,
+) -> anyhow::Result<Object<'_>> {
+    let value = match (parse_any, limits) {
+        (true, Some(limits)) => DuperParser::parse_duper_value_with_limits(&input, &limits.into()),
+        (false, Some(limits)) => DuperParser::parse_duper_trunk_with_limits(&input, &limits.into()),
+        (true, None) => DuperParser::parse_duper_value(&input),
+        (false, None) => DuperParser::parse_duper_trunk(&input),
     }
     .map_err(|err| {
         DuperError::Parse(
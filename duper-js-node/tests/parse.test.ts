@@ -49,4 +49,16 @@ describe("parse", () => {
     expect(() => parse(`Instant('2025-11-16')`)).toThrow();
     expect(() => parse(`Iñvalid({})`)).toThrow();
   });
+
+  it("enforces parse limits", () => {
+    expect(parse(`[[1, 2, 3]]`, false, { maxDepth: 2 }).type).toEqual("Array");
+    expect(() => parse(`[[[1]]]`, false, { maxDepth: 2 })).toThrow(
+      /nesting depth exceeds limit of 2/,
+    );
+    expect(() => parse(`[1, 2, 3]`, false, { maxElements: 2 })).toThrow(
+      /collection exceeds limit of 2 elements/,
+    );
+    expect(() => parse(`"Duper!"`, false, { maxStringLength: 5 })).toThrow();
+    expect(parse(`[[[1]]]`).type).toEqual("Array");
+  });
 });
@@ -6,6 +6,8 @@ that custom Pydantic ``BaseModel``s are returned.."""
 from ._duper import (
     Duper,
     DuperType,
+    ParseLimitError,
+    ParseLimits,
     TemporalString,
     dump,
     dumps,
@@ -18,6 +20,8 @@ __all__ = [
     "BaseModel",
     "Duper",
     "DuperType",
+    "ParseLimitError",
+    "ParseLimits",
     "TemporalString",
     "dump",
     "dumps",
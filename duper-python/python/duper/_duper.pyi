@@ -1,11 +1,13 @@
 from io import TextIOBase
-from typing import Any, Literal, TypeAlias, overload
+from typing import Any, Literal, TypeAlias, TypeVar, overload
 
 from pydantic import BaseModel
 
 __all__ = [
     "Duper",
     "DuperType",
+    "ParseLimitError",
+    "ParseLimits",
     "TemporalString",
     "dump",
     "dumps",
@@ -36,6 +38,43 @@ class Duper:
     @property
     def identifier(self) -> str | None: ...
 
+class ParseLimitError(ValueError):
+    """Raised when the input exceeds the given parse limits."""
+
+class ParseLimits:
+    """Limits for parsing untrusted Duper input. Unspecified limits take
+    their default values.
+
+    >>> from duper import ParseLimits, loads
+    >>> loads("[[[1]]]", limits=ParseLimits(max_depth=2))
+    Traceback (most recent call last):
+      ...
+    ParseLimitError: ...nesting depth exceeds limit of 2...
+    """
+
+    def __init__(
+        self,
+        *,
+        max_depth: int | None = None,
+        max_string_length: int | None = None,
+        max_elements: int | None = None,
+        max_document_size: int | None = None,
+    ) -> None: ...
+    @property
+    def max_depth(self) -> int:
+        """The maximum nesting of objects, arrays, and tuples. Defaults to 128."""
+    @property
+    def max_string_length(self) -> int:
+        """The maximum length of a string or bytes literal, in bytes as written
+        in the input. Defaults to 1 MiB."""
+    @property
+    def max_elements(self) -> int:
+        """The maximum number of elements in a single object, array, or
+        tuple. Defaults to 65,536."""
+    @property
+    def max_document_size(self) -> int:
+        """The maximum size of the input, in bytes. Defaults to 2 MiB."""
+
 class TemporalString:
     """A string representing a valid Temporal value. An optional type may be
     provided during initialization to enforce strict parsing."""
@@ -86,6 +125,12 @@ DuperType: TypeAlias = (
 )
 """All possible Python return types for Duper values."""
 
+DuperIdentifiers: TypeAlias = dict[tuple[str | int, ...], str]
+"""Identifiers of plain Duper values, by the path of object keys and array or
+tuple indices leading to each value."""
+
+T = TypeVar("T")
+
 def dumps(
     obj: Any,  # pyright: ignore[reportExplicitAny, reportAny]
     *,
@@ -130,27 +175,125 @@ def dump(
 
 @overload
 def loads(
-    s: str, *, parse_any: Literal[False] = False
+    s: str,
+    *,
+    parse_any: Literal[False] = False,
+    limits: ParseLimits | None = None,
+    type: None = None,
+    plain: Literal[False] = False,
 ) -> BaseModel | dict[str, DuperType] | list[DuperType] | tuple[DuperType, ...]: ...
 @overload
-def loads(s: str, *, parse_any: Literal[True]) -> BaseModel | DuperType: ...
-def loads(s: str, *, parse_any: bool = False) -> BaseModel | DuperType:
+def loads(
+    s: str,
+    *,
+    parse_any: Literal[True],
+    limits: ParseLimits | None = None,
+    type: None = None,
+    plain: Literal[False] = False,
+) -> BaseModel | DuperType: ...
+@overload
+def loads(
+    s: str,
+    *,
+    parse_any: bool = False,
+    limits: ParseLimits | None = None,
+    type: type[T],
+    plain: Literal[False] = False,
+) -> T: ...
+@overload
+def loads(
+    s: str,
+    *,
+    parse_any: bool = False,
+    limits: ParseLimits | None = None,
+    type: None = None,
+    plain: Literal[True],
+) -> tuple[DuperType, DuperIdentifiers]: ...
+def loads(
+    s: str,
+    *,
+    parse_any: bool = False,
+    limits: ParseLimits | None = None,
+    type: type[T] | None = None,
+    plain: bool = False,
+) -> BaseModel | DuperType | T | tuple[DuperType, DuperIdentifiers]:
     """Deserialize ``s`` (a ``str`` instance containing a Duper object or
     array) to a Pydantic model.
 
     If ``parse_any`` is ``True``, then this function will also deserialize
     types other than objects, arrays, and tuples.
+
+    If ``limits`` is specified, input exceeding them raises a
+    ``ParseLimitError``.
+
+    If ``type`` is specified, the value is validated directly into it, such
+    as a Pydantic model, a dataclass, or a ``TypedDict``. Validation errors
+    raise a Pydantic ``ValidationError``.
+
+    If ``plain`` is ``True``, then this function returns a tuple of the
+    value, with objects as ``dict``, and a ``dict`` mapping the path of
+    each identified value to its identifier. This is incompatible with the
+    ``type`` option.
     """
 
 @overload
 def load(
-    fp: TextIOBase, *, parse_any: Literal[False] = False
+    fp: TextIOBase,
+    *,
+    parse_any: Literal[False] = False,
+    limits: ParseLimits | None = None,
+    type: None = None,
+    plain: Literal[False] = False,
 ) -> BaseModel | dict[str, DuperType] | list[DuperType] | tuple[DuperType, ...]: ...
 @overload
-def load(fp: TextIOBase, *, parse_any: Literal[True]) -> BaseModel | DuperType: ...
-def load(fp: TextIOBase, *, parse_any: bool = False) -> BaseModel | DuperType:
+def load(
+    fp: TextIOBase,
+    *,
+    parse_any: Literal[True],
+    limits: ParseLimits | None = None,
+    type: None = None,
+    plain: Literal[False] = False,
+) -> BaseModel | DuperType: ...
+@overload
+def load(
+    fp: TextIOBase,
+    *,
+    parse_any: bool = False,
+    limits: ParseLimits | None = None,
+    type: type[T],
+    plain: Literal[False] = False,
+) -> T: ...
+@overload
+def load(
+    fp: TextIOBase,
+    *,
+    parse_any: bool = False,
+    limits: ParseLimits | None = None,
+    type: None = None,
+    plain: Literal[True],
+) -> tuple[DuperType, DuperIdentifiers]: ...
+def load(
+    fp: TextIOBase,
+    *,
+    parse_any: bool = False,
+    limits: ParseLimits | None = None,
+    type: type[T] | None = None,
+    plain: bool = False,
+) -> BaseModel | DuperType | T | tuple[DuperType, DuperIdentifiers]:
     """Deserialize ``fp`` (a ``.read()``-supporting file-like object
     containing a Duper object or array) to a Pydantic model.
 
     If ``parse_any`` is ``True``, then this function will also deserialize
-    types other than objects, arrays, and tuples."""
+    types other than objects, arrays, and tuples.
+
+    If ``limits`` is specified, input exceeding them raises a
+    ``ParseLimitError``.
+
+    If ``type`` is specified, the value is validated directly into it, such
+    as a Pydantic model, a dataclass, or a ``TypedDict``. Validation errors
+    raise a Pydantic ``ValidationError``.
+
+    If ``plain`` is ``True``, then this function returns a tuple of the
+    value, with objects as ``dict``, and a ``dict`` mapping the path of
+    each identified value to its identifier. This is incompatible with the
+    ``type`` option."""
@@ -10,7 +10,7 @@ from starlette.background import BackgroundTask
 from starlette.responses import Response
 from typing_extensions import override
 
-from ._duper import dumps, loads
+from ._duper import ParseLimitError, ParseLimits, dumps, loads
 
 __all__ = [
     "DuperBody",
@@ -65,10 +65,15 @@ class DuperResponse(Response):
         ).encode("utf-8")
 
 
-def DuperBody(model_type: type[T]) -> Any:  # pyright: ignore[reportExplicitAny, reportAny]
+def DuperBody(
+    model_type: type[T], *, limits: ParseLimits | None = None
+) -> Any:  # pyright: ignore[reportExplicitAny, reportAny]
     """
     A dependency providing automatic parsing of an HTTP request containing a Duper value.
 
+    The body is checked against ``limits``, or the default ``ParseLimits`` if
+    unspecified, and rejected with a 413 status if it exceeds them.
+
     >>> from typing import Any
     >>> import FastAPI
     >>> from duper.fastapi import DuperBody
@@ -80,6 +85,8 @@ def DuperBody(model_type: type[T]) -> Any:  # pyright: ignore[reportExplicitAny,
     ...     print(body)
     """
 
+    parse_limits = limits if limits is not None else ParseLimits()
+
     async def _get_duper_body(request: Request) -> T:
         content_type = request.headers.get("Content-Type")
         if not content_type or content_type.split(";", 1)[0] not in (
@@ -93,12 +100,14 @@ def DuperBody(model_type: type[T]) -> Any:  # pyright: ignore[reportExplicitAny,
             )
 
         body = await request.body()
-        parsed = loads(body.decode(encoding="utf-8"), parse_any=True)
-        if isinstance(parsed, PydanticBaseModel):
-            dumped = parsed.model_dump(mode="python")
-        else:
-            adapter = TypeAdapter(type(parsed))
-            dumped = adapter.dump_python(parsed)  # pyright: ignore[reportAny]
+        text = body.decode(encoding="utf-8")
+        try:
+            dumped, _ = loads(text, parse_any=True, limits=parse_limits, plain=True)
+        except ParseLimitError as error:
+            raise HTTPException(
+                status_code=status.HTTP_413_CONTENT_TOO_LARGE,
+                detail="Duper input exceeds the parse limits",
+            ) from error
 
         if inspect.isclass(model_type) and issubclass(model_type, PydanticBaseModel):
             return model_type.model_validate(dumped)
@@ -1,5 +1,6 @@
 import sys
 from collections.abc import Callable
+from functools import lru_cache
 from typing import Any, TypeVar, cast
 
 from pydantic import (
@@ -9,7 +10,7 @@ from pydantic import (
     ConfigDict,
     SerializationInfo,
     SerializerFunctionWrapHandler,
-    ValidationError,
+    TypeAdapter,
     model_serializer,
 )
 from pydantic import (
@@ -130,16 +131,9 @@ class BaseModel(PydanticBaseModel):
         if type(serialized) is str:
             from ._duper import loads
 
-            loaded = loads(serialized, parse_any=False)
-            print(loaded)
-            if isinstance(loaded, list):
-                raise ValidationError("cannot validate Duper list")
-            if isinstance(loaded, tuple):
-                raise ValidationError("cannot validate Duper tuple")
+            loaded, _ = loads(serialized, parse_any=False, plain=True)
             return cls.model_validate(
-                loaded
-                if isinstance(loaded, dict)
-                else loaded.model_dump(mode="python"),
+                loaded,
                 strict=strict,
                 extra=extra,
                 from_attributes=from_attributes,
@@ -160,6 +154,28 @@ class BaseModel(PydanticBaseModel):
 
 
 ModelT = TypeVar("ModelT", bound=BaseModel)
+T = TypeVar("T")
+
+
+@lru_cache(maxsize=256)
+def _type_adapter(type_: Any) -> TypeAdapter[Any]:  # pyright: ignore[reportExplicitAny]
+    return TypeAdapter(type_)  # pyright: ignore[reportAny]
+
+
+def validate_python(type_: type[T], value: object) -> T:
+    """Validates a plain Duper value into ``type_``, such as a Pydantic model,
+    a dataclass, or a ``TypedDict``.
+
+    Type adapters are cached, so that repeated loads of the same type don't
+    rebuild its schema."""
+    if isinstance(type_, type) and issubclass(type_, PydanticBaseModel):
+        return type_.model_validate(value)
+    try:
+        adapter = _type_adapter(type_)
+    except TypeError:
+        # Unhashable types, such as some parametrized generics
+        adapter = TypeAdapter(type_)
+    return adapter.validate_python(value)  # pyright: ignore[reportAny]
 
 
 def create_model(
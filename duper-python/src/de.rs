@@ -6,6 +6,13 @@ use crate::{Duper, temporal::TemporalString};
 #[derive(Clone)]
 pub(crate) struct Visitor<'py> {
     pub(crate) py: Python<'py>,
+    /// Whether to return plain values instead of Pydantic models and
+    /// annotations.
+    plain: bool,
+    /// In plain mode, the identifiers of the values by their path, if they're
+    /// being recorded.
+    pub(crate) identifiers: Option<Bound<'py, PyDict>>,
+    path: Vec<Bound<'py, PyAny>>,
 }
 
 pub(crate) struct VisitorValue<'py> {
@@ -13,6 +20,78 @@ pub(crate) struct VisitorValue<'py> {
     pub(crate) duper: Option<Bound<'py, Duper>>,
 }
 
+impl<'py> Visitor<'py> {
+    pub(crate) fn new(py: Python<'py>) -> Self {
+        Self {
+            py,
+            plain: false,
+            identifiers: None,
+            path: vec![],
+        }
+    }
+
+    /// Creates a visitor that returns plain dicts instead of Pydantic models, and
+    /// `datetime` values instead of temporal strings where possible.
+    pub(crate) fn plain(py: Python<'py>) -> Self {
+        Self {
+            py,
+            plain: true,
+            identifiers: None,
+            path: vec![],
+        }
+    }
+
+    /// Records the identifiers of the values by their path in plain mode.
+    pub(crate) fn with_identifiers(self) -> Self {
+        Self {
+            identifiers: Some(PyDict::new(self.py)),
+            ..self
+        }
+    }
+
+    /// Returns the annotation for the identifier or, in plain mode, records it
+    /// by the path of the current value.
+    fn annotation(
+        &self,
+        identifier: Option<&duper::DuperIdentifier<'_>>,
+    ) -> PyResult<Option<Bound<'py, Duper>>> {
+        let Some(identifier) = identifier else {
+            return Ok(None);
+        };
+        if !self.plain {
+            return Ok(Some(
+                Duper::from_identifier(identifier)?.into_pyobject(self.py)?,
+            ));
+        }
+        if let Some(identifiers) = &self.identifiers {
+            identifiers.set_item(PyTuple::new(self.py, &self.path)?, identifier.as_ref())?;
+        }
+        Ok(None)
+    }
+
+    fn visit_elements<'a>(
+        &mut self,
+        elements: &[duper::DuperValue<'a>],
+    ) -> PyResult<Vec<Bound<'py, PyAny>>> {
+        if self.identifiers.is_none() {
+            return elements
+                .iter()
+                .map(|value| Ok(value.accept(self)?.value))
+                .collect();
+        }
+        elements
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                self.path.push(PyInt::new(self.py, i).into_any());
+                let value = value.accept(self);
+                self.path.pop();
+                Ok(value?.value)
+            })
+            .collect()
+    }
+}
+
 impl<'py> DuperVisitor for Visitor<'py> {
     type Value = PyResult<VisitorValue<'py>>;
 
@@ -21,6 +100,20 @@ impl<'py> DuperVisitor for Visitor<'py> {
         identifier: Option<&duper::DuperIdentifier<'a>>,
         object: &duper::DuperObject<'a>,
     ) -> Self::Value {
+        if self.plain {
+            let dict = PyDict::new(self.py);
+            for (key, value) in object.iter() {
+                self.path
+                    .push(PyString::new(self.py, key.as_ref()).into_any());
+                let value = value.accept(self);
+                let key = self.path.pop().expect("key was pushed");
+                dict.set_item(key, value?.value)?;
+            }
+            return Ok(VisitorValue {
+                value: dict.into_any(),
+                duper: self.annotation(identifier)?,
+            });
+        }
         let seq = object
             .iter()
             .map(|(key, value)| {
@@ -69,15 +162,10 @@ impl<'py> DuperVisitor for Visitor<'py> {
         identifier: Option<&duper::DuperIdentifier<'a>>,
         array: &[duper::DuperValue<'a>],
     ) -> Self::Value {
-        let vec: PyResult<Vec<_>> = array
-            .iter()
-            .map(|value| Ok(value.accept(self)?.value))
-            .collect();
+        let vec = self.visit_elements(array);
         Ok(VisitorValue {
             value: PyList::new(self.py, vec?).map(|value| value.into_any())?,
-            duper: identifier
-                .map(|identifier| Duper::from_identifier(identifier)?.into_pyobject(self.py))
-                .transpose()?,
+            duper: self.annotation(identifier)?,
         })
     }
 
@@ -86,15 +174,10 @@ impl<'py> DuperVisitor for Visitor<'py> {
         identifier: Option<&duper::DuperIdentifier<'a>>,
         tuple: &[duper::DuperValue<'a>],
     ) -> Self::Value {
-        let vec: PyResult<Vec<_>> = tuple
-            .iter()
-            .map(|value| Ok(value.accept(self)?.value))
-            .collect();
+        let vec = self.visit_elements(tuple);
         Ok(VisitorValue {
             value: PyTuple::new(self.py, vec?).map(|value| value.into_any())?,
-            duper: identifier
-                .map(|identifier| Duper::from_identifier(identifier)?.into_pyobject(self.py))
-                .transpose()?,
+            duper: self.annotation(identifier)?,
         })
     }
 
@@ -105,9 +188,7 @@ impl<'py> DuperVisitor for Visitor<'py> {
     ) -> Self::Value {
         Ok(VisitorValue {
             value: PyString::new(self.py, string).into_any(),
-            duper: identifier
-                .map(|identifier| Duper::from_identifier(identifier)?.into_pyobject(self.py))
-                .transpose()?,
+            duper: self.annotation(identifier)?,
         })
     }
 
@@ -118,19 +199,19 @@ impl<'py> DuperVisitor for Visitor<'py> {
     ) -> Self::Value {
         Ok(VisitorValue {
             value: PyBytes::new(self.py, bytes).into_any(),
-            duper: identifier
-                .map(|identifier| Duper::from_identifier(identifier)?.into_pyobject(self.py))
-                .transpose()?,
+            duper: self.annotation(identifier)?,
         })
     }
 
     fn visit_temporal<'a>(&mut self, temporal: &DuperTemporal<'a>) -> Self::Value {
+        let value = TemporalString::from_temporal(temporal)?;
         Ok(VisitorValue {
-            value: TemporalString::from_temporal(temporal)?.into_bound_py_any(self.py)?,
-            duper: temporal
-                .identifier()
-                .map(|identifier| Duper::from_identifier(&identifier)?.into_pyobject(self.py))
-                .transpose()?,
+            value: if self.plain {
+                value.to_python(self.py)?
+            } else {
+                value.into_bound_py_any(self.py)?
+            },
+            duper: self.annotation(temporal.identifier().as_ref())?,
         })
     }
 
@@ -141,9 +222,7 @@ impl<'py> DuperVisitor for Visitor<'py> {
     ) -> Self::Value {
         Ok(VisitorValue {
             value: PyInt::new(self.py, integer).into_any(),
-            duper: identifier
-                .map(|identifier| Duper::from_identifier(identifier)?.into_pyobject(self.py))
-                .transpose()?,
+            duper: self.annotation(identifier)?,
         })
     }
 
@@ -154,9 +233,7 @@ impl<'py> DuperVisitor for Visitor<'py> {
     ) -> Self::Value {
         Ok(VisitorValue {
             value: PyFloat::new(self.py, float.into_inner()).into_any(),
-            duper: identifier
-                .map(|identifier| Duper::from_identifier(identifier)?.into_pyobject(self.py))
-                .transpose()?,
+            duper: self.annotation(identifier)?,
         })
     }
 
@@ -167,18 +244,14 @@ impl<'py> DuperVisitor for Visitor<'py> {
     ) -> Self::Value {
         Ok(VisitorValue {
             value: PyBool::new(self.py, boolean).to_owned().into_any(),
-            duper: identifier
-                .map(|identifier| Duper::from_identifier(identifier)?.into_pyobject(self.py))
-                .transpose()?,
+            duper: self.annotation(identifier)?,
         })
     }
 
     fn visit_null<'a>(&mut self, identifier: Option<&duper::DuperIdentifier<'a>>) -> Self::Value {
         Ok(VisitorValue {
             value: self.py.None().into_bound(self.py).into_any(),
-            duper: identifier
-                .map(|identifier| Duper::from_identifier(identifier)?.into_pyobject(self.py))
-                .transpose()?,
+            duper: self.annotation(identifier)?,
         })
     }
 }
@@ -1,4 +1,4 @@
-use pyo3::{exceptions::PyValueError, prelude::*};
+use pyo3::{create_exception, exceptions::PyValueError, prelude::*};
 
 mod de;
 mod ser;
@@ -53,17 +53,82 @@ impl Duper {
     }
 }
 
+create_exception!(
+    duper,
+    ParseLimitError,
+    PyValueError,
+    "Raised when the input exceeds the given parse limits."
+);
+
+#[pyclass(frozen, module = "duper", name = "ParseLimits")]
+#[derive(Debug, Clone)]
+pub(crate) struct PyParseLimits(pub(crate) duper::ParseLimits);
+
+#[pymethods]
+impl PyParseLimits {
+    #[new]
+    #[pyo3(signature = (*, max_depth=None, max_string_length=None, max_elements=None, max_document_size=None))]
+    fn new(
+        max_depth: Option<usize>,
+        max_string_length: Option<usize>,
+        max_elements: Option<usize>,
+        max_document_size: Option<usize>,
+    ) -> Self {
+        Self(duper::ParseLimits::with_defaults(
+            max_depth,
+            max_string_length,
+            max_elements,
+            max_document_size,
+        ))
+    }
+
+    #[getter]
+    fn max_depth(&self) -> usize {
+        self.0.max_depth
+    }
+
+    #[getter]
+    fn max_string_length(&self) -> usize {
+        self.0.max_string_length
+    }
+
+    #[getter]
+    fn max_elements(&self) -> usize {
+        self.0.max_elements
+    }
+
+    #[getter]
+    fn max_document_size(&self) -> usize {
+        self.0.max_document_size
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ParseLimits(max_depth={}, max_string_length={}, max_elements={}, max_document_size={})",
+            self.0.max_depth,
+            self.0.max_string_length,
+            self.0.max_elements,
+            self.0.max_document_size,
+        )
+    }
+
+    fn __str__(&self) -> String {
+        self.__repr__()
+    }
+}
+
 #[pyo3::pymodule(name = "_duper")]
 mod duper_py {
     use duper::{DuperParser, DuperValue, PrettyPrinter, Serializer};
     use pyo3::{
+        IntoPyObjectExt,
         exceptions::PyValueError,
         prelude::*,
         types::{PyInt, PyString},
     };
 
     #[pymodule_export]
-    use crate::{Duper, DuperType, temporal::TemporalString};
+    use crate::{Duper, DuperType, ParseLimitError, PyParseLimits, temporal::TemporalString};
     use crate::{de::Visitor, ser::serialize_pyany};
 
     #[pyfunction]
@@ -145,43 +210,83 @@ mod duper_py {
         Ok(())
     }
 
-    #[pyfunction]
-    #[pyo3(signature = (s, *, parse_any=false))]
-    fn loads<'py>(py: Python<'py>, s: &str, parse_any: bool) -> PyResult<Bound<'py, PyAny>> {
-        let value = match parse_any {
-            true => DuperParser::parse_duper_value(s),
-            false => DuperParser::parse_duper_trunk(s),
+    fn parse<'a>(
+        s: &'a str,
+        parse_any: bool,
+        limits: Option<PyRef<'_, PyParseLimits>>,
+    ) -> PyResult<DuperValue<'a>> {
+        let prettify = |err: Vec<_>| {
+            DuperParser::prettify_error(s, &err, None).unwrap_or_else(|_| format!("{err:?}"))
+        };
+        match (parse_any, limits) {
+            (true, Some(limits)) => DuperParser::parse_duper_value_with_limits(s, &limits.0),
+            (false, Some(limits)) => DuperParser::parse_duper_trunk_with_limits(s, &limits.0),
+            (true, None) => DuperParser::parse_duper_value(s),
+            (false, None) => DuperParser::parse_duper_trunk(s),
         }
         .map_err(|err| {
-            PyErr::new::<PyValueError, String>(
-                DuperParser::prettify_error(s, &err, None).unwrap_or_else(|_| format!("{err:?}")),
-            )
-        })?;
-        value
-            .accept(&mut Visitor { py })
-            .map(|visitor_value| visitor_value.value)
+            if err.iter().any(duper::ParseLimits::is_limit_error) {
+                ParseLimitError::new_err(prettify(err))
+            } else {
+                PyErr::new::<PyValueError, String>(prettify(err))
+            }
+        })
+    }
+
+    /// Converts a parsed value into Python objects, either as Pydantic models,
+    /// validated into the given type, or as plain values and their identifiers.
+    fn decode<'py>(
+        py: Python<'py>,
+        value: DuperValue<'_>,
+        r#type: Option<Bound<'py, PyAny>>,
+        plain: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        match (r#type, plain) {
+            (Some(_), true) => Err(PyValueError::new_err(
+                "cannot load with both type and plain options",
+            )),
+            (Some(r#type), false) => {
+                let value = value.accept(&mut Visitor::plain(py))?.value;
+                py.import("duper.pydantic")?
+                    .getattr("validate_python")?
+                    .call1((r#type, value))
+            }
+            (None, true) => {
+                let mut visitor = Visitor::plain(py).with_identifiers();
+                let value = value.accept(&mut visitor)?.value;
+                (value, visitor.identifiers).into_bound_py_any(py)
+            }
+            (None, false) => value
+                .accept(&mut Visitor::new(py))
+                .map(|visitor_value| visitor_value.value),
+        }
     }
 
     #[pyfunction]
-    #[pyo3(signature = (fp, *, parse_any=false))]
+    #[pyo3(signature = (s, *, parse_any=false, limits=None, r#type=None, plain=false))]
+    fn loads<'py>(
+        py: Python<'py>,
+        s: &str,
+        parse_any: bool,
+        limits: Option<PyRef<'py, PyParseLimits>>,
+        r#type: Option<Bound<'py, PyAny>>,
+        plain: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        decode(py, parse(s, parse_any, limits)?, r#type, plain)
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (fp, *, parse_any=false, limits=None, r#type=None, plain=false))]
     fn load<'py>(
         py: Python<'py>,
         fp: Bound<'py, PyAny>,
         parse_any: bool,
+        limits: Option<PyRef<'py, PyParseLimits>>,
+        r#type: Option<Bound<'py, PyAny>>,
+        plain: bool,
     ) -> PyResult<Bound<'py, PyAny>> {
         let read = fp.call_method0("read")?;
         let s: &str = read.extract()?;
-        let value = match parse_any {
-            true => DuperParser::parse_duper_value(s),
-            false => DuperParser::parse_duper_trunk(s),
-        }
-        .map_err(|err| {
-            PyErr::new::<PyValueError, String>(
-                DuperParser::prettify_error(s, &err, None).unwrap_or_else(|_| format!("{err:?}")),
-            )
-        })?;
-        value
-            .accept(&mut Visitor { py })
-            .map(|visitor_value| visitor_value.value)
+        decode(py, parse(s, parse_any, limits)?, r#type, plain)
     }
 }
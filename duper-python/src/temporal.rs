@@ -4,11 +4,15 @@ use pyo3::{
     IntoPyObjectExt,
     exceptions::PyValueError,
     prelude::*,
+    sync::PyOnceLock,
     types::{PyCFunction, PyDict, PyTuple, PyType},
 };
 
 use crate::ser::serialize_pyany;
 
+/// The `TypeAdapter(timedelta)` used to parse durations, built on first use.
+static TIMEDELTA_ADAPTER: PyOnceLock<Py<PyAny>> = PyOnceLock::new();
+
 #[pyclass(frozen, module = "duper", eq)]
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct TemporalString {
@@ -21,6 +25,55 @@ impl TemporalString {
             temporal: temporal.static_clone(),
         })
     }
+
+    /// Converts the value into the equivalent `datetime` type, if there is one
+    /// and it can represent the value. Otherwise, the value is returned as is.
+    pub(crate) fn to_python<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let converted = match &self.temporal {
+            duper::DuperTemporal::Instant { inner } => {
+                // `fromisoformat` only accepts a trailing `Z` since Python 3.11
+                let inner = match inner.as_ref().strip_suffix(['Z', 'z']) {
+                    Some(inner) => Cow::Owned(format!("{inner}+00:00")),
+                    None => Cow::Borrowed(inner.as_ref()),
+                };
+                let datetime = py.import("datetime")?.getattr("datetime")?;
+                datetime.getattr("fromisoformat")?.call1((inner.as_ref(),))
+            }
+            duper::DuperTemporal::PlainDateTime { inner } => {
+                let datetime = py.import("datetime")?.getattr("datetime")?;
+                datetime.getattr("fromisoformat")?.call1((inner.as_ref(),))
+            }
+            duper::DuperTemporal::PlainDate { inner } => {
+                let date = py.import("datetime")?.getattr("date")?;
+                date.getattr("fromisoformat")?.call1((inner.as_ref(),))
+            }
+            duper::DuperTemporal::PlainTime { inner } => {
+                let time = py.import("datetime")?.getattr("time")?;
+                time.getattr("fromisoformat")?.call1((inner.as_ref(),))
+            }
+            duper::DuperTemporal::Duration { inner } => {
+                let adapter = TIMEDELTA_ADAPTER.get_or_try_init(py, || {
+                    let timedelta = py.import("datetime")?.getattr("timedelta")?;
+                    py.import("pydantic")?
+                        .getattr("TypeAdapter")?
+                        .call1((timedelta,))
+                        .map(Bound::unbind)
+                })?;
+                adapter
+                    .bind(py)
+                    .getattr("validate_python")?
+                    .call1((inner.as_ref(),))
+            }
+            _ => return self.clone().into_bound_py_any(py),
+        };
+        match converted {
+            // e.g. values with a calendar annotation, or out of range
+            Err(err) if err.is_instance_of::<PyValueError>(py) => {
+                self.clone().into_bound_py_any(py)
+            }
+            converted => converted,
+        }
+    }
 }
 
 #[pymethods]
@@ -166,48 +219,7 @@ impl TemporalString {
             |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<_> {
                 let py = args.py();
                 let value = args.extract::<(TemporalString,)>()?.0;
-                match value.temporal {
-                    duper::DuperTemporal::Instant { inner } => {
-                        let datetime = py.import("datetime")?.getattr("datetime")?;
-                        Ok(datetime
-                            .getattr("fromisoformat")?
-                            .call1((inner.as_ref(),))?
-                            .unbind())
-                    }
-                    duper::DuperTemporal::PlainDateTime { inner } => {
-                        let datetime = py.import("datetime")?.getattr("datetime")?;
-                        Ok(datetime
-                            .getattr("fromisoformat")?
-                            .call1((inner.as_ref(),))?
-                            .unbind())
-                    }
-                    duper::DuperTemporal::PlainDate { inner } => {
-                        let date = py.import("datetime")?.getattr("date")?;
-                        Ok(date
-                            .getattr("fromisoformat")?
-                            .call1((inner.as_ref(),))?
-                            .unbind())
-                    }
-                    duper::DuperTemporal::PlainTime { inner } => {
-                        let time = py.import("datetime")?.getattr("time")?;
-                        Ok(time
-                            .getattr("fromisoformat")?
-                            .call1((inner.as_ref(),))?
-                            .unbind())
-                    }
-                    duper::DuperTemporal::Duration { inner } => {
-                        let timedelta = py.import("datetime")?.getattr("timedelta")?;
-                        let adapter = py
-                            .import("pydantic")?
-                            .getattr("TypeAdapter")?
-                            .call1((timedelta,))?;
-                        Ok(adapter
-                            .getattr("validate_python")?
-                            .call1((inner.as_ref(),))?
-                            .unbind())
-                    }
-                    _ => value.into_py_any(py),
-                }
+                Ok(value.to_python(py)?.unbind())
             };
         let serialize_fn = PyCFunction::new_closure(py, None, None, serialize)?;
 
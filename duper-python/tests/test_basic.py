@@ -1,5 +1,11 @@
+from dataclasses import dataclass
+from datetime import datetime, timedelta, timezone
+from uuid import UUID
+
 import duper
 import pytest
+from pydantic import ValidationError
+from typing_extensions import TypedDict
 
 DUPER_DATA = """
 APIResponse({
@@ -67,3 +73,104 @@ def test_complete():
         ValueError, match="cannot stringify with both indent and minify options"
     ):
         _ = duper.dumps(obj, indent=2, minify=True)
+
+
+def test_parse_limits():
+    limits = duper.ParseLimits(max_depth=2, max_elements=3)
+    assert limits.max_depth == 2
+    assert limits.max_string_length == 1 << 20
+
+    assert duper.loads("[[1, 2, 3]]", limits=limits) == [[1, 2, 3]]
+    with pytest.raises(duper.ParseLimitError, match="nesting depth exceeds limit of 2"):
+        _ = duper.loads("[[[1]]]", limits=limits)
+    with pytest.raises(ValueError, match="collection exceeds limit of 3 elements"):
+        _ = duper.loads("[1, 2, 3, 4]", limits=limits)
+
+    assert duper.loads("[[[1]]]") == [[[1]]]
+
+
+def test_temporal():
+    obj, _ = duper.loads(
+        "[Instant('2024-01-15T10:30:00Z'), Instant('2024-01-15T10:30:00z'),"
+        " Instant('2024-01-15T10:30:00+02:00'), Duration('P1DT2H'), Duration('PT5S')]",
+        plain=True,
+    )
+    assert obj == [
+        datetime(2024, 1, 15, 10, 30, tzinfo=timezone.utc),
+        datetime(2024, 1, 15, 10, 30, tzinfo=timezone.utc),
+        datetime(2024, 1, 15, 10, 30, tzinfo=timezone(timedelta(hours=2))),
+        timedelta(days=1, hours=2),
+        timedelta(seconds=5),
+    ]
+
+    # Values which `datetime` can't represent are kept as Temporal strings
+    obj, _ = duper.loads(
+        "[PlainDate('+010000-01-01'), Instant('+010000-01-01T00:00:00Z')]",
+        plain=True,
+    )
+    assert obj == [
+        duper.TemporalString("+010000-01-01", "PlainDate"),
+        duper.TemporalString("+010000-01-01T00:00:00Z", "Instant"),
+    ]
+
+
+def test_plain():
+    obj, identifiers = duper.loads(DUPER_DATA, plain=True)
+    assert type(obj) is dict
+    assert obj["headers"] == {
+        "content_type": "application/duper",
+        "cache_control": "max-age=3600",
+    }
+    assert obj["body"]["users"][0]["roles"] == ["admin", "user"]
+    assert obj["body"]["users"][0]["metadata"]["last_login"] == datetime(
+        2024, 1, 15, 10, 30, tzinfo=timezone.utc
+    )
+    assert identifiers == {
+        (): "APIResponse",
+        ("body", "users", 0): "User",
+        ("body", "users", 0, "id"): "Uuid",
+        ("body", "users", 0, "email"): "Email",
+        ("body", "users", 0, "metadata"): "Metadata",
+        ("body", "users", 0, "metadata", "last_login"): "Instant",
+        ("body", "users", 0, "metadata", "ip"): "IPV4",
+    }
+
+    assert duper.loads("(1, [2])", plain=True) == ((1, [2]), {})
+
+    with pytest.raises(ValueError, match="cannot load with both type and plain options"):
+        _ = duper.loads("{}", type=dict, plain=True)
+
+
+def test_typed():
+    class Metadata(TypedDict):
+        last_login: datetime
+        ip: str
+
+    @dataclass
+    class User:
+        id: UUID
+        name: str
+        email: str
+        roles: list[str]
+        metadata: Metadata
+
+    class Body(duper.BaseModel):
+        users: list[User]
+
+    class APIResponse(duper.BaseModel):
+        status: int
+        headers: dict[str, str]
+        body: Body
+
+    obj = duper.loads(DUPER_DATA, type=APIResponse)
+    assert isinstance(obj, APIResponse)
+    user = obj.body.users[0]
+    assert user.id == UUID("7039311b-02d2-4849-a6de-900d4dbe9acb")
+    assert user.metadata["last_login"] == datetime(
+        2024, 1, 15, 10, 30, tzinfo=timezone.utc
+    )
+
+    assert duper.loads("[1, 2]", type=tuple[int, ...]) == (1, 2)
+
+    with pytest.raises(ValidationError):
+        _ = duper.loads("{status: \"OK\"}", type=APIResponse)
@@ -141,6 +141,10 @@ export async function activate(context: ExtensionContext) {
     documentSelector: [{ language: "duper", pattern: DUPER_BLOB }],
     synchronize: {
       fileEvents: deleteWatcher,
+      configurationSection: CONFIG_KEY,
+    },
+    initializationOptions: {
+      schemas: workspace.getConfiguration(CONFIG_KEY).get("schemas"),
     },
     diagnosticCollectionName: NAME,
   };
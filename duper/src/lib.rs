@@ -54,7 +54,7 @@ pub use ast::{
     DuperTemporalPlainYearMonth, DuperTemporalTryFromError, DuperTemporalUnspecified,
     DuperTemporalZonedDateTime, DuperValue,
 };
-pub use parser::DuperParser;
+pub use parser::{DuperParser, ParseLimitKind, ParseLimits};
 #[cfg(feature = "ansi")]
 pub use visitor::ansi::Ansi;
 #[cfg(feature = "json-compat")]
@@ -0,0 +1,254 @@
+//! Resource limits for parsing untrusted Duper input.
+
+use chumsky::{
+    error::{Rich, RichReason},
+    input::{Checkpoint, Cursor},
+    inspector::Inspector,
+    span::SimpleSpan,
+};
+
+/// Limits enforced by [`DuperParser::parse_duper_value_with_limits`] and
+/// [`DuperParser::parse_duper_trunk_with_limits`].
+///
+/// The document size is checked before parsing, and the other limits are
+/// enforced by the parser as it goes, so a hostile document is rejected
+/// without exhausting the stack.
+///
+/// [`DuperParser::parse_duper_value_with_limits`]: crate::DuperParser::parse_duper_value_with_limits
+/// [`DuperParser::parse_duper_trunk_with_limits`]: crate::DuperParser::parse_duper_trunk_with_limits
+///
+/// ## Example
+///
+/// ```
+/// use duper::{DuperParser, ParseLimits};
+///
+/// let limits = ParseLimits {
+///     max_depth: 2,
+///     ..Default::default()
+/// };
+/// assert!(DuperParser::parse_duper_value_with_limits("[[1]]", &limits).is_ok());
+/// assert!(DuperParser::parse_duper_value_with_limits("[[[1]]]", &limits).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseLimits {
+    /// The maximum nesting of objects, arrays, and tuples. The parentheses of
+    /// identifiers don't count towards this limit.
+    pub max_depth: usize,
+    /// The maximum length of a string or bytes literal in bytes, once escape
+    /// sequences and Base64 are decoded. Object keys count as strings.
+    pub max_string_length: usize,
+    /// The maximum number of elements in a single object, array, or tuple.
+    pub max_elements: usize,
+    /// The maximum size of the whole input in bytes.
+    pub max_document_size: usize,
+}
+
+/// Which of the [`ParseLimits`] an input exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseLimitKind {
+    /// [`ParseLimits::max_document_size`]
+    DocumentSize,
+    /// [`ParseLimits::max_depth`]
+    Depth,
+    /// [`ParseLimits::max_elements`]
+    Elements,
+    /// [`ParseLimits::max_string_length`]
+    StringLength,
+}
+
+impl ParseLimitKind {
+    const ALL: [ParseLimitKind; 4] = [
+        ParseLimitKind::DocumentSize,
+        ParseLimitKind::Depth,
+        ParseLimitKind::Elements,
+        ParseLimitKind::StringLength,
+    ];
+
+    /// The text around the limit in the error message.
+    fn affixes(self) -> (&'static str, &'static str) {
+        match self {
+            ParseLimitKind::DocumentSize => ("document exceeds limit of ", " bytes"),
+            ParseLimitKind::Depth => ("nesting depth exceeds limit of ", ""),
+            ParseLimitKind::Elements => ("collection exceeds limit of ", " elements"),
+            ParseLimitKind::StringLength => ("string exceeds limit of ", " bytes"),
+        }
+    }
+
+    fn error<'a>(self, span: SimpleSpan, limit: usize) -> Rich<'a, char> {
+        let (prefix, suffix) = self.affixes();
+        Rich::custom(span, format!("{prefix}{limit}{suffix}"))
+    }
+}
+
+impl ParseLimits {
+    /// Limits with the given values, and those of [`ParseLimits::default`]
+    /// for the rest.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use duper::ParseLimits;
+    ///
+    /// let limits = ParseLimits::with_defaults(Some(2), None, None, None);
+    /// assert_eq!(limits.max_depth, 2);
+    /// assert_eq!(limits.max_elements, ParseLimits::default().max_elements);
+    /// ```
+    pub fn with_defaults(
+        max_depth: Option<usize>,
+        max_string_length: Option<usize>,
+        max_elements: Option<usize>,
+        max_document_size: Option<usize>,
+    ) -> Self {
+        let default = ParseLimits::default();
+        ParseLimits {
+            max_depth: max_depth.unwrap_or(default.max_depth),
+            max_string_length: max_string_length.unwrap_or(default.max_string_length),
+            max_elements: max_elements.unwrap_or(default.max_elements),
+            max_document_size: max_document_size.unwrap_or(default.max_document_size),
+        }
+    }
+
+    /// Limits which accept any input.
+    ///
+    /// Unlike the parser functions without limits, which still bound the
+    /// nesting depth to that of [`ParseLimits::default`], the nesting depth
+    /// isn't limited either, so deeply nested input can overflow the stack.
+    pub const fn unlimited() -> Self {
+        ParseLimits {
+            max_depth: usize::MAX,
+            max_string_length: usize::MAX,
+            max_elements: usize::MAX,
+            max_document_size: usize::MAX,
+        }
+    }
+
+    /// Check the size of the input against [`ParseLimits::max_document_size`].
+    ///
+    /// The other limits are only enforced while parsing.
+    pub fn check_document_size<'a>(&self, input: &'a str) -> Result<(), Vec<Rich<'a, char>>> {
+        if input.len() > self.max_document_size {
+            Err(vec![ParseLimitKind::DocumentSize.error(
+                SimpleSpan::from(self.max_document_size..input.len()),
+                self.max_document_size,
+            )])
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Which limit a parse error was caused by exceeding, or [`None`] if it
+    /// was caused by invalid syntax.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use duper::{DuperParser, ParseLimitKind, ParseLimits};
+    ///
+    /// let limits = ParseLimits::with_defaults(None, None, Some(2), None);
+    /// let errors = DuperParser::parse_duper_value_with_limits("[1, 2, 3]", &limits).unwrap_err();
+    /// assert_eq!(ParseLimits::limit_kind(&errors[0]), Some(ParseLimitKind::Elements));
+    /// let errors = DuperParser::parse_duper_value_with_limits("[1, 2,", &limits).unwrap_err();
+    /// assert_eq!(ParseLimits::limit_kind(&errors[0]), None);
+    /// ```
+    pub fn limit_kind(error: &Rich<'_, char>) -> Option<ParseLimitKind> {
+        let RichReason::Custom(message) = error.reason() else {
+            return None;
+        };
+        ParseLimitKind::ALL.into_iter().find(|kind| {
+            let (prefix, suffix) = kind.affixes();
+            message
+                .strip_prefix(prefix)
+                .and_then(|message| message.strip_suffix(suffix))
+                .is_some_and(|limit| limit.parse::<usize>().is_ok())
+        })
+    }
+
+    /// Whether a parse error was caused by exceeding one of these limits,
+    /// rather than by invalid syntax. See [`ParseLimits::limit_kind`].
+    pub fn is_limit_error(error: &Rich<'_, char>) -> bool {
+        ParseLimits::limit_kind(error).is_some()
+    }
+
+    pub(crate) fn depth_error<'a>(&self, span: SimpleSpan) -> Rich<'a, char> {
+        ParseLimitKind::Depth.error(span, self.max_depth)
+    }
+
+    pub(crate) fn elements_error<'a>(&self, span: SimpleSpan) -> Rich<'a, char> {
+        ParseLimitKind::Elements.error(span, self.max_elements)
+    }
+
+    pub(crate) fn string_error<'a>(&self, span: SimpleSpan) -> Rich<'a, char> {
+        ParseLimitKind::StringLength.error(span, self.max_string_length)
+    }
+}
+
+impl Default for ParseLimits {
+    /// Limits suitable for untrusted input: a depth of 128, strings up to
+    /// 1 MiB, up to 65,536 elements per collection, and documents up to 2 MiB.
+    fn default() -> Self {
+        ParseLimits {
+            max_depth: 128,
+            max_string_length: 1 << 20,
+            max_elements: 1 << 16,
+            max_document_size: 2 << 20,
+        }
+    }
+}
+
+/// The state threaded through the Duper parsers, which enforces
+/// [`ParseLimits`] while parsing.
+///
+/// The default state only bounds the nesting depth, like
+/// [`ParseLimits::default`], so that the parser functions without limits
+/// can't overflow the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseState {
+    pub(crate) limits: ParseLimits,
+    pub(crate) depth: usize,
+}
+
+impl ParseState {
+    /// Create a parser state which enforces the given limits.
+    pub const fn new(limits: ParseLimits) -> Self {
+        ParseState { limits, depth: 0 }
+    }
+
+    /// Enter an object, array, or tuple, whose opening delimiter is at `span`.
+    pub(crate) fn enter<'a>(&mut self, span: SimpleSpan) -> Result<(), Rich<'a, char>> {
+        if self.depth >= self.limits.max_depth {
+            Err(self.limits.depth_error(span))
+        } else {
+            self.depth += 1;
+            Ok(())
+        }
+    }
+
+    /// Leave the innermost object, array, or tuple.
+    pub(crate) fn leave(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+impl Default for ParseState {
+    fn default() -> Self {
+        ParseState::new(ParseLimits {
+            max_depth: ParseLimits::default().max_depth,
+            ..ParseLimits::unlimited()
+        })
+    }
+}
+
+impl<'a> Inspector<'a, &'a str> for ParseState {
+    type Checkpoint = usize;
+
+    fn on_token(&mut self, _: &char) {}
+
+    fn on_save<'parse>(&self, _: &Cursor<'a, 'parse, &'a str>) -> Self::Checkpoint {
+        self.depth
+    }
+
+    fn on_rewind<'parse>(&mut self, marker: &Checkpoint<'a, 'parse, &'a str, Self::Checkpoint>) {
+        self.depth = *marker.inspector();
+    }
+}
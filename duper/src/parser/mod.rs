@@ -3,22 +3,31 @@
 use std::borrow::Cow;
 
 use base64::Engine;
-use chumsky::prelude::*;
+use chumsky::{input::MapExtra, prelude::*};
 
+mod limits;
 pub(crate) mod temporal;
 
+pub use limits::{ParseLimitKind, ParseLimits, ParseState};
+
 use crate::{
     DuperFloat,
-    ast::{DuperIdentifier, DuperKey, DuperObject, DuperValue},
+    ast::{DuperIdentifier, DuperKey, DuperObject, DuperObjectTryFromError, DuperValue},
     escape::{unescape_bytes, unescape_str},
     parser::temporal::{temporal_specified, temporal_unspecified},
 };
 
+/// The parser extra of the Duper parsers, whose [`ParseState`] enforces
+/// [`ParseLimits`] while parsing.
+pub type Extra<'a> = extra::Full<Rich<'a, char>, ParseState, ()>;
+
 pub struct DuperParser;
 
 impl DuperParser {
     /// Parse a Duper trunk, i.e. only an array, tuple, or object at the top level.
     ///
+    /// Only the nesting depth is limited, to that of [`ParseLimits::default`].
+    ///
     /// A pretty-printed version of the error can be obtained from the
     /// [`DuperParser::prettify_error`] method.
     ///
@@ -42,6 +51,8 @@ impl DuperParser {
 
     /// Parse a Duper value at the top level.
     ///
+    /// Only the nesting depth is limited, to that of [`ParseLimits::default`].
+    ///
     /// A pretty-printed version of the error can be obtained from the
     /// [`DuperParser::prettify_error`] method.
     ///
@@ -58,6 +69,116 @@ impl DuperParser {
         duper_value().parse(input).into_result()
     }
 
+    /// Parse a Duper trunk like [`DuperParser::parse_duper_trunk`], recovering
+    /// from errors to report all of them at once.
+    ///
+    /// See [`DuperParser::parse_duper_value_recovering`] for how errors are
+    /// recovered from.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use duper::DuperParser;
+    ///
+    /// let (value, errors) = DuperParser::parse_duper_trunk_recovering("[1 2, 3]");
+    /// assert_eq!(errors.len(), 1);
+    /// assert!(value.is_some());
+    /// ```
+    pub fn parse_duper_trunk_recovering<'a>(
+        input: &'a str,
+    ) -> (Option<DuperValue<'a>>, Vec<Rich<'a, char>>) {
+        duper_trunk_recovering().parse(input).into_output_errors()
+    }
+
+    /// Parse a Duper value like [`DuperParser::parse_duper_value`], recovering
+    /// from errors to report all of them at once.
+    ///
+    /// The output is a best-effort value, which is only [`None`] if no value
+    /// could be found at all. Missing commas are assumed, unclosed objects,
+    /// arrays, and tuples are closed, entries with duplicate keys are dropped
+    /// in favor of the first one, and any other invalid value is replaced by
+    /// an `Error(null)` placeholder.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use duper::{DuperParser, DuperValue};
+    ///
+    /// let (value, errors) = DuperParser::parse_duper_value_recovering(r#"
+    ///     [1 2, "unknown escape \e", {a: 3, a: 4}]
+    /// "#);
+    /// assert_eq!(errors.len(), 3);
+    /// let Some(DuperValue::Array { inner, .. }) = value else {
+    ///     panic!("expected an array");
+    /// };
+    /// assert_eq!(inner.len(), 4);
+    /// assert!(matches!(
+    ///     &inner[2],
+    ///     DuperValue::Null { identifier: Some(identifier) } if identifier.as_ref() == "Error"
+    /// ));
+    /// ```
+    pub fn parse_duper_value_recovering<'a>(
+        input: &'a str,
+    ) -> (Option<DuperValue<'a>>, Vec<Rich<'a, char>>) {
+        duper_value_recovering().parse(input).into_output_errors()
+    }
+
+    /// Parse a Duper trunk like [`DuperParser::parse_duper_trunk`], rejecting
+    /// input which exceeds the given [`ParseLimits`].
+    ///
+    /// Use this method for untrusted input, since the parser without limits
+    /// only bounds the nesting depth.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use duper::{DuperParser, ParseLimits};
+    ///
+    /// let limits = ParseLimits {
+    ///     max_elements: 2,
+    ///     ..Default::default()
+    /// };
+    /// assert!(DuperParser::parse_duper_trunk_with_limits("[1, 2]", &limits).is_ok());
+    /// assert!(DuperParser::parse_duper_trunk_with_limits("[1, 2, 3]", &limits).is_err());
+    /// ```
+    pub fn parse_duper_trunk_with_limits<'a>(
+        input: &'a str,
+        limits: &ParseLimits,
+    ) -> Result<DuperValue<'a>, Vec<Rich<'a, char>>> {
+        limits.check_document_size(input)?;
+        duper_trunk()
+            .parse_with_state(input, &mut ParseState::new(*limits))
+            .into_result()
+    }
+
+    /// Parse a Duper value like [`DuperParser::parse_duper_value`], rejecting
+    /// input which exceeds the given [`ParseLimits`].
+    ///
+    /// Use this method for untrusted input, since the parser without limits
+    /// only bounds the nesting depth.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use duper::{DuperParser, ParseLimits};
+    ///
+    /// let limits = ParseLimits {
+    ///     max_string_length: 5,
+    ///     ..Default::default()
+    /// };
+    /// assert!(DuperParser::parse_duper_value_with_limits(r#""Duper""#, &limits).is_ok());
+    /// assert!(DuperParser::parse_duper_value_with_limits(r#""Duper!""#, &limits).is_err());
+    /// ```
+    pub fn parse_duper_value_with_limits<'a>(
+        input: &'a str,
+        limits: &ParseLimits,
+    ) -> Result<DuperValue<'a>, Vec<Rich<'a, char>>> {
+        limits.check_document_size(input)?;
+        duper_value()
+            .parse_with_state(input, &mut ParseState::new(*limits))
+            .into_result()
+    }
+
     /// Prettifies the errors generated by this parser.
     pub fn prettify_error<'a>(
         input: &'a str,
@@ -90,20 +211,41 @@ impl DuperParser {
 
 // Base rules
 
-pub(crate) fn duper_trunk<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn duper_trunk<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
     identified_trunk().then_ignore(end())
 }
 
-pub(crate) fn duper_value<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn duper_value<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
     identified_value().then_ignore(end())
 }
 
+pub(crate) fn duper_trunk_recovering<'a>()
+-> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    identified_trunk_with(true).then_ignore(end_recovering())
+}
+
+pub(crate) fn duper_value_recovering<'a>()
+-> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    identified_value_with(true).then_ignore(end_recovering())
+}
+
+/// Skips any trailing input after the top-level value.
+/// The value that replaces invalid values when recovering from errors:
+/// `Error(null)`.
+fn placeholder<'a>() -> DuperValue<'a> {
+    DuperValue::Null {
+        identifier: Some(DuperIdentifier(Cow::Borrowed("Error"))),
+    }
+}
+
+fn end_recovering<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
+    end().recover_with(via_parser(any().repeated().at_least(1)))
+}
+
 // Semantic rules
 
 pub(crate) fn identifier_lossy<'a>()
--> impl Parser<'a, &'a str, DuperIdentifier<'static>, extra::Err<Rich<'a, char>>> + Clone {
+-> impl Parser<'a, &'a str, DuperIdentifier<'static>, Extra<'a>> + Clone {
     let skippable_characters = none_of("-_")
         .and_is(ascii_alphanumeric().not())
         .labelled("non-ASCII alphanumeric")
@@ -140,8 +282,7 @@ pub(crate) fn identifier_lossy<'a>()
 }
 
 /// Parse an identifier.
-pub fn identifier<'a>()
--> impl Parser<'a, &'a str, DuperIdentifier<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn identifier<'a>() -> impl Parser<'a, &'a str, DuperIdentifier<'a>, Extra<'a>> + Clone {
     one_of('A'..='Z')
         .labelled("ASCII uppercase letter")
         .then(one_of("-_").or_not().then(ascii_alphanumeric()).repeated())
@@ -150,12 +291,18 @@ pub fn identifier<'a>()
 }
 
 /// Parse a trunk value with an optional identifier.
-pub fn identified_trunk<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn identified_trunk<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    identified_trunk_with(false)
+}
+
+fn identified_trunk_with<'a>(
+    recover: bool,
+) -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    let identified_value = identified_value_with(recover);
     let inner_trunk = choice((
-        object(identified_value()),
-        array(identified_value()),
-        tuple(identified_value()),
+        object_with(identified_value.clone(), recover),
+        array_with(identified_value.clone(), recover),
+        tuple_with(identified_value, recover),
     ))
     .padded_by(whitespace_and_comments())
     .boxed();
@@ -172,13 +319,18 @@ pub fn identified_trunk<'a>()
 }
 
 /// Parse a Duper value with an optional identifier.
-pub fn identified_value<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn identified_value<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    identified_value_with(false)
+}
+
+fn identified_value_with<'a>(
+    recover: bool,
+) -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
     recursive(move |identified_value| {
         let inner_value = choice((
-            object(identified_value.clone()),
-            array(identified_value.clone()),
-            tuple(identified_value),
+            object_with(identified_value.clone(), recover),
+            array_with(identified_value.clone(), recover),
+            tuple_with(identified_value, recover),
             base64_bytes().map(|bytes| DuperValue::Bytes {
                 identifier: None,
                 inner: Cow::Owned(bytes),
@@ -216,7 +368,7 @@ pub fn identified_value<'a>()
         .boxed()
         .padded_by(whitespace_and_comments());
 
-        choice((
+        let value = choice((
             temporal_specified(),
             identifier()
                 .then(temporal_unspecified().delimited_by(just('('), just(')')))
@@ -235,41 +387,225 @@ pub fn identified_value<'a>()
             temporal_unspecified(),
             inner_value,
         ))
-        .padded_by(whitespace_and_comments())
-        .boxed()
+        .padded_by(whitespace_and_comments());
+
+        if recover {
+            // Replace invalid values with a placeholder, skipping up to the
+            // end of the value.
+            value
+                .recover_with(via_parser(nested_delimiters(
+                    '{',
+                    '}',
+                    [('[', ']'), ('(', ')')],
+                    |_| placeholder(),
+                )))
+                .recover_with(via_parser(nested_delimiters(
+                    '[',
+                    ']',
+                    [('{', '}'), ('(', ')')],
+                    |_| placeholder(),
+                )))
+                .recover_with(via_parser(
+                    whitespace_and_comments()
+                        .ignore_then(invalid_value())
+                        .then_ignore(whitespace_and_comments())
+                        .map(|_| placeholder()),
+                ))
+                .boxed()
+        } else {
+            value.boxed()
+        }
     })
 }
 
+/// Skips over an invalid value, up to the next separator or closing
+/// delimiter.
+fn invalid_value<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
+    choice((
+        just('"')
+            .then(
+                none_of("\"\\")
+                    .ignored()
+                    .or(just('\\').then(any()).ignored())
+                    .repeated(),
+            )
+            .then(just('"'))
+            .ignored(),
+        just('\'')
+            .then(none_of("'").repeated())
+            .then(just('\''))
+            .ignored(),
+        none_of(",:]})").ignored(),
+    ))
+    .repeated()
+    .at_least(1)
+}
+
+/// Recovers from a missing separator if the start of another element or entry
+/// follows, or else by skipping up to the next separator.
+fn separator<'a>(
+    next: impl Parser<'a, &'a str, (), Extra<'a>> + Clone + 'a,
+    close: char,
+    recover: bool,
+) -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
+    let separator = just(',').padded_by(whitespace_and_comments()).ignored();
+    if recover {
+        separator
+            .recover_with(via_parser(
+                whitespace_and_comments().then(next.rewind()).ignored(),
+            ))
+            .recover_with(skip_then_retry_until(
+                any().ignored(),
+                one_of([',', close]).ignored(),
+            ))
+            .boxed()
+    } else {
+        separator.boxed()
+    }
+}
+
+/// Whether an element follows, without parsing it.
+fn next_element<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
+    none_of(",:]})").ignored()
+}
+
+/// Recovers from a missing closing delimiter at the end of the input or
+/// before a different closing delimiter, or else by skipping up to it.
+fn closing<'a>(close: char, recover: bool) -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
+    let closing = just(close).padded_by(whitespace_and_comments()).ignored();
+    if recover {
+        closing
+            .recover_with(via_parser(
+                end().or(one_of("]})").and_is(just(close).not()).rewind().ignored()),
+            ))
+            .recover_with(skip_then_retry_until(any().ignored(), end()))
+            .boxed()
+    } else {
+        closing.boxed()
+    }
+}
+
+/// Parses the opening delimiter of an object, array, or tuple, entering it.
+fn opening<'a>(open: char) -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
+    just(open)
+        .try_map_with(|_, e: &mut MapExtra<'a, '_, &'a str, Extra<'a>>| {
+            let span = e.span();
+            e.state().enter(span)
+        })
+        .padded_by(whitespace_and_comments())
+}
+
+/// Rejects collections with more elements than the limit.
+fn check_elements<'a, 'b, T>(
+    inner: Vec<T>,
+    e: &mut MapExtra<'a, 'b, &'a str, Extra<'a>>,
+) -> Result<Vec<T>, Rich<'a, char>> {
+    let limits = e.state().limits;
+    if inner.len() > limits.max_elements {
+        Err(limits.elements_error(e.span()))
+    } else {
+        Ok(inner)
+    }
+}
+
+/// Rejects strings and bytes longer than the limit, once decoded.
+fn check_string_length<'a, 'b>(
+    length: usize,
+    e: &mut MapExtra<'a, 'b, &'a str, Extra<'a>>,
+) -> Result<(), Rich<'a, char>> {
+    let limits = e.state().limits;
+    if length > limits.max_string_length {
+        Err(limits.string_error(e.span()))
+    } else {
+        Ok(())
+    }
+}
+
 /// Parse a Duper object.
 pub fn object<'a>(
-    identified_value: impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone + 'a,
-) -> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
-    object_key()
+    identified_value: impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone + 'a,
+) -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    object_with(identified_value, false)
+}
+
+fn object_with<'a>(
+    identified_value: impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone + 'a,
+    recover: bool,
+) -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    let next_entry = object_key()
+        .then(just(':').padded_by(whitespace_and_comments()))
+        .ignored();
+    let value = if recover {
+        // A value is always expected after the colon, even if it's missing.
+        identified_value
+            .recover_with(via_parser(empty().map(|_| placeholder())))
+            .boxed()
+    } else {
+        identified_value.boxed()
+    };
+    let entries = object_key()
+        .map_with(|key, e| (key, e.span()))
         .padded_by(whitespace_and_comments())
         .then_ignore(just(':').padded_by(whitespace_and_comments()))
-        .then(identified_value.padded_by(whitespace_and_comments()))
-        .separated_by(just(',').padded_by(whitespace_and_comments()))
+        .then(value.padded_by(whitespace_and_comments()))
+        .separated_by(separator(next_entry, '}', recover))
         .allow_trailing()
         .collect::<Vec<_>>()
-        .try_map(|object, span| {
-            DuperObject::try_from(object)
-                .map_err(|err| Rich::custom(span, err))
-                .map(|object| DuperValue::Object {
+        .try_map_with(check_elements);
+    let object = if recover {
+        // Report every duplicate key, but keep the first one.
+        entries
+            .validate(|entries, _, emitter| {
+                let mut keys = std::collections::HashSet::with_capacity(entries.len());
+                for ((key, span), _) in &entries {
+                    if !keys.insert(key) {
+                        emitter.emit(Rich::custom(
+                            *span,
+                            DuperObjectTryFromError::DuplicateKey(key.0.clone()),
+                        ));
+                    }
+                }
+                DuperValue::Object {
                     identifier: None,
-                    inner: object,
-                })
-        })
+                    inner: DuperObject::from_lossy(
+                        entries
+                            .into_iter()
+                            .map(|((key, _), value)| (key, value))
+                            .collect(),
+                    ),
+                }
+            })
+            .boxed()
+    } else {
+        entries
+            .map(|entries| {
+                entries
+                    .into_iter()
+                    .map(|((key, _), value)| (key, value))
+                    .collect::<Vec<_>>()
+            })
+            .try_map(|object, span| {
+                DuperObject::try_from(object)
+                    .map_err(|err| Rich::custom(span, err))
+                    .map(|object| DuperValue::Object {
+                        identifier: None,
+                        inner: object,
+                    })
+            })
+            .boxed()
+    };
+    object
         .padded_by(whitespace_and_comments())
-        .delimited_by(
-            just('{').padded_by(whitespace_and_comments()),
-            just('}').padded_by(whitespace_and_comments()),
-        )
+        .delimited_by(opening('{'), closing('}', recover))
+        .map_with(|value, e| {
+            e.state().leave();
+            value
+        })
         .boxed()
 }
 
 /// Parse a Duper object key.
-pub fn object_key<'a>() -> impl Parser<'a, &'a str, DuperKey<'a>, extra::Err<Rich<'a, char>>> + Clone
-{
+pub fn object_key<'a>() -> impl Parser<'a, &'a str, DuperKey<'a>, Extra<'a>> + Clone {
     let plain_key = ascii_alphabetic()
         .to_slice()
         .or(just('_').then(ascii_alphanumeric()).to_slice())
@@ -286,105 +622,126 @@ pub fn object_key<'a>() -> impl Parser<'a, &'a str, DuperKey<'a>, extra::Err<Ric
 
 /// Parse a Duper array.
 pub fn array<'a>(
-    identified_value: impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone + 'a,
-) -> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
-    identified_value
+    identified_value: impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone + 'a,
+) -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    array_with(identified_value, false)
+}
+
+fn array_with<'a>(
+    identified_value: impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone + 'a,
+    recover: bool,
+) -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    // The empty form with a comma goes first, since recovering from a missing
+    // element would otherwise skip over the comma.
+    just(',')
         .padded_by(whitespace_and_comments())
-        .separated_by(just(',').padded_by(whitespace_and_comments()))
-        .allow_trailing()
-        .collect::<Vec<_>>()
-        .map(|inner| DuperValue::Array {
+        .delimited_by(opening('['), just(']').padded_by(whitespace_and_comments()))
+        .map(|_| DuperValue::Array {
             identifier: None,
-            inner,
+            inner: vec![],
         })
-        .padded_by(whitespace_and_comments())
-        .delimited_by(
-            just('[').padded_by(whitespace_and_comments()),
-            just(']').padded_by(whitespace_and_comments()),
-        )
-        .or(just(',')
+        .or(identified_value
             .padded_by(whitespace_and_comments())
-            .delimited_by(
-                just('[').padded_by(whitespace_and_comments()),
-                just(']').padded_by(whitespace_and_comments()),
-            )
-            .map(|_| DuperValue::Array {
+            .separated_by(separator(next_element(), ']', recover))
+            .allow_trailing()
+            .collect::<Vec<_>>()
+            .try_map_with(check_elements)
+            .map(|inner| DuperValue::Array {
                 identifier: None,
-                inner: vec![],
-            }))
+                inner,
+            })
+            .padded_by(whitespace_and_comments())
+            .delimited_by(opening('['), closing(']', recover)))
+        .map_with(|value, e| {
+            e.state().leave();
+            value
+        })
         .boxed()
 }
 
 /// Parse a Duper tuple.
 pub fn tuple<'a>(
-    identified_value: impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone + 'a,
-) -> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
-    identified_value
+    identified_value: impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone + 'a,
+) -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    tuple_with(identified_value, false)
+}
+
+fn tuple_with<'a>(
+    identified_value: impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone + 'a,
+    recover: bool,
+) -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
+    // The empty form with a comma goes first, since recovering from a missing
+    // element would otherwise skip over the comma.
+    just(',')
         .padded_by(whitespace_and_comments())
-        .separated_by(just(',').padded_by(whitespace_and_comments()))
-        .allow_trailing()
-        .collect::<Vec<_>>()
-        .map(|inner| DuperValue::Tuple {
+        .delimited_by(opening('('), just(')').padded_by(whitespace_and_comments()))
+        .map(|_| DuperValue::Tuple {
             identifier: None,
-            inner,
+            inner: vec![],
         })
-        .padded_by(whitespace_and_comments())
-        .delimited_by(
-            just('(').padded_by(whitespace_and_comments()),
-            just(')').padded_by(whitespace_and_comments()),
-        )
-        .or(just(',')
+        .or(identified_value
             .padded_by(whitespace_and_comments())
-            .delimited_by(
-                just('(').padded_by(whitespace_and_comments()),
-                just(')').padded_by(whitespace_and_comments()),
-            )
-            .map(|_| DuperValue::Tuple {
+            .separated_by(separator(next_element(), ')', recover))
+            .allow_trailing()
+            .collect::<Vec<_>>()
+            .try_map_with(check_elements)
+            .map(|inner| DuperValue::Tuple {
                 identifier: None,
-                inner: vec![],
-            }))
+                inner,
+            })
+            .padded_by(whitespace_and_comments())
+            .delimited_by(opening('('), closing(')', recover)))
+        .map_with(|value, e| {
+            e.state().leave();
+            value
+        })
         .boxed()
 }
 
 /// Parse a quote-delimited Duper string.
-pub fn quoted_string<'a>()
--> impl Parser<'a, &'a str, Cow<'a, str>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn quoted_string<'a>() -> impl Parser<'a, &'a str, Cow<'a, str>, Extra<'a>> + Clone {
     quoted_inner()
-        .try_map(|str, span| unescape_str(str).map_err(|err| Rich::custom(span, err)))
+        .try_map_with(|str, e| {
+            let str = unescape_str(str).map_err(|err| Rich::custom(e.span(), err))?;
+            check_string_length(str.len(), e).map(|()| str)
+        })
         .delimited_by(just('"'), just('"'))
         .boxed()
 }
 
 /// Parse a Base64 Duper byte string.
-pub fn base64_bytes<'a>() -> impl Parser<'a, &'a str, Vec<u8>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn base64_bytes<'a>() -> impl Parser<'a, &'a str, Vec<u8>, Extra<'a>> + Clone {
     base64_digit()
         .padded()
         .repeated()
         .then(just('=').repeated())
         .collect::<String>()
-        .try_map(|bytes, span| {
-            base64::engine::GeneralPurpose::new(
+        .try_map_with(|bytes, e| {
+            let bytes = base64::engine::GeneralPurpose::new(
                 &base64::alphabet::STANDARD,
                 base64::engine::GeneralPurposeConfig::new()
                     .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent),
             )
             .decode(bytes)
-            .map_err(|err| Rich::custom(span, err))
+            .map_err(|err| Rich::custom(e.span(), err))?;
+            check_string_length(bytes.len(), e).map(|()| bytes)
         })
         .delimited_by(just("b64\""), just('"'))
 }
 
 /// Parse a quote-delimited Duper byte string.
-pub fn quoted_bytes<'a>()
--> impl Parser<'a, &'a str, Cow<'a, [u8]>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn quoted_bytes<'a>() -> impl Parser<'a, &'a str, Cow<'a, [u8]>, Extra<'a>> + Clone {
     quoted_inner()
-        .try_map(|bytes, span| unescape_bytes(bytes).map_err(|err| Rich::custom(span, err)))
+        .try_map_with(|bytes, e| {
+            let bytes = unescape_bytes(bytes).map_err(|err| Rich::custom(e.span(), err))?;
+            check_string_length(bytes.len(), e).map(|()| bytes)
+        })
         .delimited_by(just("b\""), just('"'))
         .boxed()
 }
 
 /// Parse the inner contents of a Duper quoted string or quoted byte string.
-pub fn quoted_inner<'a>() -> impl Parser<'a, &'a str, &'a str, extra::Err<Rich<'a, char>>> + Clone {
+pub fn quoted_inner<'a>() -> impl Parser<'a, &'a str, &'a str, Extra<'a>> + Clone {
     let escaped_characters = just('\\')
         .then(choice((
             one_of("\"\\/bfnrt0").to_slice(),
@@ -403,7 +760,7 @@ pub fn quoted_inner<'a>() -> impl Parser<'a, &'a str, &'a str, extra::Err<Rich<'
 }
 
 /// Parse a raw Duper string.
-pub fn raw_string<'a>() -> impl Parser<'a, &'a str, &'a str, extra::Err<Rich<'a, char>>> + Clone {
+pub fn raw_string<'a>() -> impl Parser<'a, &'a str, &'a str, Extra<'a>> + Clone {
     let hashtags = just('#')
         .repeated()
         .count()
@@ -442,11 +799,12 @@ pub fn raw_string<'a>() -> impl Parser<'a, &'a str, &'a str, extra::Err<Rich<'a,
                     ),
                 ),
         )
+        .try_map_with(|str: &str, e| check_string_length(str.len(), e).map(|()| str))
         .boxed()
 }
 
 /// Parse a raw Duper byte string.
-pub fn raw_bytes<'a>() -> impl Parser<'a, &'a str, &'a [u8], extra::Err<Rich<'a, char>>> + Clone {
+pub fn raw_bytes<'a>() -> impl Parser<'a, &'a str, &'a [u8], Extra<'a>> + Clone {
     let hashtags = just('#')
         .repeated()
         .to_slice()
@@ -485,11 +843,12 @@ pub fn raw_bytes<'a>() -> impl Parser<'a, &'a str, &'a [u8], extra::Err<Rich<'a,
                     ),
                 ),
         )
+        .try_map_with(|bytes: &[u8], e| check_string_length(bytes.len(), e).map(|()| bytes))
         .boxed()
 }
 
 /// Parse a Duper float.
-pub fn float<'a>() -> impl Parser<'a, &'a str, f64, extra::Err<Rich<'a, char>>> + Clone {
+pub fn float<'a>() -> impl Parser<'a, &'a str, f64, Extra<'a>> + Clone {
     let decimal = one_of("+-").or_not().then(integer_digits()).to_slice();
 
     let fractional = just('.').then(
@@ -526,7 +885,7 @@ pub fn float<'a>() -> impl Parser<'a, &'a str, f64, extra::Err<Rich<'a, char>>>
 }
 
 /// Parse a Duper integer.
-pub fn integer<'a>() -> impl Parser<'a, &'a str, i64, extra::Err<Rich<'a, char>>> + Clone {
+pub fn integer<'a>() -> impl Parser<'a, &'a str, i64, Extra<'a>> + Clone {
     let decimal_integer = one_of("+-")
         .or_not()
         .then(integer_digits())
@@ -573,19 +932,18 @@ pub fn integer<'a>() -> impl Parser<'a, &'a str, i64, extra::Err<Rich<'a, char>>
 }
 
 /// Parse a Duper boolean.
-pub fn boolean<'a>() -> impl Parser<'a, &'a str, bool, extra::Err<Rich<'a, char>>> + Clone {
+pub fn boolean<'a>() -> impl Parser<'a, &'a str, bool, Extra<'a>> + Clone {
     choice((just("true").to(true), just("false").to(false)))
 }
 
 /// Parse a Duper null value.
-pub fn null<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn null<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     just("null").to(())
 }
 
 // Atoms
 
-pub(crate) fn whitespace_and_comments<'a>()
--> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn whitespace_and_comments<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     choice((
         just("//")
             .then(none_of("\r\n").repeated())
@@ -603,8 +961,7 @@ pub(crate) fn whitespace_and_comments<'a>()
     .boxed()
 }
 
-pub(crate) fn base64_digit<'a>()
--> impl Parser<'a, &'a str, char, extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn base64_digit<'a>() -> impl Parser<'a, &'a str, char, Extra<'a>> + Clone {
     choice((
         one_of('a'..='z'),
         one_of('A'..='Z'),
@@ -615,21 +972,18 @@ pub(crate) fn base64_digit<'a>()
     .labelled("a Base64 digit")
 }
 
-pub(crate) fn ascii_alphabetic<'a>()
--> impl Parser<'a, &'a str, char, extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn ascii_alphabetic<'a>() -> impl Parser<'a, &'a str, char, Extra<'a>> + Clone {
     one_of('a'..='z')
         .or(one_of('A'..='Z'))
         .labelled("an ASCII letter")
 }
 
-pub(crate) fn ascii_alphanumeric<'a>()
--> impl Parser<'a, &'a str, char, extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn ascii_alphanumeric<'a>() -> impl Parser<'a, &'a str, char, Extra<'a>> + Clone {
     choice((one_of('a'..='z'), one_of('A'..='Z'), one_of('0'..='9')))
         .labelled("an ASCII alphanumeric")
 }
 
-pub(crate) fn integer_digits<'a>()
--> impl Parser<'a, &'a str, &'a str, extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn integer_digits<'a>() -> impl Parser<'a, &'a str, &'a str, Extra<'a>> + Clone {
     one_of('1'..='9')
         .labelled("a digit 1 through 9")
         .then(
@@ -642,19 +996,16 @@ pub(crate) fn integer_digits<'a>()
         .or(one_of('0'..='9').labelled("a digit").to_slice())
 }
 
-pub(crate) fn hex_digit<'a>() -> impl Parser<'a, &'a str, char, extra::Err<Rich<'a, char>>> + Clone
-{
+pub(crate) fn hex_digit<'a>() -> impl Parser<'a, &'a str, char, Extra<'a>> + Clone {
     choice((one_of('0'..='9'), one_of('a'..='f'), one_of('A'..='F')))
         .labelled("a hexadecimal digit")
 }
 
-pub(crate) fn octal_digit<'a>() -> impl Parser<'a, &'a str, char, extra::Err<Rich<'a, char>>> + Clone
-{
+pub(crate) fn octal_digit<'a>() -> impl Parser<'a, &'a str, char, Extra<'a>> + Clone {
     one_of('0'..='7').labelled("an octal digit")
 }
 
-pub(crate) fn control_character<'a>()
--> impl Parser<'a, &'a str, char, extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn control_character<'a>() -> impl Parser<'a, &'a str, char, Extra<'a>> + Clone {
     choice((
         one_of('\u{0000}'..='\u{0009}'),
         one_of('\u{000b}'..='\u{001f}'),
@@ -667,7 +1018,7 @@ pub(crate) fn control_character<'a>()
 mod duper_parser_tests {
     use crate::{
         DuperFloat, DuperIdentifier, DuperKey, DuperObject, DuperParser, DuperTemporal,
-        DuperTemporalInstant, DuperValue,
+        DuperTemporalInstant, DuperValue, ParseLimitKind, ParseLimits,
     };
 
     #[test]
@@ -1627,4 +1978,183 @@ mod duper_parser_tests {
             }
         );
     }
+
+    #[test]
+    fn parsing_recovery() {
+        // Valid input is parsed the same as without recovery
+        let input = r#"
+            Foo({a: [1, 2,], b: (,), "c": r"raw", d: {}, /* comment */})
+        "#;
+        let (value, errors) = DuperParser::parse_duper_value_recovering(input);
+        assert!(errors.is_empty());
+        assert_eq!(value, DuperParser::parse_duper_value(input).ok());
+
+        // Missing commas
+        let (value, errors) = DuperParser::parse_duper_value_recovering("[1 2, {a: 1 b: 2} (3 4)]");
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| error.span().into_range())
+                .collect::<Vec<_>>(),
+            vec![3..4, 12..13, 18..19, 21..22]
+        );
+        assert_eq!(
+            value,
+            DuperParser::parse_duper_value("[1, 2, {a: 1, b: 2}, (3, 4)]").ok()
+        );
+
+        // Invalid and missing values
+        let (value, errors) =
+            DuperParser::parse_duper_value_recovering(r#"{a: "\e", b: @, c: , d: 4}"#);
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            value,
+            DuperParser::parse_duper_value(
+                "{a: Error(null), b: Error(null), c: Error(null), d: 4}"
+            )
+            .ok()
+        );
+
+        // Duplicate keys
+        let (value, errors) =
+            DuperParser::parse_duper_value_recovering("{a: 1, b: 2, a: 3, b: 4, a: 5}");
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| (error.to_string(), error.span().into_range()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("duplicate key a in object".into(), 13..14),
+                ("duplicate key b in object".into(), 19..20),
+                ("duplicate key a in object".into(), 25..26),
+            ]
+        );
+        assert_eq!(value, DuperParser::parse_duper_value("{a: 1, b: 2}").ok());
+
+        // Unclosed delimiters and trailing input
+        let (value, errors) = DuperParser::parse_duper_value_recovering("{a: [1, 2}");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(value, DuperParser::parse_duper_value("{a: [1, 2]}").ok());
+        let (value, errors) = DuperParser::parse_duper_value_recovering("[1, (2, 3");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(value, DuperParser::parse_duper_value("[1, (2, 3)]").ok());
+        let (value, errors) = DuperParser::parse_duper_trunk_recovering("[1] [2]");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(value, DuperParser::parse_duper_trunk("[1]").ok());
+
+        // No value at all
+        let (value, errors) = DuperParser::parse_duper_value_recovering("  // nothing");
+        assert_eq!(errors.len(), 1);
+        assert!(value.is_none());
+        let (value, errors) = DuperParser::parse_duper_trunk_recovering(r#""not a trunk""#);
+        assert!(!errors.is_empty());
+        assert!(value.is_none());
+    }
+
+    #[test]
+    fn parse_limits() {
+        let limits = ParseLimits {
+            max_depth: 2,
+            max_string_length: 8,
+            max_elements: 3,
+            max_document_size: 128,
+        };
+
+        // Depth
+        assert!(DuperParser::parse_duper_value_with_limits("[{a: (1,)}]", &limits).is_err());
+        assert!(DuperParser::parse_duper_value_with_limits("[{a: 1}]", &limits).is_ok());
+        assert!(
+            DuperParser::parse_duper_value_with_limits("Foo([Bar({a: Baz(1)})])", &limits).is_ok()
+        );
+        assert!(
+            DuperParser::parse_duper_value_with_limits(
+                r##"[ "[[[", r#"{{"#, b"((", /* [[[ */ ] // [[["##,
+                &limits,
+            )
+            .is_ok()
+        );
+        let errors = DuperParser::parse_duper_value_with_limits("  [[[[]]]]", &limits).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span().into_range(), 4..5);
+        assert_eq!(errors[0].to_string(), "nesting depth exceeds limit of 2");
+        assert_eq!(
+            ParseLimits::limit_kind(&errors[0]),
+            Some(ParseLimitKind::Depth)
+        );
+
+        // Strings and bytes
+        assert!(DuperParser::parse_duper_value_with_limits(r#""12345678""#, &limits).is_ok());
+        let errors =
+            DuperParser::parse_duper_value_with_limits(r#""123456789""#, &limits).unwrap_err();
+        assert_eq!(
+            ParseLimits::limit_kind(&errors[0]),
+            Some(ParseLimitKind::StringLength)
+        );
+        assert!(DuperParser::parse_duper_value_with_limits(r#"b"123456789""#, &limits).is_err());
+        assert!(DuperParser::parse_duper_value_with_limits(r##"br#"12"3456"#"##, &limits).is_ok());
+        assert!(
+            DuperParser::parse_duper_value_with_limits(r##"r#"12"345678"#"##, &limits).is_err()
+        );
+        assert!(DuperParser::parse_duper_value_with_limits(r#"b64"AAAAAAAA""#, &limits).is_ok());
+        assert!(
+            DuperParser::parse_duper_value_with_limits(r#"b64"AAAAAAAAAAAA""#, &limits).is_err()
+        );
+
+        // Elements
+        assert!(DuperParser::parse_duper_value_with_limits("[1, 2, 3,]", &limits).is_ok());
+        let errors =
+            DuperParser::parse_duper_value_with_limits("[1, 2, 3, 4]", &limits).unwrap_err();
+        assert_eq!(
+            ParseLimits::limit_kind(&errors[0]),
+            Some(ParseLimitKind::Elements)
+        );
+        assert!(DuperParser::parse_duper_value_with_limits("(,)", &limits).is_ok());
+        assert!(
+            DuperParser::parse_duper_value_with_limits("{a: [1, 2], b: {}, c: ()}", &limits)
+                .is_ok()
+        );
+        assert!(
+            DuperParser::parse_duper_trunk_with_limits("{a: 1, b: 2, c: 3, d: 4}", &limits)
+                .is_err()
+        );
+
+        // Document size
+        let input = format!("[{}]", " ".repeat(126));
+        assert!(DuperParser::parse_duper_value_with_limits(&input, &limits).is_ok());
+        let input = format!("[{}]", " ".repeat(127));
+        let errors = DuperParser::parse_duper_value_with_limits(&input, &limits).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "document exceeds limit of 128 bytes");
+        assert_eq!(
+            ParseLimits::limit_kind(&errors[0]),
+            Some(ParseLimitKind::DocumentSize)
+        );
+
+        // Keys count as strings
+        assert!(
+            DuperParser::parse_duper_value_with_limits(r#"{"123456789": 1}"#, &limits).is_err()
+        );
+
+        // Syntax errors are still reported by the parser
+        assert!(DuperParser::parse_duper_value_with_limits("[1, 2", &limits).is_err());
+        let errors = DuperParser::parse_duper_value_with_limits("[1 2]", &limits).unwrap_err();
+        assert!(!errors.iter().any(ParseLimits::is_limit_error));
+        assert!(DuperParser::parse_duper_value_with_limits(r#"["1, 2]"#, &limits).is_err());
+        let errors =
+            DuperParser::parse_duper_value_with_limits("{a: 1, a: 2}", &limits).unwrap_err();
+        assert_eq!(ParseLimits::limit_kind(&errors[0]), None);
+
+        // The default depth can be parsed without overflowing the stack
+        let limits = ParseLimits::default();
+        let input = format!("{}{}", "[".repeat(128), "]".repeat(128));
+        assert!(DuperParser::parse_duper_value_with_limits(&input, &limits).is_ok());
+        let input = format!("{}{}", "[".repeat(129), "]".repeat(129));
+        assert!(DuperParser::parse_duper_value_with_limits(&input, &limits).is_err());
+
+        // The parser without limits still bounds the depth
+        assert!(DuperParser::parse_duper_value(&input).is_err());
+        assert!(DuperParser::parse_duper_trunk(&input).is_err());
+        let (_, errors) = DuperParser::parse_duper_value_recovering(&input);
+        assert!(errors.iter().any(ParseLimits::is_limit_error));
+    }
 }
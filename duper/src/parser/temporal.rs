@@ -11,14 +11,13 @@ use crate::{
         DuperTemporalPlainYearMonth, DuperTemporalUnspecified, DuperTemporalZonedDateTime,
         DuperValue,
     },
-    parser::{ascii_alphabetic, ascii_alphanumeric, whitespace_and_comments},
+    parser::{Extra, ascii_alphabetic, ascii_alphanumeric, whitespace_and_comments},
 };
 
 // Duper Temporal values
 
 /// Parse a known Temporal value.
-pub fn temporal_specified<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn temporal_specified<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
     choice((
         temporal_instant(),
         temporal_zoned_date_time(),
@@ -34,8 +33,7 @@ pub fn temporal_specified<'a>()
 }
 
 /// Parse a Temporal Instant, including the identifier and single quotes.
-pub fn temporal_instant<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn temporal_instant<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
     just("Instant")
         .padded_by(whitespace_and_comments())
         .ignore_then(just('('))
@@ -57,8 +55,8 @@ pub fn temporal_instant<'a>()
 }
 
 /// Parse a Temporal ZonedDateTime, including the identifier and single quotes.
-pub fn temporal_zoned_date_time<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn temporal_zoned_date_time<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone
+{
     just("ZonedDateTime")
         .padded_by(whitespace_and_comments())
         .ignore_then(just('('))
@@ -80,8 +78,7 @@ pub fn temporal_zoned_date_time<'a>()
 }
 
 /// Parse a Temporal PlainDate, including the identifier and single quotes.
-pub fn temporal_plain_date<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn temporal_plain_date<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
     just("PlainDate")
         .padded_by(whitespace_and_comments())
         .ignore_then(just('('))
@@ -103,8 +100,7 @@ pub fn temporal_plain_date<'a>()
 }
 
 /// Parse a Temporal PlainTime, including the identifier and single quotes.
-pub fn temporal_plain_time<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn temporal_plain_time<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
     just("PlainTime")
         .padded_by(whitespace_and_comments())
         .ignore_then(just('('))
@@ -126,8 +122,8 @@ pub fn temporal_plain_time<'a>()
 }
 
 /// Parse a Temporal PlainDateTime, including the identifier and single quotes.
-pub fn temporal_plain_date_time<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn temporal_plain_date_time<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone
+{
     just("PlainDateTime")
         .padded_by(whitespace_and_comments())
         .ignore_then(just('('))
@@ -149,8 +145,8 @@ pub fn temporal_plain_date_time<'a>()
 }
 
 /// Parse a Temporal PlainYearMonth, including the identifier and single quotes.
-pub fn temporal_plain_year_month<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn temporal_plain_year_month<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone
+{
     just("PlainYearMonth")
         .padded_by(whitespace_and_comments())
         .ignore_then(just('('))
@@ -172,8 +168,8 @@ pub fn temporal_plain_year_month<'a>()
 }
 
 /// Parse a Temporal PlainMonthDay, including the identifier and single quotes.
-pub fn temporal_plain_month_day<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn temporal_plain_month_day<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone
+{
     just("PlainMonthDay")
         .padded_by(whitespace_and_comments())
         .ignore_then(just('('))
@@ -195,8 +191,7 @@ pub fn temporal_plain_month_day<'a>()
 }
 
 /// Parse a Temporal Duration, including the identifier and single quotes.
-pub fn temporal_duration<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn temporal_duration<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
     just("Duration")
         .padded_by(whitespace_and_comments())
         .ignore_then(just('('))
@@ -218,8 +213,7 @@ pub fn temporal_duration<'a>()
 }
 
 /// Parse an unspecified Temporal value, delimited by single quotes.
-pub fn temporal_unspecified<'a>()
--> impl Parser<'a, &'a str, DuperValue<'a>, extra::Err<Rich<'a, char>>> + Clone {
+pub fn temporal_unspecified<'a>() -> impl Parser<'a, &'a str, DuperValue<'a>, Extra<'a>> + Clone {
     unspecified()
         .to_slice()
         .delimited_by(just('\''), just('\''))
@@ -236,7 +230,7 @@ pub fn temporal_unspecified<'a>()
 // Inner values
 
 /// Parse a ZonedDateTime.
-pub fn zoned_date_time<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn zoned_date_time<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     date_time()
         .then(time_offset())
         .then(timezone())
@@ -246,8 +240,7 @@ pub fn zoned_date_time<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a,
 }
 
 /// Parse a ZonedDateTime with a non-Z offset.
-pub fn non_z_zoned_date_time<'a>()
--> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn non_z_zoned_date_time<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     date_time()
         .then(time_num_offset())
         .then(timezone())
@@ -257,7 +250,7 @@ pub fn non_z_zoned_date_time<'a>()
 }
 
 /// Parse an Instant.
-pub fn instant<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn instant<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     choice((
         zoned_date_time(),
         date_time().then(time_offset()).padded().ignored(),
@@ -265,7 +258,7 @@ pub fn instant<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>>
 }
 
 /// Parse an instant with a non-Z offset.
-pub fn non_z_instant<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn non_z_instant<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     choice((
         non_z_zoned_date_time(),
         date_time().then(time_num_offset()).padded().ignored(),
@@ -273,7 +266,7 @@ pub fn non_z_instant<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, c
 }
 
 /// Parse a PlainDate.
-pub fn plain_date<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn plain_date<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     choice((
         plain_date_time(),
         date().then(suffix_tag().repeated()).padded().ignored(),
@@ -281,7 +274,7 @@ pub fn plain_date<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char
 }
 
 /// Parse a PlainTime.
-pub fn plain_time<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn plain_time<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     choice((
         plain_date_time(),
         time().then(suffix_tag().repeated()).padded().ignored(),
@@ -289,7 +282,7 @@ pub fn plain_time<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char
 }
 
 /// Parse a PlainDateTime.
-pub fn plain_date_time<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn plain_date_time<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     choice((
         non_z_instant(),
         date_time().then(suffix_tag().repeated()).padded().ignored(),
@@ -297,7 +290,7 @@ pub fn plain_date_time<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a,
 }
 
 /// Parse a PlainYearMonth.
-pub fn plain_year_month<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn plain_year_month<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     choice((
         plain_date(),
         year_month()
@@ -308,7 +301,7 @@ pub fn plain_year_month<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a
 }
 
 /// Parse a PlainMonthDay.
-pub fn plain_month_day<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn plain_month_day<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     choice((
         plain_date(),
         month_day().then(suffix_tag().repeated()).padded().ignored(),
@@ -316,7 +309,7 @@ pub fn plain_month_day<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a,
 }
 
 /// Parse a Duration.
-pub fn duration<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn duration<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     let fractional = text::int(10).then(just('.').then(text::digits(10).at_most(9)).or_not());
 
     let duration_time = one_of("Tt").then(choice((
@@ -381,7 +374,7 @@ pub fn duration<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>
 }
 
 /// Parse an unspecified Temporal value.
-pub fn unspecified<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub fn unspecified<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     choice((
         instant(),
         plain_year_month(),
@@ -394,7 +387,7 @@ pub fn unspecified<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, cha
 
 // Atoms
 
-pub(crate) fn hour<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn hour<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     choice((
         one_of("01").then(one_of('0'..='9')),
         just('2').then(one_of('0'..='3')),
@@ -402,13 +395,11 @@ pub(crate) fn hour<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, cha
     .ignored()
 }
 
-pub(crate) fn minute_or_second<'a>()
--> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn minute_or_second<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     one_of('0'..='5').then(one_of('0'..='9')).ignored()
 }
 
-pub(crate) fn time_num_offset<'a>()
--> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn time_num_offset<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     one_of("+-")
         .then(hour())
         .then(just(':').or_not().then(minute_or_second()).or_not())
@@ -422,12 +413,11 @@ pub(crate) fn time_num_offset<'a>()
         .boxed()
 }
 
-pub(crate) fn time_offset<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone
-{
+pub(crate) fn time_offset<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     one_of("Zz").ignored().or(time_num_offset()).boxed()
 }
 
-pub(crate) fn time<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn time<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     hour()
         .then(
             just(':')
@@ -470,7 +460,7 @@ pub(crate) fn time<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, cha
         .boxed()
 }
 
-pub(crate) fn month_day<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn month_day<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     let days_31 = one_of('0'..='2')
         .then(one_of('0'..='9'))
         .ignored()
@@ -517,8 +507,7 @@ pub(crate) fn month_day<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a
     )
 }
 
-pub(crate) fn year_month<'a>()
--> impl Parser<'a, &'a str, (u32, u32), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn year_month<'a>() -> impl Parser<'a, &'a str, (u32, u32), Extra<'a>> + Clone {
     choice((
         text::digits(10).exactly(4).to_slice(),
         one_of("+-").then(text::digits(10).exactly(6)).to_slice(),
@@ -545,7 +534,7 @@ pub(crate) fn year_month<'a>()
     .boxed()
 }
 
-pub(crate) fn date<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn date<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     let days_28 = one_of('0'..='1')
         .then(one_of('0'..='9'))
         .ignored()
@@ -578,11 +567,11 @@ pub(crate) fn date<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, cha
         .boxed()
 }
 
-pub(crate) fn date_time<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn date_time<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     date().then(one_of("tT ")).then(time()).ignored().boxed()
 }
 
-pub(crate) fn timezone<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn timezone<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     let timezone_part = choice((ascii_alphabetic(), one_of("._")))
         .then(choice((ascii_alphanumeric(), one_of("._-+"))).repeated())
         .and_is(choice((just('.').ignored(), just("..").ignored())).not());
@@ -601,7 +590,7 @@ pub(crate) fn timezone<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a,
         .boxed()
 }
 
-pub(crate) fn suffix_tag<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+pub(crate) fn suffix_tag<'a>() -> impl Parser<'a, &'a str, (), Extra<'a>> + Clone {
     let suffix_key = choice((one_of('a'..='z'), just('_')))
         .then(choice((one_of('a'..='z'), one_of('0'..='9'), one_of("_-"))).repeated());
     let suffix_value = ascii_alphanumeric().repeated().at_least(1);
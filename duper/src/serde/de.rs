@@ -2,7 +2,7 @@
 
 use std::borrow::Cow;
 
-use crate::{DuperKey, DuperParser, DuperValue, serde::temporal::TemporalString};
+use crate::{DuperKey, DuperParser, DuperValue, ParseLimits, serde::temporal::TemporalString};
 use indexmap::IndexMap;
 use serde_core::{
     Deserialize,
@@ -26,6 +26,17 @@ impl<'de> Deserializer<'de> {
         Ok(Self { value: Some(value) })
     }
 
+    /// Creates a Duper deserializer from a `&str`, rejecting input which
+    /// exceeds the given [`ParseLimits`].
+    pub fn from_string_with_limits(
+        input: &'de str,
+        limits: &ParseLimits,
+    ) -> Result<Self, DuperSerdeError> {
+        let value = DuperParser::parse_duper_value_with_limits(input, limits)
+            .map_err(|err| DuperSerdeError::parse(input, err))?;
+        Ok(Self { value: Some(value) })
+    }
+
     /// Creates a Duper deserializer from a [`DuperValue`].
     pub fn from_value(value: DuperValue<'de>) -> Self {
         Self { value: Some(value) }
@@ -75,6 +86,36 @@ where
     Ok(t)
 }
 
+/// Deserialize an instance of type `T` from a str slice of Duper text, like
+/// [`from_string`], but rejecting input which exceeds the given
+/// [`ParseLimits`].
+///
+/// # Example
+///
+/// ```
+/// use duper::ParseLimits;
+///
+/// let limits = ParseLimits {
+///     max_elements: 3,
+///     ..Default::default()
+/// };
+///
+/// let ok: Vec<u32> = duper::serde::de::from_string_with_limits("[1, 2, 3]", &limits).unwrap();
+/// assert_eq!(ok, vec![1, 2, 3]);
+/// assert!(duper::serde::de::from_string_with_limits::<Vec<u32>>("[1, 2, 3, 4]", &limits).is_err());
+/// ```
+pub fn from_string_with_limits<'a, T>(
+    input: &'a str,
+    limits: &ParseLimits,
+) -> Result<T, DuperSerdeError>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_string_with_limits(input, limits)?;
+    let t = T::deserialize(&mut deserializer)?;
+    Ok(t)
+}
+
 /// Interpret a [`DuperValue`] as an instance of type `T`.
 ///
 /// # Example
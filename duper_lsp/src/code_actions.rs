@@ -0,0 +1,466 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use duper::{DuperKey, DuperParser, DuperValue};
+use line_index::LineIndex;
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, NumberOrString, Position, Range,
+    TextEdit, Url, WorkspaceEdit,
+};
+use tree_sitter::{Node, Tree};
+
+use crate::{
+    diagnostics::{DUPLICATE_KEY, MISSING_COMMA, to_byte_range, to_point, to_range},
+    symbols::key_name,
+};
+
+/// Returns the refactorings available for the selection, and the quick fixes
+/// for the diagnostics in it.
+pub(crate) fn get_code_actions(
+    uri: &Url,
+    source: &str,
+    tree: &Tree,
+    range: Range,
+    diagnostics: &[Diagnostic],
+    is_utf8: bool,
+) -> Vec<CodeActionOrCommand> {
+    let index = LineIndex::new(source);
+    let (Some(start), Some(end)) = (
+        to_point(range.start, &index, is_utf8),
+        to_point(range.end, &index, is_utf8),
+    ) else {
+        return vec![];
+    };
+    let mut builder = ActionBuilder {
+        uri,
+        source,
+        index: &index,
+        is_utf8,
+        actions: vec![],
+    };
+
+    for diagnostic in diagnostics {
+        match &diagnostic.code {
+            Some(NumberOrString::String(code)) if code == DUPLICATE_KEY => {
+                builder.remove_duplicate_key(tree, diagnostic)
+            }
+            Some(NumberOrString::String(code)) if code == MISSING_COMMA => {
+                builder.insert_missing_comma(diagnostic)
+            }
+            _ => (),
+        }
+    }
+
+    let Some(node) = tree
+        .root_node()
+        .named_descendant_for_point_range(start, end)
+    else {
+        return builder.actions;
+    };
+    let mut literal = None;
+    let mut key = None;
+    let mut value = None;
+    let mut current = Some(node);
+    while let Some(node) = current {
+        match node.kind() {
+            "string" | "bytes" | "integer" if literal.is_none() && key.is_none() => {
+                literal = Some(node)
+            }
+            "object_key" if key.is_none() && value.is_none() => key = Some(node),
+            "duper_value" if value.is_none() && key.is_none() => value = Some(node),
+            _ => (),
+        }
+        current = node.parent();
+    }
+    if let Some(literal) = literal.filter(|literal| !literal.has_error()) {
+        match literal.kind() {
+            "string" => builder.convert_string(literal),
+            "bytes" => builder.convert_bytes(literal),
+            _ => builder.convert_integer(literal),
+        }
+    }
+    if let Some(key) = key.filter(|key| !key.has_error()) {
+        builder.convert_key(key);
+    }
+    if let Some(value) = value.filter(|value| !value.has_error()) {
+        builder.wrap_in_identifier(value);
+    }
+    builder.actions
+}
+
+struct ActionBuilder<'a> {
+    uri: &'a Url,
+    source: &'a str,
+    index: &'a LineIndex,
+    is_utf8: bool,
+    actions: Vec<CodeActionOrCommand>,
+}
+
+impl<'a> ActionBuilder<'a> {
+    fn text(&self, node: Node<'_>) -> &'a str {
+        node.utf8_text(self.source.as_bytes()).unwrap_or_default()
+    }
+
+    fn push(
+        &mut self,
+        title: String,
+        kind: CodeActionKind,
+        edits: Vec<TextEdit>,
+        diagnostics: Option<Vec<Diagnostic>>,
+    ) {
+        self.actions
+            .push(CodeActionOrCommand::CodeAction(CodeAction {
+                title,
+                is_preferred: Some(kind == CodeActionKind::QUICKFIX),
+                kind: Some(kind),
+                diagnostics,
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(self.uri.clone(), edits)])),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }));
+    }
+
+    fn replace(&mut self, title: &str, node: Node<'_>, new_text: String) {
+        let edit = TextEdit::new(to_range(node.range(), self.index, self.is_utf8), new_text);
+        self.push(
+            title.into(),
+            CodeActionKind::REFACTOR_REWRITE,
+            vec![edit],
+            None,
+        );
+    }
+
+    fn convert_string(&mut self, string: Node<'_>) {
+        let Some(inner) = string.named_child(0) else {
+            return;
+        };
+        match inner.kind() {
+            "quoted_string" => {
+                let content = inner
+                    .named_child(0)
+                    .map(|content| self.text(content))
+                    .unwrap_or_default();
+                if let Ok(content) = duper::escape::unescape_str(content) {
+                    let formatted = duper::format::format_duper_string(&content);
+                    if formatted.starts_with('r') {
+                        self.replace("Convert to raw string", string, formatted);
+                    }
+                }
+            }
+            "raw_string" => {
+                let content = Cow::Borrowed(raw_content(inner, self.source));
+                let quoted = format!("\"{}\"", duper::escape::escape_str(&content));
+                self.replace("Convert to quoted string", string, quoted);
+            }
+            _ => (),
+        }
+    }
+
+    fn convert_bytes(&mut self, bytes: Node<'_>) {
+        let Some(inner) = bytes.named_child(0) else {
+            return;
+        };
+        let content = inner.named_child(0).map(|content| self.text(content));
+        let decoded: Cow<'_, [u8]> = match inner.kind() {
+            "quoted_bytes" => match duper::escape::unescape_bytes(content.unwrap_or_default()) {
+                Ok(decoded) => decoded,
+                Err(_) => return,
+            },
+            "raw_bytes" => Cow::Borrowed(raw_content(inner, self.source).as_bytes()),
+            "base64_bytes" => {
+                let encoded: String = content
+                    .unwrap_or_default()
+                    .chars()
+                    .filter(|c| !c.is_ascii_whitespace())
+                    .collect();
+                match base64::engine::GeneralPurpose::new(
+                    &base64::alphabet::STANDARD,
+                    base64::engine::GeneralPurposeConfig::new()
+                        .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent),
+                )
+                .decode(encoded)
+                {
+                    Ok(decoded) => Cow::Owned(decoded),
+                    Err(_) => return,
+                }
+            }
+            _ => return,
+        };
+        if inner.kind() != "base64_bytes" {
+            let encoded = format!("b64\"{}\"", BASE64_STANDARD.encode(&decoded));
+            self.replace("Convert to Base64 bytes", bytes, encoded);
+        }
+        if inner.kind() != "quoted_bytes" {
+            let quoted = format!("b\"{}\"", duper::escape::escape_bytes(&decoded));
+            self.replace("Convert to quoted bytes", bytes, quoted);
+        }
+    }
+
+    fn convert_integer(&mut self, integer: Node<'_>) {
+        let Some(inner) = integer.named_child(0) else {
+            return;
+        };
+        let Ok(DuperValue::Integer { inner: value, .. }) =
+            DuperParser::parse_duper_value(self.text(integer))
+        else {
+            return;
+        };
+        if inner.kind() != "hex_integer" && value >= 0 {
+            self.replace("Convert to hexadecimal", integer, format!("{value:#x}"));
+        }
+        if inner.kind() != "decimal_integer" {
+            self.replace("Convert to decimal", integer, value.to_string());
+        }
+    }
+
+    fn convert_key(&mut self, key: Node<'_>) {
+        let Some(inner) = key.named_child(0) else {
+            return;
+        };
+        let name = key_name(key, self.source);
+        if inner.kind() == "plain_key" {
+            let name = Cow::Borrowed(name.as_str());
+            let quoted = format!("\"{}\"", duper::escape::escape_str(&name));
+            self.replace("Quote key", key, quoted);
+        } else {
+            let name = DuperKey::from(name);
+            let formatted = duper::format::format_key(&name);
+            if !formatted.starts_with('"') {
+                let plain = formatted.into_owned();
+                self.replace("Unquote key", key, plain);
+            }
+        }
+    }
+
+    fn wrap_in_identifier(&mut self, value: Node<'_>) {
+        let mut cursor = value.walk();
+        let Some(inner) = value
+            .named_children(&mut cursor)
+            .find(|child| !matches!(child.kind(), "line_comment" | "block_comment"))
+        else {
+            return;
+        };
+        if inner.kind() != "identified_value" {
+            let wrapped = format!("Identifier({})", self.text(inner));
+            self.replace("Wrap value in identifier", inner, wrapped);
+        }
+    }
+
+    fn remove_duplicate_key(&mut self, tree: &Tree, diagnostic: &Diagnostic) {
+        let Some(point) = to_point(diagnostic.range.start, self.index, self.is_utf8) else {
+            return;
+        };
+        let mut current = tree
+            .root_node()
+            .named_descendant_for_point_range(point, point);
+        while let Some(node) = current
+            && node.kind() != "object_entry"
+        {
+            current = node.parent();
+        }
+        let Some(entry) = current else {
+            return;
+        };
+        let comma = entry.next_sibling().filter(|sibling| sibling.kind() == ",");
+        // Remove the entry along with its separator, keeping the indentation
+        // of the next entry.
+        let range = match comma.and_then(|comma| comma.next_named_sibling()) {
+            Some(next) => entry.start_byte()..next.start_byte(),
+            // The last entry keeps the trailing comma, if any, of the
+            // previous one.
+            None => {
+                let previous = entry.prev_sibling().filter(|sibling| sibling.kind() == ",");
+                match (previous, comma) {
+                    (Some(previous), Some(comma)) => previous.end_byte()..comma.end_byte(),
+                    (Some(previous), None) => previous.start_byte()..entry.end_byte(),
+                    (None, _) => entry.start_byte()..comma.unwrap_or(entry).end_byte(),
+                }
+            }
+        };
+        let edit = TextEdit::new(
+            to_byte_range(range, self.index, self.is_utf8),
+            String::new(),
+        );
+        let title = format!(
+            "Remove duplicate key `{}`",
+            self.text(entry.named_child(0).unwrap_or(entry))
+        );
+        self.push(
+            title,
+            CodeActionKind::QUICKFIX,
+            vec![edit],
+            Some(vec![diagnostic.clone()]),
+        );
+    }
+
+    fn insert_missing_comma(&mut self, diagnostic: &Diagnostic) {
+        let Some(position) = diagnostic
+            .data
+            .clone()
+            .and_then(|data| serde_json::from_value::<Position>(data).ok())
+        else {
+            return;
+        };
+        self.push(
+            "Insert missing comma".into(),
+            CodeActionKind::QUICKFIX,
+            vec![TextEdit::new(Range::new(position, position), ",".into())],
+            Some(vec![diagnostic.clone()]),
+        );
+    }
+}
+
+fn raw_content<'s>(raw: Node<'_>, source: &'s str) -> &'s str {
+    let mut cursor = raw.walk();
+    raw.named_children(&mut cursor)
+        .find(|child| child.kind() == "raw_content")
+        .and_then(|content| content.utf8_text(source.as_bytes()).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod code_actions_tests {
+    use line_index::LineCol;
+
+    use super::*;
+    use crate::diagnostics::get_diagnostics;
+
+    /// Returns the title of each code action at `offset` in `source`, along
+    /// with the document after applying it.
+    fn actions(source: &str, offset: usize) -> Vec<(String, String)> {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let index = LineIndex::new(source);
+        let line_col = index.line_col((offset as u32).into());
+        let position = Position::new(line_col.line, line_col.col);
+        let uri = Url::parse("file:///test.duper").unwrap();
+        let diagnostics = get_diagnostics(source, &tree, true);
+        get_code_actions(
+            &uri,
+            source,
+            &tree,
+            Range::new(position, position),
+            &diagnostics,
+            true,
+        )
+        .into_iter()
+        .map(|action| {
+            let CodeActionOrCommand::CodeAction(action) = action else {
+                panic!("expected a code action");
+            };
+            let mut edits = action.edit.unwrap().changes.unwrap().remove(&uri).unwrap();
+            edits.sort_by_key(|edit| (edit.range.start.line, edit.range.start.character));
+            let mut document = source.to_string();
+            for edit in edits.into_iter().rev() {
+                let offset = |position: Position| {
+                    usize::from(
+                        index
+                            .offset(LineCol {
+                                line: position.line,
+                                col: position.character,
+                            })
+                            .unwrap(),
+                    )
+                };
+                document.replace_range(
+                    offset(edit.range.start)..offset(edit.range.end),
+                    &edit.new_text,
+                );
+            }
+            (action.title, document)
+        })
+        .collect()
+    }
+
+    fn action(source: &str, offset: usize, title: &str) -> Option<String> {
+        actions(source, offset)
+            .into_iter()
+            .find_map(|(action, document)| (action == title).then_some(document))
+    }
+
+    #[test]
+    fn missing_comma() {
+        assert_eq!(
+            action("[1 2]", 0, "Insert missing comma").as_deref(),
+            Some("[1, 2]")
+        );
+        assert_eq!(
+            action("{\n  a: 1\n  b: 2,\n}", 0, "Insert missing comma").as_deref(),
+            Some("{\n  a: 1,\n  b: 2,\n}")
+        );
+        // Other syntax errors have no fix.
+        assert_eq!(action("[1, @]", 0, "Insert missing comma"), None);
+    }
+
+    #[test]
+    fn duplicate_key() {
+        assert_eq!(
+            action("{a: 1, a: 2, b: 3}", 0, "Remove duplicate key `a`").as_deref(),
+            Some("{a: 1, b: 3}")
+        );
+        assert_eq!(
+            action(
+                "{\n  a: 1,\n  b: 2,\n  \"a\": 3,\n}",
+                0,
+                "Remove duplicate key `\"a\"`"
+            )
+            .as_deref(),
+            Some("{\n  a: 1,\n  b: 2,\n}")
+        );
+        assert_eq!(
+            action("{a: 1, b: 2, a: 3}", 0, "Remove duplicate key `a`").as_deref(),
+            Some("{a: 1, b: 2}")
+        );
+    }
+
+    #[test]
+    fn convert_literals() {
+        let source = r#"[r"C:\path", "say \"hi\"", "plain", b"hi", 255, 0xff]"#;
+        let at = |literal: &str| source.find(literal).unwrap() + 1;
+        assert_eq!(
+            action(source, at(r#"r"C"#), "Convert to quoted string").as_deref(),
+            Some(r#"["C:\\path", "say \"hi\"", "plain", b"hi", 255, 0xff]"#)
+        );
+        assert_eq!(
+            action(source, at("\"say"), "Convert to raw string").as_deref(),
+            Some(r##"[r"C:\path", r#"say "hi""#, "plain", b"hi", 255, 0xff]"##)
+        );
+        // Raw strings are only offered if they need fewer escapes.
+        assert_eq!(action(source, at("\"plain"), "Convert to raw string"), None);
+        assert_eq!(
+            action(source, at("b\"hi"), "Convert to Base64 bytes").as_deref(),
+            Some(r#"[r"C:\path", "say \"hi\"", "plain", b64"aGk=", 255, 0xff]"#)
+        );
+        assert_eq!(
+            action(source, at("255"), "Convert to hexadecimal").as_deref(),
+            Some(r#"[r"C:\path", "say \"hi\"", "plain", b"hi", 0xff, 0xff]"#)
+        );
+        assert_eq!(
+            action(source, at("0xff"), "Convert to decimal").as_deref(),
+            Some(r#"[r"C:\path", "say \"hi\"", "plain", b"hi", 255, 255]"#)
+        );
+    }
+
+    #[test]
+    fn convert_keys_and_identifiers() {
+        assert_eq!(
+            action(r#"{a: 1, "b": 2}"#, 1, "Quote key").as_deref(),
+            Some(r#"{"a": 1, "b": 2}"#)
+        );
+        assert_eq!(
+            action(r#"{a: 1, "b": 2}"#, 8, "Unquote key").as_deref(),
+            Some("{a: 1, b: 2}")
+        );
+        assert_eq!(action(r#"{"a b": 1}"#, 2, "Unquote key"), None);
+        assert_eq!(
+            action("{a: 1}", 4, "Wrap value in identifier").as_deref(),
+            Some("{a: Identifier(1)}")
+        );
+        assert_eq!(action("{a: Count(1)}", 4, "Wrap value in identifier"), None);
+    }
+}
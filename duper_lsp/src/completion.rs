@@ -0,0 +1,464 @@
+use duper::Serializer;
+use line_index::{LineCol, LineIndex};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Documentation, MarkupContent, MarkupKind, Position,
+};
+
+use crate::{
+    diagnostics::to_point,
+    schema::{Schema, Segment, escape_key},
+};
+
+/// Returns completions for keys and identifiers at the cursor, as described by
+/// the schema of the document.
+pub(crate) fn get_completions(
+    source: &str,
+    position: Position,
+    schema: &Schema,
+    is_utf8: bool,
+) -> Vec<CompletionItem> {
+    let index = LineIndex::new(source);
+    let Some(offset) = to_point(position, &index, is_utf8).and_then(|point| {
+        index.offset(LineCol {
+            line: point.row as u32,
+            col: point.column as u32,
+        })
+    }) else {
+        return vec![];
+    };
+    let Some(context) = Scanner::context_at(source, usize::from(offset)) else {
+        return vec![];
+    };
+    match context {
+        Context::Key { path, present } => {
+            let Some(schema) = schema.resolve(&path) else {
+                return vec![];
+            };
+            schema
+                .properties()
+                .filter(|(name, _, _)| !present.iter().any(|key| key == name))
+                .map(|(name, property, required)| {
+                    let key = escape_key(name);
+                    CompletionItem {
+                        label: key.to_string(),
+                        kind: Some(CompletionItemKind::PROPERTY),
+                        detail: Some(property.summary()),
+                        documentation: property.description().map(documentation),
+                        insert_text: Some(format!("{key}: ")),
+                        // Suggest required keys first.
+                        sort_text: Some(format!("{}{name}", if required { 0 } else { 1 })),
+                        ..Default::default()
+                    }
+                })
+                .collect()
+        }
+        Context::Value { path } => {
+            let Some(schema) = schema.resolve(&path) else {
+                return vec![];
+            };
+            let identifiers = schema
+                .identifiers()
+                .iter()
+                .map(|identifier| CompletionItem {
+                    label: identifier.clone(),
+                    kind: Some(CompletionItemKind::CLASS),
+                    detail: Some(schema.summary()),
+                    documentation: schema.description().map(documentation),
+                    ..Default::default()
+                });
+            let values = schema.values().iter().map(|value| CompletionItem {
+                label: Serializer::new(false, true).serialize(value),
+                kind: Some(CompletionItemKind::ENUM_MEMBER),
+                documentation: schema.description().map(documentation),
+                ..Default::default()
+            });
+            let keywords = [("true", "boolean"), ("false", "boolean"), ("null", "null")]
+                .into_iter()
+                .filter(|(_, ty)| schema.values().is_empty() && schema.has_type(ty))
+                .map(|(keyword, _)| CompletionItem {
+                    label: keyword.into(),
+                    kind: Some(CompletionItemKind::KEYWORD),
+                    ..Default::default()
+                });
+            identifiers.chain(values).chain(keywords).collect()
+        }
+    }
+}
+
+fn documentation(description: &str) -> Documentation {
+    Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value: description.into(),
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Context {
+    /// The cursor is where a key of an object goes.
+    Key {
+        path: Vec<Segment>,
+        present: Vec<String>,
+    },
+    /// The cursor is where a value goes.
+    Value { path: Vec<Segment> },
+}
+
+enum Frame {
+    Object {
+        key: Option<String>,
+        in_value: bool,
+        keys: Vec<String>,
+    },
+    Array {
+        index: usize,
+    },
+    /// The parentheses of an identified value, e.g. `MyIdentifier(...)`.
+    Identified,
+}
+
+/// A lenient scanner that tracks the structure of a possibly incomplete
+/// document, since the syntax tree is unreliable while a value is being typed.
+struct Scanner<'a> {
+    source: &'a [u8],
+    offset: usize,
+    stack: Vec<Frame>,
+    after_identifier: bool,
+}
+
+impl<'a> Scanner<'a> {
+    fn context_at(source: &'a str, cursor: usize) -> Option<Context> {
+        let mut scanner = Scanner {
+            source: source.as_bytes(),
+            offset: 0,
+            stack: vec![],
+            after_identifier: false,
+        };
+        while scanner.offset < cursor {
+            // The cursor is inside of a string or comment.
+            if !scanner.step(cursor) {
+                return None;
+            }
+        }
+
+        let path = scanner
+            .stack
+            .iter()
+            .filter_map(|frame| match frame {
+                Frame::Object {
+                    key: Some(key),
+                    in_value: true,
+                    ..
+                } => Some(Segment::Key(key.clone())),
+                Frame::Array { index } => Some(Segment::Index(*index)),
+                _ => None,
+            })
+            .collect();
+        if !matches!(
+            scanner.stack.last(),
+            Some(Frame::Object {
+                in_value: false,
+                ..
+            })
+        ) {
+            return Some(Context::Value { path });
+        }
+        // Find the keys after the cursor, up to the end of the object.
+        let depth = scanner.stack.len();
+        while scanner.offset < source.len()
+            && (scanner.stack.len() > depth
+                || !matches!(source.as_bytes()[scanner.offset], b'}' | b']' | b')'))
+        {
+            scanner.step(usize::MAX);
+        }
+        match scanner.stack.pop() {
+            Some(Frame::Object { keys, .. }) => Some(Context::Key {
+                path,
+                present: keys,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Consumes a token. Returns `false` without consuming anything if `end`
+    /// is inside of a string or comment.
+    fn step(&mut self, end: usize) -> bool {
+        let start = self.offset;
+        let rest = &self.source[start..];
+        let after_identifier = std::mem::take(&mut self.after_identifier);
+        match rest {
+            [b' ' | b'\t' | b'\r' | b'\n', ..] => {
+                self.after_identifier = after_identifier;
+                self.offset += 1;
+            }
+            [b'/', b'/', ..] => {
+                let stop = find(self.source, start, b"\n").unwrap_or(self.source.len());
+                if start < end && end <= stop {
+                    return false;
+                }
+                self.after_identifier = after_identifier;
+                self.offset = stop;
+            }
+            [b'/', b'*', ..] => {
+                let stop =
+                    find(self.source, start + 2, b"*/").map_or(self.source.len(), |stop| stop + 2);
+                if start < end && end < stop {
+                    return false;
+                }
+                self.after_identifier = after_identifier;
+                self.offset = stop;
+            }
+            [b'"', ..] => {
+                let (content_end, stop) = quoted_end(self.source, start + 1);
+                if start < end && end < stop {
+                    return false;
+                }
+                let content = String::from_utf8_lossy(&self.source[start + 1..content_end]);
+                let key = duper::escape::unescape_str(&content)
+                    .map(|key| key.into_owned())
+                    .unwrap_or_else(|_| content.to_string());
+                self.record_key(key);
+                self.offset = stop;
+            }
+            [b'r', ..] if raw_hashes(&rest[1..]).is_some() => {
+                let hashes = raw_hashes(&rest[1..]).unwrap_or_default();
+                let content_start = start + 2 + hashes;
+                let (content_end, stop) = raw_end(self.source, content_start, hashes);
+                if start < end && end < stop {
+                    return false;
+                }
+                let key = String::from_utf8_lossy(&self.source[content_start..content_end]);
+                self.record_key(key.into_owned());
+                self.offset = stop;
+            }
+            [b'b', b'r', ..] if raw_hashes(&rest[2..]).is_some() => {
+                let hashes = raw_hashes(&rest[2..]).unwrap_or_default();
+                let (_, stop) = raw_end(self.source, start + 3 + hashes, hashes);
+                if start < end && end < stop {
+                    return false;
+                }
+                self.offset = stop;
+            }
+            [b'b', b'"', ..] | [b'b', b'6', b'4', b'"', ..] => {
+                let quote = if rest[1] == b'"' { 1 } else { 3 };
+                let (_, stop) = quoted_end(self.source, start + quote + 1);
+                if start < end && end < stop {
+                    return false;
+                }
+                self.offset = stop;
+            }
+            [b'\'', ..] => {
+                let stop =
+                    find(self.source, start + 1, b"'").map_or(self.source.len(), |stop| stop + 1);
+                if start < end && end < stop {
+                    return false;
+                }
+                self.offset = stop;
+            }
+            [b'{', ..] => {
+                self.stack.push(Frame::Object {
+                    key: None,
+                    in_value: false,
+                    keys: vec![],
+                });
+                self.offset += 1;
+            }
+            [b'[', ..] => {
+                self.stack.push(Frame::Array { index: 0 });
+                self.offset += 1;
+            }
+            [b'(', ..] => {
+                self.stack.push(if after_identifier {
+                    Frame::Identified
+                } else {
+                    Frame::Array { index: 0 }
+                });
+                self.offset += 1;
+            }
+            [b'}' | b']' | b')', ..] => {
+                self.stack.pop();
+                self.offset += 1;
+            }
+            [b':', ..] => {
+                if let Some(Frame::Object { in_value, .. }) = self.stack.last_mut() {
+                    *in_value = true;
+                }
+                self.offset += 1;
+            }
+            [b',', ..] => {
+                match self.stack.last_mut() {
+                    Some(Frame::Object { key, in_value, .. }) => {
+                        *key = None;
+                        *in_value = false;
+                    }
+                    Some(Frame::Array { index }) => *index += 1,
+                    _ => (),
+                }
+                self.offset += 1;
+            }
+            [byte, ..] if is_word(*byte) => {
+                let stop = rest
+                    .iter()
+                    .position(|byte| !is_word(*byte))
+                    .map_or(self.source.len(), |length| start + length);
+                // A word ending at the cursor is still being typed.
+                if stop != end {
+                    let word = String::from_utf8_lossy(&self.source[start..stop]);
+                    self.after_identifier = word.starts_with(|c: char| c.is_ascii_uppercase());
+                    self.record_key(word.into_owned());
+                }
+                self.offset = stop;
+            }
+            _ => self.offset += 1,
+        }
+        true
+    }
+
+    /// Records the key of the current entry, if the scanner is before the
+    /// colon of an object entry.
+    fn record_key(&mut self, name: String) {
+        if let Some(Frame::Object {
+            key,
+            in_value: false,
+            keys,
+        }) = self.stack.last_mut()
+        {
+            keys.push(name.clone());
+            *key = Some(name);
+        }
+    }
+}
+
+fn is_word(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'+' | b'.')
+}
+
+/// Returns the number of hashes of a raw string's opening delimiter.
+fn raw_hashes(rest: &[u8]) -> Option<usize> {
+    let hashes = rest.iter().take_while(|byte| **byte == b'#').count();
+    (rest.get(hashes) == Some(&b'"')).then_some(hashes)
+}
+
+/// Returns the end of a raw string's content and of the raw string itself.
+fn raw_end(source: &[u8], from: usize, hashes: usize) -> (usize, usize) {
+    let mut delimiter = vec![b'"'];
+    delimiter.extend(std::iter::repeat_n(b'#', hashes));
+    match find(source, from.min(source.len()), &delimiter) {
+        Some(content_end) => (content_end, content_end + delimiter.len()),
+        None => (source.len(), source.len()),
+    }
+}
+
+/// Returns the end of a quoted string's content and of the quoted string
+/// itself.
+fn quoted_end(source: &[u8], mut from: usize) -> (usize, usize) {
+    while let Some(byte) = source.get(from) {
+        match byte {
+            b'\\' => from += 2,
+            b'"' => return (from, from + 1),
+            _ => from += 1,
+        }
+    }
+    (source.len(), source.len())
+}
+
+fn find(haystack: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| from + position)
+}
+
+#[cfg(test)]
+mod completion_tests {
+    use super::*;
+
+    /// Returns the context at the `|` in `source`.
+    fn context(source: &str) -> Option<Context> {
+        let cursor = source.find('|').expect("source has a cursor");
+        Scanner::context_at(&source.replace('|', ""), cursor)
+    }
+
+    fn key(key: &str) -> Segment {
+        Segment::Key(key.into())
+    }
+
+    fn keys(path: Vec<Segment>, present: &[&str]) -> Option<Context> {
+        Some(Context::Key {
+            path,
+            present: present.iter().map(|key| key.to_string()).collect(),
+        })
+    }
+
+    fn value(path: Vec<Segment>) -> Option<Context> {
+        Some(Context::Value { path })
+    }
+
+    #[test]
+    fn nested_objects() {
+        assert_eq!(context("|"), value(vec![]));
+        assert_eq!(context("{|}"), keys(vec![], &[]));
+        assert_eq!(context("{a: 1, |}"), keys(vec![], &["a"]));
+        assert_eq!(context("{a: 1, | c: 3}"), keys(vec![], &["a", "c"]));
+        assert_eq!(context("{a: {b: |}}"), value(vec![key("a"), key("b")]));
+        assert_eq!(
+            context("{a: {b: {}, |}, c: 3}"),
+            keys(vec![key("a")], &["b"])
+        );
+        assert_eq!(
+            context(r#"{"a b": {|}, r"c": {}}"#),
+            keys(vec![key("a b")], &[])
+        );
+        // A key being typed isn't present yet.
+        assert_eq!(context("{a: 1, na|}"), keys(vec![], &["a"]));
+        // Incomplete documents are still scanned.
+        assert_eq!(context("{a: {b: 1, |"), keys(vec![key("a")], &["b"]));
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(context("[|]"), value(vec![Segment::Index(0)]));
+        assert_eq!(
+            context("{items: [1, {|}]}"),
+            keys(vec![key("items"), Segment::Index(1)], &[])
+        );
+        assert_eq!(
+            context("{items: [{a: [1, 2]}, {b: |}]}"),
+            value(vec![key("items"), Segment::Index(1), key("b")])
+        );
+        assert_eq!(context("(1, |)"), value(vec![Segment::Index(1)]));
+        assert_eq!(context(r#"["a,b", |]"#), value(vec![Segment::Index(1)]));
+    }
+
+    #[test]
+    fn identified_values() {
+        assert_eq!(context("{at: Instant(|)}"), value(vec![key("at")]));
+        assert_eq!(context("Config({|})"), keys(vec![], &[]));
+        assert_eq!(
+            context("{a: Point([1, |])}"),
+            value(vec![key("a"), Segment::Index(1)])
+        );
+        // Without an identifier, parentheses are a tuple.
+        assert_eq!(
+            context("{a: (1, |)}"),
+            value(vec![key("a"), Segment::Index(1)])
+        );
+        assert_eq!(
+            context("{a: x(|)}"),
+            value(vec![key("a"), Segment::Index(0)])
+        );
+    }
+
+    #[test]
+    fn strings_and_comments() {
+        assert_eq!(context(r#"{a: "te|xt"}"#), None);
+        assert_eq!(context(r##"{a: r#"te|xt"#}"##), None);
+        assert_eq!(context(r#"{a: b"te|xt"}"#), None);
+        assert_eq!(context("{a: '2024-|01-01'}"), None);
+        assert_eq!(context("{a: 1, // no|te\n}"), None);
+        assert_eq!(context("{a: 1, /* no|te */}"), None);
+        assert_eq!(context("{a: 1, /* note */ |}"), keys(vec![], &["a"]));
+        assert_eq!(context("{a: 1, // {\n|}"), keys(vec![], &["a"]));
+        assert_eq!(context(r#"{a: "}", |}"#), keys(vec![], &["a"]));
+    }
+}
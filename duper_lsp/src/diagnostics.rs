@@ -7,12 +7,20 @@ use std::{
 };
 
 use base64::Engine;
+use duper::{DuperParser, escape::UnescapeError};
 use ipnet::{IpNet, Ipv4Net, Ipv6Net};
-use line_index::{LineCol, LineIndex, WideEncoding};
-use lsp_types::{Diagnostic, DiagnosticSeverity};
+use line_index::{LineCol, LineIndex, WideEncoding, WideLineCol};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
 use regex::Regex;
 use tracing::{error, warn};
-use tree_sitter::{Query, QueryCursor, StreamingIterator, Tree};
+use tree_sitter::{Node, Query, QueryCursor, StreamingIterator, Tree};
+
+/// The code of duplicate key diagnostics.
+pub(crate) const DUPLICATE_KEY: &str = "duplicate-key";
+
+/// The code of syntax error diagnostics caused by a missing comma, whose data
+/// is the position where the comma goes.
+pub(crate) const MISSING_COMMA: &str = "missing-comma";
 
 static QUERY_ERRORS: LazyLock<Query> = LazyLock::new(|| {
     let text = r"(ERROR) @error";
@@ -161,7 +169,9 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
     let mut diagnostics = vec![];
     let index = LineIndex::new(source);
 
-    // Return errors
+    // Return errors. The errors of the core parser are attached to the closest
+    // syntax error before them, for a more precise location and message.
+    let mut error_nodes = vec![];
     let query = &QUERY_ERRORS;
     let mut cursor = QueryCursor::new();
     let mut captures = cursor.captures(query, tree.root_node(), source.as_bytes());
@@ -169,18 +179,55 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
         captures.advance();
         captures.get()
     } {
-        diagnostics.extend(m.captures.iter().map(|c| {
-            Diagnostic::new(
-                to_range(c.node.range(), &index, is_utf8),
+        error_nodes.extend(m.captures.iter().map(|c| (c.node, None)));
+    }
+    // Parser errors without a syntax error of their own, which are only
+    // reported if no other diagnostic covers them.
+    let mut other_errors = vec![];
+    if !error_nodes.is_empty() {
+        let (_, errors) = DuperParser::parse_duper_value_recovering(source);
+        for err in errors {
+            let err = (err.span().into_range(), err.to_string());
+            let closest = error_nodes
+                .iter_mut()
+                .filter(|(node, _)| node.start_byte() <= err.0.start)
+                .max_by_key(|(node, _)| node.start_byte());
+            match closest {
+                Some((_, parser_error @ None)) => *parser_error = Some(err),
+                _ => other_errors.push(err),
+            }
+        }
+    }
+    let syntax_error = |(span, message): &(std::ops::Range<usize>, String)| {
+        Diagnostic::new(
+            to_byte_range(span.clone(), &index, is_utf8),
+            Some(DiagnosticSeverity::ERROR),
+            None,
+            None,
+            format!("Syntax error: {message}"),
+            None,
+            None,
+        )
+    };
+    diagnostics.extend(error_nodes.into_iter().map(|(node, parser_error)| {
+        let mut diagnostic = match &parser_error {
+            Some(err) => syntax_error(err),
+            None => Diagnostic::new(
+                to_range(node.range(), &index, is_utf8),
                 Some(DiagnosticSeverity::ERROR),
                 None,
                 None,
                 "Syntax error".into(),
                 None,
                 None,
-            )
-        }));
-    }
+            ),
+        };
+        if let Some(point) = missing_comma(node) {
+            diagnostic.code = Some(NumberOrString::String(MISSING_COMMA.into()));
+            diagnostic.data = serde_json::to_value(to_position(point, &index, is_utf8)).ok();
+        }
+        diagnostic
+    }));
 
     // Return missing values
     let query = &QUERY_MISSING;
@@ -222,7 +269,7 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
                             diagnostics.push(Diagnostic::new(
                                 to_range(node.range(), &index, is_utf8),
                                 Some(DiagnosticSeverity::ERROR),
-                                None,
+                                Some(NumberOrString::String(DUPLICATE_KEY.into())),
                                 None,
                                 "Duplicate key".to_string(),
                                 None,
@@ -249,7 +296,7 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
                                 diagnostics.push(Diagnostic::new(
                                     to_range(node.range(), &index, is_utf8),
                                     Some(DiagnosticSeverity::ERROR),
-                                    None,
+                                    Some(NumberOrString::String(DUPLICATE_KEY.into())),
                                     None,
                                     "Duplicate key".to_string(),
                                     None,
@@ -259,15 +306,8 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
                                 keys.insert(str);
                             }
                         }
-                        Err(err) => diagnostics.push(Diagnostic::new(
-                            to_range(node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            format!("Invalid string: {err}"),
-                            None,
-                            None,
-                        )),
+                        Err(err) => diagnostics
+                            .push(escape_diagnostic(node, source, err, false, &index, is_utf8)),
                     },
                     Err(err) => diagnostics.push(Diagnostic::new(
                         to_range(node.range(), &index, is_utf8),
@@ -285,7 +325,7 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
                             diagnostics.push(Diagnostic::new(
                                 to_range(node.range(), &index, is_utf8),
                                 Some(DiagnosticSeverity::ERROR),
-                                None,
+                                Some(NumberOrString::String(DUPLICATE_KEY.into())),
                                 None,
                                 "Duplicate key".to_string(),
                                 None,
@@ -331,15 +371,8 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
                 "quote" => match node.utf8_text(source.as_bytes()) {
                     Ok(escaped_str) => {
                         if let Err(err) = duper::escape::unescape_str(escaped_str) {
-                            diagnostics.push(Diagnostic::new(
-                                to_range(node.range(), &index, is_utf8),
-                                Some(DiagnosticSeverity::ERROR),
-                                None,
-                                None,
-                                format!("Invalid string: {err}"),
-                                None,
-                                None,
-                            ));
+                            diagnostics
+                                .push(escape_diagnostic(node, source, err, false, &index, is_utf8));
                         }
                     }
                     Err(err) => diagnostics.push(Diagnostic::new(
@@ -378,15 +411,8 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
                 "quote" => match node.utf8_text(source.as_bytes()) {
                     Ok(escaped_bytes) => {
                         if let Err(err) = duper::escape::unescape_bytes(escaped_bytes) {
-                            diagnostics.push(Diagnostic::new(
-                                to_range(node.range(), &index, is_utf8),
-                                Some(DiagnosticSeverity::ERROR),
-                                None,
-                                None,
-                                format!("Invalid bytes: {err}"),
-                                None,
-                                None,
-                            ));
+                            diagnostics
+                                .push(escape_diagnostic(node, source, err, true, &index, is_utf8));
                         }
                     }
                     Err(err) => diagnostics.push(Diagnostic::new(
@@ -455,15 +481,37 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
                                     None,
                                     None,
                                 )),
-                                Err(err) => diagnostics.push(Diagnostic::new(
-                                    to_range(node.range(), &index, is_utf8),
-                                    Some(DiagnosticSeverity::ERROR),
-                                    None,
-                                    None,
-                                    format!("Invalid Base64 bytes: {err}"),
-                                    None,
-                                    None,
-                                )),
+                                Err(err) => {
+                                    // Point to the offending symbol, skipping whitespace
+                                    let symbol = match err {
+                                        base64::DecodeError::InvalidByte(offset, _)
+                                        | base64::DecodeError::InvalidLastSymbol(offset, _) => {
+                                            encoded_bytes
+                                                .char_indices()
+                                                .filter(|(_, c)| !c.is_ascii_whitespace())
+                                                .nth(offset)
+                                        }
+                                        _ => None,
+                                    };
+                                    let range = match symbol {
+                                        Some((offset, c)) => to_byte_range(
+                                            node.start_byte() + offset
+                                                ..node.start_byte() + offset + c.len_utf8(),
+                                            &index,
+                                            is_utf8,
+                                        ),
+                                        None => to_range(node.range(), &index, is_utf8),
+                                    };
+                                    diagnostics.push(Diagnostic::new(
+                                        range,
+                                        Some(DiagnosticSeverity::ERROR),
+                                        None,
+                                        None,
+                                        format!("Invalid Base64 bytes: {err}"),
+                                        None,
+                                        None,
+                                    ))
+                                }
                             }
                         }
                     }
@@ -545,105 +593,89 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
             match identifier {
                 "Instant" => {
                     if !duper::validate::is_valid_instant(temporal) {
-                        diagnostics.push(Diagnostic::new(
-                            to_range(temporal_node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            "Invalid Instant".into(),
-                            None,
-                            None,
+                        diagnostics.push(literal_diagnostic(
+                            temporal_node,
+                            source,
+                            "Invalid Instant",
+                            &index,
+                            is_utf8,
                         ));
                     }
                 }
                 "ZonedDateTime" => {
                     if !duper::validate::is_valid_zoned_date_time(temporal) {
-                        diagnostics.push(Diagnostic::new(
-                            to_range(temporal_node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            "Invalid ZonedDateTime".into(),
-                            None,
-                            None,
+                        diagnostics.push(literal_diagnostic(
+                            temporal_node,
+                            source,
+                            "Invalid ZonedDateTime",
+                            &index,
+                            is_utf8,
                         ));
                     }
                 }
                 "PlainDate" => {
                     if !duper::validate::is_valid_plain_date(temporal) {
-                        diagnostics.push(Diagnostic::new(
-                            to_range(temporal_node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            "Invalid PlainDate".into(),
-                            None,
-                            None,
+                        diagnostics.push(literal_diagnostic(
+                            temporal_node,
+                            source,
+                            "Invalid PlainDate",
+                            &index,
+                            is_utf8,
                         ));
                     }
                 }
                 "PlainTime" => {
                     if !duper::validate::is_valid_plain_time(temporal) {
-                        diagnostics.push(Diagnostic::new(
-                            to_range(temporal_node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            "Invalid PlainTime".into(),
-                            None,
-                            None,
+                        diagnostics.push(literal_diagnostic(
+                            temporal_node,
+                            source,
+                            "Invalid PlainTime",
+                            &index,
+                            is_utf8,
                         ));
                     }
                 }
                 "PlainDateTime" => {
                     if !duper::validate::is_valid_plain_date_time(temporal) {
-                        diagnostics.push(Diagnostic::new(
-                            to_range(temporal_node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            "Invalid PlainDateTime".into(),
-                            None,
-                            None,
+                        diagnostics.push(literal_diagnostic(
+                            temporal_node,
+                            source,
+                            "Invalid PlainDateTime",
+                            &index,
+                            is_utf8,
                         ));
                     }
                 }
                 "PlainYearMonth" => {
                     if !duper::validate::is_valid_plain_year_month(temporal) {
-                        diagnostics.push(Diagnostic::new(
-                            to_range(temporal_node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            "Invalid PlainYearMonth".into(),
-                            None,
-                            None,
+                        diagnostics.push(literal_diagnostic(
+                            temporal_node,
+                            source,
+                            "Invalid PlainYearMonth",
+                            &index,
+                            is_utf8,
                         ));
                     }
                 }
                 "PlainMonthDay" => {
                     if !duper::validate::is_valid_plain_month_day(temporal) {
-                        diagnostics.push(Diagnostic::new(
-                            to_range(temporal_node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            "Invalid PlainMonthDay".into(),
-                            None,
-                            None,
+                        diagnostics.push(literal_diagnostic(
+                            temporal_node,
+                            source,
+                            "Invalid PlainMonthDay",
+                            &index,
+                            is_utf8,
                         ));
                     }
                 }
                 "Duration" => {
                     if !duper::validate::is_valid_duration(temporal) {
-                        diagnostics.push(Diagnostic::new(
-                            to_range(temporal_node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            "Invalid Duration".into(),
-                            None,
-                            None,
+                        diagnostics.push(literal_diagnostic(
+                            temporal_node,
+                            source,
+                            "Invalid Duration",
+                            &index,
+                            is_utf8,
                         ));
                     }
                 }
@@ -658,14 +690,12 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
                         None,
                     ));
                     if !duper::validate::is_valid_unspecified_temporal(temporal) {
-                        diagnostics.push(Diagnostic::new(
-                            to_range(temporal_node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            "Invalid Temporal value".to_string(),
-                            None,
-                            None,
+                        diagnostics.push(literal_diagnostic(
+                            temporal_node,
+                            source,
+                            "Invalid Temporal value",
+                            &index,
+                            is_utf8,
                         ));
                     }
                 }
@@ -693,14 +723,12 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
             match capture_name {
                 "temporal" => match node.utf8_text(source.as_bytes()) {
                     Ok(parsed) if !duper::validate::is_valid_unspecified_temporal(parsed) => {
-                        diagnostics.push(Diagnostic::new(
-                            to_range(node.range(), &index, is_utf8),
-                            Some(DiagnosticSeverity::ERROR),
-                            None,
-                            None,
-                            "Invalid Temporal".into(),
-                            None,
-                            None,
+                        diagnostics.push(literal_diagnostic(
+                            node,
+                            source,
+                            "Invalid Temporal",
+                            &index,
+                            is_utf8,
                         ));
                     }
                     Ok(_) => (),
@@ -738,7 +766,9 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
             let node = capture.node;
             match capture_name {
                 "integer" => match node.utf8_text(source.as_bytes()) {
-                    Ok(parsed) if !duper::validate::is_valid_integer(parsed) => {
+                    Ok(parsed)
+                        if !node.is_missing() && !duper::validate::is_valid_integer(parsed) =>
+                    {
                         diagnostics.push(Diagnostic::new(
                             to_range(node.range(), &index, is_utf8),
                             Some(DiagnosticSeverity::ERROR),
@@ -784,7 +814,9 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
             let node = capture.node;
             match capture_name {
                 "float" => match node.utf8_text(source.as_bytes()) {
-                    Ok(parsed) if !duper::validate::is_valid_float(parsed) => {
+                    Ok(parsed)
+                        if !node.is_missing() && !duper::validate::is_valid_float(parsed) =>
+                    {
                         diagnostics.push(Diagnostic::new(
                             to_range(node.range(), &index, is_utf8),
                             Some(DiagnosticSeverity::ERROR),
@@ -854,15 +886,8 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
                             value = Some((str, node));
                         }
                         Err(err) => {
-                            diagnostics.push(Diagnostic::new(
-                                to_range(node.range(), &index, is_utf8),
-                                Some(DiagnosticSeverity::ERROR),
-                                None,
-                                None,
-                                format!("Invalid UTF-8: {err}"),
-                                None,
-                                None,
-                            ));
+                            diagnostics
+                                .push(escape_diagnostic(node, source, err, false, &index, is_utf8));
                             continue 'm;
                         }
                     },
@@ -1012,45 +1037,323 @@ pub(crate) fn get_diagnostics(source: &str, tree: &Tree, is_utf8: bool) -> Vec<D
         }
     }
 
+    // Return the remaining parser errors, such as those before the first
+    // syntax error or after another one in the same node, unless they overlap
+    // or touch another diagnostic (e.g. the parser fails right after an integer
+    // that overflows).
+    for err in other_errors {
+        let diagnostic = syntax_error(&err);
+        let range = diagnostic.range;
+        if !diagnostics.iter().any(|other| {
+            let other = other.range;
+            other.start <= range.end && range.start <= other.end
+        }) {
+            diagnostics.push(diagnostic);
+        }
+    }
+
     diagnostics
 }
 
-fn to_range(range: tree_sitter::Range, index: &LineIndex, is_utf8: bool) -> lsp_types::Range {
-    let start_line_col = LineCol {
-        line: range.start_point.row as u32,
-        col: range.start_point.column as u32,
+/// Re-parses an invalid literal with the core parser, in order to report the
+/// same message at the precise location of the error. `node` is a
+/// `temporal_content` node, so the whole value is parsed to keep its
+/// identifier and quotes.
+/// Returns where the comma goes if the syntax error is an entry or element
+/// followed by another one without a comma in between, which tree-sitter
+/// parses as an error around the first one.
+fn missing_comma(error: Node<'_>) -> Option<tree_sitter::Point> {
+    let child = error.named_child(0)?;
+    let next = error.next_named_sibling()?;
+    (error.named_child_count() == 1
+        && matches!(child.kind(), "object_entry" | "duper_value")
+        && !child.has_error()
+        && next.kind() == child.kind())
+    .then(|| child.end_position())
+}
+
+fn literal_diagnostic(
+    node: tree_sitter::Node<'_>,
+    source: &str,
+    summary: &str,
+    index: &LineIndex,
+    is_utf8: bool,
+) -> Diagnostic {
+    let value = match node.parent() {
+        Some(temporal) => match temporal.parent() {
+            Some(identified) if identified.kind() == "identified_value" => identified,
+            _ => temporal,
+        },
+        None => node,
     };
-    let end_line_col = LineCol {
-        line: range.end_point.row as u32,
-        col: range.end_point.column as u32,
+    let error = DuperParser::parse_duper_value(&source[value.byte_range()])
+        .err()
+        .and_then(|errors| errors.into_iter().next());
+    let (range, message) = match error {
+        Some(err) => {
+            let span = err.span().into_range();
+            (
+                to_byte_range(
+                    value.start_byte() + span.start..value.start_byte() + span.end,
+                    index,
+                    is_utf8,
+                ),
+                format!("{summary}: {err}"),
+            )
+        }
+        None => (to_range(node.range(), index, is_utf8), summary.into()),
     };
-    if is_utf8 {
-        lsp_types::Range::new(
-            lsp_types::Position {
-                line: start_line_col.line,
-                character: start_line_col.col,
-            },
-            lsp_types::Position {
-                line: end_line_col.line,
-                character: end_line_col.col,
+    Diagnostic::new(
+        range,
+        Some(DiagnosticSeverity::ERROR),
+        None,
+        None,
+        message,
+        None,
+        None,
+    )
+}
+
+/// Narrows down an unescaping error in quoted content to the escape sequence
+/// that caused it, e.g. a surrogate `\uD800` or a truncated `\xC3` in a string.
+fn escape_diagnostic(
+    content: tree_sitter::Node<'_>,
+    source: &str,
+    err: UnescapeError,
+    is_bytes: bool,
+    index: &LineIndex,
+    is_utf8: bool,
+) -> Diagnostic {
+    let mut cursor = content.walk();
+    let escapes: Vec<_> = content
+        .named_children(&mut cursor)
+        .filter(|child| child.kind() == "quoted_escape")
+        .collect();
+    let mut located = None;
+    let mut i = 0;
+    while i < escapes.len() {
+        // In strings, a `\x` escape starts a UTF-8 sequence of adjacent escapes
+        let text = &source[escapes[i].byte_range()];
+        let length = match text
+            .strip_prefix("\\x")
+            .map(|hex| u8::from_str_radix(hex, 16))
+        {
+            Some(Ok(byte)) if !is_bytes => match byte {
+                byte if byte & 0xE0 == 0xC0 => 2,
+                byte if byte & 0xF0 == 0xE0 => 3,
+                byte if byte & 0xF8 == 0xF0 => 4,
+                _ => 1,
             },
-        )
+            _ => 1,
+        };
+        let mut end = i;
+        while end + 1 < escapes.len()
+            && end + 1 < i + length
+            && escapes[end + 1].start_byte() == escapes[end].end_byte()
+        {
+            end += 1;
+        }
+        let range = escapes[i].start_byte()..escapes[end].end_byte();
+        let result = if is_bytes {
+            duper::escape::unescape_bytes(&source[range.clone()]).map(|_| ())
+        } else {
+            duper::escape::unescape_str(&source[range.clone()]).map(|_| ())
+        };
+        if let Err(err) = result {
+            located = Some((to_byte_range(range, index, is_utf8), err));
+            break;
+        }
+        i = end + 1;
+    }
+    let (range, err) = located.unwrap_or((to_range(content.range(), index, is_utf8), err));
+    Diagnostic::new(
+        range,
+        Some(DiagnosticSeverity::ERROR),
+        None,
+        None,
+        if is_bytes {
+            format!("Invalid bytes: {err}")
+        } else {
+            format!("Invalid string: {err}")
+        },
+        None,
+        None,
+    )
+}
+
+pub(crate) fn to_byte_range(
+    range: std::ops::Range<usize>,
+    index: &LineIndex,
+    is_utf8: bool,
+) -> lsp_types::Range {
+    let to_point = |offset: usize| {
+        let line_col = index.line_col((offset as u32).into());
+        tree_sitter::Point::new(line_col.line as usize, line_col.col as usize)
+    };
+    lsp_types::Range::new(
+        to_position(to_point(range.start), index, is_utf8),
+        to_position(to_point(range.end), index, is_utf8),
+    )
+}
+
+pub(crate) fn to_range(
+    range: tree_sitter::Range,
+    index: &LineIndex,
+    is_utf8: bool,
+) -> lsp_types::Range {
+    lsp_types::Range::new(
+        to_position(range.start_point, index, is_utf8),
+        to_position(range.end_point, index, is_utf8),
+    )
+}
+
+pub(crate) fn to_position(
+    point: tree_sitter::Point,
+    index: &LineIndex,
+    is_utf8: bool,
+) -> lsp_types::Position {
+    let line_col = LineCol {
+        line: point.row as u32,
+        col: point.column as u32,
+    };
+    if is_utf8 {
+        lsp_types::Position {
+            line: line_col.line,
+            character: line_col.col,
+        }
     } else {
-        let wide_start_line_col = index
-            .to_wide(WideEncoding::Utf16, start_line_col)
-            .expect("integer overflow");
-        let wide_end_line_col = index
-            .to_wide(WideEncoding::Utf16, end_line_col)
+        let wide_line_col = index
+            .to_wide(WideEncoding::Utf16, line_col)
             .expect("integer overflow");
-        lsp_types::Range::new(
-            lsp_types::Position {
-                line: wide_start_line_col.line,
-                character: wide_start_line_col.col,
-            },
-            lsp_types::Position {
-                line: wide_end_line_col.line,
-                character: wide_end_line_col.col,
+        lsp_types::Position {
+            line: wide_line_col.line,
+            character: wide_line_col.col,
+        }
+    }
+}
+
+pub(crate) fn to_point(
+    position: lsp_types::Position,
+    index: &LineIndex,
+    is_utf8: bool,
+) -> Option<tree_sitter::Point> {
+    let line_col = if is_utf8 {
+        LineCol {
+            line: position.line,
+            col: position.character,
+        }
+    } else {
+        index.to_utf8(
+            WideEncoding::Utf16,
+            WideLineCol {
+                line: position.line,
+                col: position.character,
             },
-        )
+        )?
+    };
+    Some(tree_sitter::Point::new(
+        line_col.line as usize,
+        line_col.col as usize,
+    ))
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use super::*;
+
+    /// Returns the text and message of each diagnostic in `source`.
+    fn diagnostics(source: &str) -> Vec<(String, String)> {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let index = LineIndex::new(source);
+        let offset = |position: lsp_types::Position| {
+            let point = to_point(position, &index, true).unwrap();
+            usize::from(
+                index
+                    .offset(LineCol {
+                        line: point.row as u32,
+                        col: point.column as u32,
+                    })
+                    .unwrap(),
+            )
+        };
+        get_diagnostics(source, &tree, true)
+            .into_iter()
+            .map(|diagnostic| {
+                let range = offset(diagnostic.range.start)..offset(diagnostic.range.end);
+                (source[range].to_string(), diagnostic.message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let message =
+            "Integer cannot be represented with I64\n  = hint: consider using a string instead";
+        assert_eq!(
+            diagnostics(
+                "{a: 9223372036854775808, b: -9223372036854775809, c: -9223372036854775808}"
+            ),
+            [
+                ("9223372036854775808".into(), message.into()),
+                ("-9223372036854775809".into(), message.into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_temporal() {
+        assert_eq!(
+            diagnostics("{at: Instant('2024-13-45')}"),
+            [(
+                "3".into(),
+                "Invalid Instant: found '3' expected '0', '1', or '2'".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        // Duper has no `\u{...}` escapes.
+        assert!(diagnostics(r#"{a: "\u{1F600}"}"#).contains(&(
+            "{".into(),
+            "Syntax error: found '{' expected a hexadecimal digit".into()
+        )));
+        let diagnostics = diagnostics(r#"{a: "ok \uD800"}"#);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, r"\uD800");
+        assert!(diagnostics[0].1.starts_with("Invalid string: "));
+    }
+
+    #[test]
+    fn invalid_base64() {
+        assert!(diagnostics(r#"{a: b64"abc$"}"#).contains(&(
+            "$".into(),
+            r#"Syntax error: found '$' expected a Base64 digit, '=', or '"'"#.into()
+        )));
+        assert_eq!(
+            diagnostics(r#"{a: b64"abc"}"#),
+            [("abc".into(), "Missing padding".into())]
+        );
+    }
+
+    #[test]
+    fn every_parser_error() {
+        // Several errors in the same syntax error.
+        let message = "Syntax error: found '{}' expected '/', or ','";
+        assert_eq!(
+            diagnostics("[1, 2 3 4 5]"),
+            ["3", "4", "5"].map(|found| (found.into(), message.replace("{}", found)))
+        );
+        // An error before the first syntax error.
+        assert!(diagnostics(r#""x": {[}"#).contains(&(
+            ":".into(),
+            "Syntax error: found ':' expected '/', or end of input".into()
+        )));
+        // Errors covered by other diagnostics are only reported once.
+        assert_eq!(diagnostics("{a: 9223372036854775808, b: ]}").len(), 2);
     }
 }
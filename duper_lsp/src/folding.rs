@@ -0,0 +1,110 @@
+use lsp_types::{FoldingRange, FoldingRangeKind};
+use tree_sitter::{Node, Tree};
+
+use crate::symbols::walk;
+
+/// Returns the folding ranges for multi-line objects, arrays, tuples, and
+/// comments. Consecutive line comments are folded together.
+pub(crate) fn get_folding_ranges(tree: &Tree) -> Vec<FoldingRange> {
+    let mut ranges = vec![];
+    let mut line_comments: Option<(usize, usize)> = None;
+    walk(tree.root_node(), |node| {
+        visit(node, &mut ranges, &mut line_comments)
+    });
+    if let Some((start, end)) = line_comments {
+        push(&mut ranges, start, end, Some(FoldingRangeKind::Comment));
+    }
+    ranges.sort_by_key(|range| (range.start_line, range.end_line));
+    ranges
+}
+
+fn visit(
+    node: Node<'_>,
+    ranges: &mut Vec<FoldingRange>,
+    line_comments: &mut Option<(usize, usize)>,
+) -> bool {
+    let start = node.start_position().row;
+    let end = node.end_position().row;
+    match node.kind() {
+        "line_comment" => {
+            *line_comments = match *line_comments {
+                Some((first, last)) if start == last + 1 => Some((first, start)),
+                previous => {
+                    if let Some((first, last)) = previous {
+                        push(ranges, first, last, Some(FoldingRangeKind::Comment));
+                    }
+                    Some((start, start))
+                }
+            };
+            return false;
+        }
+        "block_comment" => push(ranges, start, end, Some(FoldingRangeKind::Comment)),
+        // Keep the closing bracket visible.
+        "object" | "array" | "tuple" => push(ranges, start, end.saturating_sub(1), None),
+        _ => (),
+    }
+    true
+}
+
+fn push(ranges: &mut Vec<FoldingRange>, start: usize, end: usize, kind: Option<FoldingRangeKind>) {
+    if end > start {
+        ranges.push(FoldingRange {
+            start_line: start as u32,
+            start_character: None,
+            end_line: end as u32,
+            end_character: None,
+            kind,
+            collapsed_text: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod folding_tests {
+    use super::*;
+
+    fn folding_ranges(source: &str) -> Vec<(u32, u32, Option<FoldingRangeKind>)> {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        get_folding_ranges(&tree)
+            .into_iter()
+            .map(|range| (range.start_line, range.end_line, range.kind))
+            .collect()
+    }
+
+    #[test]
+    fn nested_ranges() {
+        let source = r#"{
+  a: [
+    (1,
+     2),
+    {b: 3},
+  ],
+  // first
+  // second
+
+  // third
+  c: /* multi-line
+        comment */ 4,
+}"#;
+        assert_eq!(
+            folding_ranges(source),
+            vec![
+                (0, 11, None),
+                (1, 4, None),
+                (6, 7, Some(FoldingRangeKind::Comment)),
+                (10, 11, Some(FoldingRangeKind::Comment)),
+            ]
+        );
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let source = format!("{}1{}", "[\n".repeat(100_000), "]".repeat(100_000));
+        // The innermost array is on a single line.
+        assert_eq!(folding_ranges(&source).len(), 99_999);
+    }
+}
@@ -0,0 +1,322 @@
+use duperfmt::format_duper;
+use line_index::{LineCol, LineIndex};
+use lsp_types::{FormattingOptions, Position, Range, TextEdit};
+use tracing::debug;
+use tree_sitter::{Node, Tree};
+
+use crate::diagnostics::{to_byte_range, to_point};
+
+/// Returns the indentation for the formatting options.
+pub(crate) fn indent(options: &FormattingOptions) -> String {
+    if options.insert_spaces {
+        (0..options.tab_size).map(|_| ' ').collect()
+    } else {
+        "\t".into()
+    }
+}
+
+/// Formats the smallest value without errors that contains the range.
+///
+/// If every value containing the range has errors, the values without errors
+/// inside of the range are formatted individually instead.
+pub(crate) fn get_range_formatting(
+    source: &str,
+    tree: &Tree,
+    range: Range,
+    indent: &str,
+    is_utf8: bool,
+    debug: bool,
+) -> Vec<TextEdit> {
+    let index = LineIndex::new(source);
+    let (Some(start), Some(end)) = (
+        to_offset(range.start, &index, is_utf8),
+        to_offset(range.end, &index, is_utf8),
+    ) else {
+        return vec![];
+    };
+    format_nodes(source, tree, start..end, indent, &index, is_utf8, debug)
+}
+
+/// Formats the value that was just closed by `}` or `]`, or the container of
+/// the element or entry that was just ended by `,`.
+pub(crate) fn get_on_type_formatting(
+    source: &str,
+    tree: &Tree,
+    position: Position,
+    ch: &str,
+    indent: &str,
+    is_utf8: bool,
+    debug: bool,
+) -> Vec<TextEdit> {
+    let index = LineIndex::new(source);
+    let Some(end) = to_offset(position, &index, is_utf8) else {
+        return vec![];
+    };
+    let Some(token) = end
+        .checked_sub(ch.len())
+        .and_then(|start| tree.root_node().descendant_for_byte_range(start, end))
+        .filter(|token| token.kind() == ch && token.end_byte() == end)
+    else {
+        return vec![];
+    };
+    let start = match ch {
+        "," => token
+            .prev_named_sibling()
+            .map_or(token.start_byte(), |sibling| sibling.start_byte()),
+        _ => token.start_byte(),
+    };
+    format_nodes(source, tree, start..end, indent, &index, is_utf8, debug)
+}
+
+fn format_nodes(
+    source: &str,
+    tree: &Tree,
+    range: std::ops::Range<usize>,
+    indent: &str,
+    index: &LineIndex,
+    is_utf8: bool,
+    debug: bool,
+) -> Vec<TextEdit> {
+    let Some(node) = tree
+        .root_node()
+        .descendant_for_byte_range(range.start, range.end)
+    else {
+        return vec![];
+    };
+    let mut current = Some(node);
+    while let Some(node) = current
+        && !node.has_error()
+    {
+        if node.kind() == "duper_value" {
+            return format_node(source, node, indent, index, is_utf8, debug)
+                .into_iter()
+                .collect();
+        }
+        current = node.parent();
+    }
+
+    let mut nodes = vec![];
+    collect_values(node, &range, &mut nodes);
+    nodes
+        .into_iter()
+        .filter_map(|node| format_node(source, node, indent, index, is_utf8, debug))
+        .collect()
+}
+
+/// Collects the outermost values without errors that intersect the range.
+fn collect_values<'t>(node: Node<'t>, range: &std::ops::Range<usize>, nodes: &mut Vec<Node<'t>>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if child.end_byte() <= range.start || child.start_byte() >= range.end {
+            continue;
+        }
+        if child.kind() == "duper_value" && !child.has_error() {
+            nodes.push(child);
+        } else {
+            collect_values(child, range, nodes);
+        }
+    }
+}
+
+/// Formats a value on its own, indenting it to match the line it starts on.
+fn format_node(
+    source: &str,
+    node: Node<'_>,
+    indent: &str,
+    index: &LineIndex,
+    is_utf8: bool,
+    debug: bool,
+) -> Option<TextEdit> {
+    let input = &source[node.byte_range()];
+    let mut parser = tree_sitter::Parser::new();
+    parser
+        .set_language(&tree_sitter_duper::LANGUAGE.into())
+        .expect("Error loading Duper parser");
+    let tree = parser.parse(input, None)?;
+    if tree.root_node().has_error() {
+        return None;
+    }
+    let mut buf = Vec::new();
+    if let Err(err) = format_duper(tree, input, &mut buf, Some(indent.into()), debug) {
+        debug!(?err, "Failed to format Duper value");
+        return None;
+    }
+    let formatted = String::from_utf8(buf).expect("formatting output is valid Duper");
+    let formatted = formatted.trim_end();
+
+    let line_start = source[..node.start_byte()]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line_indent: String = source[line_start..node.start_byte()]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+    let new_text = reindent(formatted, &line_indent, &mut parser);
+    (new_text != input)
+        .then(|| TextEdit::new(to_byte_range(node.byte_range(), index, is_utf8), new_text))
+}
+
+/// Prefixes every line but the first with the indentation, except for lines
+/// inside of multi-line strings and comments.
+fn reindent(formatted: &str, line_indent: &str, parser: &mut tree_sitter::Parser) -> String {
+    if line_indent.is_empty() {
+        return formatted.into();
+    }
+    let tree = parser.parse(formatted, None);
+    let mut output = String::with_capacity(formatted.len());
+    let mut offset = 0;
+    for (i, line) in formatted.split('\n').enumerate() {
+        if i > 0 {
+            output.push('\n');
+            let newline = offset - 1;
+            let verbatim = tree.as_ref().is_some_and(|tree| {
+                tree.root_node()
+                    .descendant_for_byte_range(newline, newline + 1)
+                    .is_some_and(|node| {
+                        matches!(
+                            node.kind(),
+                            "raw_content" | "base64_content" | "block_comment"
+                        )
+                    })
+            });
+            if !line.is_empty() && !verbatim {
+                output.push_str(line_indent);
+            }
+        }
+        output.push_str(line);
+        offset += line.len() + 1;
+    }
+    output
+}
+
+fn to_offset(position: Position, index: &LineIndex, is_utf8: bool) -> Option<usize> {
+    let point = to_point(position, index, is_utf8)?;
+    index
+        .offset(LineCol {
+            line: point.row as u32,
+            col: point.column as u32,
+        })
+        .map(usize::from)
+}
+
+#[cfg(test)]
+mod formatting_tests {
+    use super::*;
+
+    fn parse(source: &str) -> Tree {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    fn apply(source: &str, edits: Vec<TextEdit>, is_utf8: bool) -> String {
+        let index = LineIndex::new(source);
+        let mut document = source.to_string();
+        for edit in edits.into_iter().rev() {
+            let range = to_offset(edit.range.start, &index, is_utf8).unwrap()
+                ..to_offset(edit.range.end, &index, is_utf8).unwrap();
+            document.replace_range(range, &edit.new_text);
+        }
+        document
+    }
+
+    /// Formats the range between the first two `|` in `source`.
+    fn range_formatting(source: &str) -> String {
+        let start = source.find('|').unwrap();
+        let end = source.rfind('|').unwrap() - 1;
+        let source = source.replace('|', "");
+        let index = LineIndex::new(&source);
+        let position = |offset: usize| {
+            let line_col = index.line_col((offset as u32).into());
+            Position::new(line_col.line, line_col.col)
+        };
+        let edits = get_range_formatting(
+            &source,
+            &parse(&source),
+            Range::new(position(start), position(end)),
+            "  ",
+            true,
+            false,
+        );
+        apply(&source, edits, true)
+    }
+
+    /// Formats after typing the character before `|` in `source`.
+    fn on_type_formatting(source: &str, is_utf8: bool) -> String {
+        let offset = source.find('|').unwrap();
+        let ch = &source[..offset][source[..offset].len() - 1..];
+        let source = source.replace('|', "");
+        let index = LineIndex::new(&source);
+        let line_col = index.line_col((offset as u32).into());
+        let character = if is_utf8 {
+            line_col.col
+        } else {
+            let line_start = offset - line_col.col as usize;
+            source[line_start..offset].encode_utf16().count() as u32
+        };
+        let edits = get_on_type_formatting(
+            &source,
+            &parse(&source),
+            Position::new(line_col.line, character),
+            ch,
+            "  ",
+            is_utf8,
+            false,
+        );
+        apply(&source, edits, is_utf8)
+    }
+
+    #[test]
+    fn range_edges() {
+        // Partial lines only format the smallest value around them.
+        assert_eq!(
+            range_formatting("{\n  a:    1,\n  b: [1,|2,  |  3],\n}"),
+            "{\n  a:    1,\n  b: [1, 2, 3],\n}"
+        );
+        assert_eq!(
+            range_formatting("{\n  a:  |  1,\n  b: [1,|2,    3],\n}"),
+            "{\n  a: 1,\n  b: [1, 2, 3],\n}"
+        );
+        // An empty range formats the value it's in.
+        assert_eq!(
+            range_formatting("[true,   {a:  |  |1}]"),
+            "[true,   {a: 1}]"
+        );
+    }
+
+    #[test]
+    fn nested_ranges() {
+        // Values inside of nested containers keep the indentation of their
+        // line.
+        assert_eq!(
+            range_formatting("{\n  a: {\n    b: {c:|1, d:|2},\n  },\n}"),
+            "{\n  a: {\n    b: {c: 1, d: 2},\n  },\n}"
+        );
+        assert_eq!(
+            range_formatting("{\n  a: [\n    {b:|1,\n  c:|2},\n  ],\n}"),
+            "{\n  a: [\n    {\n      b: 1,\n      c: 2,\n    },\n  ],\n}"
+        );
+        // Values without errors are formatted on their own.
+        assert_eq!(
+            range_formatting("[|{a:1}, @, [2,3]|]"),
+            "[{a: 1}, @, [2, 3]]"
+        );
+    }
+
+    #[test]
+    fn trigger_characters() {
+        assert_eq!(on_type_formatting("[1,2, {a:1}|]", true), "[1,2, {a: 1}]");
+        assert_eq!(on_type_formatting("{a: [1,2,3]|}", true), "{a: [1, 2, 3]}");
+        assert_eq!(on_type_formatting("{b: {x:1},|}", true), "{b: {x: 1}}");
+        // Positions are in UTF-16 code units unless UTF-8 was negotiated.
+        assert_eq!(
+            on_type_formatting("[\"😎\", {a:1}|]", false),
+            "[\"😎\", {a: 1}]"
+        );
+        // Characters inside of strings and comments are ignored.
+        assert_eq!(on_type_formatting("[\"{a:1}|\"]", true), "[\"{a:1}\"]");
+        assert_eq!(on_type_formatting("[1,2 // x,|\n]", true), "[1,2 // x,\n]");
+    }
+}
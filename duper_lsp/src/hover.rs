@@ -0,0 +1,336 @@
+use std::str::FromStr;
+
+use duper::{DuperKey, DuperParser, DuperTemporal, DuperValue};
+use line_index::LineIndex;
+use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position};
+use tree_sitter::{Node, Tree};
+
+use crate::{
+    diagnostics::{to_point, to_range},
+    symbols::key_name,
+};
+
+pub(crate) fn get_hover(
+    source: &str,
+    tree: &Tree,
+    position: Position,
+    is_utf8: bool,
+) -> Option<Hover> {
+    let index = LineIndex::new(source);
+    let point = to_point(position, &index, is_utf8)?;
+    let node = tree
+        .root_node()
+        .named_descendant_for_point_range(point, point)?;
+    let value_node = value_at(node)?;
+    if value_node.has_error() {
+        return None;
+    }
+
+    let text = value_node.utf8_text(source.as_bytes()).ok()?;
+    let value = DuperParser::parse_duper_value(text).ok()?;
+
+    let mut contents = describe(&value);
+    contents.push_str("\n\nPath: `");
+    contents.push_str(&key_path(value_node, source));
+    contents.push('`');
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: contents,
+        }),
+        range: Some(to_range(value_node.range(), &index, is_utf8)),
+    })
+}
+
+/// Finds the value under the cursor. Hovering a key resolves to the value of
+/// its entry.
+fn value_at(node: Node<'_>) -> Option<Node<'_>> {
+    let mut current = Some(node);
+    while let Some(node) = current {
+        match node.kind() {
+            "duper_value" => return Some(node),
+            "object_key" => {
+                let entry = node.parent()?;
+                let mut cursor = entry.walk();
+                return entry
+                    .named_children(&mut cursor)
+                    .find(|child| child.kind() == "duper_value");
+            }
+            _ => current = node.parent(),
+        }
+    }
+    None
+}
+
+/// The path from the document root to the value, in `duperq` accessor syntax.
+pub(crate) fn key_path(node: Node<'_>, source: &str) -> String {
+    format_path(node, source, false)
+}
+
+/// The path from the document root to the value, with `[]` in place of array
+/// indices so that it matches every element of the arrays along the way.
+pub(crate) fn key_pattern(node: Node<'_>, source: &str) -> String {
+    format_path(node, source, true)
+}
+
+fn format_path(node: Node<'_>, source: &str, any_index: bool) -> String {
+    let mut segments = vec![];
+    let mut current = node;
+    while let Some(parent) = current.parent() {
+        match parent.kind() {
+            "object_entry" => {
+                if let Some(key) = parent
+                    .named_child(0)
+                    .filter(|_| current.kind() == "duper_value")
+                {
+                    segments.push(key_segment(key, source));
+                }
+            }
+            "array" if any_index => segments.push("[]".into()),
+            "array" | "tuple" => {
+                let mut cursor = parent.walk();
+                let position = parent
+                    .named_children(&mut cursor)
+                    .filter(|child| child.kind() == "duper_value")
+                    .position(|child| child.id() == current.id());
+                if let Some(position) = position {
+                    segments.push(format!("[{position}]"));
+                }
+            }
+            _ => (),
+        }
+        current = parent;
+    }
+    if segments.is_empty() {
+        ".".into()
+    } else {
+        segments.reverse();
+        segments.concat()
+    }
+}
+
+/// Formats the key as an accessor, quoting it only if it isn't a valid plain
+/// key.
+fn key_segment(key: Node<'_>, source: &str) -> String {
+    let key = DuperKey::from(key_name(key, source));
+    format!(".{}", duper::format::format_key(&key))
+}
+
+fn describe(value: &DuperValue<'_>) -> String {
+    let identifier = value
+        .identifier()
+        .map(|identifier| format!(" `{}`", identifier.as_ref()))
+        .unwrap_or_default();
+    match value {
+        DuperValue::Object { inner, .. } => {
+            format!("**Object**{identifier} ({})", plural(inner.len(), "key"))
+        }
+        DuperValue::Array { inner, .. } => {
+            format!("**Array**{identifier} ({})", plural(inner.len(), "element"))
+        }
+        DuperValue::Tuple { inner, .. } => {
+            format!("**Tuple**{identifier} ({})", plural(inner.len(), "element"))
+        }
+        DuperValue::String { inner, .. } => format!(
+            "**String**{identifier} ({})\n\n{}",
+            plural(inner.chars().count(), "character"),
+            fenced(inner)
+        ),
+        DuperValue::Bytes { inner, .. } => {
+            format!("**Bytes**{identifier} ({})", plural(inner.len(), "byte"))
+        }
+        DuperValue::Temporal(temporal) => describe_temporal(temporal),
+        DuperValue::Integer { inner, .. } => format!("**Integer**{identifier} `{inner}`"),
+        DuperValue::Float { inner, .. } => format!("**Float**{identifier} `{inner}`"),
+        DuperValue::Boolean { inner, .. } => format!("**Boolean**{identifier} `{inner}`"),
+        DuperValue::Null { .. } => format!("**Null**{identifier}"),
+    }
+}
+
+fn describe_temporal(temporal: &DuperTemporal<'_>) -> String {
+    let input = temporal.as_ref().trim();
+    let (kind, normalized) = match temporal {
+        DuperTemporal::Instant { .. } => ("Instant", normalize_instant(input)),
+        DuperTemporal::ZonedDateTime { .. } => ("ZonedDateTime", normalize_zoned(input)),
+        DuperTemporal::PlainDate { .. } => ("PlainDate", normalize::<jiff::civil::Date>(input)),
+        DuperTemporal::PlainTime { .. } => ("PlainTime", normalize::<jiff::civil::Time>(input)),
+        DuperTemporal::PlainDateTime { .. } => {
+            ("PlainDateTime", normalize::<jiff::civil::DateTime>(input))
+        }
+        DuperTemporal::PlainYearMonth { .. } => ("PlainYearMonth", None),
+        DuperTemporal::PlainMonthDay { .. } => ("PlainMonthDay", None),
+        DuperTemporal::Duration { .. } => ("Duration", normalize_duration(input)),
+        DuperTemporal::Unspecified { identifier, .. } => {
+            let kind = match identifier {
+                Some(identifier) => format!("Temporal `{}`", identifier.as_ref()),
+                None => "Temporal".into(),
+            };
+            return match guess_temporal(input) {
+                Some((guess, normalized)) => {
+                    format!("**{kind}** (unspecified, parses as {guess})\n\n{normalized}")
+                }
+                None => format!("**{kind}** (unspecified)"),
+            };
+        }
+    };
+    match normalized {
+        Some(normalized) => format!("**{kind}**\n\n{normalized}"),
+        None => format!("**{kind}**"),
+    }
+}
+
+fn normalize<T: FromStr + std::fmt::Display>(input: &str) -> Option<String> {
+    T::from_str(input)
+        .ok()
+        .map(|value| format!("Normalized: `{value}`"))
+}
+
+fn normalize_instant(input: &str) -> Option<String> {
+    jiff::Timestamp::from_str(input)
+        .ok()
+        .map(|timestamp| format!("UTC: `{timestamp}`"))
+}
+
+fn normalize_zoned(input: &str) -> Option<String> {
+    jiff::Zoned::from_str(input)
+        .ok()
+        .map(|zoned| format!("Normalized: `{zoned}`\n\nUTC: `{}`", zoned.timestamp()))
+}
+
+fn normalize_duration(input: &str) -> Option<String> {
+    jiff::Span::from_str(input)
+        .ok()
+        .map(|span| format!("Normalized: `{span}` ({span:#})"))
+}
+
+/// Guesses the kind of an unspecified Temporal value, in order from most to
+/// least specific.
+fn guess_temporal(input: &str) -> Option<(&'static str, String)> {
+    if let Some(normalized) = normalize_zoned(input) {
+        Some(("a ZonedDateTime", normalized))
+    } else if let Some(normalized) = normalize_instant(input) {
+        Some(("an Instant", normalized))
+    } else if let Some(normalized) = normalize::<jiff::civil::DateTime>(input) {
+        Some(("a PlainDateTime", normalized))
+    } else if let Some(normalized) = normalize::<jiff::civil::Date>(input) {
+        Some(("a PlainDate", normalized))
+    } else if let Some(normalized) = normalize::<jiff::civil::Time>(input) {
+        Some(("a PlainTime", normalized))
+    } else {
+        normalize_duration(input).map(|normalized| ("a Duration", normalized))
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+/// Wraps text in a Markdown code block, with a fence longer than any run of
+/// backticks in it.
+fn fenced(text: &str) -> String {
+    let longest_run = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{fence}text\n{text}\n{fence}")
+}
+
+#[cfg(test)]
+mod hover_tests {
+    use super::*;
+
+    fn parse(source: &str) -> Tree {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    /// Returns the hover contents at the first occurrence of `needle`.
+    fn hover(source: &str, needle: &str) -> Option<String> {
+        let offset = source.find(needle).unwrap();
+        let hover = get_hover(
+            source,
+            &parse(source),
+            Position::new(0, offset as u32),
+            true,
+        )?;
+        let HoverContents::Markup(contents) = hover.contents else {
+            panic!("expected Markdown contents");
+        };
+        Some(contents.value)
+    }
+
+    #[test]
+    fn key_paths() {
+        let source = r#"{"a b": {"plain": [1, {r"x\y": true, _k-1: 'P1D'}]}, c: null}"#;
+        assert_eq!(
+            hover(source, "true").as_deref(),
+            Some("**Boolean** `true`\n\nPath: `.\"a b\".plain[1].r\"x\\y\"`")
+        );
+        assert_eq!(
+            hover(source, "'P1D'").as_deref(),
+            Some(
+                "**Temporal** (unspecified, parses as a Duration)\n\n\
+                Normalized: `P1D` (1d)\n\nPath: `.\"a b\".plain[1]._k-1`"
+            )
+        );
+        // Hovering a key describes its value.
+        assert_eq!(
+            hover(source, "c:").as_deref(),
+            Some("**Null**\n\nPath: `.c`")
+        );
+        assert_eq!(
+            hover(source, "{").as_deref(),
+            Some("**Object** (2 keys)\n\nPath: `.`")
+        );
+    }
+
+    #[test]
+    fn key_patterns() {
+        let source = r#"{"a": [[{b: 1}], ("t", 2)]}"#;
+        let tree = parse(source);
+        let node = |needle: &str| {
+            let offset = source.find(needle).unwrap();
+            value_at(
+                tree.root_node()
+                    .named_descendant_for_byte_range(offset, offset)
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+        assert_eq!(key_path(node("1"), source), ".a[0][0].b");
+        assert_eq!(key_pattern(node("1"), source), ".a[][].b");
+        assert_eq!(key_pattern(node("2"), source), ".a[][1]");
+    }
+
+    #[test]
+    fn descriptions() {
+        assert_eq!(
+            hover(r#"Name("a `b`")"#, "\"").as_deref(),
+            Some("**String** `Name` (5 characters)\n\n```text\na `b`\n```\n\nPath: `.`")
+        );
+        assert_eq!(
+            hover("[b64\"aGk=\", Instant('2025-01-01T00:00:00+01:00')]", "b64").as_deref(),
+            Some("**Bytes** (2 bytes)\n\nPath: `[0]`")
+        );
+        assert_eq!(
+            hover(
+                "[b64\"aGk=\", Instant('2025-01-01T00:00:00+01:00')]",
+                "Instant"
+            )
+            .as_deref(),
+            Some("**Instant**\n\nUTC: `2024-12-31T23:00:00Z`\n\nPath: `[1]`")
+        );
+        // Values with errors have no hover.
+        assert_eq!(hover("[1, {a: @}]", "{"), None);
+    }
+}
@@ -1,34 +1,138 @@
 use std::ops::ControlFlow;
-use std::{collections::HashMap, mem};
+use std::{collections::HashMap, mem, path::Path, sync::Arc};
 
 use async_lsp::{
-    ClientSocket, LanguageClient, client_monitor::ClientProcessMonitorLayer,
-    concurrency::ConcurrencyLayer, panic::CatchUnwindLayer, router::Router, server::LifecycleLayer,
-    tracing::TracingLayer,
+    ClientSocket, ErrorCode, LanguageClient, ResponseError,
+    client_monitor::ClientProcessMonitorLayer, concurrency::ConcurrencyLayer,
+    panic::CatchUnwindLayer, router::Router, server::LifecycleLayer, tracing::TracingLayer,
 };
 use clap::Parser as _;
+use duper::DuperIdentifier;
 use duperfmt::format_duper;
 use line_index::{LineCol, LineIndex, WideEncoding, WideLineCol};
 use lsp_types::{
-    FileOperationFilter, FileOperationPattern, FileOperationRegistrationOptions, InitializeResult,
-    OneOf, Position, PositionEncodingKind, PublishDiagnosticsParams, Range, ServerCapabilities,
-    ServerInfo, TextDocumentItem, TextDocumentSyncCapability, TextDocumentSyncKind,
-    TextDocumentSyncOptions, TextEdit, Url, WorkspaceFileOperationsServerCapabilities,
-    WorkspaceServerCapabilities, notification, request,
+    CodeActionKind, CodeActionOptions, CodeActionProviderCapability, CompletionOptions,
+    CompletionResponse, DidChangeWatchedFilesRegistrationOptions, DocumentOnTypeFormattingOptions,
+    DocumentSymbolResponse, FileChangeType, FileOperationFilter, FileOperationPattern,
+    FileOperationRegistrationOptions, FileSystemWatcher, FoldingRangeProviderCapability,
+    GlobPattern, HoverProviderCapability, InitializeResult, OneOf, Position, PositionEncodingKind,
+    PrepareRenameResponse, PublishDiagnosticsParams, Range, Registration, RegistrationParams,
+    RenameOptions, SelectionRangeProviderCapability, SemanticTokens, SemanticTokensFullOptions,
+    SemanticTokensOptions, SemanticTokensRangeResult, SemanticTokensResult,
+    SemanticTokensServerCapabilities, ServerCapabilities, ServerInfo, TextDocumentItem,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TextDocumentSyncSaveOptions, TextEdit, Url, WorkspaceEdit,
+    WorkspaceFileOperationsServerCapabilities, WorkspaceServerCapabilities,
+    WorkspaceSymbolResponse,
+    notification::{self, Notification as _},
+    request,
 };
 use tower::ServiceBuilder;
 use tracing::{Level, debug};
 use tree_sitter::{InputEdit, Point, Tree};
 
+mod code_actions;
+mod completion;
 mod diagnostics;
+mod folding;
+mod formatting;
+mod hover;
+mod schema;
+mod selection;
+mod semantic_tokens;
+mod symbols;
+mod workspace;
 
-use crate::diagnostics::get_diagnostics;
+use crate::{
+    code_actions::get_code_actions,
+    completion::get_completions,
+    diagnostics::get_diagnostics,
+    folding::get_folding_ranges,
+    formatting::{get_on_type_formatting, get_range_formatting, indent},
+    hover::get_hover,
+    schema::{Schema, SchemaCache, SchemaSettings, find_schema, get_schema_diagnostics},
+    selection::get_selection_ranges,
+    semantic_tokens::get_semantic_tokens,
+    symbols::get_document_symbols,
+    workspace::{
+        OccurrenceKind, WorkspaceIndex, WorkspaceScan, is_renameable, occurrence_at, scan,
+    },
+};
 
 struct ServerState {
     client: ClientSocket,
     is_utf8: bool,
     parser: tree_sitter::Parser,
     documents: HashMap<String, (TextDocumentItem, Tree)>,
+    schemas: SchemaSettings,
+    schema_cache: SchemaCache,
+    workspace: WorkspaceIndex,
+    /// Whether the client can watch files for changes on request.
+    watch_files: bool,
+}
+
+impl ServerState {
+    /// Returns the schema at `path`, preferring the unsaved contents of an open
+    /// document over the file on disk.
+    fn load_schema(&mut self, path: &Path) -> Arc<Result<Schema, String>> {
+        load_schema(&mut self.schema_cache, &self.documents, path)
+    }
+
+    /// Publishes the diagnostics of an open document.
+    fn publish_diagnostics(&mut self, uri: &Url) {
+        let Some((document, tree)) = self.documents.get(uri.as_str()) else {
+            return;
+        };
+        let mut diagnostics = get_diagnostics(&document.text, tree, self.is_utf8);
+        diagnostics.extend(get_schema_diagnostics(
+            uri,
+            &document.text,
+            tree,
+            &self.schemas,
+            |path| load_schema(&mut self.schema_cache, &self.documents, path),
+            self.is_utf8,
+        ));
+        let _ = self.client.publish_diagnostics(PublishDiagnosticsParams {
+            uri: uri.clone(),
+            diagnostics,
+            version: Some(document.version),
+        });
+    }
+
+    /// Discards the cached schema of a file that changed, and revalidates the
+    /// open documents using it.
+    fn invalidate_schema(&mut self, uri: &Url) {
+        let Ok(path) = uri.to_file_path() else {
+            return;
+        };
+        if !self.schema_cache.invalidate(&path) {
+            return;
+        }
+        let dependents: Vec<Url> = self
+            .documents
+            .iter()
+            .filter(|(_, (document, _))| {
+                find_schema(&document.uri, &document.text, &self.schemas)
+                    .is_some_and(|source| source.path() == path)
+            })
+            .map(|(_, (document, _))| document.uri.clone())
+            .collect();
+        for uri in dependents {
+            self.publish_diagnostics(&uri);
+        }
+    }
+}
+
+fn load_schema(
+    cache: &mut SchemaCache,
+    documents: &HashMap<String, (TextDocumentItem, Tree)>,
+    path: &Path,
+) -> Arc<Result<Schema, String>> {
+    let text = Url::from_file_path(path)
+        .ok()
+        .and_then(|uri| documents.get(uri.as_str()))
+        .map(|(document, _)| document.text.as_str());
+    cache.load(path, text)
 }
 
 #[derive(clap::Parser)]
@@ -55,6 +159,10 @@ async fn main() {
             is_utf8: false,
             parser,
             documents: HashMap::new(),
+            schemas: SchemaSettings::default(),
+            schema_cache: SchemaCache::default(),
+            workspace: WorkspaceIndex::default(),
+            watch_files: false,
         });
         router
             .request::<request::Initialize, _>(|state, params| {
@@ -66,6 +174,23 @@ async fn main() {
                         .contains(&PositionEncodingKind::UTF8)
                 });
                 state.is_utf8 = is_utf8;
+                state.watch_files = params
+                    .capabilities
+                    .workspace
+                    .and_then(|workspace| workspace.did_change_watched_files)
+                    .and_then(|watched_files| watched_files.dynamic_registration)
+                    .unwrap_or(false);
+                #[allow(deprecated)]
+                let root = params
+                    .workspace_folders
+                    .and_then(|folders| folders.into_iter().next())
+                    .map(|folder| folder.uri)
+                    .or(params.root_uri);
+                state.schemas.set_root(root.as_ref());
+                state.workspace.set_root(root.as_ref());
+                if let Some(options) = &params.initialization_options {
+                    state.schemas.update(options);
+                }
                 async move {
                     let filters = vec![FileOperationFilter {
                         pattern: FileOperationPattern {
@@ -77,15 +202,56 @@ async fn main() {
                     let operation_options = Some(FileOperationRegistrationOptions { filters });
                     Ok(InitializeResult {
                         capabilities: ServerCapabilities {
-                            // hover_provider: Some(HoverProviderCapability::Simple(true)),
+                            hover_provider: Some(HoverProviderCapability::Simple(true)),
+                            completion_provider: Some(CompletionOptions::default()),
+                            code_action_provider: Some(CodeActionProviderCapability::Options(
+                                CodeActionOptions {
+                                    code_action_kinds: Some(vec![
+                                        CodeActionKind::QUICKFIX,
+                                        CodeActionKind::REFACTOR_REWRITE,
+                                    ]),
+                                    ..Default::default()
+                                },
+                            )),
+                            semantic_tokens_provider: Some(
+                                SemanticTokensServerCapabilities::SemanticTokensOptions(
+                                    SemanticTokensOptions {
+                                        legend: semantic_tokens::legend(),
+                                        full: Some(SemanticTokensFullOptions::Bool(true)),
+                                        range: Some(true),
+                                        ..Default::default()
+                                    },
+                                ),
+                            ),
+                            document_symbol_provider: Some(OneOf::Left(true)),
+                            workspace_symbol_provider: Some(OneOf::Left(true)),
+                            references_provider: Some(OneOf::Left(true)),
+                            rename_provider: Some(OneOf::Right(RenameOptions {
+                                prepare_provider: Some(true),
+                                work_done_progress_options: Default::default(),
+                            })),
+                            folding_range_provider: Some(FoldingRangeProviderCapability::Simple(
+                                true,
+                            )),
+                            selection_range_provider: Some(
+                                SelectionRangeProviderCapability::Simple(true),
+                            ),
                             text_document_sync: Some(TextDocumentSyncCapability::Options(
                                 TextDocumentSyncOptions {
                                     change: Some(TextDocumentSyncKind::INCREMENTAL),
                                     open_close: Some(true),
+                                    save: Some(TextDocumentSyncSaveOptions::Supported(true)),
                                     ..Default::default()
                                 },
                             )),
                             document_formatting_provider: Some(OneOf::Left(true)),
+                            document_range_formatting_provider: Some(OneOf::Left(true)),
+                            document_on_type_formatting_provider: Some(
+                                DocumentOnTypeFormattingOptions {
+                                    first_trigger_character: ",".into(),
+                                    more_trigger_character: Some(vec!["}".into(), "]".into()]),
+                                },
+                            ),
                             workspace: Some(WorkspaceServerCapabilities {
                                 file_operations: Some(WorkspaceFileOperationsServerCapabilities {
                                     did_delete: operation_options.clone(),
@@ -105,7 +271,233 @@ async fn main() {
                     })
                 }
             })
-            .request::<request::HoverRequest, _>(|_, _| async move { Ok(None) })
+            .request::<request::HoverRequest, _>(|state, params| {
+                let uri = params.text_document_position_params.text_document.uri;
+                let hover = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => get_hover(
+                        &document.text,
+                        tree,
+                        params.text_document_position_params.position,
+                        state.is_utf8,
+                    ),
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(hover) }
+            })
+            .request::<request::Completion, _>(|state, params| {
+                let uri = params.text_document_position.text_document.uri;
+                let schema = match state.documents.get(uri.as_str()) {
+                    Some((document, _)) => find_schema(&uri, &document.text, &state.schemas),
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                }
+                .map(|source| state.load_schema(source.path()));
+                let completions = match (schema.as_deref(), state.documents.get(uri.as_str())) {
+                    (Some(Ok(schema)), Some((document, _))) => {
+                        Some(CompletionResponse::Array(get_completions(
+                            &document.text,
+                            params.text_document_position.position,
+                            schema,
+                            state.is_utf8,
+                        )))
+                    }
+                    _ => None,
+                };
+                async move { Ok(completions) }
+            })
+            .request::<request::CodeActionRequest, _>(|state, params| {
+                let uri = params.text_document.uri;
+                let actions = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => Some(get_code_actions(
+                        &uri,
+                        &document.text,
+                        tree,
+                        params.range,
+                        &params.context.diagnostics,
+                        state.is_utf8,
+                    )),
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(actions) }
+            })
+            .request::<request::WorkspaceSymbolRequest, _>(|state, params| {
+                let symbols = WorkspaceSymbolResponse::Flat(state.workspace.symbols(&params.query));
+                async move { Ok(Some(symbols)) }
+            })
+            .request::<request::References, _>(|state, params| {
+                let uri = params.text_document_position.text_document.uri;
+                let references = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => occurrence_at(
+                        &document.text,
+                        tree,
+                        params.text_document_position.position,
+                        state.is_utf8,
+                    )
+                    .map(|occurrence| {
+                        state
+                            .workspace
+                            .references(occurrence.kind, &occurrence.name)
+                    }),
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(references) }
+            })
+            .request::<request::PrepareRenameRequest, _>(|state, params| {
+                let uri = params.text_document.uri;
+                let range = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => {
+                        occurrence_at(&document.text, tree, params.position, state.is_utf8)
+                            .filter(|occurrence| {
+                                occurrence.kind == OccurrenceKind::Identifier
+                                    && is_renameable(
+                                        &document.text,
+                                        tree,
+                                        params.position,
+                                        state.is_utf8,
+                                    )
+                            })
+                            .map(|occurrence| PrepareRenameResponse::Range(occurrence.range))
+                    }
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(range) }
+            })
+            .request::<request::Rename, _>(|state, params| {
+                let uri = params.text_document_position.text_document.uri;
+                let position = params.text_document_position.position;
+                let occurrence = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => {
+                        occurrence_at(&document.text, tree, position, state.is_utf8).filter(
+                            |occurrence| {
+                                occurrence.kind == OccurrenceKind::Identifier
+                                    && is_renameable(&document.text, tree, position, state.is_utf8)
+                            },
+                        )
+                    }
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                let result = match (
+                    occurrence,
+                    DuperIdentifier::try_from(params.new_name.as_str()),
+                ) {
+                    (_, Err(err)) => Err(ResponseError::new(
+                        ErrorCode::INVALID_PARAMS,
+                        format!("Invalid identifier: {err}"),
+                    )),
+                    (None, _) => Ok(None),
+                    // The index is missing files until the scan finishes.
+                    (Some(_), Ok(_)) if state.workspace.is_scanning() => Err(ResponseError::new(
+                        ErrorCode::REQUEST_FAILED,
+                        "The workspace is still being indexed, try again later",
+                    )),
+                    (Some(occurrence), Ok(_)) => {
+                        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+                        for location in state
+                            .workspace
+                            .references(OccurrenceKind::Identifier, &occurrence.name)
+                        {
+                            changes
+                                .entry(location.uri)
+                                .or_default()
+                                .push(TextEdit::new(location.range, params.new_name.clone()));
+                        }
+                        Ok(Some(WorkspaceEdit::new(changes)))
+                    }
+                };
+                async move { result }
+            })
+            .request::<request::SemanticTokensFullRequest, _>(|state, params| {
+                let uri = params.text_document.uri;
+                let tokens = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => Some(SemanticTokensResult::Tokens(SemanticTokens {
+                        result_id: None,
+                        data: get_semantic_tokens(&document.text, tree, None, state.is_utf8),
+                    })),
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(tokens) }
+            })
+            .request::<request::SemanticTokensRangeRequest, _>(|state, params| {
+                let uri = params.text_document.uri;
+                let tokens = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => {
+                        Some(SemanticTokensRangeResult::Tokens(SemanticTokens {
+                            result_id: None,
+                            data: get_semantic_tokens(
+                                &document.text,
+                                tree,
+                                Some(params.range),
+                                state.is_utf8,
+                            ),
+                        }))
+                    }
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(tokens) }
+            })
+            .request::<request::DocumentSymbolRequest, _>(|state, params| {
+                let uri = params.text_document.uri;
+                let symbols = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => Some(DocumentSymbolResponse::Nested(
+                        get_document_symbols(&document.text, tree, state.is_utf8),
+                    )),
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(symbols) }
+            })
+            .request::<request::FoldingRangeRequest, _>(|state, params| {
+                let uri = params.text_document.uri;
+                let ranges = match state.documents.get(uri.as_str()) {
+                    Some((_, tree)) => Some(get_folding_ranges(tree)),
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(ranges) }
+            })
+            .request::<request::SelectionRangeRequest, _>(|state, params| {
+                let uri = params.text_document.uri;
+                let ranges = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => Some(get_selection_ranges(
+                        &document.text,
+                        tree,
+                        params.positions,
+                        state.is_utf8,
+                    )),
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(ranges) }
+            })
             .request::<request::Formatting, _>(move |state, params| {
                 let uri = params.text_document.uri.as_str();
                 let entry = state.documents.get(uri).cloned();
@@ -120,12 +512,8 @@ async fn main() {
                     if input.trim().is_empty() {
                         return Ok(None);
                     }
-                    let indent: String = if params.options.insert_spaces {
-                        (0..params.options.tab_size).map(|_| ' ').collect()
-                    } else {
-                        "\t".into()
-                    };
                     let mut buf = Vec::new();
+                    let indent = indent(&params.options);
                     if let Err(err) = format_duper(tree, &input, &mut buf, Some(indent), debug) {
                         debug!(?err, "Failed to format Duper document");
                         return Ok(None);
@@ -144,21 +532,112 @@ async fn main() {
                     )]))
                 }
             })
-            .notification::<notification::Initialized>(|_, _| ControlFlow::Continue(()))
-            .notification::<notification::DidChangeConfiguration>(|_, _| ControlFlow::Continue(()))
+            .request::<request::RangeFormatting, _>(move |state, params| {
+                let uri = params.text_document.uri;
+                let edits = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => Some(get_range_formatting(
+                        &document.text,
+                        tree,
+                        params.range,
+                        &indent(&params.options),
+                        state.is_utf8,
+                        debug,
+                    )),
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(edits) }
+            })
+            .request::<request::OnTypeFormatting, _>(move |state, params| {
+                let uri = params.text_document_position.text_document.uri;
+                let edits = match state.documents.get(uri.as_str()) {
+                    Some((document, tree)) => Some(get_on_type_formatting(
+                        &document.text,
+                        tree,
+                        params.text_document_position.position,
+                        &params.ch,
+                        &indent(&params.options),
+                        state.is_utf8,
+                        debug,
+                    )),
+                    None => {
+                        debug!(?uri, "Text document not found");
+                        None
+                    }
+                };
+                async move { Ok(edits) }
+            })
+            .notification::<notification::Initialized>(|state, _| {
+                if state.watch_files {
+                    let mut client = state.client.clone();
+                    tokio::spawn(async move {
+                        let options = DidChangeWatchedFilesRegistrationOptions {
+                            watchers: vec![FileSystemWatcher {
+                                glob_pattern: GlobPattern::String("**/*.duper".into()),
+                                kind: None,
+                            }],
+                        };
+                        let registration = Registration {
+                            id: "duper-watched-files".into(),
+                            method: notification::DidChangeWatchedFiles::METHOD.into(),
+                            register_options: serde_json::to_value(options).ok(),
+                        };
+                        if let Err(err) = client
+                            .register_capability(RegistrationParams {
+                                registrations: vec![registration],
+                            })
+                            .await
+                        {
+                            debug!(?err, "Failed to watch Duper files");
+                        }
+                    });
+                }
+                // Index the workspace in the background, and add the files to
+                // the index once done.
+                if let Some(root) = state.workspace.start_scan() {
+                    let client = state.client.clone();
+                    let is_utf8 = state.is_utf8;
+                    tokio::task::spawn_blocking(move || {
+                        let _ = client.emit(scan(root, is_utf8));
+                    });
+                }
+                ControlFlow::Continue(())
+            })
+            .event::<WorkspaceScan>(|state, scan| {
+                state.workspace.finish_scan(scan);
+                ControlFlow::Continue(())
+            })
+            .notification::<notification::DidChangeConfiguration>(|state, params| {
+                state.schemas.update(&params.settings);
+                let uris: Vec<Url> = state
+                    .documents
+                    .values()
+                    .map(|(document, _)| document.uri.clone())
+                    .collect();
+                for uri in uris {
+                    state.publish_diagnostics(&uri);
+                }
+                ControlFlow::Continue(())
+            })
             .notification::<notification::DidOpenTextDocument>(|state, params| {
-                let uri = params.text_document.uri.to_string();
+                let uri = params.text_document.uri.clone();
                 let tree = state
                     .parser
                     .parse(&params.text_document.text, None)
                     .expect("parser was properly initialized");
-                let diagnostics = PublishDiagnosticsParams {
-                    uri: params.text_document.uri.clone(),
-                    diagnostics: get_diagnostics(&params.text_document.text, &tree, state.is_utf8),
-                    version: Some(params.text_document.version),
-                };
-                let _ = state.client.publish_diagnostics(diagnostics);
-                state.documents.insert(uri, (params.text_document, tree));
+                state.workspace.update(
+                    uri.clone(),
+                    &params.text_document.text,
+                    &tree,
+                    state.is_utf8,
+                );
+                state
+                    .documents
+                    .insert(uri.to_string(), (params.text_document, tree));
+                state.publish_diagnostics(&uri);
+                state.invalidate_schema(&uri);
                 ControlFlow::Continue(())
             })
             .notification::<notification::DidChangeTextDocument>(|state, params| {
@@ -261,39 +740,74 @@ async fn main() {
                     params.text_document.version,
                     text,
                 );
-                let _ = state.client.publish_diagnostics(PublishDiagnosticsParams {
-                    uri,
-                    diagnostics: get_diagnostics(&document.text, tree, state.is_utf8),
-                    version: Some(params.text_document.version),
-                });
+                state
+                    .workspace
+                    .update(uri.clone(), &document.text, tree, state.is_utf8);
+                state.publish_diagnostics(&uri);
+                state.invalidate_schema(&uri);
+                ControlFlow::Continue(())
+            })
+            .notification::<notification::DidSaveTextDocument>(|state, params| {
+                state.invalidate_schema(&params.text_document.uri);
                 ControlFlow::Continue(())
             })
             .notification::<notification::DidCloseTextDocument>(|state, params| {
-                state.documents.remove(params.text_document.uri.as_str());
+                let uri = params.text_document.uri;
+                state.documents.remove(uri.as_str());
+                // Discard unsaved changes from the index and the schemas.
+                state.invalidate_schema(&uri);
+                state.workspace.load(uri, &mut state.parser, state.is_utf8);
+                ControlFlow::Continue(())
+            })
+            .notification::<notification::DidChangeWatchedFiles>(|state, params| {
+                for change in params.changes {
+                    state.invalidate_schema(&change.uri);
+                    if change.typ == FileChangeType::DELETED {
+                        state.workspace.remove(&change.uri);
+                    } else if !state.documents.contains_key(change.uri.as_str()) {
+                        state
+                            .workspace
+                            .load(change.uri, &mut state.parser, state.is_utf8);
+                    }
+                }
+                ControlFlow::Continue(())
+            })
+            .notification::<notification::DidCreateFiles>(|state, params| {
+                for file in params.files {
+                    if let Ok(uri) = Url::parse(&file.uri) {
+                        state.invalidate_schema(&uri);
+                        state.workspace.load(uri, &mut state.parser, state.is_utf8);
+                    }
+                }
                 ControlFlow::Continue(())
             })
-            .notification::<notification::DidChangeWatchedFiles>(|_, _| ControlFlow::Continue(()))
             .notification::<notification::DidRenameFiles>(|state, params| {
                 for file in params.files {
-                    let Some((document, tree)) = state.documents.remove(&file.old_uri) else {
+                    if let (Ok(old_uri), Ok(new_uri)) =
+                        (Url::parse(&file.old_uri), Url::parse(&file.new_uri))
+                    {
+                        state.workspace.rename(&old_uri, &new_uri);
+                        state.invalidate_schema(&old_uri);
+                        state.invalidate_schema(&new_uri);
+                    }
+                    let Some((mut document, tree)) = state.documents.remove(&file.old_uri) else {
                         debug!(uri = file.old_uri, "Text document not found");
                         continue;
                     };
                     let uri = Url::parse(&file.new_uri).expect("URI is valid");
-                    let diagnostics = get_diagnostics(&document.text, &tree, state.is_utf8);
-                    let version = Some(document.version);
+                    document.uri = uri.clone();
                     state.documents.insert(file.new_uri, (document, tree));
-                    let _ = state.client.publish_diagnostics(PublishDiagnosticsParams {
-                        uri,
-                        diagnostics,
-                        version,
-                    });
+                    state.publish_diagnostics(&uri);
                 }
                 ControlFlow::Continue(())
             })
             .notification::<notification::DidDeleteFiles>(|state, params| {
                 for file in params.files {
                     state.documents.remove(&file.uri);
+                    if let Ok(uri) = Url::parse(&file.uri) {
+                        state.workspace.remove(&uri);
+                        state.invalidate_schema(&uri);
+                    }
                 }
                 ControlFlow::Continue(())
             });
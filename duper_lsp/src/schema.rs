@@ -0,0 +1,654 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, LazyLock},
+};
+
+use duper::{DuperParser, DuperValue, Serializer};
+use line_index::LineIndex;
+use lsp_types::{Diagnostic, DiagnosticSeverity, LSPAny, Url};
+use regex::Regex;
+use tree_sitter::{Node, Point, Tree};
+
+use crate::{
+    diagnostics::{to_position, to_range},
+    symbols::{inner_value, key_name},
+};
+
+static REGEX_SCHEMA_HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*//\s*schema:\s*(\S+)\s*$").expect("valid schema header regex")
+});
+
+/// The types that a schema may accept.
+const TYPES: &[&str] = &[
+    "any", "object", "array", "tuple", "string", "bytes", "temporal", "integer", "float", "number",
+    "boolean", "null",
+];
+
+/// A schema for Duper values, itself written in Duper:
+///
+/// ```duper
+/// {
+///   type: "object",
+///   properties: {
+///     port: {type: "integer", description: "The port to listen on."},
+///     started_at: {type: "temporal", identifier: "Instant"},
+///     mode: {enum: ["dev", "prod"]},
+///   },
+///   required: ["port"],
+///   additional_properties: false,
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub(crate) struct Schema {
+    types: Vec<String>,
+    identifiers: Vec<String>,
+    description: Option<String>,
+    properties: Vec<(String, Schema)>,
+    required: Vec<String>,
+    additional_properties: AdditionalProperties,
+    items: Option<Box<Schema>>,
+    values: Vec<DuperValue<'static>>,
+}
+
+#[derive(Debug, Default)]
+enum AdditionalProperties {
+    #[default]
+    Allowed,
+    Denied,
+    Schema(Box<Schema>),
+}
+
+/// A step from a value into one of its children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl Schema {
+    fn from_value(value: &DuperValue<'_>, path: &str) -> Result<Self, String> {
+        let DuperValue::Object { inner, .. } = value else {
+            return Err(format!("`{}` must be an object", display_path(path)));
+        };
+        let mut schema = Schema::default();
+        for (key, value) in inner.iter() {
+            let path = format!("{path}.{}", key.as_ref());
+            match key.as_ref() {
+                "type" => {
+                    schema.types = strings(value, &path)?;
+                    if let Some(ty) = schema.types.iter().find(|ty| !TYPES.contains(&ty.as_str())) {
+                        return Err(format!("Unknown type `{ty}` in `{path}`"));
+                    }
+                }
+                "identifier" => schema.identifiers = strings(value, &path)?,
+                "description" => match value {
+                    DuperValue::String { inner, .. } => {
+                        schema.description = Some(inner.to_string())
+                    }
+                    _ => return Err(format!("`{path}` must be a string")),
+                },
+                "properties" => {
+                    let DuperValue::Object { inner, .. } = value else {
+                        return Err(format!("`{path}` must be an object"));
+                    };
+                    schema.properties = inner
+                        .iter()
+                        .map(|(key, value)| {
+                            Ok((
+                                key.as_ref().to_string(),
+                                Schema::from_value(value, &format!("{path}.{}", key.as_ref()))?,
+                            ))
+                        })
+                        .collect::<Result<_, String>>()?;
+                }
+                "required" => schema.required = strings(value, &path)?,
+                "additional_properties" => {
+                    schema.additional_properties = match value {
+                        DuperValue::Boolean { inner: true, .. } => AdditionalProperties::Allowed,
+                        DuperValue::Boolean { inner: false, .. } => AdditionalProperties::Denied,
+                        value => AdditionalProperties::Schema(Box::new(Schema::from_value(
+                            value, &path,
+                        )?)),
+                    }
+                }
+                "items" => schema.items = Some(Box::new(Schema::from_value(value, &path)?)),
+                "enum" => match value {
+                    DuperValue::Array { inner, .. } => {
+                        schema.values = inner.iter().map(DuperValue::static_clone).collect()
+                    }
+                    _ => return Err(format!("`{path}` must be an array")),
+                },
+                _ => return Err(format!("Unknown schema key `{path}`")),
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Returns the schema of a descendant value.
+    pub(crate) fn resolve(&self, path: &[Segment]) -> Option<&Schema> {
+        let Some((segment, rest)) = path.split_first() else {
+            return Some(self);
+        };
+        let child = match segment {
+            Segment::Key(key) => self.property(key).or(match &self.additional_properties {
+                AdditionalProperties::Schema(schema) => Some(schema.as_ref()),
+                _ => None,
+            }),
+            Segment::Index(_) => self.items.as_deref(),
+        };
+        child?.resolve(rest)
+    }
+
+    fn property(&self, key: &str) -> Option<&Schema> {
+        self.properties
+            .iter()
+            .find_map(|(name, schema)| (name == key).then_some(schema))
+    }
+
+    pub(crate) fn properties(&self) -> impl Iterator<Item = (&str, &Schema, bool)> {
+        self.properties
+            .iter()
+            .map(|(name, schema)| (name.as_str(), schema, self.required.contains(name)))
+    }
+
+    pub(crate) fn identifiers(&self) -> &[String] {
+        &self.identifiers
+    }
+
+    pub(crate) fn values(&self) -> &[DuperValue<'static>] {
+        &self.values
+    }
+
+    pub(crate) fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub(crate) fn accepts(&self, ty: &str) -> bool {
+        self.types.is_empty()
+            || self.types.iter().any(|accepted| {
+                accepted == ty
+                    || accepted == "any"
+                    || (accepted == "number" && matches!(ty, "integer" | "float"))
+            })
+    }
+
+    /// Whether the type is explicitly listed in the schema.
+    pub(crate) fn has_type(&self, ty: &str) -> bool {
+        self.types.iter().any(|accepted| accepted == ty)
+    }
+
+    /// A short description of the accepted values, e.g. `Instant temporal`.
+    pub(crate) fn summary(&self) -> String {
+        let types = if self.types.is_empty() {
+            "any".to_string()
+        } else {
+            self.types.join(" | ")
+        };
+        if self.identifiers.is_empty() {
+            types
+        } else {
+            format!("{} {types}", self.identifiers.join(" | "))
+        }
+    }
+}
+
+fn strings(value: &DuperValue<'_>, path: &str) -> Result<Vec<String>, String> {
+    match value {
+        DuperValue::String { inner, .. } => Ok(vec![inner.to_string()]),
+        DuperValue::Array { inner, .. } => inner
+            .iter()
+            .map(|value| match value {
+                DuperValue::String { inner, .. } => Ok(inner.to_string()),
+                _ => Err(format!("`{path}` must be a string or an array of strings")),
+            })
+            .collect(),
+        _ => Err(format!("`{path}` must be a string or an array of strings")),
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() { "." } else { path }
+}
+
+/// Maps glob patterns of document paths to schema paths, relative to the
+/// workspace root.
+#[derive(Debug, Default)]
+pub(crate) struct SchemaSettings {
+    root: Option<PathBuf>,
+    mappings: Vec<(String, String)>,
+}
+
+impl SchemaSettings {
+    pub(crate) fn set_root(&mut self, root: Option<&Url>) {
+        self.root = root.and_then(|root| root.to_file_path().ok());
+    }
+
+    /// Reads the `schemas` mapping from the initialization options or the
+    /// workspace settings, optionally nested under `duper`.
+    pub(crate) fn update(&mut self, settings: &LSPAny) {
+        let settings = settings.get("duper").unwrap_or(settings);
+        if let Some(schemas) = settings
+            .get("schemas")
+            .and_then(|schemas| schemas.as_object())
+        {
+            self.mappings = schemas
+                .iter()
+                .filter_map(|(pattern, schema)| Some((pattern.clone(), schema.as_str()?.into())))
+                .collect();
+        }
+    }
+}
+
+/// The schema associated with a document, and where the association was made.
+pub(crate) struct SchemaSource {
+    path: PathBuf,
+    header: Option<tree_sitter::Range>,
+}
+
+/// Finds the schema of a document, from a `// schema: <path>` comment at the
+/// top of the document or from a glob mapping in the settings.
+pub(crate) fn find_schema(
+    uri: &Url,
+    source: &str,
+    settings: &SchemaSettings,
+) -> Option<SchemaSource> {
+    let document_path = uri.to_file_path().ok();
+    let mut start_byte = 0;
+    for (row, line) in source.split('\n').enumerate() {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with("//") {
+            break;
+        }
+        if let Some(schema) = REGEX_SCHEMA_HEADER
+            .captures(line.trim_end_matches('\r'))
+            .and_then(|captures| captures.get(1))
+        {
+            let path = match Url::parse(schema.as_str()) {
+                Ok(url) if url.scheme() == "file" => url.to_file_path().ok()?,
+                _ => document_path
+                    .as_deref()
+                    .and_then(Path::parent)
+                    .map(|parent| parent.join(schema.as_str()))
+                    .unwrap_or_else(|| schema.as_str().into()),
+            };
+            let path = normalize(&path);
+            let header = tree_sitter::Range {
+                start_byte: start_byte + schema.start(),
+                end_byte: start_byte + schema.end(),
+                start_point: Point::new(row, schema.start()),
+                end_point: Point::new(row, schema.end()),
+            };
+            return Some(SchemaSource {
+                path,
+                header: Some(header),
+            });
+        }
+        start_byte += line.len() + 1;
+    }
+
+    let document_path = document_path?;
+    let relative_path = match &settings.root {
+        Some(root) => document_path.strip_prefix(root).unwrap_or(&document_path),
+        None => &document_path,
+    };
+    let relative_path = relative_path.to_string_lossy().replace('\\', "/");
+    let file_name = relative_path.rsplit('/').next().unwrap_or_default();
+    settings
+        .mappings
+        .iter()
+        .find(|(pattern, _)| {
+            let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+            let target = if pattern.contains('/') {
+                &relative_path
+            } else {
+                file_name
+            };
+            glob_matches(pattern.as_bytes(), target.as_bytes())
+        })
+        .map(|(_, schema)| SchemaSource {
+            path: match &settings.root {
+                Some(root) => normalize(&root.join(schema)),
+                None => normalize(Path::new(schema)),
+            },
+            header: None,
+        })
+}
+
+/// Resolves `.` and `..` components without touching the file system, so that
+/// a schema path can be compared with the paths of documents.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Matches a path against a glob pattern with `*`, `**`, and `?` wildcards.
+fn glob_matches(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            rest.is_empty()
+                || (0..=path.len())
+                    .filter(|&i| i == 0 || path[i - 1] == b'/')
+                    .any(|i| glob_matches(rest, &path[i..]))
+        }
+        [b'*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != b'/')
+            .any(|i| glob_matches(rest, &path[i..])),
+        [b'?', rest @ ..] => {
+            path.first().is_some_and(|&c| c != b'/') && glob_matches(rest, &path[1..])
+        }
+        [c, rest @ ..] => path.first() == Some(c) && glob_matches(rest, &path[1..]),
+    }
+}
+
+impl SchemaSource {
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Parsed schemas by path, so that they aren't read and parsed again on every
+/// change or completion.
+#[derive(Debug, Default)]
+pub(crate) struct SchemaCache {
+    schemas: HashMap<PathBuf, Arc<Result<Schema, String>>>,
+}
+
+impl SchemaCache {
+    /// Returns the schema at `path`, parsing `text` (the contents of an open
+    /// document) or else the file on disk if it isn't cached yet.
+    pub(crate) fn load(&mut self, path: &Path, text: Option<&str>) -> Arc<Result<Schema, String>> {
+        self.schemas
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                Arc::new(match text {
+                    Some(text) => parse_schema(text),
+                    None => std::fs::read_to_string(path)
+                        .map_err(|err| format!("Failed to read schema: {err}"))
+                        .and_then(|text| parse_schema(&text)),
+                })
+            })
+            .clone()
+    }
+
+    /// Discards the schema at `path` after it changed, returning whether it
+    /// was cached.
+    pub(crate) fn invalidate(&mut self, path: &Path) -> bool {
+        self.schemas.remove(path).is_some()
+    }
+}
+
+fn parse_schema(text: &str) -> Result<Schema, String> {
+    let value = DuperParser::parse_duper_value(text).map_err(|errors| {
+        let reason = errors
+            .first()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| "invalid Duper".into());
+        format!("Failed to parse schema: {reason}")
+    })?;
+    Schema::from_value(&value, "").map_err(|err| format!("Invalid schema: {err}"))
+}
+
+/// Validates the document against its associated schema, if any, which is
+/// retrieved with `load`.
+pub(crate) fn get_schema_diagnostics(
+    uri: &Url,
+    source: &str,
+    tree: &Tree,
+    settings: &SchemaSettings,
+    load: impl FnOnce(&Path) -> Arc<Result<Schema, String>>,
+    is_utf8: bool,
+) -> Vec<Diagnostic> {
+    let Some(schema_source) = find_schema(uri, source, settings) else {
+        return vec![];
+    };
+    let index = LineIndex::new(source);
+    let schema = load(&schema_source.path);
+    let schema = match schema.as_ref() {
+        Ok(schema) => schema,
+        Err(message) => {
+            let range = match schema_source.header {
+                Some(header) => to_range(header, &index, is_utf8),
+                None => {
+                    let start = to_position(Point::new(0, 0), &index, is_utf8);
+                    lsp_types::Range::new(start, start)
+                }
+            };
+            return vec![warning(range, message.clone())];
+        }
+    };
+    let mut validator = Validator {
+        source,
+        index: &index,
+        is_utf8,
+        diagnostics: vec![],
+    };
+    if tree.root_node().kind() == "duper_value" {
+        validator.validate(tree.root_node(), schema);
+    }
+    validator.diagnostics
+}
+
+fn warning(range: lsp_types::Range, message: String) -> Diagnostic {
+    Diagnostic::new(
+        range,
+        Some(DiagnosticSeverity::WARNING),
+        None,
+        Some("duper schema".into()),
+        message,
+        None,
+        None,
+    )
+}
+
+struct Validator<'a> {
+    source: &'a str,
+    index: &'a LineIndex,
+    is_utf8: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, node: Node<'_>, message: String) {
+        self.diagnostics.push(warning(
+            to_range(node.range(), self.index, self.is_utf8),
+            message,
+        ));
+    }
+
+    fn validate(&mut self, value: Node<'_>, schema: &Schema) {
+        let Some((identifier, inner)) = inner_value(value) else {
+            return;
+        };
+        let ty = inner.kind();
+        // Values being typed are reported as syntax errors instead.
+        if !TYPES.contains(&ty)
+            || (inner.has_error() && !matches!(ty, "object" | "array" | "tuple"))
+        {
+            return;
+        }
+        if !schema.accepts(ty) {
+            self.report(
+                inner,
+                format!("Expected {}, found {ty}", schema.types.join(" or ")),
+            );
+            return;
+        }
+        if !schema.identifiers.is_empty() {
+            let text =
+                identifier.and_then(|identifier| identifier.utf8_text(self.source.as_bytes()).ok());
+            if !text.is_some_and(|text| schema.identifiers.iter().any(|expected| expected == text))
+            {
+                let expected = schema
+                    .identifiers
+                    .iter()
+                    .map(|identifier| format!("`{identifier}`"))
+                    .collect::<Vec<_>>()
+                    .join(" or ");
+                self.report(
+                    identifier.unwrap_or(inner),
+                    format!("Expected identifier {expected}"),
+                );
+            }
+        }
+        if !schema.values.is_empty()
+            && !value.has_error()
+            && let Ok(text) = value.utf8_text(self.source.as_bytes())
+            && let Ok(parsed) = DuperParser::parse_duper_value(text)
+            && !schema.values.contains(&parsed)
+        {
+            let expected = schema
+                .values
+                .iter()
+                .map(|value| Serializer::new(false, true).serialize(value))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.report(inner, format!("Expected one of: {expected}"));
+        }
+
+        let mut cursor = inner.walk();
+        match ty {
+            "object" => {
+                let mut keys = vec![];
+                for entry in inner
+                    .named_children(&mut cursor)
+                    .filter(|entry| entry.kind() == "object_entry")
+                {
+                    let Some(key) = entry.named_child(0) else {
+                        continue;
+                    };
+                    let Some(child) = entry
+                        .named_children(&mut entry.walk())
+                        .find(|child| child.kind() == "duper_value")
+                    else {
+                        continue;
+                    };
+                    let name = key_name(key, self.source);
+                    match schema.property(&name) {
+                        Some(property) => self.validate(child, property),
+                        None => match &schema.additional_properties {
+                            AdditionalProperties::Allowed => (),
+                            AdditionalProperties::Denied => {
+                                self.report(key, format!("Unknown key `{}`", escape_key(&name)))
+                            }
+                            AdditionalProperties::Schema(schema) => self.validate(child, schema),
+                        },
+                    }
+                    keys.push(name);
+                }
+                // Keys may be hidden in syntax errors.
+                if !inner.has_error() {
+                    let brace = inner.child(0).unwrap_or(inner);
+                    for required in &schema.required {
+                        if !keys.contains(required) {
+                            self.report(
+                                brace,
+                                format!("Missing required key `{}`", escape_key(required)),
+                            );
+                        }
+                    }
+                }
+            }
+            "array" | "tuple" => {
+                if let Some(items) = &schema.items {
+                    for element in inner
+                        .named_children(&mut cursor)
+                        .filter(|element| element.kind() == "duper_value")
+                    {
+                        self.validate(element, items);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Formats a key the way it must be written in an object.
+pub(crate) fn escape_key(key: &str) -> Cow<'_, str> {
+    static REGEX_PLAIN_KEY: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^(_[a-zA-Z0-9]|[a-zA-Z])([_-]?[a-zA-Z0-9])*$").expect("valid plain key regex")
+    });
+
+    if REGEX_PLAIN_KEY.is_match(key) {
+        Cow::Borrowed(key)
+    } else {
+        let key = Cow::Borrowed(key);
+        Cow::Owned(format!("\"{}\"", duper::escape::escape_str(&key)))
+    }
+}
+
+#[cfg(test)]
+mod schema_tests {
+    use super::*;
+
+    #[test]
+    fn normalized_paths() {
+        assert_eq!(
+            normalize(Path::new("/workspace/data/../schemas/./config.duper")),
+            Path::new("/workspace/schemas/config.duper")
+        );
+        assert_eq!(
+            normalize(Path::new("../schemas/config.duper")),
+            Path::new("../schemas/config.duper")
+        );
+    }
+
+    #[test]
+    fn header_schema() {
+        let uri = Url::parse("file:///workspace/data/app.duper").unwrap();
+        let source = "// schema: ../schemas/app.duper\n{port: 80}\n";
+        let schema = find_schema(&uri, source, &SchemaSettings::default()).unwrap();
+        assert_eq!(schema.path(), Path::new("/workspace/schemas/app.duper"));
+    }
+
+    #[test]
+    fn cached_schemas() {
+        let path = Path::new("/nonexistent/schema.duper");
+        let mut cache = SchemaCache::default();
+        let schema = cache.load(path, Some(r#"{type: "object"}"#));
+        assert!(schema.as_ref().as_ref().unwrap().has_type("object"));
+        // The cached schema is kept until invalidated.
+        let schema = cache.load(path, Some(r#"{type: "array"}"#));
+        assert!(schema.as_ref().as_ref().unwrap().has_type("object"));
+        assert!(cache.invalidate(path));
+        assert!(!cache.invalidate(path));
+        let schema = cache.load(path, Some(r#"{type: "array"}"#));
+        assert!(schema.as_ref().as_ref().unwrap().has_type("array"));
+
+        assert!(cache.invalidate(path));
+        let error = cache.load(path, None);
+        assert!(
+            error
+                .as_ref()
+                .as_ref()
+                .unwrap_err()
+                .starts_with("Failed to read schema: ")
+        );
+        assert_eq!(
+            cache
+                .load(Path::new("/other.duper"), Some(r#"{type: "text"}"#))
+                .as_ref()
+                .as_ref()
+                .unwrap_err(),
+            "Invalid schema: Unknown type `text` in `.type`"
+        );
+    }
+}
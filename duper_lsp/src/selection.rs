@@ -0,0 +1,112 @@
+use line_index::LineIndex;
+use lsp_types::{Position, Range, SelectionRange};
+use tree_sitter::Tree;
+
+use crate::diagnostics::{to_point, to_range};
+
+/// Returns the selection ranges for each position, expanding from the
+/// innermost syntax node up to the whole document.
+pub(crate) fn get_selection_ranges(
+    source: &str,
+    tree: &Tree,
+    positions: Vec<Position>,
+    is_utf8: bool,
+) -> Vec<SelectionRange> {
+    let index = LineIndex::new(source);
+    positions
+        .into_iter()
+        .map(|position| {
+            let mut ranges: Vec<Range> = vec![];
+            let mut node = to_point(position, &index, is_utf8).and_then(|point| {
+                tree.root_node()
+                    .named_descendant_for_point_range(point, point)
+            });
+            while let Some(current) = node {
+                let range = to_range(current.range(), &index, is_utf8);
+                if ranges.last() != Some(&range) {
+                    ranges.push(range);
+                }
+                node = current.parent();
+            }
+            ranges
+                .into_iter()
+                .rev()
+                .fold(None, |parent, range| {
+                    Some(SelectionRange {
+                        range,
+                        parent: parent.map(Box::new),
+                    })
+                })
+                .unwrap_or(SelectionRange {
+                    range: Range::new(position, position),
+                    parent: None,
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod selection_tests {
+    use super::*;
+
+    /// Returns the text of each selection range at `position`, from the
+    /// innermost to the outermost.
+    fn selections(source: &str, position: Position, is_utf8: bool) -> Vec<String> {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let index = LineIndex::new(source);
+        let [selection] = get_selection_ranges(source, &tree, vec![position], is_utf8)
+            .try_into()
+            .unwrap();
+        let mut texts = vec![];
+        let mut current = Some(selection);
+        while let Some(selection) = current {
+            let offset = |position: Position| {
+                let point = to_point(position, &index, is_utf8).unwrap();
+                usize::from(
+                    index
+                        .offset(line_index::LineCol {
+                            line: point.row as u32,
+                            col: point.column as u32,
+                        })
+                        .unwrap(),
+                )
+            };
+            texts.push(source[offset(selection.range.start)..offset(selection.range.end)].into());
+            current = selection.parent.map(|parent| *parent);
+        }
+        texts
+    }
+
+    #[test]
+    fn nested_ranges() {
+        let source = "{\n  a: [1, Id(\"xy\")],\n}";
+        assert_eq!(
+            selections(source, Position::new(1, 13), true),
+            [
+                "xy",
+                "\"xy\"",
+                "Id(\"xy\")",
+                "[1, Id(\"xy\")]",
+                "a: [1, Id(\"xy\")]",
+                source,
+            ]
+        );
+    }
+
+    #[test]
+    fn utf16_positions() {
+        let source = "[\"😎\", 12]";
+        assert_eq!(
+            selections(source, Position::new(0, 7), false),
+            ["12", source]
+        );
+        assert_eq!(
+            selections(source, Position::new(0, 9), true),
+            ["12", source]
+        );
+    }
+}
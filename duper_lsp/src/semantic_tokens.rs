@@ -0,0 +1,278 @@
+use line_index::LineIndex;
+use lsp_types::{
+    Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensLegend,
+};
+use tree_sitter::{Node, Point, Tree};
+
+use crate::{
+    diagnostics::{to_point, to_position},
+    symbols::walk,
+};
+
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::STRING,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::COMMENT,
+];
+
+const TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[
+    SemanticTokenModifier::DEFAULT_LIBRARY,
+    SemanticTokenModifier::new("bytes"),
+    SemanticTokenModifier::new("temporal"),
+];
+
+const PROPERTY: u32 = 0;
+const TYPE: u32 = 1;
+const STRING: u32 = 2;
+const NUMBER: u32 = 3;
+const KEYWORD: u32 = 4;
+const COMMENT: u32 = 5;
+
+const DEFAULT_LIBRARY: u32 = 1 << 0;
+const BYTES: u32 = 1 << 1;
+const TEMPORAL: u32 = 1 << 2;
+
+/// Identifiers of Temporal values with a specific kind.
+const TEMPORAL_KINDS: &[&str] = &[
+    "Instant",
+    "ZonedDateTime",
+    "PlainDate",
+    "PlainTime",
+    "PlainDateTime",
+    "PlainYearMonth",
+    "PlainMonthDay",
+    "Duration",
+];
+
+pub(crate) fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+/// Returns the semantic tokens of the document, optionally limited to those
+/// overlapping `range`.
+pub(crate) fn get_semantic_tokens(
+    source: &str,
+    tree: &Tree,
+    range: Option<Range>,
+    is_utf8: bool,
+) -> Vec<SemanticToken> {
+    let index = LineIndex::new(source);
+    let bounds = match range {
+        Some(range) => {
+            let (Some(start), Some(end)) = (
+                to_point(range.start, &index, is_utf8),
+                to_point(range.end, &index, is_utf8),
+            ) else {
+                return vec![];
+            };
+            Some((start, end))
+        }
+        None => None,
+    };
+    let mut builder = TokenBuilder {
+        source,
+        lines: source.split('\n').collect(),
+        index: &index,
+        is_utf8,
+        bounds,
+        tokens: vec![],
+        previous: (0, 0),
+    };
+    walk(tree.root_node(), |node| builder.visit(node));
+    builder.tokens
+}
+
+struct TokenBuilder<'a> {
+    source: &'a str,
+    lines: Vec<&'a str>,
+    index: &'a LineIndex,
+    is_utf8: bool,
+    bounds: Option<(Point, Point)>,
+    tokens: Vec<SemanticToken>,
+    /// The line and start character of the last token.
+    previous: (u32, u32),
+}
+
+impl TokenBuilder<'_> {
+    /// Adds a token for the node if it has one, returning whether its
+    /// children should be visited instead.
+    fn visit(&mut self, node: Node<'_>) -> bool {
+        if let Some((start, end)) = self.bounds
+            && (node.end_position() < start || node.start_position() > end)
+        {
+            return false;
+        }
+        let token = match node.kind() {
+            "object_key" => Some((PROPERTY, 0)),
+            "identifier" => {
+                let is_temporal_kind = TEMPORAL_KINDS
+                    .contains(&node.utf8_text(self.source.as_bytes()).unwrap_or_default())
+                    && node
+                        .next_named_sibling()
+                        .is_some_and(|sibling| sibling.kind() == "temporal");
+                Some((TYPE, if is_temporal_kind { DEFAULT_LIBRARY } else { 0 }))
+            }
+            "string" => Some((STRING, 0)),
+            "bytes" => Some((STRING, BYTES)),
+            "temporal" => Some((STRING, TEMPORAL)),
+            "integer" | "float" => Some((NUMBER, 0)),
+            "boolean" | "null" => Some((KEYWORD, 0)),
+            "line_comment" | "block_comment" => Some((COMMENT, 0)),
+            _ => None,
+        };
+        match token {
+            Some((token_type, modifiers)) => {
+                self.push(node, token_type, modifiers);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Adds a token for the node, split into one token per line.
+    fn push(&mut self, node: Node<'_>, token_type: u32, modifiers: u32) {
+        let start = node.start_position();
+        let end = node.end_position();
+        for row in start.row..=end.row {
+            let Some(line) = self.lines.get(row) else {
+                break;
+            };
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let from = if row == start.row { start.column } else { 0 };
+            let to = if row == end.row {
+                end.column.min(line.len())
+            } else {
+                line.len()
+            };
+            let Some(text) = line.get(from..to).filter(|text| !text.is_empty()) else {
+                continue;
+            };
+            let position = to_position(Point::new(row, from), self.index, self.is_utf8);
+            let length = if self.is_utf8 {
+                text.len()
+            } else {
+                text.encode_utf16().count()
+            } as u32;
+            let (previous_line, previous_start) = self.previous;
+            self.tokens.push(SemanticToken {
+                delta_line: position.line - previous_line,
+                delta_start: if position.line == previous_line {
+                    position.character - previous_start
+                } else {
+                    position.character
+                },
+                length,
+                token_type,
+                token_modifiers_bitset: modifiers,
+            });
+            self.previous = (position.line, position.character);
+        }
+    }
+}
+
+#[cfg(test)]
+mod semantic_tokens_tests {
+    use lsp_types::Position;
+
+    use super::*;
+
+    /// Returns the semantic tokens as `(delta_line, delta_start, length,
+    /// token_type, modifiers)`.
+    fn tokens(source: &str, range: Option<Range>, is_utf8: bool) -> Vec<(u32, u32, u32, u32, u32)> {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        get_semantic_tokens(source, &tree, range, is_utf8)
+            .into_iter()
+            .map(|token| {
+                (
+                    token.delta_line,
+                    token.delta_start,
+                    token.length,
+                    token.token_type,
+                    token.token_modifiers_bitset,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn delta_encoding() {
+        assert_eq!(
+            tokens("{a: 1, b: \"x\"}\n  // c\nnull", None, true),
+            vec![
+                (0, 1, 1, PROPERTY, 0),
+                (0, 3, 1, NUMBER, 0),
+                (0, 3, 1, PROPERTY, 0),
+                (0, 3, 3, STRING, 0),
+                (1, 2, 4, COMMENT, 0),
+                (1, 0, 4, KEYWORD, 0),
+            ]
+        );
+        assert_eq!(
+            tokens("[Instant('2025-01-01T00:00:00Z'), Id(b\"x\")]", None, true),
+            vec![
+                (0, 1, 7, TYPE, DEFAULT_LIBRARY),
+                (0, 8, 22, STRING, TEMPORAL),
+                (0, 25, 2, TYPE, 0),
+                (0, 3, 4, STRING, BYTES),
+            ]
+        );
+    }
+
+    #[test]
+    fn multi_line_tokens() {
+        // One token per line, without the line breaks.
+        assert_eq!(
+            tokens("/* a\r\n   b */ 1", None, true),
+            vec![
+                (0, 0, 4, COMMENT, 0),
+                (1, 0, 7, COMMENT, 0),
+                (0, 8, 1, NUMBER, 0)
+            ]
+        );
+        assert_eq!(
+            tokens("[r#\"\n\n\"#]", None, true),
+            vec![(0, 1, 3, STRING, 0), (2, 0, 2, STRING, 0)]
+        );
+    }
+
+    #[test]
+    fn utf16_columns() {
+        let source = "[\"😎\", 1]";
+        assert_eq!(
+            tokens(source, None, false),
+            vec![(0, 1, 4, STRING, 0), (0, 6, 1, NUMBER, 0)]
+        );
+        assert_eq!(
+            tokens(source, None, true),
+            vec![(0, 1, 6, STRING, 0), (0, 8, 1, NUMBER, 0)]
+        );
+    }
+
+    #[test]
+    fn range() {
+        let range = Range::new(Position::new(1, 0), Position::new(1, 5));
+        assert_eq!(
+            tokens("[\n  1,\n  2,\n]", Some(range), true),
+            vec![(1, 2, 1, NUMBER, 0)]
+        );
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let source = format!("{}1{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert_eq!(
+            tokens(&source, None, true),
+            vec![(0, 100_000, 1, NUMBER, 0)]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use line_index::LineIndex;
+use lsp_types::{DocumentSymbol, SymbolKind};
+use tree_sitter::{Node, Tree};
+
+use crate::diagnostics::to_range;
+
+/// The maximum nesting of symbols, past which children are left out.
+const MAX_DEPTH: usize = 128;
+
+/// Returns the tree of object keys and array indices in the document.
+pub(crate) fn get_document_symbols(
+    source: &str,
+    tree: &Tree,
+    is_utf8: bool,
+) -> Vec<DocumentSymbol> {
+    let index = LineIndex::new(source);
+    children(tree.root_node(), source, &index, is_utf8, 0)
+}
+
+/// Visits the node and its descendants in document order, skipping the
+/// descendants of nodes for which `visit` returns `false`.
+///
+/// The tree is walked with a cursor, so that deeply nested documents can't
+/// overflow the stack.
+pub(crate) fn walk<'t>(node: Node<'t>, mut visit: impl FnMut(Node<'t>) -> bool) {
+    let mut cursor = node.walk();
+    loop {
+        if visit(cursor.node()) && cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the collection in a `duper_value` node, along with its identifier.
+pub(crate) fn inner_value<'t>(value: Node<'t>) -> Option<(Option<Node<'t>>, Node<'t>)> {
+    let inner = value
+        .named_children(&mut value.walk())
+        .find(|child| !matches!(child.kind(), "line_comment" | "block_comment"))?;
+    if inner.kind() == "identified_value" {
+        let mut cursor = inner.walk();
+        let mut identifier = None;
+        for child in inner.named_children(&mut cursor) {
+            match child.kind() {
+                "identifier" => identifier = Some(child),
+                "line_comment" | "block_comment" => (),
+                _ => return Some((identifier, child)),
+            }
+        }
+        None
+    } else {
+        Some((None, inner))
+    }
+}
+
+/// Returns the display name of an object key.
+pub(crate) fn key_name(key: Node<'_>, source: &str) -> String {
+    let Some(key) = key.named_child(0) else {
+        return String::new();
+    };
+    let text = key.utf8_text(source.as_bytes()).unwrap_or_default();
+    match key.kind() {
+        "quoted_string" => {
+            let content = text
+                .strip_prefix('"')
+                .and_then(|text| text.strip_suffix('"'))
+                .unwrap_or(text);
+            duper::escape::unescape_str(content)
+                .map(|content| content.into_owned())
+                .unwrap_or_else(|_| content.to_string())
+        }
+        "raw_string" => {
+            let mut cursor = key.walk();
+            key.named_children(&mut cursor)
+                .find(|child| child.kind() == "raw_content")
+                .and_then(|content| content.utf8_text(source.as_bytes()).ok())
+                .unwrap_or_default()
+                .to_string()
+        }
+        _ => text.to_string(),
+    }
+}
+
+fn children(
+    value: Node<'_>,
+    source: &str,
+    index: &LineIndex,
+    is_utf8: bool,
+    depth: usize,
+) -> Vec<DocumentSymbol> {
+    if depth >= MAX_DEPTH {
+        return vec![];
+    }
+    let Some((_, inner)) = inner_value(value) else {
+        return vec![];
+    };
+    let mut cursor = inner.walk();
+    match inner.kind() {
+        "object" => inner
+            .named_children(&mut cursor)
+            .filter(|entry| entry.kind() == "object_entry")
+            .filter_map(|entry| {
+                let key = entry.child_by_field_name("key").or(entry.named_child(0))?;
+                let value = entry
+                    .named_children(&mut entry.walk())
+                    .find(|child| child.kind() == "duper_value")?;
+                Some(symbol(
+                    key_name(key, source),
+                    entry,
+                    key,
+                    value,
+                    source,
+                    index,
+                    is_utf8,
+                    depth,
+                ))
+            })
+            .collect(),
+        "array" | "tuple" => inner
+            .named_children(&mut cursor)
+            .filter(|element| element.kind() == "duper_value")
+            .enumerate()
+            .map(|(position, element)| {
+                symbol(
+                    format!("[{position}]"),
+                    element,
+                    element,
+                    element,
+                    source,
+                    index,
+                    is_utf8,
+                    depth,
+                )
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+#[allow(deprecated, clippy::too_many_arguments)]
+fn symbol(
+    name: String,
+    node: Node<'_>,
+    selection: Node<'_>,
+    value: Node<'_>,
+    source: &str,
+    index: &LineIndex,
+    is_utf8: bool,
+    depth: usize,
+) -> DocumentSymbol {
+    let (identifier, inner) = inner_value(value).unzip();
+    let kind = match inner.map(|inner| inner.kind()) {
+        Some("object") => SymbolKind::OBJECT,
+        Some("array" | "tuple") => SymbolKind::ARRAY,
+        Some("string") => SymbolKind::STRING,
+        Some("integer" | "float") => SymbolKind::NUMBER,
+        Some("boolean") => SymbolKind::BOOLEAN,
+        Some("null") => SymbolKind::NULL,
+        _ => SymbolKind::CONSTANT,
+    };
+    let detail = match (identifier.flatten(), inner) {
+        (Some(identifier), _) => identifier.utf8_text(source.as_bytes()).ok().map(Into::into),
+        (None, Some(inner)) if !matches!(inner.kind(), "object" | "array" | "tuple") => inner
+            .utf8_text(source.as_bytes())
+            .ok()
+            .and_then(|text| text.lines().next())
+            .map(|text| match text.char_indices().nth(40) {
+                Some((end, _)) => format!("{}…", &text[..end]),
+                None => text.to_string(),
+            }),
+        _ => None,
+    };
+    let children = children(value, source, index, is_utf8, depth + 1);
+    DocumentSymbol {
+        name: if name.is_empty() { "\"\"".into() } else { name },
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: to_range(node.range(), index, is_utf8),
+        selection_range: to_range(selection.range(), index, is_utf8),
+        children: (!children.is_empty()).then_some(children),
+    }
+}
+
+#[cfg(test)]
+mod symbols_tests {
+    use super::*;
+
+    fn symbols(source: &str) -> Vec<DocumentSymbol> {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        get_document_symbols(source, &tree, true)
+    }
+
+    /// Returns the name, kind, detail, and children of each symbol.
+    fn outline(symbols: &[DocumentSymbol]) -> String {
+        symbols
+            .iter()
+            .map(|symbol| {
+                let children = symbol.children.as_deref().map(outline).unwrap_or_default();
+                format!(
+                    "{} {:?} {}[{children}]",
+                    symbol.name,
+                    symbol.kind,
+                    symbol.detail.as_deref().unwrap_or("-"),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    #[test]
+    fn nested_symbols() {
+        let source = r#"Config({
+            name: "duper",
+            "quoted key": [1, Point((2.5, true))],
+            r"": null,
+            // comment
+            nested: {deep: {deeper: Instant('2025-01-01T00:00:00Z')}},
+        })"#;
+        assert_eq!(
+            outline(&symbols(source)),
+            "name String \"duper\"[], \
+            quoted key Array -[[0] Number 1[], [1] Array Point[[0] Number 2.5[], [1] Boolean true[]]], \
+            \"\" Null null[], \
+            nested Object -[deep Object -[deeper Constant Instant[]]]"
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let source = "{\n  a: [\n    1,\n  ],\n}";
+        let symbols = symbols(source);
+        let a = &symbols[0];
+        assert_eq!((a.range.start.line, a.range.end.line), (1, 3));
+        assert_eq!(
+            (
+                a.selection_range.start.character,
+                a.selection_range.end.character
+            ),
+            (2, 3)
+        );
+        let element = &a.children.as_ref().unwrap()[0];
+        assert_eq!(
+            (element.range.start.line, element.range.start.character),
+            (2, 4)
+        );
+    }
+
+    #[test]
+    fn depth_limit() {
+        let source = format!("{}1{}", "[".repeat(1_000), "]".repeat(1_000));
+        let mut symbols = symbols(&source);
+        let mut depth = 0;
+        while let Some(symbol) = symbols.pop() {
+            depth += 1;
+            symbols = symbol.children.unwrap_or_default();
+        }
+        assert_eq!(depth, MAX_DEPTH);
+    }
+
+    #[test]
+    fn walk_order() {
+        let source = "[1, {a: 2}, (3,)]";
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let mut integers = vec![];
+        walk(tree.root_node(), |node| {
+            if node.kind() == "integer" {
+                integers.push(node.utf8_text(source.as_bytes()).unwrap());
+            }
+            node.kind() != "object"
+        });
+        assert_eq!(integers, ["1", "3"]);
+    }
+}
@@ -0,0 +1,390 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use line_index::LineIndex;
+use lsp_types::{Location, Position, Range, SymbolInformation, SymbolKind, Url};
+use tracing::debug;
+use tree_sitter::{Node, Tree};
+
+use crate::{
+    diagnostics::{to_point, to_range},
+    hover::key_pattern,
+};
+
+/// Directories that are skipped when indexing the workspace, besides hidden
+/// ones.
+const IGNORED_DIRECTORIES: &[&str] = &["node_modules", "target"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OccurrenceKind {
+    Identifier,
+    Key,
+}
+
+/// An identifier or object key found in a document.
+#[derive(Debug, Clone)]
+pub(crate) struct Occurrence {
+    pub(crate) kind: OccurrenceKind,
+    /// The identifier, or the path of the key with `[]` for array indices.
+    pub(crate) name: String,
+    pub(crate) range: Range,
+    /// The path of the identified value, or of the object containing the key.
+    container: String,
+}
+
+/// An index of the identifiers and keys of every Duper file in the workspace.
+///
+/// Open documents are indexed from their current contents, and every other
+/// file from disk.
+#[derive(Debug, Default)]
+pub(crate) struct WorkspaceIndex {
+    root: Option<PathBuf>,
+    files: HashMap<Url, Vec<Occurrence>>,
+    /// The files removed while the workspace is being scanned, which the scan
+    /// must not add back.
+    removed: Option<Vec<Url>>,
+}
+
+/// The files indexed by [`scan`], to be added with
+/// [`WorkspaceIndex::finish_scan`].
+#[derive(Debug)]
+pub(crate) struct WorkspaceScan(HashMap<Url, Vec<Occurrence>>);
+
+impl WorkspaceIndex {
+    pub(crate) fn set_root(&mut self, root: Option<&Url>) {
+        self.root = root.and_then(|root| root.to_file_path().ok());
+    }
+
+    /// Returns the workspace root to [`scan`], if any.
+    pub(crate) fn start_scan(&mut self) -> Option<PathBuf> {
+        let root = self.root.clone()?;
+        self.removed = Some(vec![]);
+        Some(root)
+    }
+
+    /// Adds the files found by [`scan`], unless they were indexed or removed
+    /// in the meantime.
+    pub(crate) fn finish_scan(&mut self, scan: WorkspaceScan) {
+        let removed = self.removed.take().unwrap_or_default();
+        for (uri, occurrences) in scan.0 {
+            if !removed.iter().any(|removed| contains(removed, &uri)) {
+                self.files.entry(uri).or_insert(occurrences);
+            }
+        }
+        debug!(files = self.files.len(), "Indexed workspace");
+    }
+
+    /// Whether the workspace is still being scanned, in which case the index
+    /// may be missing files.
+    pub(crate) fn is_scanning(&self) -> bool {
+        self.removed.is_some()
+    }
+
+    /// Indexes a document from its contents.
+    pub(crate) fn update(&mut self, uri: Url, source: &str, tree: &Tree, is_utf8: bool) {
+        let index = LineIndex::new(source);
+        let mut occurrences = vec![];
+        collect_occurrences(tree.root_node(), source, &index, is_utf8, &mut occurrences);
+        self.files.insert(uri, occurrences);
+    }
+
+    /// Indexes a file from disk, or removes it from the index if it can't be
+    /// read.
+    pub(crate) fn load(&mut self, uri: Url, parser: &mut tree_sitter::Parser, is_utf8: bool) {
+        let source = uri
+            .to_file_path()
+            .ok()
+            .filter(|path| is_duper_file(path))
+            .and_then(|path| fs::read_to_string(path).ok());
+        match source.and_then(|source| Some((parser.parse(&source, None)?, source))) {
+            Some((tree, source)) => self.update(uri, &source, &tree, is_utf8),
+            None => self.remove(&uri),
+        }
+    }
+
+    /// Removes a file, or every file in a directory, from the index.
+    pub(crate) fn remove(&mut self, uri: &Url) {
+        if let Some(removed) = &mut self.removed {
+            removed.push(uri.clone());
+        }
+        self.files.retain(|file, _| !contains(uri, file));
+    }
+
+    /// Moves a file, or every file in a directory, to a new location.
+    pub(crate) fn rename(&mut self, old_uri: &Url, new_uri: &Url) {
+        if let Some(removed) = &mut self.removed {
+            removed.push(old_uri.clone());
+        }
+        let moved: Vec<_> = self
+            .files
+            .keys()
+            .filter(|file| contains(old_uri, file))
+            .cloned()
+            .collect();
+        for file in moved {
+            let occurrences = self.files.remove(&file).unwrap_or_default();
+            let suffix = &file.as_str()[old_uri.as_str().len()..];
+            if let Ok(file) = Url::parse(&format!("{new_uri}{suffix}")) {
+                self.files.insert(file, occurrences);
+            }
+        }
+    }
+
+    /// Returns the identifiers in the workspace that match the query.
+    pub(crate) fn symbols(&self, query: &str) -> Vec<SymbolInformation> {
+        let query = query.to_lowercase();
+        let mut symbols: Vec<_> = self
+            .files
+            .iter()
+            .flat_map(|(uri, occurrences)| {
+                occurrences
+                    .iter()
+                    .filter(|occurrence| {
+                        occurrence.kind == OccurrenceKind::Identifier
+                            && occurrence.name.to_lowercase().contains(&query)
+                    })
+                    .map(|occurrence| {
+                        #[allow(deprecated)]
+                        SymbolInformation {
+                            name: occurrence.name.clone(),
+                            kind: SymbolKind::CLASS,
+                            tags: None,
+                            deprecated: None,
+                            location: Location::new(uri.clone(), occurrence.range),
+                            container_name: Some(occurrence.container.clone()),
+                        }
+                    })
+            })
+            .collect();
+        symbols.sort_by(|a, b| {
+            (a.location.uri.as_str(), a.location.range.start)
+                .cmp(&(b.location.uri.as_str(), b.location.range.start))
+        });
+        symbols
+    }
+
+    /// Returns the locations of every occurrence of an identifier or key path.
+    pub(crate) fn references(&self, kind: OccurrenceKind, name: &str) -> Vec<Location> {
+        let mut locations: Vec<_> = self
+            .files
+            .iter()
+            .flat_map(|(uri, occurrences)| {
+                occurrences
+                    .iter()
+                    .filter(|occurrence| occurrence.kind == kind && occurrence.name == name)
+                    .map(|occurrence| Location::new(uri.clone(), occurrence.range))
+            })
+            .collect();
+        locations
+            .sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+        locations
+    }
+}
+
+/// Finds the identifier or key under the cursor.
+pub(crate) fn occurrence_at(
+    source: &str,
+    tree: &Tree,
+    position: Position,
+    is_utf8: bool,
+) -> Option<Occurrence> {
+    let index = LineIndex::new(source);
+    let point = to_point(position, &index, is_utf8)?;
+    let mut current = tree
+        .root_node()
+        .named_descendant_for_point_range(point, point);
+    while let Some(node) = current {
+        if let Some(occurrence) = occurrence(node, source, &index, is_utf8) {
+            return Some(occurrence);
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// Whether the identifier under the cursor can be renamed, i.e. it doesn't
+/// identify a Temporal value.
+pub(crate) fn is_renameable(source: &str, tree: &Tree, position: Position, is_utf8: bool) -> bool {
+    let index = LineIndex::new(source);
+    to_point(position, &index, is_utf8)
+        .and_then(|point| {
+            tree.root_node()
+                .named_descendant_for_point_range(point, point)
+        })
+        .filter(|node| node.kind() == "identifier")
+        .and_then(|node| node.next_named_sibling())
+        .is_some_and(|value| value.kind() != "temporal")
+}
+
+fn collect_occurrences(
+    node: Node<'_>,
+    source: &str,
+    index: &LineIndex,
+    is_utf8: bool,
+    occurrences: &mut Vec<Occurrence>,
+) {
+    if let Some(occurrence) = occurrence(node, source, index, is_utf8) {
+        occurrences.push(occurrence);
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_occurrences(child, source, index, is_utf8, occurrences);
+    }
+}
+
+fn occurrence(
+    node: Node<'_>,
+    source: &str,
+    index: &LineIndex,
+    is_utf8: bool,
+) -> Option<Occurrence> {
+    match node.kind() {
+        "identifier" => {
+            let value = node.parent()?;
+            Some(Occurrence {
+                kind: OccurrenceKind::Identifier,
+                name: node.utf8_text(source.as_bytes()).ok()?.into(),
+                range: to_range(node.range(), index, is_utf8),
+                container: key_pattern(value, source),
+            })
+        }
+        "object_key" => {
+            let entry = node.parent()?;
+            let mut cursor = entry.walk();
+            let value = entry
+                .named_children(&mut cursor)
+                .find(|child| child.kind() == "duper_value")?;
+            Some(Occurrence {
+                kind: OccurrenceKind::Key,
+                name: key_pattern(value, source),
+                range: to_range(node.range(), index, is_utf8),
+                container: key_pattern(entry, source),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Indexes every `.duper` file under the workspace root. This reads the whole
+/// workspace, so it runs outside of the main loop.
+pub(crate) fn scan(root: PathBuf, is_utf8: bool) -> WorkspaceScan {
+    let mut parser = tree_sitter::Parser::new();
+    parser
+        .set_language(&tree_sitter_duper::LANGUAGE.into())
+        .expect("Error loading Duper parser");
+    let mut index = WorkspaceIndex::default();
+    let mut directories = vec![root];
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !name.starts_with('.') && !IGNORED_DIRECTORIES.contains(&name.as_ref()) {
+                    directories.push(path);
+                }
+            } else if is_duper_file(&path)
+                && let Ok(uri) = Url::from_file_path(&path)
+            {
+                index.load(uri, &mut parser, is_utf8);
+            }
+        }
+    }
+    WorkspaceScan(index.files)
+}
+
+/// Whether `file` is the file or inside of the directory at `uri`.
+fn contains(uri: &Url, file: &Url) -> bool {
+    file == uri
+        || file
+            .as_str()
+            .strip_prefix(uri.as_str().trim_end_matches('/'))
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn is_duper_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "duper")
+}
+
+#[cfg(test)]
+mod workspace_tests {
+    use super::*;
+
+    fn names(index: &WorkspaceIndex) -> Vec<String> {
+        index
+            .symbols("")
+            .into_iter()
+            .map(|symbol| symbol.name)
+            .collect()
+    }
+
+    #[test]
+    fn background_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        for (path, source) in [
+            ("a.duper", "A(1)"),
+            ("removed/b.duper", "B(2)"),
+            ("opened.duper", "C(3)"),
+            ("node_modules/d.duper", "D(4)"),
+            (".hidden/e.duper", "E(5)"),
+            ("f.json", "F(6)"),
+        ] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+
+        let mut index = WorkspaceIndex::default();
+        index.set_root(Some(&Url::from_directory_path(&root).unwrap()));
+        assert!(!index.is_scanning());
+        let scanned = scan(index.start_scan().unwrap(), true);
+        assert!(index.is_scanning());
+        dir.close().unwrap();
+
+        // Changes while scanning take precedence over the scan.
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        let opened = Url::from_file_path(root.join("opened.duper")).unwrap();
+        let source = "G(3)";
+        let tree = parser.parse(source, None).unwrap();
+        index.update(opened, source, &tree, true);
+        index.remove(&Url::from_file_path(root.join("removed")).unwrap());
+        index.finish_scan(scanned);
+        assert_eq!(names(&index), ["A", "G"]);
+
+        // Removals after the scan aren't tracked anymore.
+        assert!(!index.is_scanning());
+        assert!(index.removed.is_none());
+    }
+
+    #[test]
+    fn contained_files() {
+        let directory = Url::parse("file:///workspace/data").unwrap();
+        assert!(contains(&directory, &directory));
+        assert!(contains(
+            &directory,
+            &Url::parse("file:///workspace/data/a.duper").unwrap()
+        ));
+        assert!(contains(
+            &Url::parse("file:///workspace/data/").unwrap(),
+            &Url::parse("file:///workspace/data/a.duper").unwrap()
+        ));
+        assert!(!contains(
+            &directory,
+            &Url::parse("file:///workspace/database.duper").unwrap()
+        ));
+    }
+}
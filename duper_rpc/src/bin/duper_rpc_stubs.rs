@@ -0,0 +1,56 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use duper::{DuperKey, DuperParser, DuperValue};
+use duper_rpc::discover::{ServiceDescription, to_rust_stubs};
+
+/// Generate Rust client stubs from the output of `rpc.discover`.
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    /// The file containing the service description (or the whole RPC
+    /// response), or stdin if unspecified.
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// The file to save to, or stdout if unspecified.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
+    let input = if let Some(file) = cli.file.as_ref() {
+        fs::read_to_string(file)?
+    } else {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf)?;
+        buf
+    };
+
+    let value = DuperParser::parse_duper_value(&input)
+        .map_err(|errors| DuperParser::prettify_error(&input, &errors, None).unwrap_or_default())?;
+    // Accept a whole RPC response as well.
+    let value = match value {
+        DuperValue::Object { inner, .. } if inner.get(&DuperKey::from("result")).is_some() => inner
+            .into_inner()
+            .shift_remove(&DuperKey::from("result"))
+            .expect("key exists"),
+        value => value,
+    };
+    let description = ServiceDescription::try_from(value)?;
+
+    let stubs = to_rust_stubs(&description);
+    if let Some(output) = cli.output {
+        fs::write(output, stubs)?;
+    } else {
+        print!("{stubs}");
+    }
+
+    Ok(())
+}
@@ -21,6 +21,7 @@ impl<'de> Visitor<'de> for ErrorVisitor {
     {
         let mut typ: Option<String> = None;
         let mut value: Option<DuperValue<'de>> = None;
+        let mut message: Option<String> = None;
 
         while let Some(key) = map.next_key::<String>()? {
             match key.as_ref() {
@@ -38,10 +39,17 @@ impl<'de> Visitor<'de> for ErrorVisitor {
                         value = Some(map.next_value()?);
                     }
                 }
+                "message" => {
+                    if message.is_some() {
+                        return Err(serde_core::de::Error::duplicate_field("message"));
+                    } else {
+                        message = Some(map.next_value()?);
+                    }
+                }
                 field => {
                     return Err(serde_core::de::Error::unknown_field(
                         field,
-                        &["type", "value"],
+                        &["type", "value", "message"],
                     ));
                 }
             }
@@ -50,6 +58,11 @@ impl<'de> Visitor<'de> for ErrorVisitor {
         let Some(typ) = typ else {
             return Err(serde_core::de::Error::missing_field("type"));
         };
+        if message.is_some() && typ != "InvalidParams" {
+            return Err(serde_core::de::Error::custom(format!(
+                "{typ} cannot have message"
+            )));
+        }
         match typ.as_ref() {
             "ParseError" => {
                 if value.is_some() {
@@ -81,7 +94,7 @@ impl<'de> Visitor<'de> for ErrorVisitor {
                         "InvalidParams cannot have value",
                     ));
                 }
-                Ok(Error::InvalidParams)
+                Ok(Error::InvalidParams(message))
             }
             "InternalError" => {
                 if value.is_some() {
@@ -0,0 +1,433 @@
+//! Method introspection for Duper RPC servers.
+//!
+//! Every server built with [`Server`](crate::Server) answers the reserved
+//! [`DISCOVER_METHOD`] with a [`ServiceDescription`], listing the name,
+//! parameters, result, and documentation of each of its methods:
+//!
+//! ```duper
+//! RpcService({
+//!   methods: [
+//!     RpcMethod({
+//!       name: "list",
+//!       doc: "List the posts of a user.",
+//!       params: [{name: "user_id", type: "u64"}, {name: "limit", type: "usize"}],
+//!       result: "Vec<Post>",
+//!     }),
+//!   ],
+//! })
+//! ```
+//!
+//! Types are described by their Rust names, without module paths. The
+//! description can be turned into client stubs with [`to_rust_stubs`], or
+//! with the `duper_rpc_stubs` binary (behind the `cli` feature).
+
+use std::{collections::HashSet, fmt::Write};
+
+use duper::{DuperIdentifier, DuperKey, DuperObject, DuperValue};
+
+/// The reserved method name for introspection.
+pub const DISCOVER_METHOD: &str = "rpc.discover";
+
+/// A description of all methods exposed by a server.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ServiceDescription {
+    /// The methods, in the order they were added to the server.
+    pub methods: Vec<MethodDescription>,
+}
+
+/// A description of a single method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodDescription {
+    /// The name of the method.
+    pub name: String,
+    /// The documentation of the method, if any.
+    pub doc: Option<String>,
+    /// The positional parameters of the method.
+    pub params: Vec<ParamDescription>,
+    /// The type of the result.
+    pub result: String,
+}
+
+/// A description of a method parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamDescription {
+    /// The name of the parameter, if declared with
+    /// [`method_with_params`](crate::Server::method_with_params).
+    pub name: Option<String>,
+    /// The type of the parameter.
+    pub r#type: String,
+}
+
+/// Strip module paths from a type name, eg. `alloc::vec::Vec<u64>` becomes
+/// `Vec<u64>`.
+pub(crate) fn short_type_name(name: &str) -> String {
+    let mut output = String::with_capacity(name.len());
+    let mut segment_start = 0;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            output.truncate(segment_start);
+        } else {
+            output.push(c);
+            if !(c.is_alphanumeric() || c == '_') {
+                segment_start = output.len();
+            }
+        }
+    }
+    output
+}
+
+fn string(inner: String) -> DuperValue<'static> {
+    DuperValue::String {
+        identifier: None,
+        inner: inner.into(),
+    }
+}
+
+fn identifier(name: &'static str) -> Option<DuperIdentifier<'static>> {
+    Some(DuperIdentifier::try_from(name).expect("valid identifier"))
+}
+
+fn object(entries: Vec<(&'static str, DuperValue<'static>)>) -> DuperObject<'static> {
+    DuperObject::try_from(
+        entries
+            .into_iter()
+            .map(|(key, value)| (DuperKey::from(key), value))
+            .collect::<Vec<_>>(),
+    )
+    .expect("no duplicate keys")
+}
+
+impl From<ServiceDescription> for DuperValue<'static> {
+    fn from(value: ServiceDescription) -> Self {
+        DuperValue::Object {
+            identifier: identifier("RpcService"),
+            inner: object(vec![(
+                "methods",
+                DuperValue::Array {
+                    identifier: None,
+                    inner: value.methods.into_iter().map(Into::into).collect(),
+                },
+            )]),
+        }
+    }
+}
+
+impl From<MethodDescription> for DuperValue<'static> {
+    fn from(value: MethodDescription) -> Self {
+        DuperValue::Object {
+            identifier: identifier("RpcMethod"),
+            inner: object(vec![
+                ("name", string(value.name)),
+                (
+                    "doc",
+                    value
+                        .doc
+                        .map(string)
+                        .unwrap_or(DuperValue::Null { identifier: None }),
+                ),
+                (
+                    "params",
+                    DuperValue::Array {
+                        identifier: None,
+                        inner: value.params.into_iter().map(Into::into).collect(),
+                    },
+                ),
+                ("result", string(value.result)),
+            ]),
+        }
+    }
+}
+
+impl From<ParamDescription> for DuperValue<'static> {
+    fn from(value: ParamDescription) -> Self {
+        DuperValue::Object {
+            identifier: None,
+            inner: object(vec![
+                (
+                    "name",
+                    value
+                        .name
+                        .map(string)
+                        .unwrap_or(DuperValue::Null { identifier: None }),
+                ),
+                ("type", string(value.r#type)),
+            ]),
+        }
+    }
+}
+
+fn get<'a, 'b>(object: &'a DuperObject<'b>, key: &str) -> Option<&'a DuperValue<'b>> {
+    object
+        .iter()
+        .find_map(|(k, value)| (k.as_ref() == key).then_some(value))
+}
+
+fn get_string(
+    object: &DuperObject<'_>,
+    key: &'static str,
+    error: &'static str,
+) -> Result<String, &'static str> {
+    match get(object, key) {
+        Some(DuperValue::String { inner, .. }) => Ok(inner.to_string()),
+        _ => Err(error),
+    }
+}
+
+fn get_optional_string(
+    object: &DuperObject<'_>,
+    key: &'static str,
+    error: &'static str,
+) -> Result<Option<String>, &'static str> {
+    match get(object, key) {
+        Some(DuperValue::String { inner, .. }) => Ok(Some(inner.to_string())),
+        Some(DuperValue::Null { .. }) | None => Ok(None),
+        _ => Err(error),
+    }
+}
+
+impl TryFrom<DuperValue<'_>> for ServiceDescription {
+    type Error = &'static str;
+
+    fn try_from(value: DuperValue<'_>) -> Result<Self, Self::Error> {
+        let DuperValue::Object { inner, .. } = value else {
+            return Err("expected service description object");
+        };
+        let Some(DuperValue::Array { inner: methods, .. }) = get(&inner, "methods") else {
+            return Err("expected methods array");
+        };
+        let methods = methods
+            .iter()
+            .map(|method| MethodDescription::try_from(method.clone()))
+            .collect::<Result<_, _>>()?;
+        Ok(ServiceDescription { methods })
+    }
+}
+
+impl TryFrom<DuperValue<'_>> for MethodDescription {
+    type Error = &'static str;
+
+    fn try_from(value: DuperValue<'_>) -> Result<Self, Self::Error> {
+        let DuperValue::Object { inner, .. } = value else {
+            return Err("expected method description object");
+        };
+        let Some(DuperValue::Array { inner: params, .. }) = get(&inner, "params") else {
+            return Err("expected method params array");
+        };
+        let params = params
+            .iter()
+            .map(|param| ParamDescription::try_from(param.clone()))
+            .collect::<Result<_, _>>()?;
+        Ok(MethodDescription {
+            name: get_string(&inner, "name", "expected method name string")?,
+            doc: get_optional_string(&inner, "doc", "expected method doc string or null")?,
+            params,
+            result: get_string(&inner, "result", "expected method result string")?,
+        })
+    }
+}
+
+impl TryFrom<DuperValue<'_>> for ParamDescription {
+    type Error = &'static str;
+
+    fn try_from(value: DuperValue<'_>) -> Result<Self, Self::Error> {
+        let DuperValue::Object { inner, .. } = value else {
+            return Err("expected param description object");
+        };
+        Ok(ParamDescription {
+            name: get_optional_string(&inner, "name", "expected param name string or null")?,
+            r#type: get_string(&inner, "type", "expected param type string")?,
+        })
+    }
+}
+
+/// Generate Rust client stubs from a service description.
+///
+/// Each method becomes a function returning a
+/// [`RequestBuilderSingle`](crate::request::RequestBuilderSingle). Methods
+/// with named parameters use [`request_named`](crate::RequestBuilder::request_named),
+/// as do methods with more positional parameters than the `request{n}` methods
+/// support, whose parameters are then sent as a tuple. Names which would clash
+/// once turned into Rust identifiers get a numeric suffix.
+pub fn to_rust_stubs(description: &ServiceDescription) -> String {
+    let mut output = String::from(
+        "// Generated from `rpc.discover`.\n\n\
+         use duper_rpc::{RequestBuilder, RequestId, request::RequestBuilderSingle};\n",
+    );
+    let mut functions = HashSet::new();
+    for method in &description.methods {
+        if method.name == DISCOVER_METHOD {
+            continue;
+        }
+        let function = unique_identifier(rust_identifier(&method.name), &mut functions);
+        let mut taken = HashSet::from(["id".to_string()]);
+        let args: Vec<String> = method
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| {
+                let arg = match &param.name {
+                    Some(name) => rust_identifier(name),
+                    None => format!("p{}", i + 1),
+                };
+                unique_identifier(arg, &mut taken)
+            })
+            .collect();
+        let named = method.params.iter().all(|param| param.name.is_some());
+
+        output.push('\n');
+        if let Some(doc) = &method.doc {
+            for line in doc.lines() {
+                let _ = writeln!(output, "/// {line}");
+            }
+            output.push_str("///\n");
+        }
+        let _ = writeln!(output, "/// Returns `{}`.", method.result);
+        // Clippy's default limit, counting the `id` argument
+        if args.len() + 1 > 7 {
+            output.push_str("#[allow(clippy::too_many_arguments)]\n");
+        }
+        let _ = write!(output, "pub fn {function}(\n    id: Option<RequestId>,\n");
+        for (arg, param) in args.iter().zip(&method.params) {
+            let _ = writeln!(
+                output,
+                "    {arg}: &impl serde::Serialize, // {}",
+                param.r#type
+            );
+        }
+        output.push_str(") -> RequestBuilderSingle {\n");
+        if named && !args.is_empty() {
+            output.push_str("    #[derive(serde::Serialize)]\n    struct Params<'a");
+            for i in 0..args.len() {
+                let _ = write!(output, ", P{i}: serde::Serialize");
+            }
+            output.push_str("> {\n");
+            for (i, (arg, param)) in args.iter().zip(&method.params).enumerate() {
+                if let Some(name) = &param.name
+                    && arg.strip_prefix("r#").unwrap_or(arg) != name
+                {
+                    let _ = writeln!(output, "        #[serde(rename = {name:?})]");
+                }
+                let _ = writeln!(output, "        {arg}: &'a P{i},");
+            }
+            output.push_str("    }\n");
+            let _ = writeln!(
+                output,
+                "    RequestBuilder::new().request_named({:?}.into(), id, &Params {{ {} }})",
+                method.name,
+                args.join(", ")
+            );
+        } else if args.len() > MAX_POSITIONAL_PARAMS {
+            let _ = writeln!(
+                output,
+                "    // Too many parameters for `request{MAX_POSITIONAL_PARAMS}`, so they're sent as a tuple\n    \
+                 RequestBuilder::new().request_named({:?}.into(), id, &({}))",
+                method.name,
+                args.join(", ")
+            );
+        } else {
+            let _ = writeln!(
+                output,
+                "    RequestBuilder::new().request{}({:?}.into(), id{})",
+                args.len(),
+                method.name,
+                args.iter()
+                    .map(|arg| format!(", {arg}"))
+                    .collect::<String>()
+            );
+        }
+        output.push_str("}\n");
+    }
+    output
+}
+
+/// The most positional parameters supported by the `request{n}` methods of
+/// [`RequestBuilder`](crate::RequestBuilder).
+const MAX_POSITIONAL_PARAMS: usize = 8;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Turn a method or parameter name into a valid Rust identifier, eg. `type`
+/// becomes `r#type` and `posts.get` becomes `posts_get`.
+fn rust_identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    match identifier.as_str() {
+        // Keywords that can't be raw identifiers
+        "_" | "crate" | "self" | "Self" | "super" => identifier.push('_'),
+        keyword if RUST_KEYWORDS.contains(&keyword) => identifier.insert_str(0, "r#"),
+        _ => (),
+    }
+    identifier
+}
+
+/// Add a numeric suffix to `identifier` if it was already taken, eg. the
+/// second `posts_get` becomes `posts_get_2`.
+fn unique_identifier(identifier: String, taken: &mut HashSet<String>) -> String {
+    if taken.insert(identifier.clone()) {
+        return identifier;
+    }
+    let base = identifier.strip_prefix("r#").unwrap_or(&identifier);
+    (2..)
+        .map(|i| format!("{base}_{i}"))
+        .find(|candidate| taken.insert(candidate.clone()))
+        .expect("unbounded suffixes")
+}
+
+#[cfg(test)]
+mod discover_tests {
+    use super::*;
+
+    #[test]
+    fn short_type_names() {
+        assert_eq!(short_type_name("u64"), "u64");
+        assert_eq!(short_type_name("alloc::vec::Vec<u64>"), "Vec<u64>");
+        assert_eq!(
+            short_type_name("core::option::Option<(my_crate::Foo, &str)>"),
+            "Option<(Foo, &str)>"
+        );
+    }
+
+    #[test]
+    fn rust_identifiers() {
+        assert_eq!(rust_identifier("user_id"), "user_id");
+        assert_eq!(rust_identifier("posts.get"), "posts_get");
+        assert_eq!(rust_identifier("type"), "r#type");
+        assert_eq!(rust_identifier("fn"), "r#fn");
+        assert_eq!(rust_identifier("self"), "self_");
+        assert_eq!(rust_identifier("2fa"), "_2fa");
+        assert_eq!(rust_identifier(""), "__");
+    }
+
+    #[test]
+    fn unique_identifiers() {
+        let mut taken = HashSet::from(["id".to_string()]);
+        assert_eq!(
+            unique_identifier("posts_get".into(), &mut taken),
+            "posts_get"
+        );
+        assert_eq!(
+            unique_identifier("posts_get".into(), &mut taken),
+            "posts_get_2"
+        );
+        assert_eq!(
+            unique_identifier("posts_get".into(), &mut taken),
+            "posts_get_3"
+        );
+        assert_eq!(unique_identifier("id".into(), &mut taken), "id_2");
+        assert_eq!(unique_identifier("r#type".into(), &mut taken), "r#type");
+        assert_eq!(unique_identifier("r#type".into(), &mut taken), "type_2");
+    }
+}
@@ -9,10 +9,44 @@ pub enum Error {
     InvalidRequest,
     /// RPC method not found.
     MethodNotFound,
-    /// Parameters are invalid.
-    InvalidParams,
+    /// Parameters are invalid, with an optional message describing why.
+    InvalidParams(Option<String>),
     /// Unspecified server error.
     InternalError,
     /// A custom error containing arbitrary data.
     Custom(DuperValue<'static>),
 }
+
+impl Error {
+    /// Create an [`Error::InvalidParams`] with the given message.
+    pub fn invalid_params(message: impl std::fmt::Display) -> Self {
+        Error::InvalidParams(Some(message.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use duper::serde::{de::from_string, ser::to_string};
+
+    use super::*;
+
+    #[test]
+    fn invalid_params_message() {
+        let error = Error::invalid_params("missing parameter `x`");
+        let text = to_string(&error).unwrap();
+        assert_eq!(
+            text,
+            r#"{type: "InvalidParams", message: "missing parameter `x`"}"#
+        );
+        assert_eq!(from_string::<Error>(&text).unwrap(), error);
+
+        let text = to_string(&Error::InvalidParams(None)).unwrap();
+        assert_eq!(text, r#"{type: "InvalidParams"}"#);
+        assert_eq!(
+            from_string::<Error>(&text).unwrap(),
+            Error::InvalidParams(None)
+        );
+
+        assert!(from_string::<Error>(r#"{type: "InternalError", message: "x"}"#).is_err());
+    }
+}
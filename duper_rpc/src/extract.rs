@@ -0,0 +1,211 @@
+//! Extractors for Duper RPC handlers.
+//!
+//! Besides its positional parameters, a handler may receive any number of
+//! leading arguments implementing [`FromRequestParts`]. These are resolved
+//! from the [`RequestParts`] of the current call, before the parameters are
+//! deserialized.
+//!
+//! ```
+//! use duper_rpc::{
+//!     extract::{Context, Extension, Id, Method},
+//!     server::Server,
+//! };
+//!
+//! #[derive(Clone)]
+//! struct Caller(String);
+//!
+//! async fn audited(
+//!     Method(method): Method,
+//!     Id(id): Id,
+//!     Extension(caller): Extension<Caller>,
+//!     value: i64,
+//! ) -> duper_rpc::Result<String> {
+//!     Ok(format!("{} called {method} ({id:?}) with {value}", caller.0))
+//! }
+//!
+//! async fn handle(request: duper_rpc::Request) -> Option<duper_rpc::Response> {
+//!     let mut context = Context::new();
+//!     context.insert(Caller("admin".into()));
+//!     Server::new()
+//!         .method("audited", audited)
+//!         .handle_with_context(request, context)
+//!         .await
+//!         .unwrap()
+//! }
+//! ```
+//!
+//! Custom extractors can be created by implementing [`FromRequestParts`],
+//! which allows for authentication or auditing without smuggling data
+//! through the parameters.
+
+use std::{fmt::Debug, future::ready};
+
+use crate::{Error, RequestId, Result, server::State};
+
+/// Request-scoped data supplied by the transport.
+///
+/// This is a type map, which can hold the caller identity, transport
+/// headers, or any other value that handlers may need. The context is
+/// cloned for every call in a batch.
+#[derive(Clone, Default)]
+pub struct Context {
+    extensions: http::Extensions,
+}
+
+impl Context {
+    /// Create an empty context.
+    pub fn new() -> Self {
+        Context::default()
+    }
+
+    /// Insert a value into the context, returning the previous value of the
+    /// same type, if any.
+    pub fn insert<T>(&mut self, value: T) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.extensions.insert(value)
+    }
+
+    /// Get a reference to a value of the given type.
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.extensions.get()
+    }
+
+    /// Get a mutable reference to a value of the given type.
+    pub fn get_mut<T>(&mut self) -> Option<&mut T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.extensions.get_mut()
+    }
+
+    /// Remove a value of the given type from the context.
+    pub fn remove<T>(&mut self) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.extensions.remove()
+    }
+
+    /// Whether the context holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+}
+
+impl Debug for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Context").finish_non_exhaustive()
+    }
+}
+
+impl From<http::Extensions> for Context {
+    fn from(extensions: http::Extensions) -> Self {
+        Context { extensions }
+    }
+}
+
+impl From<Context> for http::Extensions {
+    fn from(context: Context) -> Self {
+        context.extensions
+    }
+}
+
+/// Metadata of a single RPC call, available to extractors.
+#[derive(Debug, Clone)]
+pub struct RequestParts {
+    /// The method being called.
+    pub method: String,
+    /// The ID of the call, or None for notifications.
+    pub id: Option<RequestId>,
+    /// The request-scoped context supplied by the transport.
+    pub context: Context,
+}
+
+/// Types that can be created from the [`RequestParts`] of an RPC call.
+pub trait FromRequestParts<S>: Sized {
+    /// Perform the extraction.
+    fn from_request_parts(
+        parts: &RequestParts,
+        state: &S,
+    ) -> impl Future<Output = Result<Self>> + Send;
+}
+
+/// Extractor for the name of the method being called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Method(pub String);
+
+/// Extractor for the ID of the current call.
+///
+/// The ID is None for notifications.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Id(pub Option<RequestId>);
+
+/// Extractor for a value stored in the [`Context`].
+///
+/// If no value of the given type is present, the call fails with
+/// [`Error::InternalError`].
+#[derive(Debug, Clone)]
+pub struct Extension<T>(pub T);
+
+impl<S> FromRequestParts<S> for State<S>
+where
+    S: Clone + Send + Sync,
+{
+    fn from_request_parts(
+        _parts: &RequestParts,
+        state: &S,
+    ) -> impl Future<Output = Result<Self>> + Send {
+        ready(Ok(State(state.clone())))
+    }
+}
+
+impl<S> FromRequestParts<S> for Context {
+    fn from_request_parts(
+        parts: &RequestParts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self>> + Send {
+        ready(Ok(parts.context.clone()))
+    }
+}
+
+impl<S> FromRequestParts<S> for Method {
+    fn from_request_parts(
+        parts: &RequestParts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self>> + Send {
+        ready(Ok(Method(parts.method.clone())))
+    }
+}
+
+impl<S> FromRequestParts<S> for Id {
+    fn from_request_parts(
+        parts: &RequestParts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self>> + Send {
+        ready(Ok(Id(parts.id.clone())))
+    }
+}
+
+impl<S, T> FromRequestParts<S> for Extension<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    fn from_request_parts(
+        parts: &RequestParts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self>> + Send {
+        ready(
+            parts
+                .context
+                .get::<T>()
+                .cloned()
+                .map(Extension)
+                .ok_or(Error::InternalError),
+        )
+    }
+}
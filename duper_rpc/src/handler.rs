@@ -1,29 +1,48 @@
-use std::pin::Pin;
+use std::{pin::Pin, sync::Arc};
 
 use duper::{DuperValue, serde::de::from_value};
-use serde_core::{Deserialize, Serialize};
+use serde_core::Deserialize;
 
-use crate::{Error, Result, server::State};
+use crate::{
+    Error, Result,
+    extract::{FromRequestParts, RequestParts},
+};
 
 trait HandlerArgs {
-    fn get_handler_args(params: DuperValue<'static>) -> Result<Self>
+    /// Deserialize the positional parameters, naming them after `names` in
+    /// errors if they were given by name.
+    fn get_handler_args(params: DuperValue<'static>, names: &[String]) -> Result<Self>
     where
         Self: Sized;
 }
 
+/// Create an [`Error::InvalidParams`] for the parameter at `index`.
+fn invalid_param(names: &[String], index: usize, error: impl std::fmt::Display) -> Error {
+    match names.get(index) {
+        Some(name) => Error::invalid_params(format!("invalid parameter `{name}`: {error}")),
+        None => Error::invalid_params(format!("invalid parameter {}: {error}", index + 1)),
+    }
+}
+
+impl HandlerArgs for () {
+    fn get_handler_args(_params: DuperValue<'static>, _names: &[String]) -> Result<Self> {
+        Ok(())
+    }
+}
+
 impl<T1> HandlerArgs for (T1,)
 where
     T1: for<'de> Deserialize<'de> + Send + 'static,
 {
-    fn get_handler_args(params: DuperValue<'static>) -> Result<Self> {
+    fn get_handler_args(params: DuperValue<'static>, names: &[String]) -> Result<Self> {
         match params {
             DuperValue::Tuple { mut inner, .. } if inner.len() == 1 => {
                 let t1: T1 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 0, error))?;
                 Ok((t1,))
             }
             other => {
-                let t1: T1 = from_value(other).map_err(|_| Error::InvalidParams)?;
+                let t1: T1 = from_value(other).map_err(|error| invalid_param(names, 0, error))?;
                 Ok((t1,))
             }
         }
@@ -35,16 +54,16 @@ where
     T1: for<'de> Deserialize<'de> + Send + 'static,
     T2: for<'de> Deserialize<'de> + Send + 'static,
 {
-    fn get_handler_args(params: DuperValue<'static>) -> Result<Self> {
+    fn get_handler_args(params: DuperValue<'static>, names: &[String]) -> Result<Self> {
         match params {
             DuperValue::Tuple { mut inner, .. } if inner.len() == 2 => {
                 let t2: T2 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 1, error))?;
                 let t1: T1 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 0, error))?;
                 Ok((t1, t2))
             }
-            _ => Err(Error::InvalidParams),
+            _ => Err(Error::invalid_params("expected 2 parameters")),
         }
     }
 }
@@ -55,18 +74,18 @@ where
     T2: for<'de> Deserialize<'de> + Send + 'static,
     T3: for<'de> Deserialize<'de> + Send + 'static,
 {
-    fn get_handler_args(params: DuperValue<'static>) -> Result<Self> {
+    fn get_handler_args(params: DuperValue<'static>, names: &[String]) -> Result<Self> {
         match params {
             DuperValue::Tuple { mut inner, .. } if inner.len() == 3 => {
                 let t3: T3 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 2, error))?;
                 let t2: T2 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 1, error))?;
                 let t1: T1 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 0, error))?;
                 Ok((t1, t2, t3))
             }
-            _ => Err(Error::InvalidParams),
+            _ => Err(Error::invalid_params("expected 3 parameters")),
         }
     }
 }
@@ -78,20 +97,20 @@ where
     T3: for<'de> Deserialize<'de> + Send + 'static,
     T4: for<'de> Deserialize<'de> + Send + 'static,
 {
-    fn get_handler_args(params: DuperValue<'static>) -> Result<Self> {
+    fn get_handler_args(params: DuperValue<'static>, names: &[String]) -> Result<Self> {
         match params {
             DuperValue::Tuple { mut inner, .. } if inner.len() == 4 => {
                 let t4: T4 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 3, error))?;
                 let t3: T3 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 2, error))?;
                 let t2: T2 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 1, error))?;
                 let t1: T1 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 0, error))?;
                 Ok((t1, t2, t3, t4))
             }
-            _ => Err(Error::InvalidParams),
+            _ => Err(Error::invalid_params("expected 4 parameters")),
         }
     }
 }
@@ -104,22 +123,22 @@ where
     T4: for<'de> Deserialize<'de> + Send + 'static,
     T5: for<'de> Deserialize<'de> + Send + 'static,
 {
-    fn get_handler_args(params: DuperValue<'static>) -> Result<Self> {
+    fn get_handler_args(params: DuperValue<'static>, names: &[String]) -> Result<Self> {
         match params {
             DuperValue::Tuple { mut inner, .. } if inner.len() == 5 => {
                 let t5: T5 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 4, error))?;
                 let t4: T4 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 3, error))?;
                 let t3: T3 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 2, error))?;
                 let t2: T2 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 1, error))?;
                 let t1: T1 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 0, error))?;
                 Ok((t1, t2, t3, t4, t5))
             }
-            _ => Err(Error::InvalidParams),
+            _ => Err(Error::invalid_params("expected 5 parameters")),
         }
     }
 }
@@ -133,24 +152,24 @@ where
     T5: for<'de> Deserialize<'de> + Send + 'static,
     T6: for<'de> Deserialize<'de> + Send + 'static,
 {
-    fn get_handler_args(params: DuperValue<'static>) -> Result<Self> {
+    fn get_handler_args(params: DuperValue<'static>, names: &[String]) -> Result<Self> {
         match params {
             DuperValue::Tuple { mut inner, .. } if inner.len() == 6 => {
                 let t6: T6 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 5, error))?;
                 let t5: T5 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 4, error))?;
                 let t4: T4 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 3, error))?;
                 let t3: T3 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 2, error))?;
                 let t2: T2 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 1, error))?;
                 let t1: T1 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 0, error))?;
                 Ok((t1, t2, t3, t4, t5, t6))
             }
-            _ => Err(Error::InvalidParams),
+            _ => Err(Error::invalid_params("expected 6 parameters")),
         }
     }
 }
@@ -165,26 +184,26 @@ where
     T6: for<'de> Deserialize<'de> + Send + 'static,
     T7: for<'de> Deserialize<'de> + Send + 'static,
 {
-    fn get_handler_args(params: DuperValue<'static>) -> Result<Self> {
+    fn get_handler_args(params: DuperValue<'static>, names: &[String]) -> Result<Self> {
         match params {
             DuperValue::Tuple { mut inner, .. } if inner.len() == 7 => {
                 let t7: T7 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 6, error))?;
                 let t6: T6 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 5, error))?;
                 let t5: T5 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 4, error))?;
                 let t4: T4 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 3, error))?;
                 let t3: T3 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 2, error))?;
                 let t2: T2 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 1, error))?;
                 let t1: T1 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 0, error))?;
                 Ok((t1, t2, t3, t4, t5, t6, t7))
             }
-            _ => Err(Error::InvalidParams),
+            _ => Err(Error::invalid_params("expected 7 parameters")),
         }
     }
 }
@@ -200,28 +219,28 @@ where
     T7: for<'de> Deserialize<'de> + Send + 'static,
     T8: for<'de> Deserialize<'de> + Send + 'static,
 {
-    fn get_handler_args(params: DuperValue<'static>) -> Result<Self> {
+    fn get_handler_args(params: DuperValue<'static>, names: &[String]) -> Result<Self> {
         match params {
             DuperValue::Tuple { mut inner, .. } if inner.len() == 8 => {
                 let t8: T8 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 7, error))?;
                 let t7: T7 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 6, error))?;
                 let t6: T6 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 5, error))?;
                 let t5: T5 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 4, error))?;
                 let t4: T4 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 3, error))?;
                 let t3: T3 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 2, error))?;
                 let t2: T2 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 1, error))?;
                 let t1: T1 = from_value(inner.pop().expect("length checked"))
-                    .map_err(|_| Error::InvalidParams)?;
+                    .map_err(|error| invalid_param(names, 0, error))?;
                 Ok((t1, t2, t3, t4, t5, t6, t7, t8))
             }
-            _ => Err(Error::InvalidParams),
+            _ => Err(Error::invalid_params("expected 8 parameters")),
         }
     }
 }
@@ -229,394 +248,121 @@ where
 pub trait Handler<S, R, T>: Clone + Send + Sync + Sized + 'static {
     type Future: Future<Output = Result<R>> + Send + 'static;
 
-    fn call(self, state: S, params: DuperValue<'static>) -> Self::Future;
-}
-
-impl<F, Fut, S, R> Handler<S, R, ((), ())> for F
-where
-    F: Fn() -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, _state: S, _params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move { self().await })
-    }
-}
-
-impl<F, Fut, S, R, T1> Handler<S, R, ((), (T1,))> for F
-where
-    F: Fn(T1) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, _state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1,) = HandlerArgs::get_handler_args(params)?;
-            self(args.0).await
-        })
-    }
-}
-
-impl<F, Fut, S, R, T1, T2> Handler<S, R, ((), (T1, T2))> for F
-where
-    F: Fn(T1, T2) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, _state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2) = HandlerArgs::get_handler_args(params)?;
-            self(args.0, args.1).await
-        })
-    }
+    /// Call the handler, naming the parameters after `names` in errors if
+    /// they were given by name.
+    fn call(
+        self,
+        state: S,
+        parts: RequestParts,
+        params: DuperValue<'static>,
+        names: Option<Arc<[String]>>,
+    ) -> Self::Future;
+
+    /// The type names of the positional parameters, for introspection.
+    fn param_types() -> Vec<&'static str>;
 }
 
-impl<F, Fut, S, R, T1, T2, T3> Handler<S, R, ((), (T1, T2, T3))> for F
-where
-    F: Fn(T1, T2, T3) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, _state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3) = HandlerArgs::get_handler_args(params)?;
-            self(args.0, args.1, args.2).await
-        })
-    }
+/// The positional parameters of a [`Handler`], as described by its `T` type
+/// parameter.
+pub trait HandlerParams {
+    /// The number of positional parameters.
+    const COUNT: usize;
 }
 
-impl<F, Fut, S, R, T1, T2, T3, T4> Handler<S, R, ((), (T1, T2, T3, T4))> for F
-where
-    F: Fn(T1, T2, T3, T4) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T4: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, _state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3, T4) = HandlerArgs::get_handler_args(params)?;
-            self(args.0, args.1, args.2, args.3).await
-        })
-    }
-}
-
-impl<F, Fut, S, R, T1, T2, T3, T4, T5> Handler<S, R, ((), (T1, T2, T3, T4, T5))> for F
-where
-    F: Fn(T1, T2, T3, T4, T5) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T4: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T5: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, _state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3, T4, T5) = HandlerArgs::get_handler_args(params)?;
-            self(args.0, args.1, args.2, args.3, args.4).await
-        })
-    }
-}
-
-impl<F, Fut, S, R, T1, T2, T3, T4, T5, T6> Handler<S, R, ((), (T1, T2, T3, T4, T5, T6))> for F
-where
-    F: Fn(T1, T2, T3, T4, T5, T6) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T4: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T5: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T6: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, _state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3, T4, T5, T6) = HandlerArgs::get_handler_args(params)?;
-            self(args.0, args.1, args.2, args.3, args.4, args.5).await
-        })
-    }
-}
-impl<F, Fut, S, R, T1, T2, T3, T4, T5, T6, T7> Handler<S, R, ((), (T1, T2, T3, T4, T5, T6, T7))>
-    for F
-where
-    F: Fn(T1, T2, T3, T4, T5, T6, T7) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T4: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T5: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T6: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T7: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, _state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3, T4, T5, T6, T7) = HandlerArgs::get_handler_args(params)?;
-            self(args.0, args.1, args.2, args.3, args.4, args.5, args.6).await
-        })
-    }
-}
-
-impl<F, Fut, S, R, T1, T2, T3, T4, T5, T6, T7, T8>
-    Handler<S, R, ((), (T1, T2, T3, T4, T5, T6, T7, T8))> for F
-where
-    F: Fn(T1, T2, T3, T4, T5, T6, T7, T8) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T4: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T5: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T6: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T7: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T8: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, _state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3, T4, T5, T6, T7, T8) = HandlerArgs::get_handler_args(params)?;
-            self(
-                args.0, args.1, args.2, args.3, args.4, args.5, args.6, args.7,
-            )
-            .await
-        })
-    }
-}
-
-impl<F, Fut, S, R> Handler<S, R, (State<S>, ())> for F
-where
-    F: Fn(State<S>) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + Send + 'static,
-    S: Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, state: S, _params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move { self(State(state)).await })
-    }
-}
-
-impl<F, Fut, S, R, T1> Handler<S, R, (State<S>, (T1,))> for F
-where
-    F: Fn(State<S>, T1) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + Send + 'static,
-    S: Send + Sync + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1,) = HandlerArgs::get_handler_args(params)?;
-            self(State(state), args.0).await
-        })
-    }
-}
-
-impl<F, Fut, S, R, T1, T2> Handler<S, R, (State<S>, (T1, T2))> for F
-where
-    F: Fn(State<S>, T1, T2) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + Send + 'static,
-    S: Send + Sync + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2) = HandlerArgs::get_handler_args(params)?;
-            self(State(state), args.0, args.1).await
-        })
-    }
-}
-
-impl<F, Fut, S, R, T1, T2, T3> Handler<S, R, (State<S>, (T1, T2, T3))> for F
-where
-    F: Fn(State<S>, T1, T2, T3) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + Send + 'static,
-    S: Send + Sync + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3) = HandlerArgs::get_handler_args(params)?;
-            self(State(state), args.0, args.1, args.2).await
-        })
-    }
-}
-
-impl<F, Fut, S, R, T1, T2, T3, T4> Handler<S, R, (State<S>, (T1, T2, T3, T4))> for F
-where
-    F: Fn(State<S>, T1, T2, T3, T4) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + Send + 'static,
-    S: Send + Sync + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T4: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3, T4) = HandlerArgs::get_handler_args(params)?;
-            self(State(state), args.0, args.1, args.2, args.3).await
-        })
-    }
-}
-
-impl<F, Fut, S, R, T1, T2, T3, T4, T5> Handler<S, R, (State<S>, (T1, T2, T3, T4, T5))> for F
-where
-    F: Fn(State<S>, T1, T2, T3, T4, T5) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + Send + 'static,
-    S: Send + Sync + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T4: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T5: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3, T4, T5) = HandlerArgs::get_handler_args(params)?;
-            self(State(state), args.0, args.1, args.2, args.3, args.4).await
-        })
-    }
-}
-
-impl<F, Fut, S, R, T1, T2, T3, T4, T5, T6> Handler<S, R, (State<S>, (T1, T2, T3, T4, T5, T6))> for F
-where
-    F: Fn(State<S>, T1, T2, T3, T4, T5, T6) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + Send + 'static,
-    S: Send + Sync + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T4: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T5: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T6: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
+/// Implements [`Handler`] for functions taking the extractors `$e`,
+/// followed by the positional parameters `$t`.
+macro_rules! impl_handler {
+    ([$($e:ident),*], [$($t:ident),*]) => {
+        impl<F, Fut, S, R, $($e,)* $($t,)*> Handler<S, R, (($($e,)*), ($($t,)*))> for F
+        where
+            F: Fn($($e,)* $($t,)*) -> Fut + Clone + Send + Sync + 'static,
+            Fut: Future<Output = Result<R>> + Send,
+            R: Send + 'static,
+            S: Send + Sync + 'static,
+            $($e: FromRequestParts<S> + Send + 'static,)*
+            $($t: for<'de> Deserialize<'de> + Send + Sync + 'static,)*
+        {
+            type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
+
+            #[allow(non_snake_case, unused_variables)]
+            fn call(
+                self,
+                state: S,
+                parts: RequestParts,
+                params: DuperValue<'static>,
+                names: Option<Arc<[String]>>,
+            ) -> Self::Future {
+                Box::pin(async move {
+                    $(let $e = $e::from_request_parts(&parts, &state).await?;)*
+                    let ($($t,)*): ($($t,)*) = HandlerArgs::get_handler_args(
+                        params,
+                        names.as_deref().unwrap_or_default(),
+                    )?;
+                    self($($e,)* $($t,)*).await
+                })
+            }
 
-    fn call(self, state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3, T4, T5, T6) = HandlerArgs::get_handler_args(params)?;
-            self(State(state), args.0, args.1, args.2, args.3, args.4, args.5).await
-        })
-    }
-}
-impl<F, Fut, S, R, T1, T2, T3, T4, T5, T6, T7>
-    Handler<S, R, (State<S>, (T1, T2, T3, T4, T5, T6, T7))> for F
-where
-    F: Fn(State<S>, T1, T2, T3, T4, T5, T6, T7) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + Send + 'static,
-    S: Send + Sync + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T4: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T5: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T6: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T7: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
+            fn param_types() -> Vec<&'static str> {
+                vec![$(std::any::type_name::<$t>(),)*]
+            }
+        }
 
-    fn call(self, state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3, T4, T5, T6, T7) = HandlerArgs::get_handler_args(params)?;
-            self(
-                State(state),
-                args.0,
-                args.1,
-                args.2,
-                args.3,
-                args.4,
-                args.5,
-                args.6,
-            )
-            .await
-        })
-    }
+        impl<$($e,)* $($t,)*> HandlerParams for (($($e,)*), ($($t,)*)) {
+            const COUNT: usize = <[&str]>::len(&[$(stringify!($t),)*]);
+        }
+    };
 }
 
-impl<F, Fut, S, R, T1, T2, T3, T4, T5, T6, T7, T8>
-    Handler<S, R, (State<S>, (T1, T2, T3, T4, T5, T6, T7, T8))> for F
-where
-    F: Fn(State<S>, T1, T2, T3, T4, T5, T6, T7, T8) -> Fut + Clone + Send + Sync + 'static,
-    Fut: Future<Output = Result<R>> + Send,
-    R: Serialize + Send + 'static,
-    S: Send + Sync + 'static,
-    T1: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T2: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T3: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T4: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T5: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T6: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T7: for<'de> Deserialize<'de> + Send + Sync + 'static,
-    T8: for<'de> Deserialize<'de> + Send + Sync + 'static,
-{
-    type Future = Pin<Box<dyn Future<Output = Result<R>> + Send>>;
-
-    fn call(self, state: S, params: DuperValue<'static>) -> Self::Future {
-        Box::pin(async move {
-            let args: (T1, T2, T3, T4, T5, T6, T7, T8) = HandlerArgs::get_handler_args(params)?;
-            self(
-                State(state),
-                args.0,
-                args.1,
-                args.2,
-                args.3,
-                args.4,
-                args.5,
-                args.6,
-                args.7,
-            )
-            .await
-        })
-    }
-}
+impl_handler!([], []);
+impl_handler!([], [T1]);
+impl_handler!([], [T1, T2]);
+impl_handler!([], [T1, T2, T3]);
+impl_handler!([], [T1, T2, T3, T4]);
+impl_handler!([], [T1, T2, T3, T4, T5]);
+impl_handler!([], [T1, T2, T3, T4, T5, T6]);
+impl_handler!([], [T1, T2, T3, T4, T5, T6, T7]);
+impl_handler!([], [T1, T2, T3, T4, T5, T6, T7, T8]);
+impl_handler!([E1], []);
+impl_handler!([E1], [T1]);
+impl_handler!([E1], [T1, T2]);
+impl_handler!([E1], [T1, T2, T3]);
+impl_handler!([E1], [T1, T2, T3, T4]);
+impl_handler!([E1], [T1, T2, T3, T4, T5]);
+impl_handler!([E1], [T1, T2, T3, T4, T5, T6]);
+impl_handler!([E1], [T1, T2, T3, T4, T5, T6, T7]);
+impl_handler!([E1], [T1, T2, T3, T4, T5, T6, T7, T8]);
+impl_handler!([E1, E2], []);
+impl_handler!([E1, E2], [T1]);
+impl_handler!([E1, E2], [T1, T2]);
+impl_handler!([E1, E2], [T1, T2, T3]);
+impl_handler!([E1, E2], [T1, T2, T3, T4]);
+impl_handler!([E1, E2], [T1, T2, T3, T4, T5]);
+impl_handler!([E1, E2], [T1, T2, T3, T4, T5, T6]);
+impl_handler!([E1, E2], [T1, T2, T3, T4, T5, T6, T7]);
+impl_handler!([E1, E2, E3], []);
+impl_handler!([E1, E2, E3], [T1]);
+impl_handler!([E1, E2, E3], [T1, T2]);
+impl_handler!([E1, E2, E3], [T1, T2, T3]);
+impl_handler!([E1, E2, E3], [T1, T2, T3, T4]);
+impl_handler!([E1, E2, E3], [T1, T2, T3, T4, T5]);
+impl_handler!([E1, E2, E3], [T1, T2, T3, T4, T5, T6]);
+impl_handler!([E1, E2, E3, E4], []);
+impl_handler!([E1, E2, E3, E4], [T1]);
+impl_handler!([E1, E2, E3, E4], [T1, T2]);
+impl_handler!([E1, E2, E3, E4], [T1, T2, T3]);
+impl_handler!([E1, E2, E3, E4], [T1, T2, T3, T4]);
+impl_handler!([E1, E2, E3, E4], [T1, T2, T3, T4, T5]);
+impl_handler!([E1, E2, E3, E4, E5], []);
+impl_handler!([E1, E2, E3, E4, E5], [T1]);
+impl_handler!([E1, E2, E3, E4, E5], [T1, T2]);
+impl_handler!([E1, E2, E3, E4, E5], [T1, T2, T3]);
+impl_handler!([E1, E2, E3, E4, E5], [T1, T2, T3, T4]);
+impl_handler!([E1, E2, E3, E4, E5, E6], []);
+impl_handler!([E1, E2, E3, E4, E5, E6], [T1]);
+impl_handler!([E1, E2, E3, E4, E5, E6], [T1, T2]);
+impl_handler!([E1, E2, E3, E4, E5, E6], [T1, T2, T3]);
+impl_handler!([E1, E2, E3, E4, E5, E6, E7], []);
+impl_handler!([E1, E2, E3, E4, E5, E6, E7], [T1]);
+impl_handler!([E1, E2, E3, E4, E5, E6, E7], [T1, T2]);
+impl_handler!([E1, E2, E3, E4, E5, E6, E7, E8], []);
+impl_handler!([E1, E2, E3, E4, E5, E6, E7, E8], [T1]);
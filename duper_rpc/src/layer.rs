@@ -0,0 +1,607 @@
+//! Per-call middleware for Duper RPC servers.
+//!
+//! The layers in this module wrap services handling a single [`Call`], and
+//! are applied with [`ServerService::layer`]. Since every call in a batch is
+//! handled by its own clone of the layered service, each entry is timed out,
+//! limited, or rejected independently of the others.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use duper_rpc::{
+//!     Error,
+//!     layer::{AllowMethodsLayer, AuthorizeLayer, ConcurrencyLimitLayer, TimeoutLayer},
+//!     server::Server,
+//! };
+//!
+//! #[derive(Clone)]
+//! struct Token(String);
+//!
+//! let service = Server::new()
+//!     .method("ping", async || Ok("pong"))
+//!     .method("admin", async || Ok(true))
+//!     .into_service()
+//!     .layer(TimeoutLayer::new(Duration::from_secs(10)))
+//!     .layer(ConcurrencyLimitLayer::new(64))
+//!     .layer(AllowMethodsLayer::new(["ping"]))
+//!     .layer(AuthorizeLayer::new(|call: &duper_rpc::server::Call| {
+//!         match call.parts.context.get::<Token>() {
+//!             Some(Token(token)) if token == "secret" => Ok(()),
+//!             _ => Err(Error::InvalidRequest),
+//!         }
+//!     }));
+//! # drop(service);
+//! ```
+//!
+//! [`ServerService::layer`]: crate::server::ServerService::layer
+
+use std::{collections::HashSet, pin::Pin, sync::Arc, task::Poll, time::Duration};
+
+use duper::{DuperIdentifier, DuperValue};
+use futures::future::{Either, select};
+use tower::{Layer, Service};
+
+use crate::{Error, Result, server::Call};
+
+pub use tower::limit::{ConcurrencyLimit, ConcurrencyLimitLayer};
+
+type CallFuture = Pin<Box<dyn Future<Output = Result<DuperValue<'static>>> + Send + 'static>>;
+
+/// A layer that fails calls which take longer than a given duration.
+///
+/// By default, timed out calls fail with a custom error containing
+/// `Timeout("Call timed out")`.
+#[derive(Debug, Clone)]
+pub struct TimeoutLayer {
+    duration: Duration,
+    error: Error,
+}
+
+impl TimeoutLayer {
+    /// Create a new timeout layer.
+    pub fn new(duration: Duration) -> Self {
+        TimeoutLayer {
+            duration,
+            error: Error::Custom(DuperValue::String {
+                identifier: Some(DuperIdentifier::try_from("Timeout").expect("valid identifier")),
+                inner: "Call timed out".into(),
+            }),
+        }
+    }
+
+    /// Replace the error returned when a call times out.
+    pub fn with_error(self, error: Error) -> Self {
+        TimeoutLayer { error, ..self }
+    }
+}
+
+impl<S> Layer<S> for TimeoutLayer {
+    type Service = Timeout<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Timeout {
+            inner,
+            duration: self.duration,
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// A service created by [`TimeoutLayer`].
+#[derive(Debug, Clone)]
+pub struct Timeout<S> {
+    inner: S,
+    duration: Duration,
+    error: Error,
+}
+
+async fn sleep(duration: Duration) {
+    #[cfg(feature = "tokio")]
+    {
+        tokio::time::sleep(duration).await;
+    }
+    #[cfg(all(not(feature = "tokio"), feature = "smol"))]
+    {
+        smol::Timer::after(duration).await;
+    }
+}
+
+impl<S> Service<Call> for Timeout<S>
+where
+    S: Service<Call, Response = DuperValue<'static>, Error = Error>,
+    S::Future: Send + 'static,
+{
+    type Response = DuperValue<'static>;
+    type Error = Error;
+    type Future = CallFuture;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, call: Call) -> Self::Future {
+        let future = self.inner.call(call);
+        let duration = self.duration;
+        let error = self.error.clone();
+        Box::pin(async move {
+            match select(Box::pin(future), Box::pin(sleep(duration))).await {
+                Either::Left((result, _)) => result,
+                Either::Right(((), _)) => Err(error),
+            }
+        })
+    }
+}
+
+/// A layer that only allows calls to the given methods.
+///
+/// Calls to any other method fail with [`Error::MethodNotFound`].
+#[derive(Debug, Clone)]
+pub struct AllowMethodsLayer {
+    methods: Arc<HashSet<String>>,
+}
+
+impl AllowMethodsLayer {
+    /// Create a new allow-list layer.
+    pub fn new<I, M>(methods: I) -> Self
+    where
+        I: IntoIterator<Item = M>,
+        M: Into<String>,
+    {
+        AllowMethodsLayer {
+            methods: Arc::new(methods.into_iter().map(Into::into).collect()),
+        }
+    }
+}
+
+impl<S> Layer<S> for AllowMethodsLayer {
+    type Service = AllowMethods<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AllowMethods {
+            inner,
+            methods: self.methods.clone(),
+        }
+    }
+}
+
+/// A service created by [`AllowMethodsLayer`].
+#[derive(Debug, Clone)]
+pub struct AllowMethods<S> {
+    inner: S,
+    methods: Arc<HashSet<String>>,
+}
+
+impl<S> Service<Call> for AllowMethods<S>
+where
+    S: Service<Call, Response = DuperValue<'static>, Error = Error>,
+    S::Future: Send + 'static,
+{
+    type Response = DuperValue<'static>;
+    type Error = Error;
+    type Future = CallFuture;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, call: Call) -> Self::Future {
+        if self.methods.contains(&call.parts.method) {
+            Box::pin(self.inner.call(call))
+        } else {
+            Box::pin(async { Err(Error::MethodNotFound) })
+        }
+    }
+}
+
+/// A layer that rejects calls for which a predicate returns an error.
+///
+/// The predicate has access to the whole [`Call`], including the request
+/// [`Context`](crate::extract::Context), which makes it suitable for
+/// authentication and authorization.
+#[derive(Debug, Clone)]
+pub struct AuthorizeLayer<F> {
+    predicate: F,
+}
+
+impl<F> AuthorizeLayer<F>
+where
+    F: Fn(&Call) -> Result<()>,
+{
+    /// Create a new authorization layer.
+    pub fn new(predicate: F) -> Self {
+        AuthorizeLayer { predicate }
+    }
+}
+
+impl<S, F> Layer<S> for AuthorizeLayer<F>
+where
+    F: Clone,
+{
+    type Service = Authorize<S, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Authorize {
+            inner,
+            predicate: self.predicate.clone(),
+        }
+    }
+}
+
+/// A service created by [`AuthorizeLayer`].
+#[derive(Debug, Clone)]
+pub struct Authorize<S, F> {
+    inner: S,
+    predicate: F,
+}
+
+impl<S, F> Service<Call> for Authorize<S, F>
+where
+    S: Service<Call, Response = DuperValue<'static>, Error = Error>,
+    S::Future: Send + 'static,
+    F: Fn(&Call) -> Result<()>,
+{
+    type Response = DuperValue<'static>;
+    type Error = Error;
+    type Future = CallFuture;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, call: Call) -> Self::Future {
+        match (self.predicate)(&call) {
+            Ok(()) => Box::pin(self.inner.call(call)),
+            Err(error) => Box::pin(async { Err(error) }),
+        }
+    }
+}
+
+/// A layer that instruments every call with a [`tracing`] span.
+///
+/// The span is named `duper_rpc_call`, and records the `method` and `id` of
+/// the call. Failed calls also emit a debug event with the error.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Default)]
+pub struct TraceLayer;
+
+#[cfg(feature = "tracing")]
+impl TraceLayer {
+    /// Create a new tracing layer.
+    pub fn new() -> Self {
+        TraceLayer
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<S> Layer<S> for TraceLayer {
+    type Service = Trace<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Trace { inner }
+    }
+}
+
+/// A service created by [`TraceLayer`].
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone)]
+pub struct Trace<S> {
+    inner: S,
+}
+
+#[cfg(feature = "tracing")]
+impl<S> Service<Call> for Trace<S>
+where
+    S: Service<Call, Response = DuperValue<'static>, Error = Error>,
+    S::Future: Send + 'static,
+{
+    type Response = DuperValue<'static>;
+    type Error = Error;
+    type Future = CallFuture;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, call: Call) -> Self::Future {
+        use tracing::Instrument;
+
+        let span = match &call.parts.id {
+            Some(crate::RequestId::String { inner, .. }) => {
+                tracing::info_span!("duper_rpc_call", method = %call.parts.method, id = %inner)
+            }
+            Some(crate::RequestId::Integer { inner, .. }) => {
+                tracing::info_span!("duper_rpc_call", method = %call.parts.method, id = inner)
+            }
+            None => tracing::info_span!("duper_rpc_call", method = %call.parts.method),
+        };
+        let future = span.in_scope(|| self.inner.call(call));
+        Box::pin(
+            async move {
+                let result = future.await;
+                if let Err(error) = &result {
+                    tracing::debug!(?error, "call failed");
+                }
+                result
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod layer_tests {
+    use tower::Service;
+
+    use crate::{
+        Request, RequestCall, RequestId, Response, ResponseError, ResponseResult, ResponseSuccess,
+        extract::Context, server::Server,
+    };
+
+    use super::*;
+
+    fn call(id: i64, method: &str, params: DuperValue<'static>) -> RequestCall {
+        RequestCall::Valid {
+            id: Some(RequestId::Integer {
+                identifier: None,
+                inner: id,
+            }),
+            method: method.into(),
+            params,
+        }
+    }
+
+    #[tokio::test]
+    async fn timeout_per_call() {
+        async fn sleep_for(millis: u64) -> Result<u64> {
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+            Ok(millis)
+        }
+
+        let mut service = Server::new()
+            .method("sleep", sleep_for)
+            .into_service()
+            .layer(TimeoutLayer::new(Duration::from_millis(100)));
+
+        let result = service
+            .call(Request::Batch(vec![
+                call(
+                    1,
+                    "sleep",
+                    DuperValue::Integer {
+                        identifier: None,
+                        inner: 10,
+                    },
+                ),
+                call(
+                    2,
+                    "sleep",
+                    DuperValue::Integer {
+                        identifier: None,
+                        inner: 1000,
+                    },
+                ),
+            ]))
+            .await;
+
+        let Ok(Some(Response::Batch(mut responses))) = result else {
+            panic!("Invalid response {:?}", result);
+        };
+        assert_eq!(responses.len(), 2);
+        let ResponseResult::Ok(ResponseSuccess { result, .. }) = responses.remove(0) else {
+            panic!("Invalid response result");
+        };
+        assert_eq!(
+            result,
+            DuperValue::Integer {
+                identifier: None,
+                inner: 10
+            }
+        );
+        let ResponseResult::Err(ResponseError { error, .. }) = responses.remove(0) else {
+            panic!("Invalid response result");
+        };
+        let Error::Custom(DuperValue::String { identifier, .. }) = error else {
+            panic!("Invalid error {:?}", error);
+        };
+        assert_eq!(identifier.as_ref().map(AsRef::as_ref), Some("Timeout"));
+    }
+
+    #[tokio::test]
+    async fn allow_methods_and_authorize() {
+        #[derive(Clone)]
+        struct Admin;
+
+        let mut service = Server::new()
+            .method("public", async || Ok(1))
+            .method("admin", async || Ok(2))
+            .method("hidden", async || Ok(3))
+            .into_service()
+            .layer(AuthorizeLayer::new(|call: &Call| {
+                if call.parts.method == "admin" && call.parts.context.get::<Admin>().is_none() {
+                    Err(Error::Custom(DuperValue::String {
+                        identifier: None,
+                        inner: "Unauthorized".into(),
+                    }))
+                } else {
+                    Ok(())
+                }
+            }))
+            .layer(AllowMethodsLayer::new(["public", "admin"]));
+
+        let null = || DuperValue::Null { identifier: None };
+        let request = Request::Batch(vec![
+            call(1, "public", null()),
+            call(2, "admin", null()),
+            call(3, "hidden", null()),
+        ]);
+
+        let Ok(Some(Response::Batch(responses))) = service.call(request.clone()).await else {
+            panic!("Invalid response");
+        };
+        let errors: Vec<_> = responses
+            .into_iter()
+            .map(|result| result.err().map(|error| error.error))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                None,
+                Some(Error::Custom(DuperValue::String {
+                    identifier: None,
+                    inner: "Unauthorized".into(),
+                })),
+                Some(Error::MethodNotFound),
+            ]
+        );
+
+        let mut context = Context::new();
+        context.insert(Admin);
+        let Ok(Some(Response::Batch(responses))) = service.call((request, context)).await else {
+            panic!("Invalid response");
+        };
+        let errors: Vec<_> = responses
+            .into_iter()
+            .map(|result| result.err().map(|error| error.error))
+            .collect();
+        assert_eq!(errors, [None, None, Some(Error::MethodNotFound)]);
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+        static MAX_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+        async fn work() -> Result<bool> {
+            let in_flight = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+            MAX_IN_FLIGHT.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+            Ok(true)
+        }
+
+        let mut service = Server::new()
+            .method("work", work)
+            .into_service()
+            .layer(ConcurrencyLimitLayer::new(2));
+
+        let null = || DuperValue::Null { identifier: None };
+        let result = service
+            .call(Request::Batch(
+                (1..=6).map(|id| call(id, "work", null())).collect(),
+            ))
+            .await;
+
+        let Ok(Some(Response::Batch(responses))) = result else {
+            panic!("Invalid response {:?}", result);
+        };
+        // Every call waits for its turn instead of failing.
+        assert_eq!(responses.len(), 6);
+        assert!(responses.iter().all(|result| result.is_ok()));
+        // Calls run concurrently, but never more than the limit at once.
+        assert_eq!(MAX_IN_FLIGHT.load(Ordering::SeqCst), 2);
+    }
+
+    /// A subscriber recording the spans and events, along with the span in
+    /// which each event was emitted.
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct Recorder {
+        spans: Arc<std::sync::Mutex<Vec<String>>>,
+        events: Arc<std::sync::Mutex<Vec<String>>>,
+        entered: Arc<std::sync::Mutex<Vec<tracing::span::Id>>>,
+    }
+
+    #[cfg(feature = "tracing")]
+    struct Fields(String);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for Fields {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut fields = Fields(span.metadata().name().to_string());
+            span.record(&mut fields);
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(fields.0);
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut fields = Fields(event.metadata().level().to_string());
+            event.record(&mut fields);
+            let span = match self.entered.lock().unwrap().last() {
+                Some(id) => self.spans.lock().unwrap()[id.into_u64() as usize - 1].clone(),
+                None => String::new(),
+            };
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{span}: {}", fields.0));
+        }
+
+        fn enter(&self, span: &tracing::span::Id) {
+            self.entered.lock().unwrap().push(span.clone());
+        }
+
+        fn exit(&self, _: &tracing::span::Id) {
+            self.entered.lock().unwrap().pop();
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn trace_spans() {
+        let recorder = Recorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
+
+        let mut service = Server::new()
+            .method("ok", async || Ok(1))
+            .method("fail", async || Result::<i64>::Err(Error::InvalidRequest))
+            .into_service()
+            .layer(TraceLayer::new());
+
+        let null = || DuperValue::Null { identifier: None };
+        let result = service
+            .call(Request::Batch(vec![
+                call(1, "ok", null()),
+                RequestCall::Valid {
+                    id: Some(RequestId::String {
+                        identifier: None,
+                        inner: "two".into(),
+                    }),
+                    method: "fail".into(),
+                    params: null(),
+                },
+            ]))
+            .await;
+        assert!(matches!(result, Ok(Some(Response::Batch(_)))));
+
+        assert_eq!(
+            *recorder.spans.lock().unwrap(),
+            [
+                "duper_rpc_call method=ok id=1",
+                "duper_rpc_call method=fail id=two"
+            ]
+        );
+        // Only the failed call emits an event, within its span.
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            ["duper_rpc_call method=fail id=two: DEBUG message=call failed error=InvalidRequest"]
+        );
+    }
+}
@@ -5,15 +5,22 @@
 //!
 //! This crate contains definitions of the base parts of the Duper RPC
 //! (including requests, responses, and errors), as well as an implementation
-//! of a [`tower`] based server (see [`server`]) and a [`RequestBuilder`].
+//! of a [`tower`] based server (see [`server`]) with handler extractors
+//! (see [`extract`]), per-call middleware (see [`layer`]), introspection
+//! (see [`discover`]), streaming and cancellation (see [`session`]), and a
+//! [`RequestBuilder`].
 
 mod de;
+pub mod discover;
 mod error;
+pub mod extract;
 mod handler;
+pub mod layer;
 pub mod request;
 pub mod response;
 mod ser;
 pub mod server;
+pub mod session;
 
 use duper::{DuperIdentifier, DuperValue};
 
@@ -34,6 +34,7 @@ pub enum RequestCall {
         ///
         /// For single-parameter calls, any non-tuple value can be used.
         /// For n-ary-parameter calls, an n-sized tuple must be used.
+        /// Methods with named parameters also accept an object.
         params: DuperValue<'static>,
     },
     /// An invalid request.
@@ -95,6 +96,32 @@ impl RequestBuilder {
         }
     }
 
+    /// Add a request with named parameters.
+    ///
+    /// `params` should serialize into an object, whose keys match the
+    /// parameter names declared by the server method.
+    pub fn request_named<P>(
+        self,
+        method: String,
+        id: Option<RequestId>,
+        params: &P,
+    ) -> RequestBuilderSingle
+    where
+        P: Serialize,
+    {
+        let params = match to_duper(params) {
+            Ok(value) => value,
+            Err(error) => return RequestBuilderSingle { call: Err(error) },
+        };
+        RequestBuilderSingle {
+            call: Ok(RequestCall::Valid {
+                id,
+                method,
+                params: params.static_clone(),
+            }),
+        }
+    }
+
     /// Add a request with 1 parameter.
     #[allow(clippy::too_many_arguments)]
     pub fn request1<T1>(
@@ -541,6 +568,40 @@ impl RequestBuilderSingle {
         }
     }
 
+    /// Add a request with named parameters.
+    ///
+    /// `params` should serialize into an object, whose keys match the
+    /// parameter names declared by the server method.
+    pub fn request_named<P>(
+        self,
+        method: String,
+        id: Option<RequestId>,
+        params: &P,
+    ) -> RequestBuilderBatch
+    where
+        P: Serialize,
+    {
+        match self.call {
+            Ok(call) => {
+                let params = match to_duper(params) {
+                    Ok(value) => value,
+                    Err(error) => return RequestBuilderBatch { calls: Err(error) },
+                };
+                RequestBuilderBatch {
+                    calls: Ok(vec![
+                        call,
+                        RequestCall::Valid {
+                            id,
+                            method,
+                            params: params.static_clone(),
+                        },
+                    ]),
+                }
+            }
+            Err(error) => RequestBuilderBatch { calls: Err(error) },
+        }
+    }
+
     /// Add a request with 1 parameter.
     #[allow(clippy::too_many_arguments)]
     pub fn request1<T1>(self, method: String, id: Option<RequestId>, t1: &T1) -> RequestBuilderBatch
@@ -1047,6 +1108,36 @@ impl RequestBuilderBatch {
         }
     }
 
+    /// Add a request with named parameters.
+    ///
+    /// `params` should serialize into an object, whose keys match the
+    /// parameter names declared by the server method.
+    pub fn request_named<P>(
+        self,
+        method: String,
+        id: Option<RequestId>,
+        params: &P,
+    ) -> RequestBuilderBatch
+    where
+        P: Serialize,
+    {
+        match self.calls {
+            Ok(mut calls) => {
+                let params = match to_duper(params) {
+                    Ok(value) => value,
+                    Err(error) => return RequestBuilderBatch { calls: Err(error) },
+                };
+                calls.push(RequestCall::Valid {
+                    id,
+                    method,
+                    params: params.static_clone(),
+                });
+                RequestBuilderBatch { calls: Ok(calls) }
+            }
+            Err(error) => RequestBuilderBatch { calls: Err(error) },
+        }
+    }
+
     /// Add a request with 1 parameter.
     #[allow(clippy::too_many_arguments)]
     pub fn request1<T1>(self, method: String, id: Option<RequestId>, t1: &T1) -> RequestBuilderBatch
@@ -29,11 +29,17 @@ impl Serialize for Error {
                 map.serialize_entry("type", "MethodNotFound")?;
                 map.end()
             }
-            Error::InvalidParams => {
+            Error::InvalidParams(None) => {
                 let mut map = serializer.serialize_map(Some(1))?;
                 map.serialize_entry("type", "InvalidParams")?;
                 map.end()
             }
+            Error::InvalidParams(Some(message)) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("type", "InvalidParams")?;
+                map.serialize_entry("message", message)?;
+                map.end()
+            }
             Error::InternalError => {
                 let mut map = serializer.serialize_map(Some(1))?;
                 map.serialize_entry("type", "InternalError")?;
@@ -49,6 +49,9 @@
 //! with [`Method::handle`] for immediate consumption, or returned via
 //! [`Method::into_service`].
 //!
+//! Per-call middleware can be added with [`ServerService::layer`], which wraps
+//! the service handling each individual [`Call`] (see [`crate::layer`]).
+//!
 //! ```
 //! use duper_rpc::server::Server;
 //!
@@ -64,16 +67,20 @@
 //! }
 //! ```
 
-use std::{convert::Infallible, marker::PhantomData, pin::Pin, task::Poll};
+use std::{convert::Infallible, marker::PhantomData, pin::Pin, sync::Arc, task::Poll};
 
-use duper::{DuperValue, serde::ser::Serializer};
-use futures::future::join_all;
-use serde_core::Serialize;
-use tower::Service;
+use duper::{DuperKey, DuperValue};
+use futures::future::{Aborted, abortable, join_all};
+use tower::{Layer, Service, ServiceExt};
 
 use crate::{
     Error, Request, RequestCall, Response, ResponseError, ResponseResult, ResponseSuccess, Result,
-    handler::Handler,
+    discover::{
+        DISCOVER_METHOD, MethodDescription, ParamDescription, ServiceDescription, short_type_name,
+    },
+    extract::{Context, RequestParts},
+    handler::{Handler, HandlerParams},
+    session::{CANCEL_METHOD, IntoCallResult, Session, cancel_call, cancelled_error},
 };
 
 /// A wrapper around a shared state.
@@ -119,6 +126,41 @@ impl<S> Server<S> {
     {
         Method {
             name: name.as_ref().to_string(),
+            doc: None,
+            params: None,
+            handler,
+            next: self,
+            _marker: Default::default(),
+        }
+    }
+
+    /// Add a method with named parameters to the server.
+    ///
+    /// Besides positional parameters, the method also accepts an object
+    /// mapping each of `params` to the respective handler parameter. Unknown
+    /// or missing keys are rejected. A method with a single object parameter
+    /// must then receive it wrapped in a unary tuple.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of `params` doesn't match the number of positional
+    /// parameters of `handler`, or if a name is repeated.
+    pub fn method_with_params<H, R, T, P>(
+        self,
+        name: impl AsRef<str>,
+        params: P,
+        handler: H,
+    ) -> Method<H, R, T, Self>
+    where
+        Self: Sized,
+        P: IntoIterator,
+        P::Item: Into<String>,
+        T: HandlerParams,
+    {
+        Method {
+            name: name.as_ref().to_string(),
+            doc: None,
+            params: Some(param_names::<T, _>(name.as_ref(), params)),
             handler,
             next: self,
             _marker: Default::default(),
@@ -129,6 +171,8 @@ impl<S> Server<S> {
 /// A method in a Duper RPC server.
 pub struct Method<H, R, T, N> {
     name: String,
+    doc: Option<String>,
+    params: Option<Arc<[String]>>,
     handler: H,
     next: N,
     _marker: PhantomData<fn(T) -> R>,
@@ -142,6 +186,8 @@ where
     fn clone(&self) -> Self {
         Self {
             name: self.name.clone(),
+            doc: self.doc.clone(),
+            params: self.params.clone(),
             handler: self.handler.clone(),
             next: self.next.clone(),
             _marker: Default::default(),
@@ -157,12 +203,56 @@ impl<H, R, T, N> Method<H, R, T, N> {
     {
         Method {
             name: name.as_ref().to_string(),
+            doc: None,
+            params: None,
             handler,
             next: self,
             _marker: Default::default(),
         }
     }
 
+    /// Add a method with named parameters to the server.
+    ///
+    /// Besides positional parameters, the method also accepts an object
+    /// mapping each of `params` to the respective handler parameter. Unknown
+    /// or missing keys are rejected. A method with a single object parameter
+    /// must then receive it wrapped in a unary tuple.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of `params` doesn't match the number of positional
+    /// parameters of `handler`, or if a name is repeated.
+    pub fn method_with_params<H2, R2, T2, P>(
+        self,
+        name: impl AsRef<str>,
+        params: P,
+        handler: H2,
+    ) -> Method<H2, R2, T2, Self>
+    where
+        Self: Sized,
+        P: IntoIterator,
+        P::Item: Into<String>,
+        T2: HandlerParams,
+    {
+        Method {
+            name: name.as_ref().to_string(),
+            doc: None,
+            params: Some(param_names::<T2, _>(name.as_ref(), params)),
+            handler,
+            next: self,
+            _marker: Default::default(),
+        }
+    }
+
+    /// Set the documentation of this method, as returned by
+    /// [`rpc.discover`](crate::discover).
+    pub fn doc(self, doc: impl Into<String>) -> Self {
+        Method {
+            doc: Some(doc.into()),
+            ..self
+        }
+    }
+
     /// Add a stateful layer to the server, which is applied
     /// to all methods defined before it.
     pub fn with_state<S2>(self, state: S2) -> WithState<S2, Self>
@@ -174,11 +264,13 @@ impl<H, R, T, N> Method<H, R, T, N> {
     }
 
     /// Convert this server into a [`tower`] service.
-    pub fn into_service(self) -> ServerService<Self>
+    pub fn into_service(self) -> ServerService<CallService<Self>>
     where
         Self: Sized + ServerPart<()> + Clone + Send + 'static,
     {
-        ServerService { inner: self }
+        ServerService {
+            inner: CallService { server: self },
+        }
     }
 
     /// Handle a single RPC [`Request`].
@@ -195,6 +287,22 @@ impl<H, R, T, N> Method<H, R, T, N> {
     {
         self.into_service().call(req)
     }
+
+    /// Handle a single RPC [`Request`] with the given request-scoped [`Context`].
+    pub fn handle_with_context(
+        self,
+        req: Request,
+        context: Context,
+    ) -> Pin<
+        Box<
+            dyn Future<Output = std::result::Result<Option<Response>, Infallible>> + Send + 'static,
+        >,
+    >
+    where
+        Self: Sized + ServerPart<()> + Clone + Send + 'static,
+    {
+        self.into_service().call((req, context))
+    }
 }
 
 /// A stateful layer in a Duper RPC server.
@@ -224,6 +332,41 @@ impl<S, N> WithState<S, N> {
     {
         Method {
             name: name.as_ref().to_string(),
+            doc: None,
+            params: None,
+            handler,
+            next: self,
+            _marker: Default::default(),
+        }
+    }
+
+    /// Add a method with named parameters to the server.
+    ///
+    /// Besides positional parameters, the method also accepts an object
+    /// mapping each of `params` to the respective handler parameter. Unknown
+    /// or missing keys are rejected. A method with a single object parameter
+    /// must then receive it wrapped in a unary tuple.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of `params` doesn't match the number of positional
+    /// parameters of `handler`, or if a name is repeated.
+    pub fn method_with_params<H, R, T, P>(
+        self,
+        name: impl AsRef<str>,
+        params: P,
+        handler: H,
+    ) -> Method<H, R, T, Self>
+    where
+        Self: Sized,
+        P: IntoIterator,
+        P::Item: Into<String>,
+        T: HandlerParams,
+    {
+        Method {
+            name: name.as_ref().to_string(),
+            doc: None,
+            params: Some(param_names::<T, _>(name.as_ref(), params)),
             handler,
             next: self,
             _marker: Default::default(),
@@ -231,11 +374,13 @@ impl<S, N> WithState<S, N> {
     }
 
     /// Convert this server into a [`tower`] service.
-    pub fn into_service(self) -> ServerService<Self>
+    pub fn into_service(self) -> ServerService<CallService<Self>>
     where
         Self: Sized + ServerPart<()> + Clone + Send + 'static,
     {
-        ServerService { inner: self }
+        ServerService {
+            inner: CallService { server: self },
+        }
     }
 
     /// Handle a single RPC [`Request`].
@@ -252,56 +397,189 @@ impl<S, N> WithState<S, N> {
     {
         self.into_service().call(req)
     }
+
+    /// Handle a single RPC [`Request`] with the given request-scoped [`Context`].
+    pub fn handle_with_context(
+        self,
+        req: Request,
+        context: Context,
+    ) -> Pin<
+        Box<
+            dyn Future<Output = std::result::Result<Option<Response>, Infallible>> + Send + 'static,
+        >,
+    >
+    where
+        Self: Sized + ServerPart<()> + Clone + Send + 'static,
+    {
+        self.into_service().call((req, context))
+    }
+}
+
+/// A single valid call within an RPC request.
+///
+/// This is the request type for the per-call services wrapped by
+/// [`ServerService::layer`].
+#[derive(Debug, Clone)]
+pub struct Call {
+    /// Metadata of the call.
+    pub parts: RequestParts,
+    /// Parameters for the call.
+    pub params: DuperValue<'static>,
 }
 
 /// A [`tower`] service created from a layered [`Server`].
-pub struct ServerService<I> {
-    inner: I,
+///
+/// It accepts either a bare [`Request`], or a `(Request, Context)` pair
+/// to make request-scoped data available to extractors. Each call in the
+/// request is handled independently by the inner per-call service.
+pub struct ServerService<C> {
+    inner: C,
 }
 
-fn handle_call<I>(
+impl<C> Clone for ServerService<C>
+where
+    C: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C> ServerService<C> {
+    /// Wrap the per-call service with a [`Layer`].
+    ///
+    /// Unlike layers applied to the whole service, this sees every [`Call`]
+    /// in a batch separately, including notifications. The layered service
+    /// must be [`Clone`], as it is cloned once per call.
+    pub fn layer<L>(self, layer: L) -> ServerService<L::Service>
+    where
+        L: Layer<C>,
+    {
+        ServerService {
+            inner: layer.layer(self.inner),
+        }
+    }
+}
+
+/// The innermost per-call service, which dispatches a [`Call`] to its method.
+///
+/// It also answers [`DISCOVER_METHOD`] with a description of the server, and
+/// handles [`CANCEL_METHOD`] for calls tracked by a [`Session`].
+pub struct CallService<I> {
     server: I,
+}
+
+impl<I> Clone for CallService<I>
+where
+    I: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            server: self.server.clone(),
+        }
+    }
+}
+
+impl<I> Service<Call> for CallService<I>
+where
+    I: ServerPart<()>,
+{
+    type Response = DuperValue<'static>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<DuperValue<'static>>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, call: Call) -> Self::Future {
+        if call.parts.method == DISCOVER_METHOD {
+            let mut methods = Vec::new();
+            self.server.describe(&mut methods);
+            methods.reverse();
+            return Box::pin(async { Ok(ServiceDescription { methods }.into()) });
+        }
+        if call.parts.method == CANCEL_METHOD {
+            let result = cancel_call(&call.parts, call.params);
+            return Box::pin(async { result.map(|()| DuperValue::Null { identifier: None }) });
+        }
+        self.server.serve((), call.parts, call.params)
+    }
+}
+
+fn handle_call<C>(
+    service: C,
     call: RequestCall,
+    context: Context,
 ) -> Pin<Box<dyn Future<Output = Option<ResponseResult>> + Send + 'static>>
 where
-    I: ServerPart<()> + Clone + Send + 'static,
+    C: Service<Call, Response = DuperValue<'static>, Error = Error> + Send + 'static,
+    C::Future: Send + 'static,
 {
     match call {
-        RequestCall::Valid { id, method, params } => match id {
-            Some(id) => Box::pin(async move {
-                match server.serve((), method, params).await {
-                    Ok(resp) => Some(ResponseResult::Ok(ResponseSuccess { id, result: resp })),
-                    Err(error) => Some(ResponseResult::Err(ResponseError {
-                        id: Some(id),
-                        error,
-                    })),
-                }
-            }),
-            None => {
-                #[cfg(feature = "tokio")]
-                {
-                    tokio::spawn(async move { server.serve((), method, params).await });
-                }
-                #[cfg(all(not(feature = "tokio"), feature = "smol"))]
-                {
-                    smol::spawn(async move { server.serve((), method, params).await })
+        RequestCall::Valid { id, method, params } => {
+            let call = Call {
+                parts: RequestParts {
+                    method,
+                    id: id.clone(),
+                    context,
+                },
+                params,
+            };
+            match id {
+                Some(id) => {
+                    // Register the call synchronously, so that a cancellation
+                    // received right after this request finds it.
+                    let session = call.parts.context.get::<Session>().cloned();
+                    let (future, handle) = abortable(service.oneshot(call));
+                    let token = session
+                        .as_ref()
+                        .map(|session| session.register(&id, handle));
+                    Box::pin(async move {
+                        let result = future.await;
+                        if let (Some(session), Some(token)) = (session, token) {
+                            session.unregister(&id, token);
+                        }
+                        match result.unwrap_or_else(|Aborted| Err(cancelled_error())) {
+                            Ok(resp) => {
+                                Some(ResponseResult::Ok(ResponseSuccess { id, result: resp }))
+                            }
+                            Err(error) => Some(ResponseResult::Err(ResponseError {
+                                id: Some(id),
+                                error,
+                            })),
+                        }
+                    })
                 }
-                #[cfg(all(not(feature = "tokio"), not(feature = "smol")))]
-                {
-                    compile_error!("duper_rpc requires an async runtime");
+                None => {
+                    #[cfg(feature = "tokio")]
+                    {
+                        tokio::spawn(service.oneshot(call));
+                    }
+                    #[cfg(all(not(feature = "tokio"), feature = "smol"))]
+                    {
+                        smol::spawn(service.oneshot(call)).detach();
+                    }
+                    #[cfg(all(not(feature = "tokio"), not(feature = "smol")))]
+                    {
+                        compile_error!("duper_rpc requires an async runtime");
+                    }
+                    Box::pin(async { None })
                 }
-                Box::pin(async { None })
             }
-        },
+        }
         RequestCall::Invalid { id, error } => {
             Box::pin(async { Some(ResponseResult::Err(ResponseError { id, error })) })
         }
     }
 }
 
-impl<I> Service<Request> for ServerService<I>
+impl<C> Service<Request> for ServerService<C>
 where
-    I: ServerPart<()> + Clone + Send + 'static,
+    C: Service<Call, Response = DuperValue<'static>, Error = Error> + Clone + Send + 'static,
+    C::Future: Send + 'static,
 {
     type Response = Option<Response>;
     type Error = Infallible;
@@ -317,18 +595,45 @@ where
     }
 
     fn call(&mut self, req: Request) -> Self::Future {
+        self.call((req, Context::default()))
+    }
+}
+
+impl<C> Service<(Request, Context)> for ServerService<C>
+where
+    C: Service<Call, Response = DuperValue<'static>, Error = Error> + Clone + Send + 'static,
+    C::Future: Send + 'static,
+{
+    type Response = Option<Response>;
+    type Error = Infallible;
+    type Future = Pin<
+        Box<dyn Future<Output = std::result::Result<Self::Response, Self::Error>> + Send + 'static>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> Poll<std::result::Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, (req, context): (Request, Context)) -> Self::Future {
         match req {
             Request::Single(call) => {
-                let server = self.inner.clone();
-                Box::pin(async move { Ok(handle_call(server, call).await.map(Response::Single)) })
+                let service = self.inner.clone();
+                Box::pin(async move {
+                    Ok(handle_call(service, call, context)
+                        .await
+                        .map(Response::Single))
+                })
             }
             Request::Batch(request_calls) => {
-                let server = self.inner.clone();
+                let service = self.inner.clone();
                 Box::pin(async move {
                     let resp: Vec<ResponseResult> =
                         join_all(request_calls.into_iter().map(|call| {
-                            let server = server.clone();
-                            handle_call(server, call)
+                            let service = service.clone();
+                            handle_call(service, call, context.clone())
                         }))
                         .await
                         .into_iter()
@@ -350,9 +655,13 @@ pub trait ServerPart<S>: private::Sealed {
     fn serve(
         &self,
         state: S,
-        name: String,
+        parts: RequestParts,
         params: DuperValue<'static>,
     ) -> Pin<Box<dyn Future<Output = Result<DuperValue<'static>>> + Send + 'static>>;
+
+    /// Describe the methods of this part and of the following ones, from the
+    /// most to the least recently added.
+    fn describe(&self, methods: &mut Vec<MethodDescription>);
 }
 
 mod private {
@@ -367,40 +676,117 @@ impl<S> ServerPart<S> for Server<S> {
     fn serve(
         &self,
         _state: S,
-        _name: String,
+        _parts: RequestParts,
         _params: DuperValue<'static>,
     ) -> Pin<Box<dyn Future<Output = Result<DuperValue<'static>>> + Send + 'static>> {
         Box::pin(async { Err(Error::MethodNotFound) })
     }
+
+    fn describe(&self, _methods: &mut Vec<MethodDescription>) {}
+}
+
+/// Collect the parameter names of a method, checking that there's one for each
+/// positional parameter of its handler.
+fn param_names<T, P>(method: &str, params: P) -> Arc<[String]>
+where
+    T: HandlerParams,
+    P: IntoIterator,
+    P::Item: Into<String>,
+{
+    let names: Arc<[String]> = params.into_iter().map(Into::into).collect();
+    assert_eq!(
+        names.len(),
+        T::COUNT,
+        "method `{method}` has {} parameter(s), but {} name(s) were given",
+        T::COUNT,
+        names.len(),
+    );
+    for (i, name) in names.iter().enumerate() {
+        assert!(
+            !names[..i].contains(name),
+            "method `{method}` has repeated parameter name `{name}`"
+        );
+    }
+    names
+}
+
+/// Map object parameters to a tuple, following the order of `names`. Missing or
+/// unknown parameters are rejected with [`Error::InvalidParams`].
+fn named_params(names: &[String], params: DuperValue<'static>) -> Result<DuperValue<'static>> {
+    let DuperValue::Object { inner, .. } = params else {
+        return Ok(params);
+    };
+    let mut inner = inner.into_inner();
+    let values = names
+        .iter()
+        .map(|name| {
+            inner
+                .shift_remove(&DuperKey::from(name.as_str()))
+                .ok_or_else(|| Error::invalid_params(format!("missing parameter `{name}`")))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(name) = inner.keys().next() {
+        return Err(Error::invalid_params(format!(
+            "unknown parameter `{}`",
+            name.as_ref()
+        )));
+    }
+    Ok(DuperValue::Tuple {
+        identifier: None,
+        inner: values,
+    })
 }
 
 impl<S, H, N, R, T> ServerPart<S> for Method<H, R, T, N>
 where
     H: Handler<S, R, T>,
     N: ServerPart<S>,
-    R: Serialize + Send + 'static,
+    R: IntoCallResult,
     S: Clone + Send + 'static,
 {
     fn serve(
         &self,
         state: S,
-        name: String,
+        parts: RequestParts,
         params: DuperValue<'static>,
     ) -> Pin<Box<dyn Future<Output = Result<DuperValue<'static>>> + Send + 'static>> {
-        if self.name == name {
+        if self.name == parts.method {
+            let params = match &self.params {
+                Some(names) => match named_params(names, params) {
+                    Ok(params) => params,
+                    Err(error) => return Box::pin(async { Err(error) }),
+                },
+                None => params,
+            };
             let handler = self.handler.clone();
+            let names = self.params.clone();
             Box::pin(async move {
-                let r = handler.call(state, params).await;
-                match r.map(|resp| resp.serialize(&mut Serializer::new())) {
-                    Ok(Ok(value)) => Ok(value),
-                    Ok(Err(_)) => Err(Error::InternalError),
-                    Err(error) => Err(error),
-                }
+                let resp = handler.call(state, parts.clone(), params, names).await?;
+                resp.into_call_result(parts).await
             })
         } else {
-            self.next.serve(state, name, params)
+            self.next.serve(state, parts, params)
         }
     }
+
+    fn describe(&self, methods: &mut Vec<MethodDescription>) {
+        let types = H::param_types();
+        let names = self.params.as_deref().unwrap_or_default();
+        methods.push(MethodDescription {
+            name: self.name.clone(),
+            doc: self.doc.clone(),
+            params: types
+                .into_iter()
+                .enumerate()
+                .map(|(i, r#type)| ParamDescription {
+                    name: names.get(i).cloned(),
+                    r#type: short_type_name(r#type),
+                })
+                .collect(),
+            result: short_type_name(std::any::type_name::<R>()),
+        });
+        self.next.describe(methods);
+    }
 }
 
 impl<S, S2, N> ServerPart<S2> for WithState<S, N>
@@ -411,11 +797,15 @@ where
     fn serve(
         &self,
         _state: S2,
-        name: String,
+        parts: RequestParts,
         params: DuperValue<'static>,
     ) -> Pin<Box<dyn Future<Output = Result<DuperValue<'static>>> + Send + 'static>> {
         let state = self.state.clone();
-        self.next.serve(state, name, params)
+        self.next.serve(state, parts, params)
+    }
+
+    fn describe(&self, methods: &mut Vec<MethodDescription>) {
+        self.next.describe(methods);
     }
 }
 
@@ -569,6 +959,321 @@ mod rpc_server_tests {
         );
     }
 
+    #[tokio::test]
+    async fn extractors() {
+        use crate::extract::{Context, Extension, Id, Method};
+
+        #[derive(Clone)]
+        struct Caller(&'static str);
+
+        async fn whoami(
+            Method(method): Method,
+            Id(id): Id,
+            State(state): State<String>,
+            Extension(caller): Extension<Caller>,
+            suffix: String,
+        ) -> Result<String> {
+            let Some(RequestId::Integer { inner, .. }) = id else {
+                return Err(Error::InternalError);
+            };
+            Ok(format!("{method}:{inner}:{state}:{}{suffix}", caller.0))
+        }
+
+        let mut context = Context::new();
+        context.insert(Caller("alice"));
+        let Ok(response) = Server::new()
+            .method("whoami", whoami)
+            .with_state("state".to_string())
+            .handle_with_context(
+                Request::Batch(vec![
+                    RequestCall::Valid {
+                        id: Some(RequestId::Integer {
+                            identifier: None,
+                            inner: 7,
+                        }),
+                        method: "whoami".into(),
+                        params: DuperValue::String {
+                            identifier: None,
+                            inner: "!".into(),
+                        },
+                    },
+                    RequestCall::Valid {
+                        id: Some(RequestId::Integer {
+                            identifier: None,
+                            inner: 8,
+                        }),
+                        method: "whoami".into(),
+                        params: DuperValue::String {
+                            identifier: None,
+                            inner: "?".into(),
+                        },
+                    },
+                ]),
+                context,
+            )
+            .await;
+
+        let Some(Response::Batch(responses)) = response else {
+            panic!("Invalid response {:?}", response);
+        };
+        let results: Vec<_> = responses
+            .into_iter()
+            .map(|result| match result {
+                Ok(ResponseSuccess {
+                    result: DuperValue::String { inner, .. },
+                    ..
+                }) => inner.into_owned(),
+                other => panic!("Invalid response result {:?}", other),
+            })
+            .collect();
+        assert_eq!(results, ["whoami:7:state:alice!", "whoami:8:state:alice?"]);
+
+        // Missing extensions result in an internal error.
+        let Ok(response) = Server::new()
+            .method("whoami", whoami)
+            .with_state("state".to_string())
+            .handle(Request::Single(RequestCall::Valid {
+                id: Some(RequestId::Integer {
+                    identifier: None,
+                    inner: 9,
+                }),
+                method: "whoami".into(),
+                params: DuperValue::String {
+                    identifier: None,
+                    inner: "!".into(),
+                },
+            }))
+            .await;
+        let Some(Response::Single(ResponseResult::Err(ResponseError { error, .. }))) = response
+        else {
+            panic!("Invalid response {:?}", response);
+        };
+        assert_eq!(error, Error::InternalError);
+    }
+
+    #[tokio::test]
+    async fn named_params() {
+        #[derive(serde::Serialize)]
+        struct ListParams {
+            user_id: u64,
+            limit: usize,
+        }
+
+        #[derive(serde::Serialize)]
+        struct PartialParams {
+            user_id: u64,
+            offset: usize,
+        }
+
+        #[derive(serde::Serialize)]
+        struct InvalidParams {
+            user_id: &'static str,
+            limit: usize,
+        }
+
+        async fn list(user_id: u64, limit: usize) -> Result<Vec<u64>> {
+            Ok(vec![user_id; limit])
+        }
+
+        let id = |inner| {
+            Some(RequestId::Integer {
+                identifier: None,
+                inner,
+            })
+        };
+        let request = Request::builder()
+            .request_named(
+                "list".into(),
+                id(1),
+                &ListParams {
+                    user_id: 7,
+                    limit: 2,
+                },
+            )
+            .request2("list".into(), id(2), &7, &1)
+            .request_named(
+                "list".into(),
+                id(3),
+                &PartialParams {
+                    user_id: 7,
+                    offset: 0,
+                },
+            )
+            .request_named(
+                "list".into(),
+                id(4),
+                &InvalidParams {
+                    user_id: "me",
+                    limit: 1,
+                },
+            )
+            .request2("list".into(), id(5), &7, &"all")
+            .build()
+            .expect("valid request");
+
+        let Ok(Some(Response::Batch(responses))) = Server::new()
+            .method_with_params("list", ["user_id", "limit"], list)
+            .handle(request)
+            .await
+        else {
+            panic!("Invalid response");
+        };
+        let results: Vec<_> = responses
+            .into_iter()
+            .map(|result| {
+                result
+                    .map(|success| success.result)
+                    .map_err(|err| err.error)
+            })
+            .collect();
+        let integers = |values: &[i64]| DuperValue::Array {
+            identifier: None,
+            inner: values
+                .iter()
+                .map(|&inner| DuperValue::Integer {
+                    identifier: None,
+                    inner,
+                })
+                .collect(),
+        };
+        assert_eq!(results[0], Ok(integers(&[7, 7])));
+        assert_eq!(results[1], Ok(integers(&[7])));
+        assert_eq!(
+            results[2],
+            Err(Error::invalid_params("missing parameter `limit`"))
+        );
+        let invalid_param = |result: &Result<DuperValue<'static>>| match result {
+            Err(Error::InvalidParams(Some(message))) => message.clone(),
+            other => panic!("Invalid result {other:?}"),
+        };
+        assert!(invalid_param(&results[3]).starts_with("invalid parameter `user_id`: "));
+        assert!(invalid_param(&results[4]).starts_with("invalid parameter `limit`: "));
+
+        let Ok(Some(Response::Single(ResponseResult::Err(ResponseError { error, .. })))) =
+            Server::new()
+                .method_with_params("list", ["user_id", "limit"], list)
+                .handle(
+                    Request::builder()
+                        .request_named(
+                            "list".into(),
+                            id(6),
+                            &DuperValue::Object {
+                                identifier: None,
+                                inner: DuperObject::try_from(vec![
+                                    (
+                                        DuperKey::from("user_id"),
+                                        DuperValue::Integer {
+                                            identifier: None,
+                                            inner: 1,
+                                        },
+                                    ),
+                                    (
+                                        DuperKey::from("limit"),
+                                        DuperValue::Integer {
+                                            identifier: None,
+                                            inner: 1,
+                                        },
+                                    ),
+                                    (
+                                        DuperKey::from("sort"),
+                                        DuperValue::Boolean {
+                                            identifier: None,
+                                            inner: true,
+                                        },
+                                    ),
+                                ])
+                                .expect("no duplicate keys"),
+                            },
+                        )
+                        .build()
+                        .expect("valid request"),
+                )
+                .await
+        else {
+            panic!("Invalid response");
+        };
+        assert_eq!(error, Error::invalid_params("unknown parameter `sort`"));
+    }
+
+    #[test]
+    #[should_panic(expected = "method `list` has 2 parameter(s), but 1 name(s) were given")]
+    fn named_params_arity() {
+        async fn list(user_id: u64, limit: usize) -> Result<Vec<u64>> {
+            Ok(vec![user_id; limit])
+        }
+
+        let _ = Server::new()
+            .method_with_params("list", ["user_id"], list)
+            .into_service();
+    }
+
+    #[test]
+    #[should_panic(expected = "method `list` has repeated parameter name `user_id`")]
+    fn named_params_repeated() {
+        async fn list(user_id: u64, limit: usize) -> Result<Vec<u64>> {
+            Ok(vec![user_id; limit])
+        }
+
+        let _ = Server::new()
+            .method_with_params("list", ["user_id", "user_id"], list)
+            .into_service();
+    }
+
+    #[tokio::test]
+    async fn discover() {
+        async fn list(State(_): State<()>, user_id: u64, limit: usize) -> Result<Vec<u64>> {
+            Ok(vec![user_id; limit])
+        }
+
+        let Ok(Some(Response::Single(ResponseResult::Ok(ResponseSuccess { result, .. })))) =
+            Server::new()
+                .method("ping", async || Ok(true))
+                .doc("Check if the server is alive.")
+                .method_with_params("list", ["user_id", "limit"], list)
+                .with_state(())
+                .handle(Request::Single(RequestCall::Valid {
+                    id: Some(RequestId::Integer {
+                        identifier: None,
+                        inner: 1,
+                    }),
+                    method: DISCOVER_METHOD.into(),
+                    params: DuperValue::Null { identifier: None },
+                }))
+                .await
+        else {
+            panic!("Invalid response");
+        };
+
+        assert_eq!(
+            ServiceDescription::try_from(result),
+            Ok(ServiceDescription {
+                methods: vec![
+                    MethodDescription {
+                        name: "ping".into(),
+                        doc: Some("Check if the server is alive.".into()),
+                        params: vec![],
+                        result: "bool".into(),
+                    },
+                    MethodDescription {
+                        name: "list".into(),
+                        doc: None,
+                        params: vec![
+                            ParamDescription {
+                                name: Some("user_id".into()),
+                                r#type: "u64".into(),
+                            },
+                            ParamDescription {
+                                name: Some("limit".into()),
+                                r#type: "usize".into(),
+                            },
+                        ],
+                        result: "Vec<u64>".into(),
+                    },
+                ],
+            })
+        );
+    }
+
     #[tokio::test]
     async fn notification() {
         async fn sleep_for_10_seconds() -> Result<()> {
@@ -721,7 +1426,10 @@ mod rpc_server_tests {
                 inner: "invalid_params".into(),
             })
         );
-        assert!(matches!(error, Error::InvalidParams));
+        let Error::InvalidParams(Some(message)) = error else {
+            panic!("Invalid error {:?}", error);
+        };
+        assert!(message.starts_with("invalid parameter 1: "));
 
         let ResponseResult::Err(ResponseError { id, error }) = responses.remove(0) else {
             panic!("Invalid response result");
@@ -0,0 +1,605 @@
+//! Streaming responses and request cancellation.
+//!
+//! A [`Session`] tracks the in-flight calls of a client connection, and
+//! optionally carries a channel for progress notifications. When a session is
+//! present in the [`Context`] of a request:
+//!
+//! - Calls with an ID can be cancelled with a [`CANCEL_METHOD`] notification,
+//!   whose parameter is the ID of the call. The handler future is dropped,
+//!   and the call fails with a custom `Cancelled` error.
+//! - Handlers returning [`Streaming`] send each item as a [`PROGRESS_METHOD`]
+//!   notification, with the call ID and the item as parameters, and resolve
+//!   to null once the stream ends.
+//!
+//! Without a progress channel (eg. over plain HTTP), streaming handlers
+//! resolve to an array of all items instead.
+//!
+//! [`serve_framed`] runs a server over any transport of framed messages,
+//! with a session set up for the connection.
+//!
+//! ```
+//! use duper_rpc::{server::Server, session::Streaming};
+//! use futures::stream;
+//!
+//! async fn count(to: u64) -> duper_rpc::Result<Streaming<impl futures::Stream<Item = duper_rpc::Result<u64>>>> {
+//!     Ok(Streaming(stream::iter((1..=to).map(Ok))))
+//! }
+//!
+//! # let _ =
+//! Server::new().method("count", count).into_service();
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use duper::{
+    DuperIdentifier, DuperValue, ParseLimits,
+    serde::{de::from_string_with_limits, ser::to_string},
+};
+use futures::{
+    FutureExt, Sink, SinkExt, Stream, StreamExt, TryStreamExt,
+    channel::mpsc::{Receiver, Sender, channel},
+    future::AbortHandle,
+    select_biased,
+    stream::FuturesUnordered,
+};
+use serde_core::Serialize;
+use tower::{Service, ServiceExt};
+
+use crate::{
+    Error, Request, RequestCall, RequestId, Response, ResponseError, ResponseResult, Result,
+    extract::{Context, RequestParts},
+    server::{Call, ServerService},
+};
+
+/// The method of progress notifications sent by the server.
+pub const PROGRESS_METHOD: &str = "$/progress";
+
+/// The method of cancellation notifications sent by the client.
+pub const CANCEL_METHOD: &str = "$/cancelRequest";
+
+/// The default number of progress notifications buffered by a session, before
+/// streaming handlers wait for them to be sent.
+pub const DEFAULT_PROGRESS_BUFFER: usize = 64;
+
+/// A hashable version of [`RequestId`], ignoring identifiers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum IdKey {
+    String(String),
+    Integer(i64),
+}
+
+impl From<&RequestId> for IdKey {
+    fn from(value: &RequestId) -> Self {
+        match value {
+            RequestId::String { inner, .. } => IdKey::String(inner.clone()),
+            RequestId::Integer { inner, .. } => IdKey::Integer(*inner),
+        }
+    }
+}
+
+pub(crate) fn request_id_to_value(id: &RequestId) -> DuperValue<'static> {
+    match id {
+        RequestId::String { identifier, inner } => DuperValue::String {
+            identifier: identifier.clone(),
+            inner: inner.clone().into(),
+        },
+        RequestId::Integer { identifier, inner } => DuperValue::Integer {
+            identifier: identifier.clone(),
+            inner: *inner,
+        },
+    }
+}
+
+#[derive(Default)]
+struct SessionInner {
+    in_flight: Mutex<HashMap<IdKey, (u64, AbortHandle)>>,
+    next_token: AtomicU64,
+    progress: Option<Sender<RequestCall>>,
+}
+
+/// The state of a client connection, shared by all of its requests.
+///
+/// Insert it into the [`Context`] of every request from the same connection.
+#[derive(Clone, Default)]
+pub struct Session {
+    inner: Arc<SessionInner>,
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session").finish_non_exhaustive()
+    }
+}
+
+impl Session {
+    /// Create a session without progress notifications.
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// Create a session with progress notifications, which must be forwarded
+    /// to the client from the returned receiver.
+    ///
+    /// Up to [`DEFAULT_PROGRESS_BUFFER`] notifications are buffered, after
+    /// which streaming handlers wait for the receiver to catch up.
+    pub fn with_progress() -> (Self, Receiver<RequestCall>) {
+        Session::with_progress_buffer(DEFAULT_PROGRESS_BUFFER)
+    }
+
+    /// Create a session with progress notifications, like
+    /// [`with_progress`](Session::with_progress), buffering up to `buffer`
+    /// notifications.
+    pub fn with_progress_buffer(buffer: usize) -> (Self, Receiver<RequestCall>) {
+        let (sender, receiver) = channel(buffer);
+        (
+            Session {
+                inner: Arc::new(SessionInner {
+                    progress: Some(sender),
+                    ..Default::default()
+                }),
+            },
+            receiver,
+        )
+    }
+
+    /// Cancel the in-flight call with the given ID, returning whether it
+    /// was found.
+    pub fn cancel(&self, id: &RequestId) -> bool {
+        let handle = self
+            .inner
+            .in_flight
+            .lock()
+            .expect("lock is not poisoned")
+            .remove(&IdKey::from(id));
+        match handle {
+            Some((_, handle)) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn register(&self, id: &RequestId, handle: AbortHandle) -> u64 {
+        let token = self.inner.next_token.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .in_flight
+            .lock()
+            .expect("lock is not poisoned")
+            .insert(IdKey::from(id), (token, handle));
+        token
+    }
+
+    pub(crate) fn unregister(&self, id: &RequestId, token: u64) {
+        let mut in_flight = self.inner.in_flight.lock().expect("lock is not poisoned");
+        let key = IdKey::from(id);
+        if in_flight.get(&key).is_some_and(|(t, _)| *t == token) {
+            in_flight.remove(&key);
+        }
+    }
+
+    async fn send_progress(&self, id: &RequestId, value: DuperValue<'static>) -> bool {
+        match &self.inner.progress {
+            Some(progress) => progress
+                .clone()
+                .send(RequestCall::Valid {
+                    id: None,
+                    method: PROGRESS_METHOD.into(),
+                    params: DuperValue::Tuple {
+                        identifier: None,
+                        inner: vec![request_id_to_value(id), value],
+                    },
+                })
+                .await
+                .is_ok(),
+            None => false,
+        }
+    }
+
+    fn has_progress(&self) -> bool {
+        self.inner.progress.is_some()
+    }
+}
+
+pub(crate) fn cancelled_error() -> Error {
+    Error::Custom(DuperValue::String {
+        identifier: Some(DuperIdentifier::try_from("Cancelled").expect("valid identifier")),
+        inner: "Call was cancelled".into(),
+    })
+}
+
+/// Handle a [`CANCEL_METHOD`] call.
+pub(crate) fn cancel_call(parts: &RequestParts, params: DuperValue<'static>) -> Result<()> {
+    let id = match params {
+        DuperValue::Tuple { mut inner, .. } | DuperValue::Array { mut inner, .. }
+            if inner.len() == 1 =>
+        {
+            inner.pop().expect("length checked")
+        }
+        other => other,
+    };
+    let id = RequestId::try_from(id).map_err(Error::invalid_params)?;
+    if let Some(session) = parts.context.get::<Session>() {
+        session.cancel(&id);
+    }
+    Ok(())
+}
+
+/// A handler result which streams its items as progress notifications.
+pub struct Streaming<St>(pub St);
+
+/// Return values of handlers, which are converted into the call result.
+pub trait IntoCallResult: Send + 'static {
+    fn into_call_result(
+        self,
+        parts: RequestParts,
+    ) -> std::pin::Pin<Box<dyn Future<Output = Result<DuperValue<'static>>> + Send + 'static>>;
+}
+
+impl<T> IntoCallResult for T
+where
+    T: Serialize + Send + 'static,
+{
+    fn into_call_result(
+        self,
+        _parts: RequestParts,
+    ) -> std::pin::Pin<Box<dyn Future<Output = Result<DuperValue<'static>>> + Send + 'static>> {
+        let result = self
+            .serialize(&mut duper::serde::ser::Serializer::new())
+            .map_err(|_| Error::InternalError);
+        Box::pin(async { result })
+    }
+}
+
+impl<St, I> IntoCallResult for Streaming<St>
+where
+    St: Stream<Item = Result<I>> + Send + 'static,
+    I: Serialize,
+{
+    fn into_call_result(
+        self,
+        parts: RequestParts,
+    ) -> std::pin::Pin<Box<dyn Future<Output = Result<DuperValue<'static>>> + Send + 'static>> {
+        let session = parts
+            .context
+            .get::<Session>()
+            .filter(|session| session.has_progress())
+            .cloned();
+        let items = self.0.map(|item| {
+            item.and_then(|item| {
+                item.serialize(&mut duper::serde::ser::Serializer::new())
+                    .map_err(|_| Error::InternalError)
+            })
+        });
+        match (session, parts.id) {
+            (Some(session), Some(id)) => Box::pin(async move {
+                let mut items = std::pin::pin!(items);
+                while let Some(item) = items.try_next().await? {
+                    session.send_progress(&id, item).await;
+                }
+                Ok(DuperValue::Null { identifier: None })
+            }),
+            _ => Box::pin(async move {
+                Ok(DuperValue::Array {
+                    identifier: None,
+                    inner: items.try_collect().await?,
+                })
+            }),
+        }
+    }
+}
+
+/// Serve Duper RPC over a transport of framed messages, such as lines of a
+/// byte stream or WebSocket text messages.
+///
+/// Each incoming message must contain a single request. Requests are handled
+/// concurrently, with a [`Session`] (including progress notifications) shared
+/// by all of them. Responses and progress notifications are sent to
+/// `outgoing` as single-line Duper values, as they become available.
+///
+/// Messages are checked against the default [`ParseLimits`], and answered
+/// with a parse error if they exceed them. Use [`serve_framed_with_limits`] to
+/// configure the limits.
+///
+/// Returns once `incoming` ends and all pending requests have been answered.
+pub async fn serve_framed<C, In, Out>(
+    service: ServerService<C>,
+    incoming: In,
+    outgoing: Out,
+) -> std::result::Result<(), Out::Error>
+where
+    C: Service<Call, Response = DuperValue<'static>, Error = Error> + Clone + Send + 'static,
+    C::Future: Send + 'static,
+    In: Stream<Item = String>,
+    Out: Sink<String> + Unpin,
+{
+    serve_framed_with_limits(service, ParseLimits::default(), incoming, outgoing).await
+}
+
+/// Serve Duper RPC over a transport of framed messages, like
+/// [`serve_framed`], checking each message against the given [`ParseLimits`].
+pub async fn serve_framed_with_limits<C, In, Out>(
+    service: ServerService<C>,
+    limits: ParseLimits,
+    incoming: In,
+    mut outgoing: Out,
+) -> std::result::Result<(), Out::Error>
+where
+    C: Service<Call, Response = DuperValue<'static>, Error = Error> + Clone + Send + 'static,
+    C::Future: Send + 'static,
+    In: Stream<Item = String>,
+    Out: Sink<String> + Unpin,
+{
+    let (session, mut progress) = Session::with_progress();
+    let mut context = Context::new();
+    context.insert(session);
+
+    let mut incoming = std::pin::pin!(incoming.fuse());
+    let mut pending = FuturesUnordered::new();
+    let mut incoming_done = false;
+
+    loop {
+        select_biased! {
+            notification = progress.select_next_some() => {
+                if let Ok(message) = to_string(&Request::Single(notification)) {
+                    outgoing.send(message).await?;
+                }
+            }
+            response = pending.select_next_some() => {
+                // Progress is sent before the handler returns, so flush it
+                // first to keep notifications ahead of the final response.
+                flush_progress(&mut progress, &mut outgoing).await?;
+                if let Ok(Some(response)) = response {
+                    outgoing.send(serialize_response(response)).await?;
+                }
+            }
+            message = incoming.next() => match message {
+                Some(message) => match from_string_with_limits::<Request>(&message, &limits) {
+                    Ok(request) => {
+                        pending.push(
+                            service
+                                .clone()
+                                .oneshot((request, context.clone()))
+                                .boxed(),
+                        );
+                    }
+                    Err(_) => {
+                        outgoing
+                            .send(serialize_response(Response::Single(ResponseResult::Err(
+                                ResponseError {
+                                    id: None,
+                                    error: Error::ParseError,
+                                },
+                            ))))
+                            .await?;
+                    }
+                },
+                None => incoming_done = true,
+            },
+            complete => break,
+        }
+        if incoming_done && pending.is_empty() {
+            break;
+        }
+    }
+
+    flush_progress(&mut progress, &mut outgoing).await?;
+    outgoing.close().await
+}
+
+async fn flush_progress<Out>(
+    progress: &mut Receiver<RequestCall>,
+    outgoing: &mut Out,
+) -> std::result::Result<(), Out::Error>
+where
+    Out: Sink<String> + Unpin,
+{
+    while let Ok(Some(notification)) = progress.try_next() {
+        if let Ok(message) = to_string(&Request::Single(notification)) {
+            outgoing.send(message).await?;
+        }
+    }
+    Ok(())
+}
+
+fn serialize_response(response: Response) -> String {
+    to_string(&response).unwrap_or_else(|_| {
+        to_string(&Response::Single(ResponseResult::Err(ResponseError {
+            id: None,
+            error: Error::InternalError,
+        })))
+        .expect("error response is serializable")
+    })
+}
+
+#[cfg(test)]
+mod session_tests {
+    use std::time::Duration;
+
+    use duper::serde::de::from_string;
+    use futures::{StreamExt, channel::mpsc::unbounded, stream};
+
+    use crate::{ResponseSuccess, server::Server};
+
+    use super::*;
+
+    fn id(inner: i64) -> Option<RequestId> {
+        Some(RequestId::Integer {
+            identifier: None,
+            inner,
+        })
+    }
+
+    fn call(id: Option<RequestId>, method: &str, params: DuperValue<'static>) -> Request {
+        Request::Single(RequestCall::Valid {
+            id,
+            method: method.into(),
+            params,
+        })
+    }
+
+    fn integer(inner: i64) -> DuperValue<'static> {
+        DuperValue::Integer {
+            identifier: None,
+            inner,
+        }
+    }
+
+    async fn count(to: u64) -> Result<Streaming<impl Stream<Item = Result<u64>> + Send>> {
+        Ok(Streaming(stream::iter((1..=to).map(Ok))))
+    }
+
+    async fn forever() -> Result<()> {
+        futures::future::pending().await
+    }
+
+    #[tokio::test]
+    async fn streaming_without_session() {
+        let Ok(Some(Response::Single(ResponseResult::Ok(ResponseSuccess { result, .. })))) =
+            Server::new()
+                .method("count", count)
+                .handle(call(id(1), "count", integer(3)))
+                .await
+        else {
+            panic!("Invalid response");
+        };
+        let DuperValue::Array { inner, .. } = result else {
+            panic!("Invalid result {:?}", result);
+        };
+        assert_eq!(inner.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn progress_backpressure() {
+        let (session, mut progress) = Session::with_progress_buffer(1);
+        let id = RequestId::Integer {
+            identifier: None,
+            inner: 1,
+        };
+        let sender = tokio::spawn(async move {
+            for i in 0..10 {
+                assert!(session.send_progress(&id, integer(i)).await);
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!sender.is_finished());
+
+        for i in 0..10 {
+            let Some(RequestCall::Valid { params, .. }) = progress.next().await else {
+                panic!("Invalid notification");
+            };
+            let DuperValue::Tuple { inner, .. } = params else {
+                panic!("Invalid params {:?}", params);
+            };
+            assert_eq!(inner[1], integer(i));
+        }
+        sender.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn progress_and_cancellation() {
+        let service = Server::new()
+            .method("count", count)
+            .method("forever", forever)
+            .into_service();
+        let (incoming_tx, incoming_rx) = unbounded::<String>();
+        let (outgoing_tx, mut outgoing_rx) = unbounded::<String>();
+        let server = tokio::spawn(serve_framed(service, incoming_rx, outgoing_tx));
+
+        let send = |request: Request| {
+            incoming_tx
+                .unbounded_send(to_string(&request).expect("serializable request"))
+                .expect("server is running");
+        };
+        send(call(id(1), "count", integer(2)));
+        for expected in 1..=2 {
+            let message = outgoing_rx.next().await.expect("progress notification");
+            assert_eq!(
+                message,
+                format!(
+                    r#"RpcRequest({{duper_rpc: "0.1", method: "$/progress", params: (1, {expected})}})"#
+                )
+            );
+        }
+        let message = outgoing_rx.next().await.expect("final response");
+        assert!(matches!(
+            from_string::<Response>(&message),
+            Ok(Response::Single(ResponseResult::Ok(ResponseSuccess {
+                result: DuperValue::Null { .. },
+                ..
+            })))
+        ));
+
+        send(call(
+            id(2),
+            "forever",
+            DuperValue::Null { identifier: None },
+        ));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        send(call(None, CANCEL_METHOD, integer(2)));
+        let message = outgoing_rx.next().await.expect("cancelled response");
+        let Ok(Response::Single(ResponseResult::Err(ResponseError {
+            id: error_id,
+            error,
+        }))) = from_string::<Response>(&message)
+        else {
+            panic!("Invalid response {message}");
+        };
+        assert_eq!(error_id, id(2));
+        assert_eq!(error, cancelled_error());
+
+        incoming_tx.unbounded_send("not a request".into()).unwrap();
+        let message = outgoing_rx.next().await.expect("parse error");
+        assert!(matches!(
+            from_string::<Response>(&message),
+            Ok(Response::Single(ResponseResult::Err(ResponseError {
+                error: Error::ParseError,
+                ..
+            })))
+        ));
+
+        drop(incoming_tx);
+        server.await.unwrap().unwrap();
+        assert!(outgoing_rx.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn parse_limits() {
+        let service = Server::new().method("count", count).into_service();
+        let limits = ParseLimits {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let (incoming_tx, incoming_rx) = unbounded::<String>();
+        let (outgoing_tx, mut outgoing_rx) = unbounded::<String>();
+        let server = tokio::spawn(serve_framed_with_limits(
+            service,
+            limits,
+            incoming_rx,
+            outgoing_tx,
+        ));
+
+        incoming_tx
+            .unbounded_send(
+                r#"RpcRequest({duper_rpc: "0.1", id: 1, method: "count", params: [[[1]]]})"#.into(),
+            )
+            .unwrap();
+        let message = outgoing_rx.next().await.expect("parse error");
+        assert!(matches!(
+            from_string::<Response>(&message),
+            Ok(Response::Single(ResponseResult::Err(ResponseError {
+                id: None,
+                error: Error::ParseError,
+            })))
+        ));
+
+        drop(incoming_tx);
+        server.await.unwrap().unwrap();
+    }
+}
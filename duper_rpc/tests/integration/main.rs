@@ -1 +1,2 @@
 mod axum_handle;
+mod stubs;
@@ -0,0 +1,139 @@
+use duper::DuperValue;
+use duper_rpc::{
+    Request, RequestCall, RequestId,
+    discover::{MethodDescription, ParamDescription, ServiceDescription, to_rust_stubs},
+};
+
+#[allow(dead_code)]
+mod generated {
+    include!("stubs/sample.rs");
+}
+
+fn param(name: Option<&str>, r#type: &str) -> ParamDescription {
+    ParamDescription {
+        name: name.map(Into::into),
+        r#type: r#type.into(),
+    }
+}
+
+fn sample() -> ServiceDescription {
+    ServiceDescription {
+        methods: vec![
+            MethodDescription {
+                name: "ping".into(),
+                doc: Some("Check if the server is alive.".into()),
+                params: vec![],
+                result: "bool".into(),
+            },
+            MethodDescription {
+                name: "posts.list".into(),
+                doc: Some("List the posts of a user.\nMost recent first.".into()),
+                params: vec![param(Some("user_id"), "u64"), param(Some("limit"), "usize")],
+                result: "Vec<Post>".into(),
+            },
+            MethodDescription {
+                name: "add".into(),
+                doc: None,
+                params: vec![param(None, "i64"), param(None, "i64")],
+                result: "i64".into(),
+            },
+            MethodDescription {
+                name: "match".into(),
+                doc: None,
+                params: vec![
+                    param(Some("type"), "String"),
+                    param(Some("fn"), "String"),
+                    param(Some("max-results"), "usize"),
+                ],
+                result: "Vec<String>".into(),
+            },
+            MethodDescription {
+                name: "posts.get".into(),
+                doc: None,
+                params: vec![param(Some("id"), "u64")],
+                result: "Post".into(),
+            },
+            MethodDescription {
+                name: "posts_get".into(),
+                doc: None,
+                params: vec![
+                    param(Some("max-results"), "usize"),
+                    param(Some("max_results"), "usize"),
+                ],
+                result: "Vec<Post>".into(),
+            },
+            MethodDescription {
+                name: "sum".into(),
+                doc: None,
+                params: (0..9).map(|_| param(None, "i64")).collect(),
+                result: "i64".into(),
+            },
+            MethodDescription {
+                name: "rpc.discover".into(),
+                doc: None,
+                params: vec![],
+                result: "RpcService".into(),
+            },
+        ],
+    }
+}
+
+#[test]
+fn stubs_snapshot() {
+    assert_eq!(to_rust_stubs(&sample()), include_str!("stubs/sample.rs"));
+}
+
+#[test]
+fn stubs_build_requests() {
+    let Request::Single(RequestCall::Valid { method, params, .. }) = generated::r#match(
+        Some(RequestId::Integer {
+            identifier: None,
+            inner: 1,
+        }),
+        &"post",
+        &"len",
+        &10,
+    )
+    .build()
+    .expect("valid request") else {
+        panic!("Invalid request");
+    };
+    assert_eq!(method, "match");
+    let DuperValue::Object { inner, .. } = params else {
+        panic!("Expected named params");
+    };
+    let keys: Vec<_> = inner.iter().map(|(key, _)| key.as_ref()).collect();
+    assert_eq!(keys, ["type", "fn", "max-results"]);
+
+    let Request::Single(RequestCall::Valid { method, params, .. }) =
+        generated::add(None, &1, &2).build().expect("valid request")
+    else {
+        panic!("Invalid request");
+    };
+    assert_eq!(method, "add");
+    assert!(matches!(params, DuperValue::Tuple { inner, .. } if inner.len() == 2));
+
+    let Request::Single(RequestCall::Valid { method, params, .. }) =
+        generated::posts_get_2(None, &1, &2)
+            .build()
+            .expect("valid request")
+    else {
+        panic!("Invalid request");
+    };
+    assert_eq!(method, "posts_get");
+    let DuperValue::Object { inner, .. } = params else {
+        panic!("Expected named params");
+    };
+    let keys: Vec<_> = inner.iter().map(|(key, _)| key.as_ref()).collect();
+    assert_eq!(keys, ["max-results", "max_results"]);
+
+    let Request::Single(RequestCall::Valid { method, params, .. }) =
+        generated::sum(None, &1, &2, &3, &4, &5, &6, &7, &8, &9)
+            .build()
+            .expect("valid request")
+    else {
+        panic!("Invalid request");
+    };
+    assert_eq!(method, "sum");
+    assert!(matches!(params, DuperValue::Tuple { inner, .. } if inner.len() == 9));
+}
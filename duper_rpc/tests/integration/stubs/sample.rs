@@ -0,0 +1,102 @@
+// Generated from `rpc.discover`.
+
+use duper_rpc::{RequestBuilder, RequestId, request::RequestBuilderSingle};
+
+/// Check if the server is alive.
+///
+/// Returns `bool`.
+pub fn ping(
+    id: Option<RequestId>,
+) -> RequestBuilderSingle {
+    RequestBuilder::new().request0("ping".into(), id)
+}
+
+/// List the posts of a user.
+/// Most recent first.
+///
+/// Returns `Vec<Post>`.
+pub fn posts_list(
+    id: Option<RequestId>,
+    user_id: &impl serde::Serialize, // u64
+    limit: &impl serde::Serialize, // usize
+) -> RequestBuilderSingle {
+    #[derive(serde::Serialize)]
+    struct Params<'a, P0: serde::Serialize, P1: serde::Serialize> {
+        user_id: &'a P0,
+        limit: &'a P1,
+    }
+    RequestBuilder::new().request_named("posts.list".into(), id, &Params { user_id, limit })
+}
+
+/// Returns `i64`.
+pub fn add(
+    id: Option<RequestId>,
+    p1: &impl serde::Serialize, // i64
+    p2: &impl serde::Serialize, // i64
+) -> RequestBuilderSingle {
+    RequestBuilder::new().request2("add".into(), id, p1, p2)
+}
+
+/// Returns `Vec<String>`.
+pub fn r#match(
+    id: Option<RequestId>,
+    r#type: &impl serde::Serialize, // String
+    r#fn: &impl serde::Serialize, // String
+    max_results: &impl serde::Serialize, // usize
+) -> RequestBuilderSingle {
+    #[derive(serde::Serialize)]
+    struct Params<'a, P0: serde::Serialize, P1: serde::Serialize, P2: serde::Serialize> {
+        r#type: &'a P0,
+        r#fn: &'a P1,
+        #[serde(rename = "max-results")]
+        max_results: &'a P2,
+    }
+    RequestBuilder::new().request_named("match".into(), id, &Params { r#type, r#fn, max_results })
+}
+
+/// Returns `Post`.
+pub fn posts_get(
+    id: Option<RequestId>,
+    id_2: &impl serde::Serialize, // u64
+) -> RequestBuilderSingle {
+    #[derive(serde::Serialize)]
+    struct Params<'a, P0: serde::Serialize> {
+        #[serde(rename = "id")]
+        id_2: &'a P0,
+    }
+    RequestBuilder::new().request_named("posts.get".into(), id, &Params { id_2 })
+}
+
+/// Returns `Vec<Post>`.
+pub fn posts_get_2(
+    id: Option<RequestId>,
+    max_results: &impl serde::Serialize, // usize
+    max_results_2: &impl serde::Serialize, // usize
+) -> RequestBuilderSingle {
+    #[derive(serde::Serialize)]
+    struct Params<'a, P0: serde::Serialize, P1: serde::Serialize> {
+        #[serde(rename = "max-results")]
+        max_results: &'a P0,
+        #[serde(rename = "max_results")]
+        max_results_2: &'a P1,
+    }
+    RequestBuilder::new().request_named("posts_get".into(), id, &Params { max_results, max_results_2 })
+}
+
+/// Returns `i64`.
+#[allow(clippy::too_many_arguments)]
+pub fn sum(
+    id: Option<RequestId>,
+    p1: &impl serde::Serialize, // i64
+    p2: &impl serde::Serialize, // i64
+    p3: &impl serde::Serialize, // i64
+    p4: &impl serde::Serialize, // i64
+    p5: &impl serde::Serialize, // i64
+    p6: &impl serde::Serialize, // i64
+    p7: &impl serde::Serialize, // i64
+    p8: &impl serde::Serialize, // i64
+    p9: &impl serde::Serialize, // i64
+) -> RequestBuilderSingle {
+    // Too many parameters for `request8`, so they're sent as a tuple
+    RequestBuilder::new().request_named("sum".into(), id, &(p1, p2, p3, p4, p5, p6, p7, p8, p9))
+}
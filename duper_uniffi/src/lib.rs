@@ -84,6 +84,44 @@ pub fn parse(input: &str, parse_any: bool) -> Result<DuperValue, DuperError> {
     Ok(value.accept(&mut UniffiVisitor))
 }
 
+pub struct ParseLimits {
+    max_depth: Option<u64>,
+    max_string_length: Option<u64>,
+    max_elements: Option<u64>,
+    max_document_size: Option<u64>,
+}
+
+impl From<ParseLimits> for duper::ParseLimits {
+    fn from(limits: ParseLimits) -> Self {
+        let limit =
+            |limit: Option<u64>| limit.map(|limit| usize::try_from(limit).unwrap_or(usize::MAX));
+        duper::ParseLimits::with_defaults(
+            limit(limits.max_depth),
+            limit(limits.max_string_length),
+            limit(limits.max_elements),
+            limit(limits.max_document_size),
+        )
+    }
+}
+
+pub fn parse_with_limits(
+    input: &str,
+    parse_any: bool,
+    limits: ParseLimits,
+) -> Result<DuperValue, DuperError> {
+    let limits = limits.into();
+    let value = match parse_any {
+        true => DuperParser::parse_duper_value_with_limits(input, &limits),
+        false => DuperParser::parse_duper_trunk_with_limits(input, &limits),
+    }
+    .map_err(|err| {
+        DuperError::Parse(
+            DuperParser::prettify_error(input, &err, None).unwrap_or_else(|_| format!("{err:?}")),
+        )
+    })?;
+    Ok(value.accept(&mut UniffiVisitor))
+}
+
 pub struct SerializeOptions {
     indent: Option<String>,
     strip_identifiers: bool,
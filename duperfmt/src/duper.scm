@@ -42,13 +42,10 @@
   "}"
 )
 
-; Add last comma if multi-line object
+; Remove space left behind by the last comma if single line object
 (object
-  (object_entry) @append_delimiter
-  .
-  "}"
-  (#delimiter! ",")
-  (#multi_line_only!)
+  "}" @prepend_antispace
+  (#single_line_only!)
 )
 
 ; Add a newline between object entries
@@ -75,15 +72,6 @@
   "]"
 )
 
-; Add last comma if multi-line array
-(array
-  (duper_value) @append_delimiter
-  .
-  "]"
-  (#delimiter! ",")
-  (#multi_line_only!)
-)
-
 ; Add a newline between array values
 (array
   "," @prepend_antispace @append_spaced_softline
@@ -102,15 +90,6 @@
   ")"
 )
 
-; Add last comma if multi-line tuple
-(tuple
-  (duper_value) @append_delimiter
-  .
-  ")"
-  (#delimiter! ",")
-  (#multi_line_only!)
-)
-
 ; Add a newline between tuple values
 (tuple
   "," @prepend_antispace @append_spaced_softline
@@ -3,31 +3,80 @@
 #![doc = include_str!("./duperfmt.md")]
 //!
 
-use std::io::Write;
+use std::{borrow::Cow, io::Write};
 
 use topiary_core::{Language, Operation, TopiaryQuery, formatter_tree};
 use tree_sitter::Tree;
 
+mod options;
+mod rewrite;
+
+pub use options::{
+    CONFIG_FILE_NAME, ConfigError, FormatOptions, IntegerStyle, KeyQuotes, StringStyle,
+    TrailingCommas,
+};
+
+use crate::rewrite::Rewriter;
+
 const DUPER_QUERY: &str = include_str!("./duper.scm");
+const TRAILING_COMMAS_QUERY: &str = include_str!("./trailing_commas.scm");
+const TRAILING_COMMAS_ALWAYS_QUERY: &str = include_str!("./trailing_commas_always.scm");
 
 /// Given a Duper [`Tree`] built from an input, formats said input into the output buffer.
 pub fn format_duper(
     tree: Tree,
     input: &str,
-    mut output: impl Write,
+    output: impl Write,
     indent: Option<String>,
     debug: bool,
 ) -> Result<(), topiary_core::FormatterError> {
+    let options = FormatOptions {
+        indent,
+        ..Default::default()
+    };
+    format_duper_with_options(tree, input, output, &options, debug)
+}
+
+/// Given a Duper [`Tree`] built from an input, formats said input into the
+/// output buffer according to the [`FormatOptions`].
+pub fn format_duper_with_options(
+    tree: Tree,
+    input: &str,
+    mut output: impl Write,
+    options: &FormatOptions,
+    debug: bool,
+) -> Result<(), topiary_core::FormatterError> {
+    let query = match options.trailing_commas {
+        TrailingCommas::Multiline => Cow::Owned(format!("{DUPER_QUERY}\n{TRAILING_COMMAS_QUERY}")),
+        TrailingCommas::Always => {
+            Cow::Owned(format!("{DUPER_QUERY}\n{TRAILING_COMMAS_ALWAYS_QUERY}"))
+        }
+        TrailingCommas::Never => Cow::Borrowed(DUPER_QUERY),
+    };
     let language = Language {
         name: "duper".to_owned(),
-        query: TopiaryQuery::new(&tree_sitter_duper::LANGUAGE.into(), DUPER_QUERY)?,
+        query: TopiaryQuery::new(&tree_sitter_duper::LANGUAGE.into(), &query)?,
         grammar: tree_sitter_duper::LANGUAGE.into(),
-        indent,
+        indent: options.indent.clone(),
+    };
+
+    let (tree, input) = if Rewriter::is_needed(options) && !tree.root_node().has_error() {
+        let rewritten = Rewriter::new(input, options).rewrite(tree.root_node(), 0, 0, 0);
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .expect("Error loading Duper parser");
+        let tree = parser
+            .parse(&rewritten, None)
+            .expect("parser was initialized");
+        (tree, Cow::Owned(rewritten))
+    } else {
+        (tree, Cow::Borrowed(input))
     };
 
     formatter_tree(
         tree.into(),
-        input,
+        &input,
         &mut output,
         &language,
         Operation::Format {
@@ -41,7 +90,10 @@ pub fn format_duper(
 mod format_duper_tests {
     use insta::assert_snapshot;
 
-    use super::{Tree, format_duper};
+    use super::{
+        FormatOptions, IntegerStyle, KeyQuotes, StringStyle, TrailingCommas, Tree, format_duper,
+        format_duper_with_options,
+    };
 
     fn parse_duper(input: &'static str) -> Tree {
         let mut parser = tree_sitter::Parser::new();
@@ -150,4 +202,131 @@ mod format_duper_tests {
         let mut output = Vec::new();
         assert!(format_duper(tree, input, &mut output, None, true).is_err());
     }
+
+    #[test]
+    fn single_line_object_trailing_comma() {
+        let input = r#"{a: 1, b: {c: 2,},}"#;
+        let tree = parse_duper(input);
+        let mut output = Vec::new();
+        format_duper(tree, input, &mut output, None, true).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "{a: 1, b: {c: 2}}\n");
+    }
+
+    #[test]
+    fn max_width() {
+        let input = r#"
+            {
+                short: {
+                    a: 1,
+                },
+                long: ["a long string", "another long string", "yet another long string"],
+                // Comments are kept
+                nested: Wrapper({x: [1, 2, 3], y: "a string that doesn't fit on the same line"}),
+                commented: [1, /* two */ 2],
+            }
+        "#;
+        let tree = parse_duper(input);
+        let mut output = Vec::new();
+        let options = FormatOptions {
+            max_width: Some(60),
+            ..Default::default()
+        };
+        format_duper_with_options(tree, input, &mut output, &options, true).unwrap();
+        assert_snapshot!(String::from_utf8(output).unwrap());
+    }
+
+    #[test]
+    fn normalized_literals() {
+        let input = r##"
+            {
+                zeta: r"C:\path",
+                "alpha": 0xBE_EF,
+                "needs quotes": +1_000,
+                // Moved along with its entry
+                beta: r#"say "hi""#,
+                gamma: [0b1010, 0o17, "tab\there"],
+            }
+        "##;
+        let tree = parse_duper(input);
+        let mut output = Vec::new();
+        let options = FormatOptions {
+            key_quotes: KeyQuotes::Minimal,
+            sort_keys: true,
+            strings: StringStyle::Quoted,
+            integers: IntegerStyle::Decimal,
+            ..Default::default()
+        };
+        format_duper_with_options(tree, input, &mut output, &options, true).unwrap();
+        assert_snapshot!(String::from_utf8(output).unwrap());
+    }
+
+    #[test]
+    fn trailing_commas() {
+        let input = r#"{a: [1, 2], b: (3,), c: {
+            d: 4
+        }}"#;
+        let mut outputs = vec![];
+        for trailing_commas in [
+            TrailingCommas::Multiline,
+            TrailingCommas::Always,
+            TrailingCommas::Never,
+        ] {
+            let tree = parse_duper(input);
+            let mut output = Vec::new();
+            let options = FormatOptions {
+                trailing_commas,
+                ..Default::default()
+            };
+            format_duper_with_options(tree, input, &mut output, &options, true).unwrap();
+            outputs.push(String::from_utf8(output).unwrap());
+        }
+        assert_snapshot!(outputs.join("\n"));
+    }
+
+    #[test]
+    fn blank_lines_between_groups() {
+        let input = "{\n  // Sizes\n  width: 1_920,\n  height: 1_080, \n\n  // Colors\n  background: \"black\",\n\n  /* Flags */\n  visible: true,\n}";
+        let format = |options: &FormatOptions| {
+            let tree = parse_duper(input);
+            let mut output = Vec::new();
+            format_duper_with_options(tree, input, &mut output, options, true).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        let expected = format(&FormatOptions::default())
+            .replace("1_920", "1920")
+            .replace("1_080", "1080");
+        assert!(expected.contains("\n\n  // Colors") && expected.contains("\n\n  /* Flags */"));
+        assert_eq!(
+            format(&FormatOptions {
+                integers: IntegerStyle::Normalized,
+                ..Default::default()
+            }),
+            expected
+        );
+        let sorted = format(&FormatOptions {
+            sort_keys: true,
+            ..Default::default()
+        });
+        assert!(sorted.contains("\n\n  // Colors") && sorted.contains("\n\n  /* Flags */"));
+    }
+
+    #[test]
+    fn config_file() {
+        let options = FormatOptions::from_config(
+            r#"{indent: "\t", max_width: 80, key_quotes: "always", integers: "normalized"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            options,
+            FormatOptions {
+                indent: Some("\t".into()),
+                max_width: Some(80),
+                key_quotes: KeyQuotes::Always,
+                integers: IntegerStyle::Normalized,
+                ..Default::default()
+            }
+        );
+        assert!(FormatOptions::from_config("{max_width: -1}").is_err());
+        assert!(FormatOptions::from_config("{unknown: true}").is_err());
+    }
 }
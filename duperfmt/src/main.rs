@@ -1,22 +1,36 @@
 use std::{
     borrow::Cow,
+    collections::BTreeSet,
     env,
     fmt::Display,
     fs,
     io::{self, Read, Write},
-    path::PathBuf,
+    path::{Component, Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
     time,
 };
 
 use clap::{CommandFactory, Parser};
-use duperfmt::format_duper;
-use miette::{Diagnostic, IntoDiagnostic, LabeledSpan, NamedSource, miette};
+use duperfmt::{
+    FormatOptions, IntegerStyle, KeyQuotes, StringStyle, TrailingCommas, format_duper_with_options,
+};
+use globset::GlobBuilder;
+use ignore::{WalkBuilder, gitignore::GitignoreBuilder};
+use miette::{Diagnostic, IntoDiagnostic, LabeledSpan, NamedSource, WrapErr, miette};
+use rayon::prelude::*;
+use similar::TextDiff;
 use thiserror::Error;
 use tree_sitter::StreamingIterator;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
+    /// Files, directories, or glob patterns to format. Directories are
+    /// searched for `.duper` files, respecting `.gitignore` files.
+    #[arg(value_name = "PATH", conflicts_with = "file")]
+    paths: Vec<PathBuf>,
+
     /// Indentation for pretty-printing.
     #[arg(short = 'I', long, value_name = "INDENT")]
     indent: Option<String>,
@@ -33,6 +47,53 @@ struct Cli {
     #[arg(short, long)]
     in_place: bool,
 
+    /// Check whether the input is formatted, printing a diff and exiting with
+    /// a non-zero code if it isn't.
+    #[arg(short, long, conflicts_with_all = ["output", "in_place"])]
+    check: bool,
+
+    /// Gitignore-style pattern of paths to skip when searching directories
+    /// and glob patterns. Can be specified multiple times.
+    #[arg(short, long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// The number of files to format in parallel, or the number of CPUs if
+    /// unspecified.
+    #[arg(short, long, value_name = "JOBS")]
+    jobs: Option<usize>,
+
+    /// The configuration file to use. Defaults to the closest `.duperfmt.duper`
+    /// file in the directory of each formatted file (or the current directory
+    /// for stdin) and its ancestors.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Maximum line width. Objects, arrays, and tuples are collapsed onto a
+    /// single line if they fit, and split into one entry per line otherwise.
+    #[arg(short = 'w', long, value_name = "WIDTH")]
+    max_width: Option<usize>,
+
+    /// When to add a comma after the last entry of objects, arrays, and
+    /// tuples.
+    #[arg(long, value_name = "POLICY")]
+    trailing_commas: Option<TrailingCommas>,
+
+    /// How object keys are quoted.
+    #[arg(long, value_name = "STYLE")]
+    key_quotes: Option<KeyQuotes>,
+
+    /// Sort object entries by key.
+    #[arg(long)]
+    sort_keys: bool,
+
+    /// Whether strings are written as quoted or raw strings.
+    #[arg(long, value_name = "STYLE")]
+    strings: Option<StringStyle>,
+
+    /// How integer literals are written.
+    #[arg(long, value_name = "STYLE")]
+    integers: Option<IntegerStyle>,
+
     /// Run in debug mode (i.e. check for formatting idempotency).
     #[arg(short, long)]
     debug: bool,
@@ -52,36 +113,109 @@ impl Display for TreeSitterReport {
     }
 }
 
+/// The result of formatting a single file.
+enum Outcome {
+    Unchanged,
+    /// The file was (or, in check mode, would be) reformatted.
+    Changed(Option<String>),
+    Failed(miette::Report),
+}
+
 fn to_labeled_span(range: tree_sitter::Range, label: Option<String>) -> LabeledSpan {
     LabeledSpan::new(label, range.start_byte, range.end_byte - range.start_byte)
 }
 
-fn main() -> miette::Result<()> {
-    let cli = Cli::parse();
+fn conflict(message: &str) -> ! {
+    let mut cmd = Cli::command();
+    cmd.error(clap::error::ErrorKind::ArgumentConflict, message)
+        .exit()
+}
+
+fn main() -> miette::Result<ExitCode> {
+    run(Cli::parse())
+}
 
+fn run(cli: Cli) -> miette::Result<ExitCode> {
     // Validate CLI options
-    let mut output = cli.output.as_ref();
-    if cli.in_place {
-        if cli.file.is_none() {
-            let mut cmd = Cli::command();
-            cmd.error(
-                clap::error::ErrorKind::ArgumentConflict,
-                "File to edit in-place is required",
-            )
-            .exit();
-        } else if output.is_some() {
-            let mut cmd = Cli::command();
-            cmd.error(
-                clap::error::ErrorKind::ArgumentConflict,
-                "Cannot specify output file with in-place option",
-            )
-            .exit();
+    if cli.in_place && cli.output.is_some() {
+        conflict("Cannot specify output file with in-place option");
+    }
+    if cli.paths.is_empty() {
+        if cli.in_place && cli.file.is_none() {
+            conflict("File to edit in-place is required");
+        }
+        return format_single(&cli, cli.file.as_deref());
+    }
+
+    let files = collect_files(&cli.paths, &cli.exclude)?;
+    if !cli.check && !cli.in_place {
+        match files.as_slice() {
+            [file] => return format_single(&cli, Some(file)),
+            _ => conflict("Formatting multiple files requires the in-place or check option"),
         }
-        output = cli.file.as_ref();
     }
 
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.jobs.unwrap_or(0))
+        .build()
+        .into_diagnostic()?;
+    let outcomes: Vec<_> = pool.install(|| {
+        files
+            .par_iter()
+            .map(|file| match format_file(&cli, file) {
+                Ok(outcome) => outcome,
+                Err(err) => Outcome::Failed(err),
+            })
+            .collect()
+    });
+
+    // Report in a stable order, regardless of which files finished first
+    let mut changed = 0;
+    let mut failed = 0;
+    let mut stdout = io::stdout().lock();
+    for outcome in outcomes {
+        match outcome {
+            Outcome::Unchanged => (),
+            Outcome::Changed(diff) => {
+                changed += 1;
+                if let Some(diff) = diff {
+                    stdout.write_all(diff.as_bytes()).into_diagnostic()?;
+                }
+            }
+            Outcome::Failed(err) => {
+                failed += 1;
+                eprintln!("{err:?}");
+            }
+        }
+    }
+    stdout.flush().into_diagnostic()?;
+
+    let total = files.len();
+    let unchanged = total - changed - failed;
+    if cli.check {
+        eprintln!(
+            "Checked {}: {changed} would be reformatted, {unchanged} already formatted, {failed} failed.",
+            plural(total, "file"),
+        );
+    } else {
+        eprintln!(
+            "Formatted {}: {changed} reformatted, {unchanged} unchanged, {failed} failed.",
+            plural(total, "file"),
+        );
+    }
+
+    if failed > 0 || (cli.check && changed > 0) {
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Formats a single file or stdin, writing the result to the output file,
+/// the input file if editing in-place, or stdout.
+fn format_single(cli: &Cli, file: Option<&Path>) -> miette::Result<ExitCode> {
     // Read Duper
-    let (src, input) = if let Some(file) = cli.file.as_ref() {
+    let (src, input) = if let Some(file) = file {
         (
             file.to_string_lossy(),
             fs::read_to_string(file).into_diagnostic()?,
@@ -92,6 +226,100 @@ fn main() -> miette::Result<()> {
         (Cow::Borrowed("<stdin>"), buf)
     };
 
+    let options = options(cli, file)?;
+    let formatted = format_source(&src, input.clone(), &options, cli.debug)?;
+
+    if cli.check {
+        if formatted == input {
+            return Ok(ExitCode::SUCCESS);
+        }
+        io::stdout()
+            .write_all(diff(&src, &input, &formatted).as_bytes())
+            .into_diagnostic()?;
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let output = if cli.in_place {
+        file
+    } else {
+        cli.output.as_deref()
+    };
+    if let Some(output) = output {
+        write_file(output, formatted.as_bytes())?;
+    } else {
+        io::stdout()
+            .write_all(formatted.as_bytes())
+            .into_diagnostic()?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Formats a file in-place or, in check mode, computes its diff.
+fn format_file(cli: &Cli, file: &Path) -> miette::Result<Outcome> {
+    let src = file.to_string_lossy();
+    let input = fs::read_to_string(file)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to read {src}"))?;
+    let options = options(cli, Some(file))?;
+    let formatted = format_source(&src, input.clone(), &options, cli.debug)?;
+    if formatted == input {
+        Ok(Outcome::Unchanged)
+    } else if cli.check {
+        Ok(Outcome::Changed(Some(diff(&src, &input, &formatted))))
+    } else {
+        write_file(file, formatted.as_bytes())
+            .wrap_err_with(|| format!("Failed to write {src}"))?;
+        Ok(Outcome::Changed(None))
+    }
+}
+
+/// Loads the closest configuration file to the file (or the current directory
+/// for stdin), and overrides it with the CLI options.
+fn options(cli: &Cli, file: Option<&Path>) -> miette::Result<FormatOptions> {
+    let config = match &cli.config {
+        Some(config) => Some(config.clone()),
+        None => {
+            let mut directory = env::current_dir().into_diagnostic()?;
+            if let Some(parent) = file.and_then(Path::parent) {
+                directory.push(parent);
+            }
+            FormatOptions::find_config(&directory)
+        }
+    };
+    let mut options = match config {
+        Some(config) => FormatOptions::load(&config).into_diagnostic()?,
+        None => FormatOptions::default(),
+    };
+    if let Some(indent) = &cli.indent {
+        options.indent = Some(indent.clone());
+    }
+    if let Some(max_width) = cli.max_width {
+        options.max_width = Some(max_width);
+    }
+    if let Some(trailing_commas) = cli.trailing_commas {
+        options.trailing_commas = trailing_commas;
+    }
+    if let Some(key_quotes) = cli.key_quotes {
+        options.key_quotes = key_quotes;
+    }
+    options.sort_keys |= cli.sort_keys;
+    if let Some(strings) = cli.strings {
+        options.strings = strings;
+    }
+    if let Some(integers) = cli.integers {
+        options.integers = integers;
+    }
+    Ok(options)
+}
+
+/// Parses the input, reporting any syntax errors, and formats it.
+fn format_source(
+    src: &str,
+    input: String,
+    options: &FormatOptions,
+    debug: bool,
+) -> miette::Result<String> {
     // Parse and check for errors
     let mut parser = tree_sitter::Parser::new();
     parser
@@ -137,24 +365,330 @@ fn main() -> miette::Result<()> {
         .into());
     }
 
-    // Format and write
+    // Format
     let mut buf = vec![];
-    format_duper(tree, &input, &mut buf, cli.indent, cli.debug)
+    format_duper_with_options(tree, &input, &mut buf, options, debug)
         .map_err(|err| miette!("Failed to format Duper value: {err}"))?;
+    String::from_utf8(buf).into_diagnostic()
+}
 
-    if let Some(output) = output {
-        let tmp_file = env::temp_dir().join(format!(
-            "/tmp_{}.duper",
-            time::SystemTime::now()
-                .duration_since(time::UNIX_EPOCH)
-                .into_diagnostic()?
-                .as_micros()
-        ));
-        fs::write(&tmp_file, &buf).into_diagnostic()?;
-        fs::rename(&tmp_file, output).into_diagnostic()?;
+/// Atomically replaces the file's contents, by writing to a temporary file in
+/// the same directory first. The temporary file gets the permissions of the
+/// original one, and symlinks are followed so that their target is replaced
+/// instead of the link itself.
+fn write_file(path: &Path, buf: &[u8]) -> miette::Result<()> {
+    let path = if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_symlink()) {
+        Cow::Owned(fs::canonicalize(path).into_diagnostic()?)
+    } else {
+        Cow::Borrowed(path)
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| miette!("Invalid output file {}", path.display()))?;
+    let tmp_file = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)
+            .into_diagnostic()?
+            .as_micros()
+    ));
+    let permissions = fs::metadata(&path)
+        .ok()
+        .map(|metadata| metadata.permissions());
+    let result = fs::write(&tmp_file, buf)
+        .and_then(|()| match permissions {
+            Some(permissions) => fs::set_permissions(&tmp_file, permissions),
+            None => Ok(()),
+        })
+        .and_then(|()| fs::rename(&tmp_file, &path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_file);
+    }
+    result.into_diagnostic()
+}
+
+fn diff(src: &str, input: &str, formatted: &str) -> String {
+    TextDiff::from_lines(input, formatted)
+        .unified_diff()
+        .header(src, src)
+        .to_string()
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+/// Expands the paths into a sorted list of files.
+///
+/// Files are kept as-is, directories are searched for `.duper` files, and
+/// anything else is treated as a glob pattern, which must match at least one
+/// file. Searches respect `.gitignore` files and skip hidden files, as well as
+/// the excluded patterns.
+fn collect_files(paths: &[PathBuf], exclude: &[String]) -> miette::Result<Vec<PathBuf>> {
+    let mut excludes = GitignoreBuilder::new(env::current_dir().into_diagnostic()?);
+    for pattern in exclude {
+        excludes
+            .add_line(None, pattern)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Invalid exclude pattern {pattern}"))?;
+    }
+    let excludes = Arc::new(excludes.build().into_diagnostic()?);
+
+    let mut files = BTreeSet::new();
+    for path in paths {
+        if path.is_file() {
+            files.insert(path.clone());
+            continue;
+        }
+        let (root, glob) = if path.is_dir() {
+            (path.clone(), None)
+        } else {
+            let pattern = path.to_string_lossy();
+            let glob = GlobBuilder::new(&pattern)
+                .literal_separator(true)
+                .build()
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Invalid glob pattern {pattern}"))?
+                .compile_matcher();
+            (glob_root(path), Some(glob))
+        };
+        if !root.exists() {
+            return Err(miette!("No such file or directory: {}", path.display()));
+        }
+        // The walker doesn't filter its root, which may be excluded as well.
+        let excluded = excludes.matched(&root, true).is_ignore();
+        let excludes = Arc::clone(&excludes);
+        let walker = (!excluded).then(|| {
+            WalkBuilder::new(&root)
+                .filter_entry(move |entry| {
+                    !excludes
+                        .matched(
+                            entry.path(),
+                            entry
+                                .file_type()
+                                .is_some_and(|file_type| file_type.is_dir()),
+                        )
+                        .is_ignore()
+                })
+                .build()
+        });
+        let mut matched = false;
+        for entry in walker.into_iter().flatten() {
+            let entry = entry.into_diagnostic()?;
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                continue;
+            }
+            let file = entry.path();
+            let matches = match &glob {
+                Some(glob) => glob.is_match(file) || glob.is_match(relative(file)),
+                None => file
+                    .extension()
+                    .is_some_and(|extension| extension == "duper"),
+            };
+            if matches {
+                matched = true;
+                files.insert(relative(file).to_path_buf());
+            }
+        }
+        if glob.is_some() && !matched {
+            return Err(miette!("No files match {}", path.display()));
+        }
+    }
+    Ok(files.into_iter().collect())
+}
+
+/// Returns the leading components of a glob pattern that don't contain any
+/// special characters, or the current directory if there are none.
+fn glob_root(pattern: &Path) -> PathBuf {
+    let root: PathBuf = pattern
+        .components()
+        .take_while(|component| {
+            !matches!(component, Component::Normal(name)
+                if name.to_string_lossy().contains(['*', '?', '[', '{']))
+        })
+        .collect();
+    if root.as_os_str().is_empty() {
+        PathBuf::from(".")
     } else {
-        io::stdout().write_all(&buf).into_diagnostic()?;
+        root
+    }
+}
+
+/// Strips the leading `./` from paths found in the current directory.
+fn relative(path: &Path) -> &Path {
+    path.strip_prefix(".").unwrap_or(path)
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// A fresh temporary directory with the given files.
+    fn temp_dir(files: &[(&str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, contents) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    /// Collects the files, relative to the directory.
+    fn collect(dir: &TempDir, paths: &[&str], exclude: &[&str]) -> miette::Result<Vec<String>> {
+        let paths: Vec<_> = paths.iter().map(|path| dir.path().join(path)).collect();
+        let exclude: Vec<_> = exclude.iter().map(ToString::to_string).collect();
+        Ok(collect_files(&paths, &exclude)?
+            .into_iter()
+            .map(|file| {
+                file.strip_prefix(dir.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect())
+    }
+
+    const FILES: &[(&str, &str)] = &[
+        ("a.duper", "{a: 1}\n"),
+        ("b.json", "{}\n"),
+        ("sub/c.duper", "{c:3}"),
+        ("sub/deep/d.duper", "{d: 4}\n"),
+        ("vendor/e.duper", "{e: 5}\n"),
+        ("f.gen.duper", "{f: 6}\n"),
+        (".hidden/g.duper", "{g: 7}\n"),
+    ];
+
+    #[test]
+    fn glob_roots() {
+        assert_eq!(glob_root(Path::new("src/**/*.duper")), Path::new("src"));
+        assert_eq!(glob_root(Path::new("*.duper")), Path::new("."));
+        assert_eq!(
+            glob_root(Path::new("/data/logs/{a,b}/*.duper")),
+            Path::new("/data/logs")
+        );
+        assert_eq!(glob_root(Path::new("a/b?c/d.duper")), Path::new("a"));
+        assert_eq!(glob_root(Path::new("a/[bc]/d.duper")), Path::new("a"));
+    }
+
+    #[test]
+    fn directories_and_files() {
+        let dir = temp_dir(FILES);
+        assert_eq!(
+            collect(&dir, &[""], &[]).unwrap(),
+            [
+                "a.duper",
+                "f.gen.duper",
+                "sub/c.duper",
+                "sub/deep/d.duper",
+                "vendor/e.duper"
+            ]
+        );
+        // Files are kept as-is, and duplicates are removed.
+        assert_eq!(
+            collect(&dir, &["b.json", "sub", "sub/c.duper"], &[]).unwrap(),
+            ["b.json", "sub/c.duper", "sub/deep/d.duper"]
+        );
+        let err = collect(&dir, &["missing"], &[]).unwrap_err();
+        assert!(err.to_string().starts_with("No such file or directory: "));
+    }
+
+    #[test]
+    fn globs() {
+        let dir = temp_dir(FILES);
+        assert_eq!(
+            collect(&dir, &["*.duper"], &[]).unwrap(),
+            ["a.duper", "f.gen.duper"]
+        );
+        assert_eq!(
+            collect(&dir, &["sub/**/*.duper"], &[]).unwrap(),
+            ["sub/c.duper", "sub/deep/d.duper"]
+        );
+        assert_eq!(
+            collect(&dir, &["{a,b}.*"], &[]).unwrap(),
+            ["a.duper", "b.json"]
+        );
+        let err = collect(&dir, &["*.toml"], &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("No files match {}", dir.path().join("*.toml").display())
+        );
+        // Globs only searching excluded files match nothing.
+        assert!(collect(&dir, &["vendor/*.duper"], &["vendor/"]).is_err());
+    }
+
+    #[test]
+    fn excludes() {
+        let dir = temp_dir(FILES);
+        assert_eq!(
+            collect(&dir, &[""], &["vendor/", "*.gen.duper"]).unwrap(),
+            ["a.duper", "sub/c.duper", "sub/deep/d.duper"]
+        );
+        assert_eq!(
+            collect(&dir, &["**/*.duper"], &["deep"]).unwrap(),
+            ["a.duper", "f.gen.duper", "sub/c.duper", "vendor/e.duper"]
+        );
     }
 
-    Ok(())
+    #[test]
+    fn check_exit_codes() {
+        let dir = temp_dir(FILES);
+        let check = |paths: &[&str]| {
+            let mut args = vec!["duperfmt".to_string(), "--check".into()];
+            args.extend(
+                paths
+                    .iter()
+                    .map(|path| dir.path().join(path).display().to_string()),
+            );
+            run(Cli::try_parse_from(args).unwrap()).unwrap()
+        };
+        assert_eq!(check(&["a.duper"]), ExitCode::SUCCESS);
+        assert_eq!(check(&["a.duper", "sub/deep"]), ExitCode::SUCCESS);
+        assert_eq!(check(&["sub/c.duper"]), ExitCode::FAILURE);
+        assert_eq!(check(&["sub"]), ExitCode::FAILURE);
+        fs::write(dir.path().join("broken.duper"), "{a: }\n").unwrap();
+        assert_eq!(check(&["a.duper", "broken.duper"]), ExitCode::FAILURE);
+        // Checking never writes.
+        assert_eq!(
+            fs::read_to_string(dir.path().join("sub/c.duper")).unwrap(),
+            "{c:3}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_through_symlinks() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dir = temp_dir(&[("target.duper", "{a:1}")]);
+        let target = dir.path().join("target.duper");
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o640)).unwrap();
+        let link = dir.path().join("link.duper");
+        symlink(&target, &link).unwrap();
+
+        write_file(&link, b"{a: 1}\n").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "{a: 1}\n");
+        assert_eq!(
+            fs::metadata(&target).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+        // No temporary files are left behind.
+        let mut files: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["link.duper", "target.duper"]);
+    }
 }
@@ -0,0 +1,143 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+/// The name of the configuration file, which is looked up in the directory of
+/// the formatted file and its ancestors.
+pub const CONFIG_FILE_NAME: &str = ".duperfmt.duper";
+
+/// Options that control the formatting style.
+///
+/// The default options only normalize whitespace, keeping objects, arrays,
+/// and tuples on a single line or on multiple lines as they are in the input.
+///
+/// They can be loaded from a Duper configuration file, such as:
+///
+/// ```duper
+/// {
+///   indent: "    ",
+///   max_width: 100,
+///   trailing_commas: "multiline",
+///   key_quotes: "minimal",
+///   sort_keys: true,
+///   strings: "auto",
+///   integers: "normalized",
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatOptions {
+    /// Indentation for pretty-printing. Defaults to two spaces.
+    pub indent: Option<String>,
+    /// The maximum line width. If set, objects, arrays, and tuples are
+    /// collapsed onto a single line when they fit, and split into one entry
+    /// per line otherwise.
+    pub max_width: Option<usize>,
+    /// When to add a comma after the last entry of an object, array, or tuple.
+    pub trailing_commas: TrailingCommas,
+    /// How object keys are quoted.
+    pub key_quotes: KeyQuotes,
+    /// Whether object entries are sorted by key.
+    pub sort_keys: bool,
+    /// Whether strings are written as quoted or raw strings.
+    pub strings: StringStyle,
+    /// How integer literals are written.
+    pub integers: IntegerStyle,
+}
+
+/// When to add a comma after the last entry of an object, array, or tuple.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingCommas {
+    /// Only when entries are split into multiple lines.
+    #[default]
+    Multiline,
+    /// After every last entry.
+    Always,
+    /// Never.
+    Never,
+}
+
+/// How object keys are quoted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyQuotes {
+    /// Keep keys as they are.
+    #[default]
+    Preserve,
+    /// Unquote keys that are valid plain keys.
+    Minimal,
+    /// Quote every key.
+    Always,
+}
+
+/// Whether strings are written as quoted or raw strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StringStyle {
+    /// Keep strings as they are.
+    #[default]
+    Preserve,
+    /// Convert raw strings into quoted strings.
+    Quoted,
+    /// Use raw strings only when they avoid escaping, like the Duper
+    /// serializer does.
+    Auto,
+}
+
+/// How integer literals are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum IntegerStyle {
+    /// Keep integers as they are.
+    #[default]
+    Preserve,
+    /// Keep the base, but remove underscores and leading plus signs, and
+    /// lowercase hexadecimal digits.
+    Normalized,
+    /// Convert every integer to decimal.
+    Decimal,
+}
+
+/// An error while loading a configuration file.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read {}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("invalid configuration in {}", path.display())]
+    Invalid {
+        path: PathBuf,
+        source: duper::serde::error::DuperSerdeError,
+    },
+}
+
+impl FormatOptions {
+    /// Parses options from the contents of a configuration file.
+    pub fn from_config(input: &str) -> Result<Self, duper::serde::error::DuperSerdeError> {
+        duper::serde::de::from_string(input)
+    }
+
+    /// Loads options from a configuration file.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let input = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.into(),
+            source,
+        })?;
+        Self::from_config(&input).map_err(|source| ConfigError::Invalid {
+            path: path.into(),
+            source,
+        })
+    }
+
+    /// Finds the closest configuration file in the directory or its ancestors.
+    pub fn find_config(directory: &Path) -> Option<PathBuf> {
+        directory
+            .ancestors()
+            .map(|directory| directory.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+}
@@ -0,0 +1,472 @@
+//! Normalizations applied to the input before it's formatted with Topiary.
+//!
+//! Topiary decides whether to split an object, array, or tuple into multiple
+//! lines based on the input, so line width is enforced by rewriting the input
+//! with the desired layout.
+
+use std::borrow::Cow;
+
+use duper::{DuperKey, DuperParser, DuperValue};
+use tree_sitter::Node;
+
+use crate::{FormatOptions, IntegerStyle, KeyQuotes, StringStyle, TrailingCommas};
+
+/// An entry of an object, array, or tuple, along with its comments.
+struct Item<'t> {
+    leading: Vec<Node<'t>>,
+    node: Node<'t>,
+    trailing: Vec<Node<'t>>,
+}
+
+pub(crate) struct Rewriter<'a> {
+    source: &'a str,
+    options: &'a FormatOptions,
+    indent_width: usize,
+}
+
+impl<'a> Rewriter<'a> {
+    pub(crate) fn new(source: &'a str, options: &'a FormatOptions) -> Self {
+        Self {
+            source,
+            options,
+            indent_width: width(options.indent.as_deref().unwrap_or("  ")),
+        }
+    }
+
+    /// Whether the options require rewriting the input at all.
+    pub(crate) fn is_needed(options: &FormatOptions) -> bool {
+        options.max_width.is_some()
+            || options.sort_keys
+            || options.key_quotes != KeyQuotes::Preserve
+            || options.strings != StringStyle::Preserve
+            || options.integers != IntegerStyle::Preserve
+    }
+
+    fn text(&self, node: Node<'_>) -> &'a str {
+        &self.source[node.byte_range()]
+    }
+
+    /// Rewrites a node, given the column that it starts at, its nesting depth,
+    /// and the width of what follows it on the same line.
+    pub(crate) fn rewrite(
+        &self,
+        node: Node<'_>,
+        column: usize,
+        depth: usize,
+        suffix: usize,
+    ) -> String {
+        match node.kind() {
+            "object" | "array" | "tuple" => self.rewrite_container(node, column, depth, suffix),
+            "object_entry" | "identified_value" | "duper_value" if has_comments(node) => {
+                self.rewrite_children(node, column, depth, suffix)
+            }
+            "object_entry" => {
+                let (Some(key), Some(value)) = (node.named_child(0), node.named_child(1)) else {
+                    return self.text(node).into();
+                };
+                let key = self.rewrite_key(key);
+                let value = self.rewrite(value, column + width(&key) + 2, depth, suffix);
+                format!("{key}: {value}")
+            }
+            "identified_value" => {
+                let (Some(identifier), Some(value)) = (node.named_child(0), node.named_child(1))
+                else {
+                    return self.text(node).into();
+                };
+                let identifier = self.text(identifier);
+                let value = self.rewrite(value, column + width(identifier) + 1, depth, suffix + 1);
+                format!("{identifier}({value})")
+            }
+            "duper_value" => match node.named_child(0) {
+                Some(value) => self.rewrite(value, column, depth, suffix),
+                None => self.text(node).into(),
+            },
+            _ => self.rewrite_leaf(node).into_owned(),
+        }
+    }
+
+    /// Rewrites the children of a node, keeping the whitespace and comments
+    /// between them.
+    fn rewrite_children(
+        &self,
+        node: Node<'_>,
+        column: usize,
+        depth: usize,
+        suffix: usize,
+    ) -> String {
+        let mut output = String::new();
+        let mut position = node.start_byte();
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            output.push_str(&self.source[position..child.start_byte()]);
+            let column = match output.rfind('\n') {
+                Some(newline) => width(&output[newline + 1..]),
+                None => column + width(&output),
+            };
+            output.push_str(&self.rewrite(child, column, depth, suffix));
+            position = child.end_byte();
+        }
+        output.push_str(&self.source[position..node.end_byte()]);
+        output
+    }
+
+    fn rewrite_container(
+        &self,
+        node: Node<'_>,
+        column: usize,
+        depth: usize,
+        suffix: usize,
+    ) -> String {
+        let fits = |text: &str| {
+            self.options
+                .max_width
+                .is_none_or(|max_width| column + width(text) + suffix <= max_width)
+        };
+        if self.options.max_width.is_none() && node.start_position().row != node.end_position().row
+        {
+            // Without a maximum width, keep the layout of the input unless the
+            // entries must be sorted
+            return if self.options.sort_keys && node.kind() == "object" {
+                self.split(node, depth)
+            } else {
+                self.rewrite_children(node, column, depth, suffix)
+            };
+        }
+        if let Some(flat) = self.flatten(node) {
+            if fits(&flat) {
+                return flat;
+            }
+        } else if node.start_position().row == node.end_position().row
+            && !(self.options.sort_keys && node.kind() == "object")
+        {
+            // Keep containers with block comments as they are if they fit,
+            // unless their entries must be sorted
+            let text = self.rewrite_children(node, column, depth, suffix);
+            if fits(&text) {
+                return text;
+            }
+        }
+        self.split(node, depth)
+    }
+
+    /// Writes every entry of an object, array, or tuple on its own line.
+    fn split(&self, node: Node<'_>, depth: usize) -> String {
+        let (open, close) = delimiters(node);
+        let (items, dangling) = self.items(node);
+        let column = (depth + 1) * self.indent_width;
+        let mut output = format!("{open}\n");
+        for item in items {
+            for (i, comment) in item.leading.iter().enumerate() {
+                if blank_line_before(*comment) {
+                    output.push('\n');
+                }
+                output.push_str(self.text(*comment));
+                let next = item.leading.get(i + 1).unwrap_or(&item.node);
+                if comment.kind() == "block_comment"
+                    && comment.end_position().row == next.start_position().row
+                {
+                    output.push(' ');
+                } else {
+                    output.push('\n');
+                }
+            }
+            output.push_str(&self.rewrite(item.node, column, depth + 1, 1));
+            output.push(',');
+            for comment in item.trailing {
+                output.push(' ');
+                output.push_str(self.text(comment));
+            }
+            output.push('\n');
+        }
+        for comment in dangling {
+            if blank_line_before(comment) {
+                output.push('\n');
+            }
+            output.push_str(self.text(comment));
+            output.push('\n');
+        }
+        output.push_str(close);
+        output
+    }
+
+    /// Writes a node on a single line, unless it has comments or multi-line
+    /// strings.
+    fn flatten(&self, node: Node<'_>) -> Option<String> {
+        if has_comments(node) {
+            return None;
+        }
+        match node.kind() {
+            "object" | "array" | "tuple" => {
+                let (open, close) = delimiters(node);
+                let (items, _) = self.items(node);
+                if items.is_empty() {
+                    return Some(format!("{open}{close}"));
+                }
+                let items = items
+                    .into_iter()
+                    .map(|item| self.flatten(item.node))
+                    .collect::<Option<Vec<_>>>()?;
+                let trailing = match self.options.trailing_commas {
+                    TrailingCommas::Always => ",",
+                    _ => "",
+                };
+                Some(format!("{open}{}{trailing}{close}", items.join(", ")))
+            }
+            "object_entry" => {
+                let key = self.rewrite_key(node.named_child(0)?);
+                let value = self.flatten(node.named_child(1)?)?;
+                Some(format!("{key}: {value}"))
+            }
+            "identified_value" => {
+                let identifier = self.text(node.named_child(0)?);
+                let value = self.flatten(node.named_child(1)?)?;
+                Some(format!("{identifier}({value})"))
+            }
+            "duper_value" => self.flatten(node.named_child(0)?),
+            _ => {
+                let text = self.rewrite_leaf(node);
+                (!text.contains('\n')).then(|| text.into_owned())
+            }
+        }
+    }
+
+    /// Collects the entries of an object, array, or tuple, sorted if needed,
+    /// along with any comments after the last entry.
+    fn items<'t>(&self, node: Node<'t>) -> (Vec<Item<'t>>, Vec<Node<'t>>) {
+        let mut items: Vec<Item<'t>> = vec![];
+        let mut pending = vec![];
+        let mut last_row = None;
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            match child.kind() {
+                "," => last_row = Some(child.end_position().row),
+                "line_comment" | "block_comment" => {
+                    // Line comments on the same line as an entry belong to it,
+                    // and any other comments to the next entry
+                    match items.last_mut() {
+                        Some(item)
+                            if child.kind() == "line_comment"
+                                && pending.is_empty()
+                                && last_row == Some(child.start_position().row) =>
+                        {
+                            item.trailing.push(child);
+                            last_row = Some(child.end_position().row);
+                        }
+                        _ => pending.push(child),
+                    }
+                }
+                _ if child.is_named() => {
+                    items.push(Item {
+                        leading: std::mem::take(&mut pending),
+                        node: child,
+                        trailing: vec![],
+                    });
+                    last_row = Some(child.end_position().row);
+                }
+                _ => (),
+            }
+        }
+        if self.options.sort_keys && node.kind() == "object" {
+            items.sort_by_cached_key(|item| {
+                item.node
+                    .named_child(0)
+                    .and_then(|key| self.key_name(key))
+                    .unwrap_or_default()
+            });
+        }
+        (items, pending)
+    }
+
+    fn rewrite_leaf(&self, node: Node<'_>) -> Cow<'a, str> {
+        match node.kind() {
+            "object_key" => self.rewrite_key(node),
+            "string" => self.rewrite_string(node),
+            "integer" => self.rewrite_integer(node),
+            _ => Cow::Borrowed(self.text(node)),
+        }
+    }
+
+    fn rewrite_key(&self, key: Node<'_>) -> Cow<'a, str> {
+        let text = Cow::Borrowed(self.text(key));
+        let Some(inner) = key.named_child(0) else {
+            return text;
+        };
+        match self.options.key_quotes {
+            KeyQuotes::Preserve => text,
+            KeyQuotes::Minimal if inner.kind() != "plain_key" => match self.key_name(key) {
+                Some(name) if is_plain_key(&name) => Cow::Owned(name.into_owned()),
+                _ => text,
+            },
+            KeyQuotes::Always if inner.kind() == "plain_key" => Cow::Owned(format!("\"{text}\"")),
+            _ => text,
+        }
+    }
+
+    fn key_name(&self, key: Node<'_>) -> Option<Cow<'a, str>> {
+        let inner = key.named_child(0)?;
+        match inner.kind() {
+            "plain_key" => Some(Cow::Borrowed(self.text(inner))),
+            _ => self.string_value(inner),
+        }
+    }
+
+    fn rewrite_string(&self, string: Node<'_>) -> Cow<'a, str> {
+        let text = Cow::Borrowed(self.text(string));
+        let Some(inner) = string.named_child(0) else {
+            return text;
+        };
+        match self.options.strings {
+            StringStyle::Preserve => text,
+            StringStyle::Quoted if inner.kind() == "raw_string" => match self.string_value(inner) {
+                Some(value) => Cow::Owned(format!("\"{}\"", duper::escape::escape_str(&value))),
+                None => text,
+            },
+            StringStyle::Quoted => text,
+            StringStyle::Auto => match self.string_value(inner) {
+                Some(value) => Cow::Owned(duper::format::format_duper_string(&value)),
+                None => text,
+            },
+        }
+    }
+
+    /// Returns the value of a quoted or raw string.
+    fn string_value(&self, string: Node<'_>) -> Option<Cow<'a, str>> {
+        let mut cursor = string.walk();
+        let content = string
+            .named_children(&mut cursor)
+            .find(|child| matches!(child.kind(), "quoted_content" | "raw_content"));
+        match (string.kind(), content) {
+            ("quoted_string", Some(content)) => {
+                duper::escape::unescape_str(self.text(content)).ok()
+            }
+            ("raw_string", Some(content)) => Some(Cow::Borrowed(self.text(content))),
+            (_, None) => Some(Cow::Borrowed("")),
+            _ => None,
+        }
+    }
+
+    fn rewrite_integer(&self, integer: Node<'_>) -> Cow<'a, str> {
+        let text = self.text(integer);
+        match self.options.integers {
+            IntegerStyle::Preserve => Cow::Borrowed(text),
+            IntegerStyle::Normalized => {
+                let digits = text.strip_prefix('+').unwrap_or(text).replace('_', "");
+                if digits.trim_start_matches('-').starts_with("0x") {
+                    Cow::Owned(digits.to_ascii_lowercase())
+                } else {
+                    Cow::Owned(digits)
+                }
+            }
+            IntegerStyle::Decimal => match DuperParser::parse_duper_value(text) {
+                Ok(DuperValue::Integer { inner, .. }) => Cow::Owned(inner.to_string()),
+                _ => Cow::Borrowed(text),
+            },
+        }
+    }
+}
+
+fn has_comments(node: Node<'_>) -> bool {
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor)
+        .any(|child| matches!(child.kind(), "line_comment" | "block_comment"))
+}
+
+/// Whether there's a blank line between a comment and what precedes it, which
+/// Topiary keeps.
+fn blank_line_before(comment: Node<'_>) -> bool {
+    comment
+        .prev_sibling()
+        .is_some_and(|prev| comment.start_position().row > prev.end_position().row + 1)
+}
+
+fn delimiters(node: Node<'_>) -> (&'static str, &'static str) {
+    match node.kind() {
+        "object" => ("{", "}"),
+        "array" => ("[", "]"),
+        _ => ("(", ")"),
+    }
+}
+
+fn is_plain_key(name: &str) -> bool {
+    !name.is_empty()
+        && matches!(
+            duper::format::format_key(&DuperKey::from(name)),
+            Cow::Borrowed(_)
+        )
+}
+
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+#[cfg(test)]
+mod rewrite_tests {
+    use super::*;
+
+    fn rewrite(input: &str, options: FormatOptions) -> String {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_duper::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(input, None).expect("parser was initialized");
+        Rewriter::new(input, &options).rewrite(tree.root_node(), 0, 0, 0)
+    }
+
+    #[test]
+    fn key_quotes_always() {
+        let options = FormatOptions {
+            key_quotes: KeyQuotes::Always,
+            ..Default::default()
+        };
+        assert_eq!(
+            rewrite(r#"{a: 1, "b": 2, r"c": 3, _d-e: {f: 4}}"#, options),
+            r#"{"a": 1, "b": 2, r"c": 3, "_d-e": {"f": 4}}"#
+        );
+    }
+
+    #[test]
+    fn auto_strings() {
+        let options = FormatOptions {
+            strings: StringStyle::Auto,
+            ..Default::default()
+        };
+        assert_eq!(
+            rewrite(
+                r##"["plain", r"raw", "say \"hi\"", r#"C:\path"#, "tab\t"]"##,
+                options
+            ),
+            r##"["plain", "raw", r#"say "hi""#, r"C:\path", "tab\t"]"##
+        );
+    }
+
+    #[test]
+    fn normalized_integers() {
+        let options = FormatOptions {
+            integers: IntegerStyle::Normalized,
+            ..Default::default()
+        };
+        assert_eq!(
+            rewrite("[+1_000, -1_000, 0xAB_cd, 0o7_7, 0b1_0]", options),
+            "[1000, -1000, 0xabcd, 0o77, 0b10]"
+        );
+    }
+
+    #[test]
+    fn sorted_keys_with_comments() {
+        let options = FormatOptions {
+            sort_keys: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            rewrite(
+                "{\n  // about c\n  c: 3, // after c\n  b: {z: 1, y: 2},\n  /* about a */ a: 1,\n  // dangling\n}",
+                options.clone()
+            ),
+            "{\n/* about a */ a: 1,\nb: {y: 2, z: 1},\n// about c\nc: 3, // after c\n// dangling\n}"
+        );
+        assert_eq!(
+            rewrite("{c: 3, /* about b */ b: 2, a: 1}", options),
+            "{\na: 1,\n/* about b */ b: 2,\nc: 3,\n}"
+        );
+    }
+}
@@ -0,0 +1,26 @@
+; Add last comma if multi-line object
+(object
+  (object_entry) @append_delimiter
+  .
+  "}"
+  (#delimiter! ",")
+  (#multi_line_only!)
+)
+
+; Add last comma if multi-line array
+(array
+  (duper_value) @append_delimiter
+  .
+  "]"
+  (#delimiter! ",")
+  (#multi_line_only!)
+)
+
+; Add last comma if multi-line tuple
+(tuple
+  (duper_value) @append_delimiter
+  .
+  ")"
+  (#delimiter! ",")
+  (#multi_line_only!)
+)
@@ -0,0 +1,23 @@
+; Add last comma to object
+(object
+  (object_entry) @append_delimiter
+  .
+  "}"
+  (#delimiter! ",")
+)
+
+; Add last comma to array
+(array
+  (duper_value) @append_delimiter
+  .
+  "]"
+  (#delimiter! ",")
+)
+
+; Add last comma to tuple
+(tuple
+  (duper_value) @append_delimiter
+  .
+  ")"
+  (#delimiter! ",")
+)
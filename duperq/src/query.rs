@@ -2,7 +2,7 @@ use chumsky::prelude::*;
 use duper::{
     Ansi, DuperValue, PrettyPrinter, Serializer,
     escape::unescape_str,
-    parser::{identified_value, integer, object_key, quoted_string},
+    parser::{Extra, identified_value, integer, object_key, quoted_string},
 };
 use serde_json::json;
 use smol::{channel, io::AsyncWrite};
@@ -29,7 +29,7 @@ type ProcessorPipeline = Vec<CreateProcessorFn<channel::Sender<Yoke<DuperValue<'
 
 /// Parses a `duperq` query.
 pub fn query<'a, O>()
--> impl Parser<'a, &'a str, (ProcessorPipeline, CreateProcessorFn<O>), extra::Err<Rich<'a, char>>>
+-> impl Parser<'a, &'a str, (ProcessorPipeline, CreateProcessorFn<O>), Extra<'a>>
 where
     O: AsyncWrite + Unpin + 'static,
 {
@@ -133,8 +133,7 @@ where
     .then_ignore(end())
 }
 
-fn filter<'a>() -> impl Parser<'a, &'a str, Box<dyn DuperFilter>, extra::Err<Rich<'a, char>>> + Clone
-{
+fn filter<'a>() -> impl Parser<'a, &'a str, Box<dyn DuperFilter>, Extra<'a>> + Clone {
     recursive(|filter| {
         let atom = filter
             .delimited_by(just('('), just(')'))
@@ -173,8 +172,7 @@ fn filter<'a>() -> impl Parser<'a, &'a str, Box<dyn DuperFilter>, extra::Err<Ric
     .boxed()
 }
 
-fn accessor<'a>()
--> impl Parser<'a, &'a str, Box<dyn DuperAccessor>, extra::Err<Rich<'a, char>>> + Clone {
+fn accessor<'a>() -> impl Parser<'a, &'a str, Box<dyn DuperAccessor>, Extra<'a>> + Clone {
     recursive(|accessor| {
         let access = choice((
             just('.').ignore_then(object_key().map(|key: duper::DuperKey<'a>| {
@@ -257,8 +255,8 @@ fn accessor<'a>()
 }
 
 fn leaf_filter<'a>(
-    accessor: impl Parser<'a, &'a str, Box<dyn DuperAccessor>, extra::Err<Rich<'a, char>>> + Clone,
-) -> impl Parser<'a, &'a str, Box<dyn DuperFilter>, extra::Err<Rich<'a, char>>> + Clone {
+    accessor: impl Parser<'a, &'a str, Box<dyn DuperAccessor>, Extra<'a>> + Clone,
+) -> impl Parser<'a, &'a str, Box<dyn DuperFilter>, Extra<'a>> + Clone {
     type ConsumeAccessor = Box<dyn FnOnce(Box<dyn DuperFilter>) -> Box<dyn DuperFilter>>;
 
     let cast_accessor = just("cast")
@@ -486,7 +484,7 @@ fn leaf_filter<'a>(
     .or(cast_accessor.map(|accessor| (accessor)(Box::new(IsTruthyFilter))))
 }
 
-fn duper_type<'a>() -> impl Parser<'a, &'a str, DuperType, extra::Err<Rich<'a, char>>> + Clone {
+fn duper_type<'a>() -> impl Parser<'a, &'a str, DuperType, Extra<'a>> + Clone {
     choice((
         just("Object").to(DuperType::Object),
         just("Array").to(DuperType::Array),
@@ -510,7 +508,7 @@ fn duper_type<'a>() -> impl Parser<'a, &'a str, DuperType, extra::Err<Rich<'a, c
     ))
 }
 
-fn fmt<'a, O>() -> impl Parser<'a, &'a str, CreateProcessorFn<O>, extra::Err<Rich<'a, char>>> + Clone
+fn fmt<'a, O>() -> impl Parser<'a, &'a str, CreateProcessorFn<O>, Extra<'a>> + Clone
 where
     O: AsyncWrite + Unpin + 'static,
 {
@@ -560,7 +558,7 @@ where
     })
 }
 
-fn quoted_inner<'a>() -> impl Parser<'a, &'a str, &'a str, extra::Err<Rich<'a, char>>> + Clone {
+fn quoted_inner<'a>() -> impl Parser<'a, &'a str, &'a str, Extra<'a>> + Clone {
     let escaped_characters = just('\\')
         .then(choice((
             one_of("\"\\/bfnrt0").to_slice(),
@@ -580,11 +578,11 @@ fn quoted_inner<'a>() -> impl Parser<'a, &'a str, &'a str, extra::Err<Rich<'a, c
         .to_slice()
 }
 
-fn hex_digit<'a>() -> impl Parser<'a, &'a str, char, extra::Err<Rich<'a, char>>> + Clone {
+fn hex_digit<'a>() -> impl Parser<'a, &'a str, char, Extra<'a>> + Clone {
     choice((one_of('0'..='9'), one_of('a'..='f'), one_of('A'..='F')))
         .labelled("a hexadecimal digit")
 }
-fn control_character<'a>() -> impl Parser<'a, &'a str, char, extra::Err<Rich<'a, char>>> + Clone {
+fn control_character<'a>() -> impl Parser<'a, &'a str, char, Extra<'a>> + Clone {
     choice((
         one_of('\u{0000}'..='\u{0009}'),
         one_of('\u{000b}'..='\u{001f}'),
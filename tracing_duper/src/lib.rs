@@ -14,7 +14,9 @@
 //!     .init();
 //! ```
 //!
-//! See [`DuperLayer`] for all the available configuration.
+//! See [`DuperLayer`] for all the available configuration. For long-running
+//! services, write logs with a [`NonBlocking`] writer and/or a
+//! [`RollingFileAppender`].
 //!
 //! Now, you can emit `tracing` spans and events as usual. If you'd like to
 //! emit Duper values, use `$duper.` as the prefix of the field, and set the
@@ -48,6 +50,27 @@
 //! {level:"INFO",timestamp:Instant('2025-12-04T13:29:34.049418692-03:00'),target:"simple",span:{count:23,span_id:2251799813685249},spans:[{count:23,span_id:2251799813685249}],fields:{span_event:"closed","span_time.busy":Duration('PT0.100555973S'),"span_time.idle":Duration('PT0.000001603S')}}
 //! ```
 //!
+//! To log any [`Serialize`](serde_core::Serialize) type as a Duper value,
+//! keeping its identifiers, bytes, and Temporal values, wrap it with [`value`]
+//! instead:
+//!
+//! ```
+//! use serde::Serialize;
+//! use tracing::info;
+//!
+//! #[derive(Serialize)]
+//! struct Delivery {
+//!     recipient: String,
+//!     gifts: usize,
+//! }
+//!
+//! let delivery = Delivery {
+//!     recipient: "Rudolph".into(),
+//!     gifts: 3,
+//! };
+//! info!(delivery = tracing_duper::value(&delivery), "delivered");
+//! ```
+//!
 //! To create Duper values programmatically, look into [`duper`] and [`duper::Serializer`], or
 //! the `serde_duper` crate.
 //!
@@ -59,17 +82,92 @@
 use std::{
     borrow::Cow,
     collections::BTreeMap,
+    fmt::Write as _,
     io::{self, Write},
     marker::PhantomData,
     time::Instant,
 };
 
-use duper::{DuperFloat, DuperIdentifier, DuperKey, DuperObject, DuperValue, Serializer};
+use duper::{
+    Ansi, DuperFloat, DuperIdentifier, DuperKey, DuperObject, DuperValue, PrettyPrinter,
+    Serializer,
+    visitor::ansi::{ANSI_THEME, AnsiTheme},
+};
 #[cfg(feature = "jiff")]
 use jiff::{Timestamp, Zoned};
+use serde_core::Serialize;
 use tracing_core::{Event, Subscriber, field};
 use tracing_subscriber::{Layer, field::VisitOutput, registry::LookupSpan};
 
+mod non_blocking;
+mod rolling;
+mod trace;
+mod tree;
+
+pub use non_blocking::{NonBlocking, NonBlockingBuilder, NonBlockingWriter, Overflow, WorkerGuard};
+pub use rolling::{RollingFileAppender, RollingFileAppenderBuilder, RollingWriter, Rotation};
+pub use trace::TraceContext;
+pub use tree::{SpanNode, SpanTree};
+
+/// The output format of a [`DuperLayer`].
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Format {
+    /// One minified Duper object per line, for machines. This is the default.
+    #[default]
+    Minified,
+    /// Indented Duper objects spanning multiple lines.
+    Pretty,
+    /// A human-readable `LEVEL target: message {fields}` line, with the
+    /// remaining fields as a Duper object. Spans are shown by name only, and
+    /// trace IDs are added to the fields.
+    Compact,
+    /// One Duper object per line, colored with the given theme for terminals.
+    Ansi(&'static AnsiTheme<'static>),
+}
+
+impl Format {
+    /// The [`Format::Ansi`] format with the default ANSI theme.
+    pub fn ansi() -> Self {
+        Format::Ansi(ANSI_THEME)
+    }
+}
+
+/// Wraps a [`Serialize`] type to be recorded as a field in a `tracing` span
+/// or event.
+///
+/// [`DuperLayer`] records it as the Duper value produced by
+/// [`duper::serde::ser::to_duper`], without going through a string. Other
+/// layers will see its Duper representation as an error message.
+///
+/// If serialization fails, the field is recorded as an `Error` string.
+pub fn value<T>(value: &T) -> impl field::Value + use<T>
+where
+    T: Serialize,
+{
+    let field: Box<dyn std::error::Error + Send + Sync> = Box::new(DuperFieldValue(
+        duper::serde::ser::to_duper(value)
+            .map(|value| value.static_clone())
+            .map_err(|error| error.to_string()),
+    ));
+    field
+}
+
+/// A serialized field value, smuggled to [`DuperVisitor`] through
+/// [`field::Visit::record_error`], since [`field::Value`] is sealed.
+#[derive(Debug)]
+struct DuperFieldValue(Result<DuperValue<'static>, String>);
+
+impl std::fmt::Display for DuperFieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Ok(value) => f.write_str(&Serializer::new(false, false).serialize(value)),
+            Err(error) => f.write_str(error),
+        }
+    }
+}
+
+impl std::error::Error for DuperFieldValue {}
+
 /// A trait to allow implementing timestamp generators.
 pub trait DuperTimer {
     /// Returns the current timestamp as a Duper value, or `None` if one cannot
@@ -129,6 +227,8 @@ pub struct DuperLayer<S, W = fn() -> io::Stdout, Timer = JiffTimestampTimer> {
     display_span_timings: bool,
     display_current_span: bool,
     display_span_list: bool,
+    display_trace_ids: bool,
+    format: Format,
     _subscriber: PhantomData<S>,
 }
 
@@ -149,6 +249,8 @@ pub struct DuperLayer<S, W = fn() -> io::Stdout, Timer = ()> {
     display_span_timings: bool,
     display_current_span: bool,
     display_span_list: bool,
+    display_trace_ids: bool,
+    format: Format,
     _subscriber: PhantomData<S>,
 }
 
@@ -168,6 +270,8 @@ impl<S> Default for DuperLayer<S> {
                 display_span_timings: false,
                 display_current_span: true,
                 display_span_list: true,
+                display_trace_ids: false,
+                format: Format::Minified,
                 _subscriber: Default::default(),
             }
         }
@@ -185,6 +289,8 @@ impl<S> Default for DuperLayer<S> {
                 display_span_timings: false,
                 display_current_span: true,
                 display_span_list: true,
+                display_trace_ids: false,
+                format: Format::Minified,
                 _subscriber: Default::default(),
             }
         }
@@ -213,6 +319,8 @@ impl<S, W, Timer> DuperLayer<S, W, Timer> {
             display_span_timings: self.display_span_timings,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            display_trace_ids: self.display_trace_ids,
+            format: self.format,
             _subscriber: Default::default(),
         }
     }
@@ -231,6 +339,8 @@ impl<S, W, Timer> DuperLayer<S, W, Timer> {
             display_span_timings: self.display_span_timings,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            display_trace_ids: self.display_trace_ids,
+            format: self.format,
             _subscriber: Default::default(),
         }
     }
@@ -249,6 +359,8 @@ impl<S, W, Timer> DuperLayer<S, W, Timer> {
             display_span_timings: self.display_span_timings,
             display_current_span: self.display_current_span,
             display_span_list: self.display_span_list,
+            display_trace_ids: self.display_trace_ids,
+            format: self.format,
             _subscriber: Default::default(),
         }
     }
@@ -334,6 +446,44 @@ impl<S, W, Timer> DuperLayer<S, W, Timer> {
         }
     }
 
+    /// Sets whether [W3C Trace Context](https://www.w3.org/TR/trace-context/)
+    /// IDs will be displayed in logs or not.
+    ///
+    /// When enabled, each span gets a [`TraceContext`], and its `span_id` is
+    /// replaced with the hex-encoded span ID, followed by `parent_span_id` and
+    /// `span_name`. Logs also display the `trace_id` and `span_id` of their
+    /// current span at the top level. The span tree can then be rebuilt from
+    /// the logs with [`SpanTree`].
+    ///
+    /// ```
+    /// use tracing_duper::DuperLayer;
+    /// use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+    ///
+    /// tracing_subscriber::registry()
+    ///     .with(DuperLayer::new().with_trace_ids(true))
+    ///     .init();
+    /// ```
+    pub fn with_trace_ids(self, display_trace_ids: bool) -> Self {
+        Self {
+            display_trace_ids,
+            ..self
+        }
+    }
+
+    /// Sets the output format of the logs. Defaults to [`Format::Minified`].
+    ///
+    /// ```
+    /// use tracing_duper::{DuperLayer, Format};
+    /// use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+    ///
+    /// tracing_subscriber::registry()
+    ///     .with(DuperLayer::new().with_format(Format::ansi()))
+    ///     .init();
+    /// ```
+    pub fn with_format(self, format: Format) -> Self {
+        Self { format, ..self }
+    }
+
     /// Sets whether timings for this span (busy, idle) should be tracked. If
     /// set to true, an event will be emitted when the span is closed.
     fn track_timings(&self) -> bool {
@@ -341,6 +491,114 @@ impl<S, W, Timer> DuperLayer<S, W, Timer> {
     }
 }
 
+impl<S, W, Timer> DuperLayer<S, W, Timer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> tracing_subscriber::fmt::writer::MakeWriter<'writer> + 'static,
+    Timer: DuperTimer + 'static,
+{
+    /// Writes an event in the [`Format::Compact`] layout.
+    fn write_compact(&self, event: &Event<'_>, ctx: &tracing_subscriber::layer::Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut line = String::new();
+
+        if self.display_timestamp
+            && let Some(timestamp) = self.timer.get_timestamp()
+        {
+            match timestamp {
+                DuperValue::Temporal(temporal) => line.push_str(temporal.as_ref()),
+                timestamp => line.push_str(&Serializer::new(false, false).serialize(&timestamp)),
+            }
+            line.push(' ');
+        }
+
+        if self.display_level {
+            let _ = write!(line, "{:>5} ", metadata.level());
+        }
+
+        if self.display_span_list {
+            if let Some(scope) = ctx.event_scope(event) {
+                for span in scope.from_root() {
+                    let _ = write!(line, "{}:", span.name());
+                }
+                line.push(' ');
+            }
+        } else if self.display_current_span
+            && let Some(span) = event
+                .parent()
+                .and_then(|id| ctx.span(id))
+                .or_else(|| ctx.lookup_current())
+        {
+            let _ = write!(line, "{}: ", span.name());
+        }
+
+        if self.display_target {
+            let _ = write!(line, "{}:", metadata.target());
+        }
+        if self.display_file {
+            let _ = write!(line, "{}:", metadata.file().unwrap_or("<unknown>"));
+        }
+        if self.display_line
+            && let Some(number) = metadata.line()
+        {
+            let _ = write!(line, "{number}:");
+        }
+        if self.display_target || self.display_file || self.display_line {
+            line.push(' ');
+        }
+
+        let mut fields_visitor = DuperVisitor::new();
+        event.record(&mut fields_visitor);
+        let message = fields_visitor.values.remove(&DuperKey::from("message"));
+        match message {
+            Some(DuperValue::String { inner, .. }) => line.push_str(&inner),
+            Some(message) => line.push_str(&Serializer::new(false, false).serialize(&message)),
+            None => (),
+        }
+        if self.display_trace_ids
+            && let Some(context) = event
+                .parent()
+                .and_then(|id| ctx.span(id))
+                .or_else(|| ctx.lookup_current())
+                .and_then(|span| span.extensions().get::<TraceContext>().copied())
+        {
+            fields_visitor.values.insert(
+                DuperKey::from("trace_id"),
+                DuperValue::String {
+                    identifier: None,
+                    inner: Cow::Owned(context.trace_id_hex()),
+                },
+            );
+            fields_visitor.values.insert(
+                DuperKey::from("span_id"),
+                DuperValue::String {
+                    identifier: None,
+                    inner: Cow::Owned(context.span_id_hex()),
+                },
+            );
+        }
+        if !fields_visitor.values.is_empty() {
+            if !line.is_empty() && !line.ends_with(' ') {
+                line.push(' ');
+            }
+            line.push_str(
+                &Serializer::new(false, false).serialize(&DuperValue::Object {
+                    identifier: None,
+                    inner: fields_visitor.finish(),
+                }),
+            );
+        }
+
+        if let Err(error) = writeln!(
+            self.make_writer.make_writer_for(metadata),
+            "{}",
+            line.trim_end()
+        ) {
+            let _ = error;
+        }
+    }
+}
+
 impl<S, W, Timer> Layer<S> for DuperLayer<S, W, Timer>
 where
     S: Subscriber + for<'span> LookupSpan<'span>,
@@ -354,16 +612,55 @@ where
         ctx: tracing_subscriber::layer::Context<'_, S>,
     ) {
         let span = ctx.span(id).expect("span should exist");
+        let parent_context = span
+            .parent()
+            .and_then(|parent| parent.extensions().get::<TraceContext>().copied());
         let mut extensions = span.extensions_mut();
 
         let mut visitor = DuperVisitor::new();
-        visitor.values.insert(
-            DuperKey::from("span_id"),
-            DuperValue::Integer {
-                identifier: None,
-                inner: id.into_u64() as i64,
-            },
-        );
+        if self.display_trace_ids {
+            let context = match extensions.get_mut::<TraceContext>() {
+                Some(context) => *context,
+                None => {
+                    let context = parent_context
+                        .map(|parent| parent.new_child())
+                        .unwrap_or_else(TraceContext::new_root);
+                    extensions.insert(context);
+                    context
+                }
+            };
+            visitor.values.insert(
+                DuperKey::from("span_id"),
+                DuperValue::String {
+                    identifier: None,
+                    inner: Cow::Owned(context.span_id_hex()),
+                },
+            );
+            if let Some(parent_span_id) = context.parent_span_id_hex() {
+                visitor.values.insert(
+                    DuperKey::from("parent_span_id"),
+                    DuperValue::String {
+                        identifier: None,
+                        inner: Cow::Owned(parent_span_id),
+                    },
+                );
+            }
+            visitor.values.insert(
+                DuperKey::from("span_name"),
+                DuperValue::String {
+                    identifier: None,
+                    inner: Cow::Borrowed(span.name()),
+                },
+            );
+        } else {
+            visitor.values.insert(
+                DuperKey::from("span_id"),
+                DuperValue::Integer {
+                    identifier: None,
+                    inner: id.into_u64() as i64,
+                },
+            );
+        }
         attrs.record(&mut visitor);
         let fields = visitor.finish();
         extensions.insert(DuperFields(fields));
@@ -398,6 +695,11 @@ where
     }
 
     fn on_event(&self, event: &Event<'_>, ctx: tracing_subscriber::layer::Context<'_, S>) {
+        if let Format::Compact = self.format {
+            self.write_compact(event, &ctx);
+            return;
+        }
+
         let mut log = vec![];
         let metadata = event.metadata();
 
@@ -453,45 +755,67 @@ where
             ));
         }
 
-        if self.display_current_span || self.display_span_list {
-            let current_span = event
-                .parent()
-                .and_then(|id| ctx.span(id))
-                .or_else(|| ctx.lookup_current());
-            if let Some(ref span) = current_span {
-                let extensions = span.extensions();
-                if self.display_current_span
-                    && let Some(fields) = extensions.get::<DuperFields>()
-                {
-                    log.push((
-                        DuperKey::from("span"),
-                        DuperValue::Object {
-                            identifier: None,
-                            inner: fields.0.clone(),
-                        },
-                    ));
-                }
-                if self.display_span_list {
-                    let mut spans = vec![];
-                    if let Some(scope) = ctx.event_scope(event) {
-                        for span in scope.from_root() {
-                            let extensions = span.extensions();
-                            if let Some(fields) = extensions.get::<DuperFields>() {
-                                spans.push(DuperValue::Object {
-                                    identifier: None,
-                                    inner: fields.0.clone(),
-                                });
-                            }
+        let current_span = event
+            .parent()
+            .and_then(|id| ctx.span(id))
+            .or_else(|| ctx.lookup_current());
+
+        if self.display_trace_ids
+            && let Some(context) = current_span
+                .as_ref()
+                .and_then(|span| span.extensions().get::<TraceContext>().copied())
+        {
+            log.push((
+                DuperKey::from("trace_id"),
+                DuperValue::String {
+                    identifier: None,
+                    inner: Cow::Owned(context.trace_id_hex()),
+                },
+            ));
+            log.push((
+                DuperKey::from("span_id"),
+                DuperValue::String {
+                    identifier: None,
+                    inner: Cow::Owned(context.span_id_hex()),
+                },
+            ));
+        }
+
+        if (self.display_current_span || self.display_span_list)
+            && let Some(ref span) = current_span
+        {
+            let extensions = span.extensions();
+            if self.display_current_span
+                && let Some(fields) = extensions.get::<DuperFields>()
+            {
+                log.push((
+                    DuperKey::from("span"),
+                    DuperValue::Object {
+                        identifier: None,
+                        inner: fields.0.clone(),
+                    },
+                ));
+            }
+            if self.display_span_list {
+                let mut spans = vec![];
+                if let Some(scope) = ctx.event_scope(event) {
+                    for span in scope.from_root() {
+                        let extensions = span.extensions();
+                        if let Some(fields) = extensions.get::<DuperFields>() {
+                            spans.push(DuperValue::Object {
+                                identifier: None,
+                                inner: fields.0.clone(),
+                            });
                         }
                     }
-                    log.push((
-                        DuperKey::from("spans"),
-                        DuperValue::Array {
-                            identifier: None,
-                            inner: spans,
-                        },
-                    ));
                 }
+                log.push((
+                    DuperKey::from("spans"),
+                    DuperValue::Array {
+                        identifier: None,
+                        inner: spans,
+                    },
+                ));
             }
         }
 
@@ -510,14 +834,24 @@ where
             ));
         }
 
-        let mut serializer = Serializer::new(false, true);
+        let log = DuperValue::Object {
+            identifier: None,
+            inner: DuperObject::from_lossy(log),
+        };
+        let output = match self.format {
+            Format::Minified | Format::Compact => Serializer::new(false, true).serialize(&log),
+            Format::Pretty => PrettyPrinter::new(false, "  ")
+                .expect("valid indentation")
+                .pretty_print(&log),
+            // Fall back to the minified output rather than dropping the event.
+            Format::Ansi(theme) => match Ansi::new(false, theme).to_ansi(&log) {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                Err(_) => Serializer::new(false, true).serialize(&log),
+            },
+        };
         if let Err(error) = writeln!(
             self.make_writer.make_writer_for(event.metadata()),
-            "{}",
-            serializer.serialize(&DuperValue::Object {
-                identifier: None,
-                inner: DuperObject::from_lossy(log),
-            })
+            "{output}"
         ) {
             let _ = error;
         }
@@ -821,6 +1155,10 @@ impl tracing_core::field::Visit for DuperVisitor<'_> {
         if self.values.contains_key(&key) {
             return;
         }
+        if let Some(DuperFieldValue(Ok(value))) = value.downcast_ref::<DuperFieldValue>() {
+            self.values.insert(key, value.clone());
+            return;
+        }
         self.values.insert(
             key,
             DuperValue::String {
@@ -848,3 +1186,175 @@ impl Timings {
         }
     }
 }
+
+#[cfg(test)]
+mod layer_tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt};
+
+    use super::*;
+
+    /// A writer which captures the logs.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Capture {
+        type Writer = Capture;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    /// Run `f` with a subscriber using the given layer settings, returning
+    /// the captured logs.
+    fn capture(
+        layer: impl FnOnce(
+            DuperLayer<tracing_subscriber::Registry, Capture, ()>,
+        ) -> DuperLayer<tracing_subscriber::Registry, Capture, ()>,
+        f: impl FnOnce(),
+    ) -> String {
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::registry().with(layer(
+            DuperLayer::new()
+                .without_timer()
+                .with_writer(capture.clone()),
+        ));
+        tracing::subscriber::with_default(subscriber, f);
+        String::from_utf8(capture.0.lock().unwrap().clone()).unwrap()
+    }
+
+    #[test]
+    fn compact_trace_ids() {
+        let logs = capture(
+            |layer| {
+                layer
+                    .with_format(Format::Compact)
+                    .with_target(false)
+                    .with_trace_ids(true)
+            },
+            || {
+                let _span = tracing::info_span!("request").entered();
+                tracing::info!(answer = 42, "done");
+            },
+        );
+        let (prefix, fields) = logs.trim_end().split_once(" {").unwrap();
+        assert_eq!(prefix, " INFO request: done");
+        let fields = duper::DuperParser::parse_duper_value(&format!("{{{fields}"))
+            .unwrap()
+            .static_clone();
+        let DuperValue::Object { inner, .. } = fields else {
+            panic!("expected an object");
+        };
+        let keys: Vec<_> = inner.iter().map(|(key, _)| key.as_ref()).collect();
+        assert_eq!(keys, ["answer", "span_id", "trace_id"]);
+
+        let logs = capture(
+            |layer| layer.with_format(Format::Compact).with_target(false),
+            || {
+                let _span = tracing::info_span!("request").entered();
+                tracing::info!("done");
+            },
+        );
+        assert_eq!(logs, " INFO request: done\n");
+    }
+
+    #[derive(serde::Serialize)]
+    struct User {
+        id: u64,
+        tags: Vec<&'static str>,
+    }
+
+    struct Unserializable;
+
+    impl Serialize for Unserializable {
+        fn serialize<S: serde_core::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(serde_core::ser::Error::custom("unserializable"))
+        }
+    }
+
+    /// Get a field of a parsed object.
+    fn get<'a>(value: &'a DuperValue<'static>, key: &str) -> &'a DuperValue<'static> {
+        let DuperValue::Object { inner, .. } = value else {
+            panic!("expected an object, got {value:?}");
+        };
+        inner
+            .iter()
+            .find(|(k, _)| k.as_ref() == key)
+            .map(|(_, value)| value)
+            .unwrap_or_else(|| panic!("missing key {key}"))
+    }
+
+    #[test]
+    fn typed_values() {
+        let logs = capture(
+            |layer| layer.with_current_span(true),
+            || {
+                let _span = tracing::info_span!(
+                    "request",
+                    user = value(&User {
+                        id: 1,
+                        tags: vec!["admin"],
+                    })
+                )
+                .entered();
+                tracing::info!(count = value(&3u8), broken = value(&Unserializable), "done");
+            },
+        );
+        let log = duper::DuperParser::parse_duper_value(logs.trim_end())
+            .unwrap()
+            .static_clone();
+
+        let user = get(get(&log, "span"), "user");
+        assert_eq!(
+            get(user, "id"),
+            &DuperValue::Integer {
+                identifier: None,
+                inner: 1
+            }
+        );
+        let DuperValue::Array { inner: tags, .. } = get(user, "tags") else {
+            panic!("expected an array");
+        };
+        assert_eq!(tags.len(), 1);
+
+        let fields = get(&log, "fields");
+        assert_eq!(
+            get(fields, "count"),
+            &DuperValue::Integer {
+                identifier: None,
+                inner: 3
+            }
+        );
+        let DuperValue::String {
+            identifier: Some(identifier),
+            inner,
+        } = get(fields, "broken")
+        else {
+            panic!("expected an error string");
+        };
+        assert_eq!(identifier.as_ref(), "Error");
+        assert!(inner.contains("unserializable"));
+    }
+
+    #[test]
+    fn ansi_format() {
+        let logs = capture(
+            |layer| layer.with_format(Format::ansi()),
+            || tracing::info!(answer = 42, "done"),
+        );
+        assert!(logs.contains("done"));
+        assert!(logs.contains("\u{1b}["));
+    }
+}
@@ -0,0 +1,331 @@
+//! A writer which offloads writing logs to a background thread.
+
+use std::{
+    io::{self, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+    },
+    thread::JoinHandle,
+};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+/// What a [`NonBlocking`] writer does when its channel is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop the log, and count it in [`NonBlocking::dropped_lines`]. This is
+    /// the default.
+    #[default]
+    Drop,
+    /// Block the logging thread until there's room in the channel.
+    Block,
+}
+
+/// A writer which sends logs through a bounded channel to a background
+/// thread, which writes them to the wrapped writer.
+///
+/// Use it with [`DuperLayer::with_writer`](crate::DuperLayer::with_writer).
+/// Keep the returned [`WorkerGuard`] alive for as long as you're logging, as
+/// dropping it flushes the remaining logs and stops the thread.
+///
+/// ```
+/// use tracing_duper::{DuperLayer, NonBlocking, Overflow};
+/// use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+///
+/// let (writer, _guard) = NonBlocking::builder()
+///     .with_capacity(1024)
+///     .with_overflow(Overflow::Block)
+///     .finish(std::io::stdout());
+/// tracing_subscriber::registry()
+///     .with(DuperLayer::new().with_writer(writer))
+///     .init();
+/// ```
+#[derive(Debug, Clone)]
+pub struct NonBlocking {
+    sender: SyncSender<Message>,
+    overflow: Overflow,
+    dropped: Arc<AtomicUsize>,
+}
+
+/// A builder for [`NonBlocking`] writers.
+#[derive(Debug, Clone)]
+pub struct NonBlockingBuilder {
+    capacity: usize,
+    overflow: Overflow,
+    thread_name: String,
+}
+
+/// Flushes the remaining logs of a [`NonBlocking`] writer and stops its
+/// thread when dropped.
+#[must_use = "dropping the guard stops the background thread"]
+#[derive(Debug)]
+pub struct WorkerGuard {
+    sender: SyncSender<Message>,
+    handle: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+enum Message {
+    Line(Vec<u8>),
+    Shutdown,
+}
+
+impl NonBlocking {
+    /// Creates a non-blocking writer with the default settings.
+    pub fn new<W>(writer: W) -> (NonBlocking, WorkerGuard)
+    where
+        W: Write + Send + 'static,
+    {
+        Self::builder().finish(writer)
+    }
+
+    /// Creates a builder to configure a non-blocking writer.
+    pub fn builder() -> NonBlockingBuilder {
+        NonBlockingBuilder::default()
+    }
+
+    /// Returns how many logs have been dropped so far due to
+    /// [`Overflow::Drop`].
+    pub fn dropped_lines(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn send(&self, buffer: Vec<u8>) -> io::Result<()> {
+        let message = Message::Line(buffer);
+        let result = match self.overflow {
+            Overflow::Drop => match self.sender.try_send(message) {
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                result => result.map_err(|_| ()),
+            },
+            Overflow::Block => self.sender.send(message).map_err(|_| ()),
+        };
+        result.map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "logging thread has stopped"))
+    }
+}
+
+impl Default for NonBlockingBuilder {
+    /// A capacity of 128,000 logs, dropping logs when full.
+    fn default() -> Self {
+        NonBlockingBuilder {
+            capacity: 128_000,
+            overflow: Overflow::Drop,
+            thread_name: "tracing-duper-writer".into(),
+        }
+    }
+}
+
+impl NonBlockingBuilder {
+    /// Sets how many logs can wait in the channel.
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        Self { capacity, ..self }
+    }
+
+    /// Sets what to do when the channel is full.
+    pub fn with_overflow(self, overflow: Overflow) -> Self {
+        Self { overflow, ..self }
+    }
+
+    /// Sets the name of the background thread.
+    pub fn with_thread_name(self, thread_name: impl Into<String>) -> Self {
+        Self {
+            thread_name: thread_name.into(),
+            ..self
+        }
+    }
+
+    /// Spawns the background thread writing to `writer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread cannot be spawned.
+    pub fn finish<W>(self, writer: W) -> (NonBlocking, WorkerGuard)
+    where
+        W: Write + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(self.capacity);
+        let handle = std::thread::Builder::new()
+            .name(self.thread_name)
+            .spawn(move || work(receiver, writer))
+            .expect("failed to spawn the logging thread");
+        (
+            NonBlocking {
+                sender: sender.clone(),
+                overflow: self.overflow,
+                dropped: Arc::new(AtomicUsize::new(0)),
+            },
+            WorkerGuard {
+                sender,
+                handle: Some(handle),
+            },
+        )
+    }
+}
+
+fn work<W: Write>(receiver: Receiver<Message>, mut writer: W) {
+    while let Ok(message) = receiver.recv() {
+        let mut message = Some(message);
+        // Write everything that's already queued before flushing.
+        while let Some(current) = message.take() {
+            match current {
+                Message::Line(line) => {
+                    let _ = writer.write_all(&line);
+                }
+                Message::Shutdown => {
+                    let _ = writer.flush();
+                    return;
+                }
+            }
+            message = receiver.try_recv().ok();
+        }
+        let _ = writer.flush();
+    }
+    let _ = writer.flush();
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        if self.sender.send(Message::Shutdown).is_ok()
+            && let Some(handle) = self.handle.take()
+        {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Write for NonBlocking {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send(buf.to_vec())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A writer for a single log, which is sent to the background thread as a
+/// whole when dropped.
+#[derive(Debug)]
+pub struct NonBlockingWriter<'a> {
+    non_blocking: &'a NonBlocking,
+    buffer: Vec<u8>,
+}
+
+impl Write for NonBlockingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.non_blocking.send(std::mem::take(&mut self.buffer))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for NonBlockingWriter<'_> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl<'a> MakeWriter<'a> for NonBlocking {
+    type Writer = NonBlockingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        NonBlockingWriter {
+            non_blocking: self,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod non_blocking_tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// A writer which only makes its writes visible once flushed, and waits
+    /// for `gate` to be unlocked before each write.
+    #[derive(Default)]
+    struct Buffered {
+        gate: Arc<Mutex<()>>,
+        buffer: Vec<u8>,
+        output: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Write for Buffered {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let _gate = self.gate.lock().unwrap();
+            self.buffer.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            let buffer = std::mem::take(&mut self.buffer);
+            self.output.lock().unwrap().extend(buffer);
+            Ok(())
+        }
+    }
+
+    fn write_log(writer: &NonBlocking, log: &str) {
+        writer.make_writer().write_all(log.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn overflow_drop() {
+        let writer = Buffered::default();
+        let gate = writer.gate.clone();
+        let output = writer.output.clone();
+        let (non_blocking, guard) = NonBlocking::builder().with_capacity(1).finish(writer);
+
+        // While the background thread is stuck on its first write, at most
+        // one more log fits in the channel.
+        let closed = gate.lock().unwrap();
+        for i in 0..10 {
+            write_log(&non_blocking, &format!("{i}\n"));
+        }
+        let dropped = non_blocking.dropped_lines();
+        assert!(dropped >= 8, "dropped {dropped} logs");
+        drop(closed);
+
+        drop(guard);
+        let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert_eq!(output.lines().count(), 10 - dropped);
+        assert!(output.starts_with("0\n"));
+    }
+
+    #[test]
+    fn guard_flushes() {
+        let writer = Buffered::default();
+        let output = writer.output.clone();
+        let (non_blocking, guard) = NonBlocking::builder()
+            .with_capacity(4)
+            .with_overflow(Overflow::Block)
+            .finish(writer);
+
+        let expected: String = (0..100).map(|i| format!("{{line: {i}}}\n")).collect();
+        for line in expected.split_inclusive('\n') {
+            write_log(&non_blocking, line);
+        }
+        drop(guard);
+        assert_eq!(non_blocking.dropped_lines(), 0);
+        assert_eq!(*output.lock().unwrap(), expected.as_bytes());
+
+        // Logs after the guard is dropped fail instead of being lost silently.
+        let mut writer = non_blocking.make_writer();
+        writer.write_all(b"{late: true}\n").unwrap();
+        assert_eq!(
+            writer.flush().unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+    }
+}
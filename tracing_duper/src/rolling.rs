@@ -0,0 +1,553 @@
+//! A file appender which rotates Duper log files by time or size.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+/// How often a [`RollingFileAppender`] starts a new file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rotation {
+    /// Never rotate by time. This is the default.
+    #[default]
+    Never,
+    /// Start a new file every minute.
+    Minutely,
+    /// Start a new file every hour.
+    Hourly,
+    /// Start a new file every day.
+    Daily,
+}
+
+impl Rotation {
+    fn period_seconds(self) -> Option<u64> {
+        match self {
+            Rotation::Never => None,
+            Rotation::Minutely => Some(60),
+            Rotation::Hourly => Some(60 * 60),
+            Rotation::Daily => Some(24 * 60 * 60),
+        }
+    }
+
+    /// Formats the start of the period containing `seconds` since the Unix
+    /// epoch, in UTC.
+    fn format_period(self, seconds: u64) -> String {
+        let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+        let hour = seconds / 3_600 % 24;
+        let minute = seconds / 60 % 60;
+        match self {
+            Rotation::Never => String::new(),
+            Rotation::Minutely => {
+                format!("{year:04}-{month:02}-{day:02}-{hour:02}-{minute:02}")
+            }
+            Rotation::Hourly => format!("{year:04}-{month:02}-{day:02}-{hour:02}"),
+            Rotation::Daily => format!("{year:04}-{month:02}-{day:02}"),
+        }
+    }
+}
+
+/// A writer which appends logs to files in a directory, starting a new file
+/// after each [`Rotation`] period or once a file reaches a maximum size.
+///
+/// Files are named `{prefix}.{period}.{sequence}.duper`, where the period (in
+/// UTC) is only present when rotating by time, and the sequence (zero-padded
+/// to six digits) only when rotating by size. Sorting the names sorts the files
+/// from oldest to newest, so they can be passed in order to tools like `duperq`
+/// with a glob such as `logs/app.*.duper`. Other files in the directory are
+/// never deleted, even if they share the prefix.
+///
+/// Each log is written whole to a single file. Use it with
+/// [`DuperLayer::with_writer`](crate::DuperLayer::with_writer), optionally
+/// wrapped in a [`NonBlocking`](crate::NonBlocking) writer.
+///
+/// ```no_run
+/// use tracing_duper::{DuperLayer, RollingFileAppender, Rotation};
+/// use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+///
+/// let appender = RollingFileAppender::builder("logs", "app")
+///     .with_rotation(Rotation::Daily)
+///     .with_max_size(10 * 1024 * 1024)
+///     .with_max_files(30)
+///     .build()
+///     .expect("log directory is writable");
+/// tracing_subscriber::registry()
+///     .with(DuperLayer::new().with_writer(appender))
+///     .init();
+/// ```
+#[derive(Debug)]
+pub struct RollingFileAppender {
+    state: Mutex<RollingState>,
+}
+
+/// A builder for [`RollingFileAppender`]s.
+#[derive(Debug, Clone)]
+pub struct RollingFileAppenderBuilder {
+    directory: PathBuf,
+    prefix: String,
+    rotation: Rotation,
+    max_size: Option<u64>,
+    max_files: Option<usize>,
+}
+
+#[derive(Debug)]
+struct RollingState {
+    config: RollingFileAppenderBuilder,
+    file: File,
+    size: u64,
+    period: Option<u64>,
+    sequence: u64,
+}
+
+impl RollingFileAppender {
+    /// Creates a builder for an appender writing files named after `prefix`
+    /// into `directory`.
+    pub fn builder(
+        directory: impl AsRef<Path>,
+        prefix: impl Into<String>,
+    ) -> RollingFileAppenderBuilder {
+        RollingFileAppenderBuilder {
+            directory: directory.as_ref().to_path_buf(),
+            prefix: prefix.into(),
+            rotation: Rotation::Never,
+            max_size: None,
+            max_files: None,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RollingState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl RollingFileAppenderBuilder {
+    /// Sets how often to start a new file.
+    pub fn with_rotation(self, rotation: Rotation) -> Self {
+        Self { rotation, ..self }
+    }
+
+    /// Sets the size in bytes after which a new file is started.
+    pub fn with_max_size(self, max_size: u64) -> Self {
+        Self {
+            max_size: Some(max_size),
+            ..self
+        }
+    }
+
+    /// Sets how many files to keep, deleting the oldest ones on rotation.
+    pub fn with_max_files(self, max_files: usize) -> Self {
+        Self {
+            max_files: Some(max_files.max(1)),
+            ..self
+        }
+    }
+
+    /// Creates the directory if needed, and opens the current log file,
+    /// continuing from the last one written in the current period.
+    pub fn build(self) -> io::Result<RollingFileAppender> {
+        fs::create_dir_all(&self.directory)?;
+        let period = self.current_period();
+        let sequence = match self.max_size {
+            Some(_) => {
+                let current = self.format_period(period);
+                self.log_files()?
+                    .into_iter()
+                    .filter(|file| file.period == current)
+                    .map(|file| file.sequence)
+                    .max()
+                    .unwrap_or(0)
+            }
+            None => 0,
+        };
+        let (file, size) = self.open(period, sequence)?;
+        let mut state = RollingState {
+            config: self,
+            file,
+            size,
+            period,
+            sequence,
+        };
+        state.refresh()?;
+        Ok(RollingFileAppender {
+            state: Mutex::new(state),
+        })
+    }
+
+    /// The index of the rotation period at the current time.
+    fn current_period(&self) -> Option<u64> {
+        self.rotation.period_seconds().map(|period| now() / period)
+    }
+
+    /// The formatted start of a period, or an empty string when not rotating
+    /// by time.
+    fn format_period(&self, period: Option<u64>) -> String {
+        match (self.rotation.period_seconds(), period) {
+            (Some(seconds), Some(period)) => self.rotation.format_period(period * seconds),
+            _ => String::new(),
+        }
+    }
+
+    /// The name of a file without its sequence and extension.
+    fn base_name(&self, period: Option<u64>) -> String {
+        match self.rotation {
+            Rotation::Never => self.prefix.clone(),
+            _ => format!("{}.{}", self.prefix, self.format_period(period)),
+        }
+    }
+
+    /// Opens a file for appending, returning it with its current size.
+    fn open(&self, period: Option<u64>, sequence: u64) -> io::Result<(File, u64)> {
+        let name = match self.max_size {
+            Some(_) => format!("{}.{sequence:06}.duper", self.base_name(period)),
+            None => format!("{}.duper", self.base_name(period)),
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.directory.join(name))?;
+        let size = file.metadata()?.len();
+        Ok((file, size))
+    }
+
+    /// The log files written by an appender with this configuration, sorted
+    /// from oldest to newest.
+    fn log_files(&self) -> io::Result<Vec<LogFile>> {
+        let mut files: Vec<_> = fs::read_dir(&self.directory)?
+            .filter_map(|entry| self.parse_name(entry.ok()?.file_name().into_string().ok()?))
+            .collect();
+        files.sort_by(|a, b| (&a.period, a.sequence).cmp(&(&b.period, b.sequence)));
+        Ok(files)
+    }
+
+    /// Parses a file name of the exact form `{prefix}.{period}.{sequence}.duper`
+    /// written with this configuration.
+    fn parse_name(&self, name: String) -> Option<LogFile> {
+        let mut parts = name
+            .strip_prefix(&self.prefix)?
+            .strip_prefix('.')?
+            .strip_suffix(".duper")?
+            .split('.');
+        let period = match self.rotation {
+            Rotation::Never => String::new(),
+            rotation => {
+                let period = parts.next()?;
+                let template = rotation.format_period(0);
+                let matches = period.len() == template.len()
+                    && period
+                        .bytes()
+                        .zip(template.bytes())
+                        .all(|(byte, expected)| {
+                            if expected == b'-' {
+                                byte == b'-'
+                            } else {
+                                byte.is_ascii_digit()
+                            }
+                        });
+                if !matches {
+                    return None;
+                }
+                period.to_string()
+            }
+        };
+        let sequence = match self.max_size {
+            Some(_) => {
+                let sequence = parts.next()?;
+                if sequence.len() < 6 || !sequence.bytes().all(|byte| byte.is_ascii_digit()) {
+                    return None;
+                }
+                sequence.parse().ok()?
+            }
+            None => 0,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(LogFile {
+            name,
+            period,
+            sequence,
+        })
+    }
+}
+
+/// A file written by a [`RollingFileAppender`].
+#[derive(Debug)]
+struct LogFile {
+    name: String,
+    period: String,
+    sequence: u64,
+}
+
+impl RollingState {
+    /// Rotates the file if its period is over or it's full, then deletes the
+    /// oldest files beyond `max_files`.
+    fn refresh(&mut self) -> io::Result<()> {
+        let period = self.config.current_period();
+        if period != self.period {
+            self.period = period;
+            self.sequence = 0;
+        } else if self
+            .config
+            .max_size
+            .is_some_and(|max_size| self.size >= max_size)
+        {
+            self.sequence += 1;
+        } else {
+            return Ok(());
+        }
+        let _ = self.file.flush();
+        (self.file, self.size) = self.config.open(self.period, self.sequence)?;
+        if let Some(max_files) = self.config.max_files {
+            let files = self.config.log_files()?;
+            let excess = files.len().saturating_sub(max_files);
+            for file in &files[..excess] {
+                let _ = fs::remove_file(self.config.directory.join(&file.name));
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+}
+
+impl Write for RollingFileAppender {
+    /// Writes a whole log, rotating the file beforehand if needed.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let state = self
+            .state
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.refresh()?;
+        state.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.state
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .file
+            .flush()
+    }
+}
+
+/// A writer for a single log in a [`RollingFileAppender`].
+#[derive(Debug)]
+pub struct RollingWriter<'a>(MutexGuard<'a, RollingState>);
+
+impl Write for RollingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.file.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RollingFileAppender {
+    type Writer = RollingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        let mut state = self.lock();
+        let _ = state.refresh();
+        RollingWriter(state)
+    }
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date in the
+/// proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod rolling_tests {
+    use super::*;
+
+    fn files(dir: &Path) -> Vec<String> {
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn write_log(appender: &RollingFileAppender, log: &str) {
+        let mut writer = appender.make_writer();
+        writer.write_all(log.as_bytes()).unwrap();
+        writer.flush().unwrap();
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(2_932_896), (9999, 12, 31));
+    }
+
+    #[test]
+    fn periods() {
+        // 2023-11-14T22:13:20Z
+        let seconds = 1_700_000_000;
+        assert_eq!(Rotation::Never.format_period(seconds), "");
+        assert_eq!(Rotation::Daily.format_period(seconds), "2023-11-14");
+        assert_eq!(Rotation::Hourly.format_period(seconds), "2023-11-14-22");
+        assert_eq!(
+            Rotation::Minutely.format_period(seconds),
+            "2023-11-14-22-13"
+        );
+        assert_eq!(Rotation::Minutely.format_period(0), "1970-01-01-00-00");
+    }
+
+    #[test]
+    fn exact_names() {
+        let config = RollingFileAppender::builder("logs", "app")
+            .with_rotation(Rotation::Hourly)
+            .with_max_size(1024);
+        let parse = |name: &str| {
+            config
+                .parse_name(name.into())
+                .map(|file| (file.period, file.sequence))
+        };
+        assert_eq!(
+            parse("app.2024-01-01-05.000003.duper"),
+            Some(("2024-01-01-05".into(), 3))
+        );
+        assert_eq!(
+            parse("app.2024-01-01-05.1000000.duper"),
+            Some(("2024-01-01-05".into(), 1_000_000))
+        );
+        assert_eq!(parse("app.2024-01-01.000003.duper"), None);
+        assert_eq!(parse("app.2024-01-01-05.duper"), None);
+        assert_eq!(parse("app.2024-01-01-05.3.duper"), None);
+        assert_eq!(parse("app.2024-01-01-05.000003.old.duper"), None);
+        assert_eq!(parse("app.notes.duper"), None);
+        assert_eq!(parse("app.2024-01-01-05.000003.txt"), None);
+        assert_eq!(parse("application.2024-01-01-05.000003.duper"), None);
+
+        let config = RollingFileAppender::builder("logs", "app.v2");
+        assert!(config.parse_name("app.v2.duper".into()).is_none());
+        let config = config.with_rotation(Rotation::Daily);
+        assert!(
+            config
+                .parse_name("app.v2.2024-01-01.duper".into())
+                .is_some()
+        );
+        assert!(
+            config
+                .parse_name("app.v2.2024-01-01.000001.duper".into())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn rotation_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let appender = RollingFileAppender::builder(dir.path(), "app")
+            .with_max_size(10)
+            .build()
+            .unwrap();
+        write_log(&appender, "{a: 12345}\n");
+        write_log(&appender, "{b: 1}\n");
+        write_log(&appender, "{c: 2}\n");
+        assert_eq!(files(dir.path()), ["app.000000.duper", "app.000001.duper"]);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("app.000001.duper")).unwrap(),
+            "{b: 1}\n{c: 2}\n"
+        );
+        drop(appender);
+
+        // A new appender continues from the last file, which is now full.
+        let appender = RollingFileAppender::builder(dir.path(), "app")
+            .with_max_size(10)
+            .build()
+            .unwrap();
+        write_log(&appender, "{d: 3}\n");
+        assert_eq!(
+            files(dir.path()),
+            ["app.000000.duper", "app.000001.duper", "app.000002.duper"]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("app.000002.duper")).unwrap(),
+            "{d: 3}\n"
+        );
+    }
+
+    #[test]
+    fn max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "app.999999.duper",
+            "app.1000000.duper",
+            "app.notes.duper",
+            "app.txt",
+            "other.000001.duper",
+        ] {
+            fs::write(dir.path().join(name), "{full: true}\n").unwrap();
+        }
+        let appender = RollingFileAppender::builder(dir.path(), "app")
+            .with_max_size(10)
+            .with_max_files(2)
+            .build()
+            .unwrap();
+        // The last file was full, so the appender starts the next one and
+        // deletes the oldest by sequence rather than by name.
+        assert_eq!(
+            files(dir.path()),
+            [
+                "app.1000000.duper",
+                "app.1000001.duper",
+                "app.notes.duper",
+                "app.txt",
+                "other.000001.duper"
+            ]
+        );
+        write_log(&appender, "{a: 12345}\n");
+        write_log(&appender, "{b: 1}\n");
+        assert_eq!(
+            files(dir.path()),
+            [
+                "app.1000001.duper",
+                "app.1000002.duper",
+                "app.notes.duper",
+                "app.txt",
+                "other.000001.duper"
+            ]
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! W3C Trace Context identifiers for spans.
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt::Write as _,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The [W3C Trace Context](https://www.w3.org/TR/trace-context/) of a span,
+/// emitted by [`DuperLayer`](crate::DuperLayer) when
+/// [`with_trace_ids`](crate::DuperLayer::with_trace_ids) is enabled.
+///
+/// By default, root spans start a new trace with random IDs, and child spans
+/// inherit the trace ID of their parent. To take the IDs from elsewhere (for
+/// example, an OpenTelemetry context or an incoming `traceparent` header),
+/// insert a `TraceContext` into the span's extensions from a layer that runs
+/// before the [`DuperLayer`](crate::DuperLayer).
+///
+/// ```
+/// use tracing_duper::TraceContext;
+///
+/// let context =
+///     TraceContext::from_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+///         .unwrap();
+/// assert_eq!(context.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+/// assert_eq!(context.parent_span_id_hex().unwrap(), "00f067aa0ba902b7");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+}
+
+impl TraceContext {
+    /// Creates a trace context from the given IDs.
+    pub fn new(trace_id: [u8; 16], span_id: [u8; 8], parent_span_id: Option<[u8; 8]>) -> Self {
+        Self {
+            trace_id,
+            span_id,
+            parent_span_id,
+        }
+    }
+
+    /// Creates the context of a root span, with a random trace ID and span ID.
+    pub fn new_root() -> Self {
+        let mut trace_id = [0; 16];
+        trace_id[..8].copy_from_slice(&random_id());
+        trace_id[8..].copy_from_slice(&random_id());
+        Self::new(trace_id, random_id(), None)
+    }
+
+    /// Creates the context of a child span in the same trace, with a random
+    /// span ID.
+    pub fn new_child(&self) -> Self {
+        Self::new(self.trace_id, random_id(), Some(self.span_id))
+    }
+
+    /// Creates the context of a span continuing a remote trace, from the value
+    /// of a `traceparent` header. The remote span becomes the parent, and the
+    /// span gets a random span ID.
+    ///
+    /// Returns `None` if the header is invalid.
+    pub fn from_traceparent(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let parent_id = parts.next()?;
+        let flags = parts.next()?;
+        // The version and flags are also lowercase hex, and version `ff` is
+        // invalid.
+        if decode_hex::<1>(version)? == [0xff]
+            || decode_hex::<1>(flags).is_none()
+            || (version == "00" && parts.next().is_some())
+        {
+            return None;
+        }
+        let trace_id = decode_hex::<16>(trace_id)?;
+        let parent_id = decode_hex::<8>(parent_id)?;
+        if trace_id == [0; 16] || parent_id == [0; 8] {
+            return None;
+        }
+        Some(Self::new(trace_id, random_id(), Some(parent_id)))
+    }
+
+    /// The trace ID.
+    pub fn trace_id(&self) -> [u8; 16] {
+        self.trace_id
+    }
+
+    /// The span ID.
+    pub fn span_id(&self) -> [u8; 8] {
+        self.span_id
+    }
+
+    /// The span ID of the parent span, if any.
+    pub fn parent_span_id(&self) -> Option<[u8; 8]> {
+        self.parent_span_id
+    }
+
+    /// The trace ID, as 32 lowercase hex digits.
+    pub fn trace_id_hex(&self) -> String {
+        encode_hex(&self.trace_id)
+    }
+
+    /// The span ID, as 16 lowercase hex digits.
+    pub fn span_id_hex(&self) -> String {
+        encode_hex(&self.span_id)
+    }
+
+    /// The span ID of the parent span, as 16 lowercase hex digits.
+    pub fn parent_span_id_hex(&self) -> Option<String> {
+        self.parent_span_id.as_ref().map(|id| encode_hex(id))
+    }
+
+    /// The `traceparent` header to propagate this span to a remote service.
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-01", self.trace_id_hex(), self.span_id_hex())
+    }
+}
+
+/// Generates a random, non-zero 64-bit ID, without depending on a random
+/// number generator crate.
+fn random_id() -> [u8; 8] {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    loop {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        let id = hasher.finish();
+        if id != 0 {
+            return id.to_be_bytes();
+        }
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+fn decode_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    if hex.len() != N * 2
+        || !hex
+            .bytes()
+            .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+    {
+        return None;
+    }
+    let mut bytes = [0; N];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use super::*;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_ID: &str = "00f067aa0ba902b7";
+
+    #[test]
+    fn valid_traceparent() {
+        let context =
+            TraceContext::from_traceparent(&format!(" 00-{TRACE_ID}-{PARENT_ID}-01\n")).unwrap();
+        assert_eq!(context.trace_id_hex(), TRACE_ID);
+        assert_eq!(context.parent_span_id_hex().as_deref(), Some(PARENT_ID));
+        assert_ne!(context.span_id(), [0; 8]);
+        assert_eq!(
+            context.traceparent(),
+            format!("00-{TRACE_ID}-{}-01", context.span_id_hex())
+        );
+        // Future versions may have more fields.
+        assert!(
+            TraceContext::from_traceparent(&format!("01-{TRACE_ID}-{PARENT_ID}-00-extra"))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn uppercase_hex() {
+        for traceparent in [
+            format!("00-{}-{PARENT_ID}-01", TRACE_ID.to_uppercase()),
+            format!("00-{TRACE_ID}-{}-01", PARENT_ID.to_uppercase()),
+            format!("0A-{TRACE_ID}-{PARENT_ID}-01"),
+            format!("00-{TRACE_ID}-{PARENT_ID}-0A"),
+        ] {
+            assert!(
+                TraceContext::from_traceparent(&traceparent).is_none(),
+                "{traceparent}"
+            );
+        }
+    }
+
+    #[test]
+    fn all_zero_ids() {
+        let zero_trace = "0".repeat(32);
+        let zero_span = "0".repeat(16);
+        assert!(
+            TraceContext::from_traceparent(&format!("00-{zero_trace}-{PARENT_ID}-01")).is_none()
+        );
+        assert!(TraceContext::from_traceparent(&format!("00-{TRACE_ID}-{zero_span}-01")).is_none());
+    }
+
+    #[test]
+    fn invalid_version() {
+        for traceparent in [
+            format!("ff-{TRACE_ID}-{PARENT_ID}-01"),
+            format!("0-{TRACE_ID}-{PARENT_ID}-01"),
+            format!("zz-{TRACE_ID}-{PARENT_ID}-01"),
+            format!("00-{TRACE_ID}-{PARENT_ID}-01-extra"),
+            format!("00-{TRACE_ID}-{PARENT_ID}"),
+        ] {
+            assert!(
+                TraceContext::from_traceparent(&traceparent).is_none(),
+                "{traceparent}"
+            );
+        }
+    }
+}
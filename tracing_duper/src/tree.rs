@@ -0,0 +1,511 @@
+//! Reconstruction of the span tree from Duper logs.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    io::{self, BufRead},
+    str::FromStr,
+};
+
+use duper::{DuperKey, DuperObject, DuperParser, DuperValue, Serializer};
+
+/// The call tree of spans, rebuilt from logs written by
+/// [`DuperLayer`](crate::DuperLayer) with [`Format::Minified`](crate::Format::Minified).
+///
+/// Each log line is attached to its innermost span. The parent of a span is
+/// taken from its `parent_span_id` when
+/// [`with_trace_ids`](crate::DuperLayer::with_trace_ids) is enabled, or from
+/// the `spans` list otherwise. Note that without trace IDs, `tracing` may
+/// reuse the ID of a closed span for a new one.
+///
+/// ```
+/// use tracing_duper::SpanTree;
+///
+/// let logs = r#"
+/// {level:"INFO",trace_id:"4bf92f3577b34da6a3ce929d0e0e4736",span:{span_id:"00f067aa0ba902b7",span_name:"request"},fields:{message:"started"}}
+/// {level:"INFO",trace_id:"4bf92f3577b34da6a3ce929d0e0e4736",span:{span_id:"b7ad6b7169203331",parent_span_id:"00f067aa0ba902b7",span_name:"query"},fields:{message:"done"}}
+/// {level:"INFO",fields:{message:"idle"}}
+/// "#;
+/// let tree: SpanTree = logs.parse().unwrap();
+/// assert_eq!(tree.roots.len(), 1);
+/// assert_eq!(tree.roots[0].name.as_deref(), Some("request"));
+/// assert_eq!(tree.roots[0].children[0].name.as_deref(), Some("query"));
+/// assert_eq!(tree.roots[0].children[0].events.len(), 1);
+/// assert_eq!(tree.events.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SpanTree {
+    /// The spans without a parent, in the order they were first seen.
+    pub roots: Vec<SpanNode>,
+    /// The logs emitted outside of any span.
+    pub events: Vec<DuperValue<'static>>,
+}
+
+/// A span in a [`SpanTree`].
+#[derive(Debug, Clone)]
+pub struct SpanNode {
+    /// The `span_id` of the span.
+    pub span_id: DuperValue<'static>,
+    /// The `trace_id` of the logs in this span, if any.
+    pub trace_id: Option<DuperValue<'static>>,
+    /// The `span_name` of the span, if any.
+    pub name: Option<String>,
+    /// The fields of the span, without its IDs and name.
+    pub fields: DuperObject<'static>,
+    /// The logs emitted directly in this span, in order.
+    pub events: Vec<DuperValue<'static>>,
+    /// The child spans, in the order they were first seen.
+    pub children: Vec<SpanNode>,
+}
+
+impl SpanTree {
+    /// Rebuilds the span tree from a reader of Duper logs, one per line.
+    ///
+    /// Blank lines are skipped. Returns an error with
+    /// [`io::ErrorKind::InvalidData`] if a line isn't a Duper object.
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut builder = TreeBuilder::default();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match DuperParser::parse_duper_value(&line) {
+                Ok(log @ DuperValue::Object { .. }) => builder.push(log.static_clone()),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {} is not a Duper object", index + 1),
+                    ));
+                }
+            }
+        }
+        Ok(builder.finish())
+    }
+}
+
+impl FromStr for SpanTree {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_reader(s.as_bytes())
+    }
+}
+
+impl Display for SpanTree {
+    /// Writes the span tree as an indented outline, with the message of each
+    /// log under its span.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            write_event(f, event, 0)?;
+        }
+        for root in &self.roots {
+            root.write(f, 0)?;
+        }
+        Ok(())
+    }
+}
+
+impl SpanNode {
+    /// Writes the span and its descendants, without recursion.
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let mut stack = vec![(self, depth)];
+        while let Some((node, depth)) = stack.pop() {
+            node.write_header(f, depth)?;
+            for event in &node.events {
+                write_event(f, event, depth + 1)?;
+            }
+            stack.extend(node.children.iter().rev().map(|child| (child, depth + 1)));
+        }
+        Ok(())
+    }
+
+    fn write_header(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let serializer = || Serializer::new(false, true);
+        write!(
+            f,
+            "{:indent$}{} {}",
+            "",
+            self.name.as_deref().unwrap_or("<span>"),
+            serializer().serialize(&self.span_id),
+            indent = depth * 2
+        )?;
+        if !self.fields.is_empty() {
+            write!(
+                f,
+                " {}",
+                serializer().serialize(&DuperValue::Object {
+                    identifier: None,
+                    inner: self.fields.clone(),
+                })
+            )?;
+        }
+        writeln!(f)
+    }
+}
+
+fn write_event(f: &mut fmt::Formatter<'_>, event: &DuperValue<'_>, depth: usize) -> fmt::Result {
+    let DuperValue::Object { inner, .. } = event else {
+        return Ok(());
+    };
+    let message = match field(inner, "fields") {
+        Some(DuperValue::Object { inner: fields, .. }) => field(fields, "message"),
+        _ => field(inner, "message"),
+    };
+    let level = match field(inner, "level") {
+        Some(DuperValue::String { inner, .. }) => inner.as_ref(),
+        _ => "-",
+    };
+    match message {
+        Some(DuperValue::String { inner, .. }) => {
+            writeln!(f, "{:indent$}{level:>5} {inner}", "", indent = depth * 2)
+        }
+        Some(message) => writeln!(
+            f,
+            "{:indent$}{level:>5} {}",
+            "",
+            Serializer::new(false, true).serialize(message),
+            indent = depth * 2
+        ),
+        None => Ok(()),
+    }
+}
+
+/// A span node while the tree is being built, with its children as indices.
+struct PendingNode {
+    node: SpanNode,
+    parent: Option<String>,
+    children: Vec<usize>,
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    nodes: Vec<PendingNode>,
+    indices: HashMap<String, usize>,
+    events: Vec<DuperValue<'static>>,
+}
+
+impl TreeBuilder {
+    fn push(&mut self, log: DuperValue<'static>) {
+        let DuperValue::Object { inner, .. } = &log else {
+            return;
+        };
+        let trace_id = field(inner, "trace_id").cloned();
+        let spans: Vec<&DuperObject<'static>> = match field(inner, "spans") {
+            Some(DuperValue::Array { inner: spans, .. }) => spans
+                .iter()
+                .filter_map(|span| match span {
+                    DuperValue::Object { inner, .. } => Some(inner),
+                    _ => None,
+                })
+                .collect(),
+            _ => match field(inner, "span") {
+                Some(DuperValue::Object { inner: span, .. }) => vec![span],
+                _ => vec![],
+            },
+        };
+
+        let mut previous = None;
+        for span in spans {
+            if let Some(index) = self.visit(span, previous.as_deref(), trace_id.as_ref()) {
+                previous = Some(key(&self.nodes[index].node.span_id));
+            }
+        }
+        match previous.and_then(|previous| self.indices.get(&previous)) {
+            Some(&index) => self.nodes[index].node.events.push(log),
+            None => self.events.push(log),
+        }
+    }
+
+    /// Registers or updates a span, returning its index.
+    fn visit(
+        &mut self,
+        span: &DuperObject<'static>,
+        previous: Option<&str>,
+        trace_id: Option<&DuperValue<'static>>,
+    ) -> Option<usize> {
+        let span_id = field(span, "span_id")?;
+        let id = key(span_id);
+        let parent = span
+            .get(&DuperKey::from("parent_span_id"))
+            .map(key)
+            .or_else(|| previous.map(str::to_string));
+        let fields: DuperObject<'static> = DuperObject::from_lossy(
+            span.iter()
+                .filter(|(key, _)| {
+                    !matches!(key.as_ref(), "span_id" | "parent_span_id" | "span_name")
+                })
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        );
+
+        if let Some(&index) = self.indices.get(&id) {
+            let pending = &mut self.nodes[index];
+            // Fields may have been recorded since the span was last seen.
+            pending.node.fields = fields;
+            if pending.node.trace_id.is_none() {
+                pending.node.trace_id = trace_id.cloned();
+            }
+            return Some(index);
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(PendingNode {
+            node: SpanNode {
+                span_id: span_id.clone(),
+                trace_id: trace_id.cloned(),
+                name: match field(span, "span_name") {
+                    Some(DuperValue::String { inner, .. }) => Some(inner.to_string()),
+                    _ => None,
+                },
+                fields,
+                events: vec![],
+                children: vec![],
+            },
+            parent,
+            children: vec![],
+        });
+        self.indices.insert(id, index);
+        Some(index)
+    }
+
+    fn finish(self) -> SpanTree {
+        let mut parents: Vec<Option<usize>> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, pending)| {
+                pending
+                    .parent
+                    .as_ref()
+                    .and_then(|parent| self.indices.get(parent).copied())
+                    .filter(|&parent| parent != index)
+            })
+            .collect();
+        break_cycles(&mut parents);
+
+        let mut roots = vec![];
+        let mut nodes: Vec<Option<PendingNode>> = self.nodes.into_iter().map(Some).collect();
+        for (index, parent) in parents.iter().enumerate() {
+            match parent {
+                Some(parent) => nodes[*parent]
+                    .as_mut()
+                    .expect("nodes are only taken when assembled")
+                    .children
+                    .push(index),
+                None => roots.push(index),
+            }
+        }
+        SpanTree {
+            roots: assemble(nodes, &roots),
+            events: self.events,
+        }
+    }
+}
+
+/// Makes a root of the first span seen in each cycle of parents, so that
+/// every span is reachable from a root.
+fn break_cycles(parents: &mut [Option<usize>]) {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Unvisited,
+        InPath,
+        Done,
+    }
+    let mut states = vec![State::Unvisited; parents.len()];
+    let mut path = vec![];
+    for start in 0..parents.len() {
+        let mut current = Some(start);
+        while let Some(index) = current
+            && states[index] == State::Unvisited
+        {
+            states[index] = State::InPath;
+            path.push(index);
+            current = parents[index];
+        }
+        if let Some(index) = current
+            && states[index] == State::InPath
+        {
+            let cycle = path.iter().position(|&node| node == index).unwrap_or(0);
+            let first = path[cycle..].iter().copied().min().unwrap_or(index);
+            parents[first] = None;
+        }
+        for index in path.drain(..) {
+            states[index] = State::Done;
+        }
+    }
+}
+
+/// Builds the span nodes from the leaves up, without recursion, so that
+/// deeply nested spans can't overflow the stack.
+fn assemble(mut nodes: Vec<Option<PendingNode>>, roots: &[usize]) -> Vec<SpanNode> {
+    // Parents always come before their children in this order.
+    let mut order = Vec::with_capacity(nodes.len());
+    let mut stack: Vec<usize> = roots.iter().rev().copied().collect();
+    while let Some(index) = stack.pop() {
+        order.push(index);
+        if let Some(pending) = &nodes[index] {
+            stack.extend(pending.children.iter().rev());
+        }
+    }
+    let mut built: Vec<Option<SpanNode>> = (0..nodes.len()).map(|_| None).collect();
+    for index in order.into_iter().rev() {
+        let Some(PendingNode {
+            mut node, children, ..
+        }) = nodes[index].take()
+        else {
+            continue;
+        };
+        node.children = children
+            .into_iter()
+            .filter_map(|child| built[child].take())
+            .collect();
+        built[index] = Some(node);
+    }
+    roots
+        .iter()
+        .filter_map(|&root| built[root].take())
+        .collect()
+}
+
+/// Returns the value with the given key in an object.
+fn field<'v, 'a>(object: &'v DuperObject<'a>, key: &str) -> Option<&'v DuperValue<'a>> {
+    object
+        .iter()
+        .find_map(|(k, value)| (k.as_ref() == key).then_some(value))
+}
+
+/// A hashable key for a span ID.
+fn key(span_id: &DuperValue<'_>) -> String {
+    match span_id {
+        DuperValue::String { inner, .. } => inner.to_string(),
+        span_id => Serializer::new(false, true).serialize(span_id),
+    }
+}
+
+#[cfg(test)]
+mod tree_tests {
+    use super::*;
+
+    fn log(span_id: &str, parent_span_id: Option<&str>, message: &str) -> String {
+        let parent = parent_span_id
+            .map(|parent| format!(",parent_span_id:\"{parent}\""))
+            .unwrap_or_default();
+        format!(
+            r#"{{level:"INFO",span:{{span_id:"{span_id}"{parent},span_name:"{span_id}"}},fields:{{message:"{message}"}}}}"#
+        )
+    }
+
+    fn names(nodes: &[SpanNode]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|node| node.name.clone().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn nested_spans() {
+        let logs = [
+            log("a", None, "1"),
+            log("b", Some("a"), "2"),
+            log("c", Some("b"), "3"),
+            log("d", Some("a"), "4"),
+            r#"{level:"WARN",fields:{message:"outside"}}"#.into(),
+        ]
+        .join("\n");
+        let tree: SpanTree = logs.parse().unwrap();
+        assert_eq!(names(&tree.roots), ["a"]);
+        assert_eq!(names(&tree.roots[0].children), ["b", "d"]);
+        assert_eq!(names(&tree.roots[0].children[0].children), ["c"]);
+        assert_eq!(
+            tree.to_string(),
+            " WARN outside\n\
+            a \"a\"\n   INFO 1\n\
+            \x20 b \"b\"\n     INFO 2\n\
+            \x20   c \"c\"\n       INFO 3\n\
+            \x20 d \"d\"\n     INFO 4\n"
+        );
+    }
+
+    #[test]
+    fn orphaned_spans() {
+        // Spans whose parent was never logged are roots.
+        let logs = [log("b", Some("a"), "1"), log("c", Some("b"), "2")].join("\n");
+        let tree: SpanTree = logs.parse().unwrap();
+        assert_eq!(names(&tree.roots), ["b"]);
+        assert_eq!(names(&tree.roots[0].children), ["c"]);
+    }
+
+    #[test]
+    fn cyclic_spans() {
+        // The first span seen in a cycle becomes a root.
+        let logs = [
+            log("a", Some("c"), "1"),
+            log("b", Some("a"), "2"),
+            log("c", Some("b"), "3"),
+            log("d", Some("d"), "4"),
+            log("e", Some("c"), "5"),
+        ]
+        .join("\n");
+        let tree: SpanTree = logs.parse().unwrap();
+        assert_eq!(names(&tree.roots), ["a", "d"]);
+        assert_eq!(names(&tree.roots[0].children), ["b"]);
+        assert_eq!(names(&tree.roots[0].children[0].children), ["c"]);
+        assert_eq!(
+            names(&tree.roots[0].children[0].children[0].children),
+            ["e"]
+        );
+        assert_eq!(tree.roots[1].events.len(), 1);
+    }
+
+    #[test]
+    fn deep_spans() {
+        // Built directly, since parsing this many logs is slow in debug builds.
+        let object = |entries: Vec<(&'static str, String)>| DuperValue::Object {
+            identifier: None,
+            inner: DuperObject::from_lossy(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let value = DuperValue::String {
+                            identifier: None,
+                            inner: value.into(),
+                        };
+                        (DuperKey::from(key), value)
+                    })
+                    .collect(),
+            ),
+        };
+        let depth: usize = 100_000;
+        let mut builder = TreeBuilder::default();
+        for index in 0..depth {
+            let mut span = vec![("span_id", index.to_string())];
+            if let Some(parent) = index.checked_sub(1) {
+                span.push(("parent_span_id", parent.to_string()));
+            }
+            let DuperValue::Object { inner: span, .. } = object(span) else {
+                unreachable!();
+            };
+            builder.push(DuperValue::Object {
+                identifier: None,
+                inner: DuperObject::from_lossy(vec![(
+                    DuperKey::from("span"),
+                    DuperValue::Object {
+                        identifier: None,
+                        inner: span,
+                    },
+                )]),
+            });
+        }
+        let tree = builder.finish();
+        let mut node = &tree.roots[0];
+        let mut count = 1;
+        while let Some(child) = node.children.first() {
+            node = child;
+            count += 1;
+        }
+        assert_eq!(count, depth);
+        // Dropping the tree recurses, so it's leaked instead.
+        std::mem::forget(tree);
+    }
+}